@@ -0,0 +1,568 @@
+use crate::common::{rerun_if_changed, CompileError};
+use crate::desktop::is_bus_name;
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use strong_xml::XmlWrite;
+
+/// The licenses which the metadata itself may be under, so that stores can
+/// show it along with their own.
+const METADATA_LICENSES: [&str; 15] = [
+    "FSFAP",
+    "MIT",
+    "0BSD",
+    "CC0-1.0",
+    "CC-BY-3.0",
+    "CC-BY-4.0",
+    "CC-BY-SA-3.0",
+    "CC-BY-SA-4.0",
+    "GFDL-1.1",
+    "GFDL-1.2",
+    "GFDL-1.3",
+    "GFDL-1.1-or-later",
+    "GFDL-1.2-or-later",
+    "GFDL-1.3-or-later",
+    "BSL-1.0",
+];
+
+/// The longest summary which is shown without being cut off.
+const SUMMARY_LENGTH: usize = 35;
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "component")]
+pub struct Component {
+    #[xml(attr = "type")]
+    pub ty: String,
+    #[xml(flatten_text = "id")]
+    pub id: String,
+    #[xml(flatten_text = "metadata_license")]
+    pub metadata_license: String,
+    #[xml(flatten_text = "project_license")]
+    pub project_license: String,
+    #[xml(flatten_text = "name")]
+    pub name: String,
+    #[xml(flatten_text = "summary")]
+    pub summary: String,
+    // The elements which there is one of are optional to strong_xml, which
+    // only writes single children that are not.
+    #[xml(child = "developer")]
+    pub developer: Option<Developer>,
+    #[xml(child = "description")]
+    pub description: Option<Description>,
+    #[xml(child = "launchable")]
+    pub launchable: Option<Launchable>,
+    #[xml(child = "url")]
+    pub urls: Vec<Url>,
+    #[xml(child = "screenshots")]
+    pub screenshots: Option<Screenshots>,
+    #[xml(child = "content_rating")]
+    pub content_rating: Option<ContentRating>,
+    #[xml(child = "releases")]
+    pub releases: Option<Releases>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "developer")]
+pub struct Developer {
+    #[xml(attr = "id")]
+    pub id: Option<String>,
+    #[xml(flatten_text = "name")]
+    pub name: String,
+}
+
+/// Text made of paragraphs and lists, which is all AppStream allows.
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "description")]
+pub struct Description {
+    #[xml(child = "p", child = "ul")]
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+pub enum Block {
+    #[xml(tag = "p")]
+    Paragraph(Paragraph),
+    #[xml(tag = "ul")]
+    List(List),
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "p")]
+pub struct Paragraph {
+    #[xml(text)]
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "ul")]
+pub struct List {
+    #[xml(flatten_text = "li")]
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "launchable")]
+pub struct Launchable {
+    #[xml(attr = "type")]
+    pub ty: String,
+    #[xml(text)]
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "url")]
+pub struct Url {
+    #[xml(attr = "type")]
+    pub ty: String,
+    #[xml(text)]
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "screenshots")]
+pub struct Screenshots {
+    #[xml(child = "screenshot")]
+    pub screenshots: Vec<Screenshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "screenshot")]
+pub struct Screenshot {
+    #[xml(attr = "type")]
+    pub ty: Option<String>,
+    #[xml(flatten_text = "image")]
+    pub image: String,
+    #[xml(flatten_text = "caption")]
+    pub caption: Option<String>,
+}
+
+/// The age rating, which is left empty for software without any of the
+/// content it rates.
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "content_rating")]
+pub struct ContentRating {
+    #[xml(attr = "type")]
+    pub ty: String,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "releases")]
+pub struct Releases {
+    #[xml(child = "release")]
+    pub releases: Vec<Release>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlWrite)]
+#[xml(tag = "release")]
+pub struct Release {
+    #[xml(attr = "version")]
+    pub version: String,
+    #[xml(attr = "date")]
+    pub date: Option<String>,
+    #[xml(child = "description")]
+    pub description: Option<Description>,
+}
+
+/// The metainfo as it is read from the package metadata.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct AppStreamDetail {
+    pub name: String,
+    pub summary: String,
+    /// The paragraphs of the description.
+    pub description: Vec<String>,
+    pub developer: String,
+    /// A reverse-DNS name for the developer, like the application ID.
+    #[serde(default)]
+    pub developer_id: Option<String>,
+    #[serde(default = "default_metadata_license")]
+    pub metadata_license: String,
+    /// The SPDX expression of the license of the application, which is
+    /// usually the `license` of the package.
+    #[serde(default)]
+    pub project_license: Option<String>,
+    #[serde(default)]
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub bugtracker: Option<String>,
+    #[serde(default)]
+    pub screenshots: Vec<ScreenshotDetail>,
+    /// The releases, newest first, which are read from `changelog` instead
+    /// if it is given.
+    #[serde(default)]
+    pub releases: Vec<ReleaseDetail>,
+    /// A changelog in the format of Keep a Changelog, relative to the
+    /// package.
+    #[serde(default)]
+    pub changelog: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScreenshotDetail {
+    pub image: String,
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseDetail {
+    pub version: String,
+    pub date: String,
+    /// The changes, which are listed in the notes.
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+fn default_metadata_license() -> String {
+    "CC0-1.0".to_owned()
+}
+
+impl AppStreamDetail {
+    /// The metainfo of the application `app_id`, whose desktop entry has the
+    /// same name. The changelog is read from `src_dir` if there is one.
+    pub fn to_component<P: AsRef<Path>>(
+        &self,
+        app_id: &str,
+        src_dir: P,
+    ) -> Result<Component, CompileError> {
+        let releases = match &self.changelog {
+            Some(changelog) => {
+                let changelog = src_dir.as_ref().join(changelog);
+
+                rerun_if_changed(&changelog);
+                parse_changelog(&fs::read_to_string(changelog)?)
+            }
+            None => self
+                .releases
+                .iter()
+                .map(|release| Release {
+                    version: release.version.clone(),
+                    date: Some(release.date.clone()),
+                    description: match release.notes.is_empty() {
+                        true => None,
+                        false => Some(Description {
+                            blocks: vec![Block::List(List {
+                                items: release.notes.clone(),
+                            })],
+                        }),
+                    },
+                })
+                .collect(),
+        };
+        let urls = [
+            ("homepage", &self.homepage),
+            ("bugtracker", &self.bugtracker),
+        ]
+        .into_iter()
+        .filter_map(|(ty, url)| {
+            Some(Url {
+                ty: ty.to_owned(),
+                url: url.clone()?,
+            })
+        })
+        .collect();
+
+        Ok(Component {
+            ty: "desktop-application".to_owned(),
+            id: app_id.to_owned(),
+            metadata_license: self.metadata_license.clone(),
+            project_license: self.project_license.clone().unwrap_or_default(),
+            name: self.name.clone(),
+            summary: self.summary.clone(),
+            developer: Some(Developer {
+                id: self.developer_id.clone(),
+                name: self.developer.clone(),
+            }),
+            description: Some(Description {
+                blocks: self
+                    .description
+                    .iter()
+                    .map(|text| Block::Paragraph(Paragraph { text: text.clone() }))
+                    .collect(),
+            }),
+            launchable: Some(Launchable {
+                ty: "desktop-id".to_owned(),
+                id: format!("{}.desktop", app_id),
+            }),
+            urls,
+            screenshots: (!self.screenshots.is_empty()).then(|| Screenshots {
+                screenshots: self
+                    .screenshots
+                    .iter()
+                    .enumerate()
+                    .map(|(index, screenshot)| Screenshot {
+                        // The first is the one shown in listings.
+                        ty: (index == 0).then(|| "default".to_owned()),
+                        image: screenshot.image.clone(),
+                        caption: screenshot.caption.clone(),
+                    })
+                    .collect(),
+            }),
+            content_rating: Some(ContentRating {
+                ty: "oars-1.1".to_owned(),
+            }),
+            releases: (!releases.is_empty()).then_some(Releases { releases }),
+        })
+    }
+
+    /// Builds the metainfo, see [`Self::to_component`], and validates it,
+    /// see [`Component::validate`], then writes it to
+    /// `metainfo/<app_id>.metainfo.xml` in `target_dir`, laid out like
+    /// `/usr/share`. What stores such as Flathub would only recommend is
+    /// printed as warnings instead. Returns the path of the file.
+    pub fn install<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        app_id: &str,
+        src_dir: P,
+        target_dir: Q,
+    ) -> Result<PathBuf, CompileError> {
+        let component = self.to_component(app_id, src_dir)?;
+        let metainfo_file = target_dir
+            .as_ref()
+            .join("metainfo")
+            .join(format!("{}.metainfo.xml", app_id));
+        let errors = component.validate();
+
+        if !errors.is_empty() {
+            return Err(CompileError::Invalid {
+                path: metainfo_file,
+                errors,
+            });
+        }
+
+        for warning in component.recommendations() {
+            println!("cargo:warning={}: {}", metainfo_file.display(), warning);
+        }
+
+        fs::create_dir_all(metainfo_file.parent().unwrap())?;
+        fs::write(
+            &metainfo_file,
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}\n",
+                component.to_string().unwrap()
+            ),
+        )?;
+
+        Ok(metainfo_file)
+    }
+}
+
+impl Component {
+    /// Finds what is missing or wrong by the AppStream specification, which
+    /// `appstreamcli validate` would fail on.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut versions = HashSet::new();
+
+        if !is_bus_name(&self.id) || self.id.split('.').count() < 3 {
+            errors.push(format!(
+                "`{}` is not a reverse-DNS ID such as `com.example.App`",
+                self.id
+            ));
+        }
+        if !METADATA_LICENSES.contains(&self.metadata_license.as_str()) {
+            errors.push(format!(
+                "the metadata license `{}` is not one of {}",
+                self.metadata_license,
+                METADATA_LICENSES.join(", ")
+            ));
+        }
+        if self.project_license.trim().is_empty() {
+            errors.push("the project license is missing".to_owned());
+        }
+        if self.name.trim().is_empty() {
+            errors.push("the name is empty".to_owned());
+        }
+        if self.summary.trim().is_empty() {
+            errors.push("the summary is empty".to_owned());
+        } else if self.summary.ends_with('.') {
+            errors.push("the summary ends with `.`".to_owned());
+        }
+        if self
+            .developer
+            .as_ref()
+            .is_some_and(|developer| developer.name.trim().is_empty())
+        {
+            errors.push("the developer's name is empty".to_owned());
+        }
+        if self
+            .description
+            .as_ref()
+            .is_none_or(|description| description.blocks.is_empty())
+        {
+            errors.push("the description is empty".to_owned());
+        }
+        if self.content_rating.is_none() {
+            errors.push("the content rating is missing".to_owned());
+        }
+
+        for url in &self.urls {
+            if !is_web_url(&url.url) {
+                errors.push(format!("the {} `{}` is not a web URL", url.ty, url.url));
+            }
+        }
+        for screenshot in self.screenshots() {
+            if !is_web_url(&screenshot.image) {
+                errors.push(format!(
+                    "the screenshot `{}` is not a web URL",
+                    screenshot.image
+                ));
+            }
+        }
+
+        for release in self.releases() {
+            if !versions.insert(&release.version) {
+                errors.push(format!(
+                    "release {} is listed more than once",
+                    release.version
+                ));
+            }
+            match &release.date {
+                Some(date) if !is_iso_date(date) => errors.push(format!(
+                    "release {} has the date `{}`, which is not like 2024-01-31",
+                    release.version, date
+                )),
+                _ => (),
+            }
+        }
+
+        let dates = self
+            .releases()
+            .filter_map(|release| release.date.as_deref())
+            .filter(|date| is_iso_date(date))
+            .collect::<Vec<_>>();
+
+        // Dates in this format sort like the days they are.
+        if dates.windows(2).any(|pair| pair[0] < pair[1]) {
+            errors.push("releases are not listed newest first".to_owned());
+        }
+
+        errors
+    }
+
+    fn screenshots(&self) -> impl Iterator<Item = &Screenshot> {
+        self.screenshots
+            .iter()
+            .flat_map(|screenshots| &screenshots.screenshots)
+    }
+
+    fn releases(&self) -> impl Iterator<Item = &Release> {
+        self.releases.iter().flat_map(|releases| &releases.releases)
+    }
+
+    /// What is not required, but which stores expect.
+    pub fn recommendations(&self) -> Vec<String> {
+        let mut recommendations = Vec::new();
+
+        if self.summary.chars().count() > SUMMARY_LENGTH {
+            recommendations.push(format!(
+                "the summary is longer than {} characters, so it may be cut off",
+                SUMMARY_LENGTH
+            ));
+        }
+        if self.screenshots().next().is_none() {
+            recommendations.push("there are no screenshots".to_owned());
+        }
+        if self.releases().next().is_none() {
+            recommendations.push("there are no releases".to_owned());
+        }
+        if !self.urls.iter().any(|url| url.ty == "homepage") {
+            recommendations.push("there is no homepage".to_owned());
+        }
+
+        recommendations
+    }
+}
+
+/// The releases of a changelog in the format of Keep a Changelog, whose
+/// headings are like `## [1.0.0] - 2024-01-31`. Unreleased changes are left
+/// out, subheadings such as `### Added` become paragraphs, and the lines
+/// starting with `-` or `*` become lists.
+pub fn parse_changelog(text: &str) -> Vec<Release> {
+    let mut releases = Vec::<Release>::new();
+    let mut blocks = None::<&mut Vec<Block>>;
+
+    for line in text.lines().map(str::trim_end) {
+        if let Some(heading) = line.strip_prefix("## ") {
+            let mut words = heading.split_whitespace();
+            let version = words
+                .next()
+                .unwrap_or_default()
+                .trim_matches(['[', ']'])
+                .trim_start_matches('v');
+            let date = words
+                .map(|word| word.trim_matches(['(', ')']))
+                .find(|word| is_iso_date(word));
+
+            if version.eq_ignore_ascii_case("unreleased") || version.is_empty() {
+                blocks = None;
+                continue;
+            }
+
+            releases.push(Release {
+                version: version.to_owned(),
+                date: date.map(str::to_owned),
+                description: Some(Description { blocks: Vec::new() }),
+            });
+            blocks = releases
+                .last_mut()
+                .and_then(|release| release.description.as_mut())
+                .map(|description| &mut description.blocks);
+            continue;
+        }
+
+        let blocks = match blocks.as_mut() {
+            Some(blocks) => blocks,
+            None => continue,
+        };
+        let line = line.trim_start();
+
+        if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+            match blocks.last_mut() {
+                Some(Block::List(list)) => list.items.push(item.trim().to_owned()),
+                _ => blocks.push(Block::List(List {
+                    items: vec![item.trim().to_owned()],
+                })),
+            }
+        } else if let Some(heading) = line.strip_prefix("### ") {
+            blocks.push(Block::Paragraph(Paragraph {
+                text: heading.trim().to_owned(),
+            }));
+        } else if !line.is_empty() && !line.starts_with('[') {
+            // Link references at the end are left out.
+            blocks.push(Block::Paragraph(Paragraph {
+                text: line.to_owned(),
+            }));
+        }
+    }
+
+    for release in &mut releases {
+        if release
+            .description
+            .as_ref()
+            .is_some_and(|description| description.blocks.is_empty())
+        {
+            release.description = None;
+        }
+    }
+
+    releases
+}
+
+fn is_web_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Whether `date` is written like `2024-01-31`.
+fn is_iso_date(date: &str) -> bool {
+    let parts = date.split('-').collect::<Vec<_>>();
+
+    parts.len() == 3
+        && [4, 2, 2]
+            .iter()
+            .zip(&parts)
+            .all(|(length, part)| part.len() == *length && part.chars().all(|c| c.is_ascii_digit()))
+}
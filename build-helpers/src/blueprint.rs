@@ -0,0 +1,49 @@
+use crate::common::{rerun_if_changed, run_compiler, CompileError};
+use crate::gresources::{walk_dir, DirOptions, GResource};
+use std::{fs, path::Path, process::Command};
+
+/// Compiles the `*.blp` files below `source_dir` with `blueprint-compiler`
+/// into `*.ui` files in `target_dir`, keeping the directories they are in.
+/// Returns a resource with the compiled files under `prefix`, aliased by their
+/// paths from `target_dir`, to be added to the bundle.
+///
+/// The compiler is only needed if there are blueprints to compile.
+pub fn compile_blueprints<P: AsRef<Path>, Q: AsRef<Path>>(
+    prefix: String,
+    source_dir: P,
+    target_dir: Q,
+) -> Result<GResource, CompileError> {
+    let source_dir = source_dir.as_ref();
+    let target_dir = target_dir.as_ref();
+    let mut blueprints = Vec::new();
+
+    // Watched even without blueprints, for when the first is added.
+    rerun_if_changed(source_dir);
+    walk_dir(source_dir, Path::new(""), &mut blueprints)?;
+    blueprints.retain(|path| path.extension().is_some_and(|extension| extension == "blp"));
+    blueprints.sort();
+
+    if blueprints.is_empty() {
+        return Ok(GResource::new(prefix));
+    }
+
+    // Blueprints which were removed should not be left in the bundle.
+    if target_dir.exists() {
+        fs::remove_dir_all(target_dir)?;
+    }
+    fs::create_dir_all(target_dir)?;
+
+    run_compiler(
+        Command::new("blueprint-compiler")
+            .arg("batch-compile")
+            .arg(target_dir)
+            .arg(source_dir)
+            .args(blueprints.iter().map(|path| source_dir.join(path))),
+    )?;
+
+    Ok(GResource::from_dir(
+        prefix,
+        target_dir,
+        &DirOptions::new().include("**/*.ui"),
+    )?)
+}
@@ -1,5 +1,12 @@
+use crate::gresources::glob_base;
 use glob::glob;
-use std::{fs, path::Path};
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
 
 pub type GlobCopySet = Vec<(String, String)>;
 
@@ -7,6 +14,8 @@ pub fn copy_globs<P: AsRef<Path>, Q: AsRef<Path>>(detail: &GlobCopySet, src_dir:
     for (src_glob, dest_part) in detail {
         let dest_dir = dest_dir.as_ref().join(dest_part);
 
+        rerun_if_changed(glob_base(&src_dir.as_ref().join(src_glob)));
+
         if dest_dir.exists() {
             fs::remove_dir_all(&dest_dir).unwrap();
         }
@@ -21,32 +30,732 @@ pub fn copy_globs<P: AsRef<Path>, Q: AsRef<Path>>(detail: &GlobCopySet, src_dir:
     }
 }
 
+/// Tells Cargo to run the build script again when `path` changes, or any file
+/// below it if it is a directory. Paths in `OUT_DIR` are left out, since
+/// they are written while the build script runs, which Cargo would take as
+/// a change every time.
+pub fn rerun_if_changed<P: AsRef<Path>>(path: P) {
+    let path = path.as_ref();
+    let is_output = match (env::var_os("OUT_DIR"), env::current_dir()) {
+        (Some(out_dir), Ok(curr_dir)) => curr_dir.join(path).starts_with(out_dir),
+        _ => false,
+    };
+
+    if !is_output {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+}
+
+/// Why running one of the GLib or GTK compilers failed.
+#[derive(Debug)]
+pub enum CompileError {
+    /// The compiler is not installed, or not in `PATH`.
+    NotFound(String),
+    /// Files could not be written, or the compiler could not be run.
+    Io(io::Error),
+    /// The compiler ran but failed, with what it printed to stderr.
+    Failed {
+        program: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+    /// A file was found to be wrong before it was compiled.
+    Invalid { path: PathBuf, errors: Vec<String> },
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound(program) => write!(
+                f,
+                "{} was not found, it has to be installed and in PATH to build",
+                program
+            ),
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Failed {
+                program,
+                status,
+                stderr,
+            } => {
+                write!(f, "{} failed with exit status {}", program, status)?;
+
+                for line in stderr.lines() {
+                    write!(f, "\n    {}", line)?;
+                }
+
+                Ok(())
+            }
+            Self::Invalid { path, errors } => {
+                write!(f, "{} is invalid", path.display())?;
+
+                for error in errors {
+                    write!(f, "\n    {}", error)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for CompileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CompileError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Runs a compiler, with its errors if it is missing or fails.
+pub fn run_compiler(command: &mut Command) -> Result<(), CompileError> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Err(CompileError::NotFound(program))
+        }
+        Err(error) => return Err(CompileError::Io(error)),
+    };
+
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(CompileError::Failed {
+            program,
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }),
+    }
+}
+
 pub mod git {
-    use git2::{build::CheckoutBuilder, Oid, Repository};
+    use crate::common::progress::{format_bytes, Progress};
+    use git2::{
+        build::{CheckoutBuilder, RepoBuilder},
+        Direction, FetchOptions, Oid, ProxyOptions, Remote, RemoteCallbacks, Repository,
+    };
     use std::path::Path;
 
-    pub fn update_repository<P: AsRef<Path>>(url: &str, commit: &str, dest_dir: P) -> bool {
+    /// Clones the repository at `url` if it is not already, through `proxy`
+    /// if there is one, and checks out `commit`. Returns whether anything
+    /// changed.
+    ///
+    /// If a `mirror_dir` is given, the repository is cloned from the bare
+    /// repository there, which is fetched into first if it does not have
+    /// `commit`, so that it is only downloaded once for every build. It is
+    /// cloned from `url` if the mirror cannot be used.
+    pub fn update_repository<P: AsRef<Path>>(
+        url: &str,
+        commit: &str,
+        dest_dir: P,
+        proxy: Option<&str>,
+        mirror_dir: Option<&Path>,
+    ) -> Result<bool, git2::Error> {
+        let dest_dir = dest_dir.as_ref();
         let mut updated = false;
 
-        let repository = match Repository::open(&dest_dir) {
+        let repository = match Repository::open(dest_dir) {
             Ok(repository) => repository,
             Err(_) => {
+                let mirrored = mirror_dir.map(|mirror_dir| {
+                    update_mirror(url, commit, mirror_dir, proxy)?;
+
+                    let repository =
+                        RepoBuilder::new().clone(&mirror_dir.to_string_lossy(), dest_dir)?;
+
+                    repository.remote_set_url("origin", url)?;
+                    Ok::<_, git2::Error>(repository)
+                });
+
                 updated = true;
-                Repository::clone(url, &dest_dir).unwrap()
+
+                match mirrored {
+                    Some(Ok(repository)) => repository,
+                    Some(Err(error)) => {
+                        println!(
+                            "cargo:warning=could not use the cached {}, cloning it instead: {}",
+                            url,
+                            error.message()
+                        );
+
+                        // Anything cloned before failing is in the way.
+                        if dest_dir.exists() {
+                            std::fs::remove_dir_all(dest_dir)
+                                .map_err(|error| git2::Error::from_str(&error.to_string()))?;
+                        }
+
+                        clone(url, dest_dir, proxy)?
+                    }
+                    None => clone(url, dest_dir, proxy)?,
+                }
             }
         };
 
-        let commit_old = repository.head().unwrap().target().unwrap();
-        let commit_new = Oid::from_str(commit).unwrap();
+        let commit_old = repository.head()?.target();
+        let commit_new = Oid::from_str(commit)?;
 
-        if commit_old != commit_new {
+        if commit_old != Some(commit_new) {
             updated = true;
-            repository.set_head_detached(commit_new).unwrap();
-            repository
-                .checkout_head(Some(&mut CheckoutBuilder::default().force()))
-                .unwrap();
+            repository.set_head_detached(commit_new)?;
+            repository.checkout_head(Some(&mut CheckoutBuilder::default().force()))?;
+        }
+
+        Ok(updated)
+    }
+
+    fn clone(url: &str, dest_dir: &Path, proxy: Option<&str>) -> Result<Repository, git2::Error> {
+        with_progress(url, "cloned", proxy, |fetch_options| {
+            RepoBuilder::new()
+                .fetch_options(fetch_options)
+                .clone(url, dest_dir)
+        })
+    }
+
+    /// Fetches the branches and tags of `url` into the bare repository
+    /// `mirror_dir`, which is created if it does not exist, unless it
+    /// already has `commit`.
+    fn update_mirror(
+        url: &str,
+        commit: &str,
+        mirror_dir: &Path,
+        proxy: Option<&str>,
+    ) -> Result<(), git2::Error> {
+        let repository = match Repository::open_bare(mirror_dir) {
+            Ok(repository) => repository,
+            Err(_) => Repository::init_bare(mirror_dir)?,
+        };
+
+        if repository.find_commit(Oid::from_str(commit)?).is_ok() {
+            return Ok(());
+        }
+
+        with_progress(url, "fetched", proxy, |mut fetch_options| {
+            repository.remote_anonymous(url)?.fetch(
+                &["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"],
+                Some(&mut fetch_options),
+                None,
+            )
+        })
+    }
+
+    /// Runs `fetch` with options which go through `proxy`, and report the
+    /// progress of a slow transfer, ending with `done`.
+    fn with_progress<T, F>(
+        url: &str,
+        done: &str,
+        proxy: Option<&str>,
+        fetch: F,
+    ) -> Result<T, git2::Error>
+    where
+        F: FnOnce(FetchOptions) -> Result<T, git2::Error>,
+    {
+        let mut progress = Progress::new(url);
+        let mut callbacks = RemoteCallbacks::new();
+        let mut fetch_options = FetchOptions::new();
+
+        callbacks.transfer_progress(|stats| {
+            progress.update(|| {
+                format!(
+                    "{} of {} objects, {}",
+                    stats.received_objects(),
+                    stats.total_objects(),
+                    format_bytes(stats.received_bytes() as u64)
+                )
+            });
+            true
+        });
+        fetch_options
+            .remote_callbacks(callbacks)
+            .proxy_options(proxy_options(proxy));
+
+        let result = fetch(fetch_options)?;
+
+        progress.finish(|| done.to_owned());
+        Ok(result)
+    }
+
+    /// The names of the tags of the repository at `url`, which are listed
+    /// without cloning it.
+    pub fn remote_tags(url: &str, proxy: Option<&str>) -> Result<Vec<String>, git2::Error> {
+        let mut remote = Remote::create_detached(url)?;
+        let connection = remote.connect_auth(Direction::Fetch, None, Some(proxy_options(proxy)))?;
+
+        let mut tags = connection
+            .list()?
+            .iter()
+            .filter_map(|head| head.name().strip_prefix("refs/tags/"))
+            // Annotated tags are listed again for the commit they point to.
+            .map(|tag| tag.trim_end_matches("^{}").to_owned())
+            .collect::<Vec<_>>();
+
+        tags.dedup();
+        Ok(tags)
+    }
+
+    fn proxy_options(proxy: Option<&str>) -> ProxyOptions<'_> {
+        let mut options = ProxyOptions::new();
+
+        if let Some(proxy) = proxy {
+            options.url(proxy);
+        }
+
+        options
+    }
+}
+
+/// Downloads kept across builds, and across `cargo clean`, in the user's
+/// cache directory.
+pub mod cache {
+    use slug::slugify;
+    use std::{
+        env, fs, io,
+        path::{Path, PathBuf},
+        process,
+    };
+
+    /// The variable which names the directory of the cache instead, or turns
+    /// it off if it is empty.
+    pub const CACHE_DIR_VAR: &str = "BUILD_HELPERS_CACHE_DIR";
+
+    /// The directory of the cache, which is `<package>-build` in
+    /// `XDG_CACHE_HOME` or `~/.cache` unless [`CACHE_DIR_VAR`] is set, if
+    /// there is one.
+    pub fn cache_dir() -> Option<PathBuf> {
+        println!("cargo:rerun-if-env-changed={}", CACHE_DIR_VAR);
+
+        match env::var_os(CACHE_DIR_VAR) {
+            Some(cache_dir) if cache_dir.is_empty() => return None,
+            Some(cache_dir) => return Some(cache_dir.into()),
+            None => (),
+        }
+
+        let cache_home = env::var_os("XDG_CACHE_HOME")
+            .filter(|cache_home| !cache_home.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+
+        Some(cache_home.join(format!("{}-build", env::var("CARGO_PKG_NAME").ok()?)))
+    }
+
+    /// The name of the entry for `url`, along with the checksum of its
+    /// contents if it is known, so that a changed file is not taken for the
+    /// one cached before.
+    pub fn key(url: &str, sha256: Option<&str>) -> String {
+        match sha256 {
+            Some(sha256) => format!("{}-{}", slugify(url), sha256.to_ascii_lowercase()),
+            None => slugify(url),
+        }
+    }
+
+    /// Hard-links `src_file` to `dest_file`, or copies it if they are on
+    /// different file systems, replacing what was there. The file is moved
+    /// into place last, so that builds running at once never see half of
+    /// it.
+    pub fn link_or_copy<P: AsRef<Path>, Q: AsRef<Path>>(
+        src_file: P,
+        dest_file: Q,
+    ) -> io::Result<()> {
+        let dest_file = dest_file.as_ref();
+        let partial_file = dest_file.with_extension(format!("{}.part", process::id()));
+
+        fs::create_dir_all(dest_file.parent().unwrap())?;
+
+        if fs::hard_link(&src_file, &partial_file).is_err() {
+            fs::copy(&src_file, &partial_file)?;
+        }
+
+        fs::rename(&partial_file, dest_file)?;
+
+        // Renaming onto another link to the same file leaves both in place.
+        match fs::remove_file(partial_file) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub mod progress {
+    use std::{
+        env,
+        time::{Duration, Instant},
+    };
+
+    /// The variable which stops the progress of downloads from being shown
+    /// when it is set to anything other than `0`.
+    pub const QUIET_VAR: &str = "BUILD_HELPERS_QUIET";
+
+    /// How long to wait between reports, and before the first.
+    const INTERVAL: Duration = Duration::from_secs(2);
+
+    /// Reports the progress of a slow download as Cargo warnings, which are
+    /// the only output of build scripts shown without `-vv`. Nothing is
+    /// shown for downloads which finish within the interval.
+    #[derive(Debug)]
+    pub struct Progress {
+        label: String,
+        quiet: bool,
+        last: Instant,
+        reported: bool,
+    }
+
+    impl Progress {
+        pub fn new(label: &str) -> Self {
+            Self {
+                label: label.to_owned(),
+                quiet: env::var(QUIET_VAR).is_ok_and(|quiet| !quiet.is_empty() && quiet != "0"),
+                last: Instant::now(),
+                reported: false,
+            }
+        }
+
+        /// Prints the `message` if the interval has passed since the last.
+        pub fn update<F: FnOnce() -> String>(&mut self, message: F) {
+            if self.quiet || self.last.elapsed() < INTERVAL {
+                return;
+            }
+
+            println!("cargo:warning={}: {}", self.label, message());
+            self.last = Instant::now();
+            self.reported = true;
+        }
+
+        /// Prints the `message` if anything was reported before, so that the
+        /// download is not left looking unfinished.
+        pub fn finish<F: FnOnce() -> String>(self, message: F) {
+            if self.reported {
+                println!("cargo:warning={}: {}", self.label, message());
+            }
+        }
+    }
+
+    /// An amount of bytes in the largest binary unit below it, such as
+    /// `1.5 MiB`.
+    pub fn format_bytes(bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        if bytes < 1024 {
+            return format!("{} B", bytes);
+        }
+
+        let mut amount = bytes as f64 / 1024.0;
+        let mut unit = 0;
+
+        while amount >= 1024.0 && unit < UNITS.len() - 1 {
+            amount /= 1024.0;
+            unit += 1;
+        }
+
+        format!("{:.1} {}", amount, UNITS[unit])
+    }
+}
+
+pub mod proxy {
+    use std::env;
+
+    /// The proxy to connect to `url` through. It is `explicit` if given,
+    /// otherwise the one from `HTTPS_PROXY` or `HTTP_PROXY` for the scheme
+    /// of the URL, or `ALL_PROXY`, unless the host is excluded by `NO_PROXY`.
+    /// The variables are also read in lowercase, like curl does.
+    pub fn proxy_for(url: &str, explicit: Option<&str>) -> Option<String> {
+        if let Some(explicit) = explicit {
+            return Some(explicit.to_owned());
+        }
+
+        let (scheme, rest) = url.split_once("://")?;
+        let host = host(rest);
+
+        if var("NO_PROXY").is_some_and(|no_proxy| is_excluded(host, &no_proxy)) {
+            return None;
+        }
+
+        match scheme {
+            "https" => var("HTTPS_PROXY"),
+            "http" => var("HTTP_PROXY"),
+            _ => None,
+        }
+        .or_else(|| var("ALL_PROXY"))
+    }
+
+    /// Whether `host` matches one of the comma-separated entries of
+    /// `no_proxy`, either exactly or as a subdomain, or if one is `*`.
+    pub fn is_excluded(host: &str, no_proxy: &str) -> bool {
+        no_proxy
+            .split(',')
+            .map(|entry| entry.trim().trim_start_matches('.'))
+            .filter(|entry| !entry.is_empty())
+            .any(|entry| {
+                entry == "*"
+                    || host.eq_ignore_ascii_case(entry)
+                    || host
+                        .to_lowercase()
+                        .ends_with(&format!(".{}", entry.to_lowercase()))
+            })
+    }
+
+    /// The host of a URL without its scheme, leaving out the user and port.
+    fn host(rest: &str) -> &str {
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+        let host = authority.rsplit('@').next().unwrap_or(authority);
+
+        match host.rsplit_once(':') {
+            Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+            _ => host,
+        }
+    }
+
+    fn var(name: &str) -> Option<String> {
+        env::var(name)
+            .or_else(|_| env::var(name.to_lowercase()))
+            .ok()
+            .filter(|value| !value.is_empty())
+    }
+}
+
+pub mod archive {
+    use crate::common::progress::{format_bytes, Progress};
+    use glob::Pattern;
+    use sha2::{Digest, Sha256};
+    use std::{
+        fs::{self, File},
+        io::{self, BufReader, Read, Write},
+        path::{Component, Path, PathBuf},
+    };
+
+    /// The kinds of archive which can be extracted, by their extension.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum ArchiveFormat {
+        TarGz,
+        TarXz,
+        Zip,
+    }
+
+    impl ArchiveFormat {
+        /// The format of an archive by the end of its file name or URL, such
+        /// as `.tar.gz` or `.tgz`.
+        pub fn from_name(name: &str) -> Option<Self> {
+            let name = name.to_lowercase();
+
+            if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+                Some(Self::TarGz)
+            } else if name.ends_with(".tar.xz") || name.ends_with(".txz") {
+                Some(Self::TarXz)
+            } else if name.ends_with(".zip") {
+                Some(Self::Zip)
+            } else {
+                None
+            }
+        }
+
+        /// The extension archives of the format are saved with.
+        pub fn extension(self) -> &'static str {
+            match self {
+                Self::TarGz => "tar.gz",
+                Self::TarXz => "tar.xz",
+                Self::Zip => "zip",
+            }
+        }
+    }
+
+    /// Which files of an archive to extract, and where.
+    #[derive(Debug, Default, Clone)]
+    pub struct ExtractOptions {
+        /// How many leading directories are removed from each path, like
+        /// `tar --strip-components`. Files with fewer are left out.
+        pub strip_components: usize,
+        /// Glob patterns matched against the paths after stripping, such as
+        /// `flags/**/*.svg`, or every file if empty.
+        pub include: Vec<Pattern>,
+    }
+
+    impl ExtractOptions {
+        /// The path of an entry in the archive below the directory it is
+        /// extracted to, or `None` if it is left out. Paths which would leave
+        /// the directory are always left out.
+        fn target(&self, path: &Path) -> Option<PathBuf> {
+            let mut components = path.components();
+
+            for _ in 0..self.strip_components {
+                components.next()?;
+            }
+
+            let relative = components.as_path();
+
+            if relative.as_os_str().is_empty()
+                || !relative
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+            {
+                return None;
+            }
+
+            match self.include.is_empty()
+                || self
+                    .include
+                    .iter()
+                    .any(|pattern| pattern.matches_path(relative))
+            {
+                true => Some(relative.to_owned()),
+                false => None,
+            }
+        }
+    }
+
+    /// Downloads `url` to `dest_file` through `proxy` if there is one. The
+    /// file is kept if it already exists so that the archive is only
+    /// downloaded once. Returns whether it was downloaded.
+    pub fn download<P: AsRef<Path>>(
+        url: &str,
+        dest_file: P,
+        proxy: Option<&str>,
+    ) -> io::Result<bool> {
+        let dest_file = dest_file.as_ref();
+
+        if dest_file.exists() {
+            return Ok(false);
+        }
+
+        let mut agent = ureq::AgentBuilder::new();
+
+        if let Some(proxy) = proxy {
+            agent = agent.proxy(ureq::Proxy::new(proxy).map_err(io::Error::other)?);
+        }
+
+        let response = agent.build().get(url).call().map_err(io::Error::other)?;
+        let total = response
+            .header("Content-Length")
+            .and_then(|length| length.parse().ok());
+        // Written beside it first, so that a failed download is not kept.
+        let partial_file = dest_file.with_extension("part");
+        let mut reader = response.into_reader();
+        let mut file = File::create(&partial_file)?;
+        let mut progress = Progress::new(url);
+        let mut buffer = [0; 64 * 1024];
+        let mut done = 0;
+
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+
+            file.write_all(&buffer[..read])?;
+            done += read as u64;
+            progress.update(|| match total {
+                Some(total) => format!("{} of {}", format_bytes(done), format_bytes(total)),
+                None => format_bytes(done),
+            });
+        }
+
+        progress.finish(|| format!("downloaded {}", format_bytes(done)));
+        fs::rename(partial_file, dest_file)?;
+
+        Ok(true)
+    }
+
+    /// The SHA-256 checksum of `file` in lowercase hexadecimal.
+    pub fn sha256<P: AsRef<Path>>(file: P) -> io::Result<String> {
+        let mut hasher = Sha256::new();
+
+        io::copy(&mut File::open(file)?, &mut hasher)?;
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Extracts the files of `archive_file` which `options` allow into
+    /// `dest_dir`, replacing what was there. Returns the paths of the files.
+    pub fn extract<P: AsRef<Path>, Q: AsRef<Path>>(
+        archive_file: P,
+        format: ArchiveFormat,
+        dest_dir: Q,
+        options: &ExtractOptions,
+    ) -> io::Result<Vec<PathBuf>> {
+        let dest_dir = dest_dir.as_ref();
+        let archive = BufReader::new(File::open(archive_file)?);
+
+        if dest_dir.exists() {
+            fs::remove_dir_all(dest_dir)?;
+        }
+        fs::create_dir_all(dest_dir)?;
+
+        let mut files = match format {
+            ArchiveFormat::TarGz => {
+                extract_tar(flate2::read::GzDecoder::new(archive), dest_dir, options)?
+            }
+            ArchiveFormat::TarXz => {
+                let mut decompressed = Vec::new();
+
+                lzma_rs::xz_decompress(&mut { archive }, &mut decompressed)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+                extract_tar(decompressed.as_slice(), dest_dir, options)?
+            }
+            ArchiveFormat::Zip => extract_zip(archive, dest_dir, options)?,
+        };
+
+        files.sort();
+        Ok(files)
+    }
+
+    fn extract_tar<R: Read>(
+        archive: R,
+        dest_dir: &Path,
+        options: &ExtractOptions,
+    ) -> io::Result<Vec<PathBuf>> {
+        let mut archive = tar::Archive::new(archive);
+        let mut files = Vec::new();
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            if let Some(relative) = options.target(&entry.path()?) {
+                let dest_file = dest_dir.join(relative);
+
+                fs::create_dir_all(dest_file.parent().unwrap())?;
+                entry.unpack(&dest_file)?;
+                files.push(dest_file);
+            }
+        }
+
+        Ok(files)
+    }
+
+    fn extract_zip<R: Read + io::Seek>(
+        archive: R,
+        dest_dir: &Path,
+        options: &ExtractOptions,
+    ) -> io::Result<Vec<PathBuf>> {
+        let mut archive = zip::ZipArchive::new(archive)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        let mut files = Vec::new();
+
+        for index in 0..archive.len() {
+            let mut entry = archive
+                .by_index(index)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            let path = match entry.enclosed_name() {
+                Some(path) if entry.is_file() => path,
+                _ => continue,
+            };
+
+            if let Some(relative) = options.target(&path) {
+                let dest_file = dest_dir.join(relative);
+
+                fs::create_dir_all(dest_file.parent().unwrap())?;
+                io::copy(&mut entry, &mut File::create(&dest_file)?)?;
+                files.push(dest_file);
+            }
         }
 
-        updated
+        Ok(files)
     }
 }
@@ -0,0 +1,39 @@
+use crate::common::CompileError;
+use clap_complete::env::Shells;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The shells which completions are installed for, with where each one looks
+/// for them below `share`, where `{}` is the name of the binary.
+const SHELLS: [(&str, &str); 3] = [
+    ("bash", "bash-completion/completions/{}"),
+    ("zsh", "zsh/site-functions/_{}"),
+    ("fish", "fish/vendor_completions.d/{}.fish"),
+];
+
+/// Writes the scripts which register completions of `bin` with each shell,
+/// laid out like `/usr/share`. The scripts ask the installed binary for the
+/// candidates by running it with `COMPLETE` set to the shell, so that they
+/// always match its arguments and can include values only known at runtime.
+pub fn install_completions<P: AsRef<Path>>(
+    bin: &str,
+    target_dir: P,
+) -> Result<Vec<PathBuf>, CompileError> {
+    let shells = Shells::builtins();
+    let mut files = Vec::new();
+
+    for (shell, path) in SHELLS {
+        let completer = shells.completer(shell).unwrap();
+        let file = target_dir.as_ref().join(path.replace("{}", bin));
+        let mut script = Vec::new();
+
+        completer.write_registration("COMPLETE", bin, bin, bin, &mut script)?;
+        fs::create_dir_all(file.parent().unwrap())?;
+        fs::write(&file, script)?;
+        files.push(file);
+    }
+
+    Ok(files)
+}
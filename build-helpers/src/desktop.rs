@@ -0,0 +1,272 @@
+use crate::common::CompileError;
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The categories of which an application should have at least one, from the
+/// Desktop Menu Specification.
+const MAIN_CATEGORIES: [&str; 13] = [
+    "AudioVideo",
+    "Audio",
+    "Video",
+    "Development",
+    "Education",
+    "Game",
+    "Graphics",
+    "Network",
+    "Office",
+    "Science",
+    "Settings",
+    "System",
+    "Utility",
+];
+
+/// The keys of a desktop entry, as they are read from the package metadata.
+/// The `Exec` lines are written as they are given, so arguments have to be
+/// quoted in them already.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DesktopEntry {
+    pub name: String,
+    #[serde(default)]
+    pub generic_name: Option<String>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// The command line which starts the application, without any field
+    /// codes such as `%u`.
+    pub exec: String,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Whether the application is started over D-Bus by its ID, in which
+    /// case a D-Bus service is installed beside the entry.
+    #[serde(default)]
+    pub dbus_activatable: bool,
+    #[serde(default)]
+    pub startup_notify: bool,
+    #[serde(default)]
+    pub actions: Vec<DesktopAction>,
+}
+
+/// An additional way to start the application, shown in the menu of its
+/// launcher.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DesktopAction {
+    /// The name of the action, which is also the application action it
+    /// activates over D-Bus.
+    pub id: String,
+    pub name: String,
+    /// The command line of the action, or `gapplication action` with its ID
+    /// for D-Bus activatable applications if not given.
+    #[serde(default)]
+    pub exec: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+}
+
+impl DesktopEntry {
+    /// Finds the keys which are missing or wrong, which `desktop-file-validate`
+    /// would complain about. The `app_id` is the name of the file without its
+    /// extension.
+    pub fn validate(&self, app_id: &str) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut ids = HashSet::new();
+
+        if self.name.trim().is_empty() {
+            errors.push("`name` is empty".to_owned());
+        }
+        if self.exec.trim().is_empty() {
+            errors.push("`exec` is empty".to_owned());
+        }
+        if !self
+            .categories
+            .iter()
+            .any(|category| MAIN_CATEGORIES.contains(&category.as_str()))
+        {
+            errors.push(format!(
+                "`categories` has none of the main categories {}",
+                MAIN_CATEGORIES.join(", ")
+            ));
+        }
+        if self.dbus_activatable && !is_bus_name(app_id) {
+            errors.push(format!(
+                "`{}` is not a D-Bus name, so it cannot be D-Bus activatable",
+                app_id
+            ));
+        }
+
+        for action in &self.actions {
+            if !ids.insert(&action.id) {
+                errors.push(format!("action `{}` is defined more than once", action.id));
+            }
+            if action.id.is_empty()
+                || !action
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            {
+                errors.push(format!(
+                    "action `{}` has characters other than letters, digits and `-`",
+                    action.id
+                ));
+            }
+            if action.name.trim().is_empty() {
+                errors.push(format!("action `{}` has an empty `name`", action.id));
+            }
+            if action.exec.is_none() && !self.dbus_activatable {
+                errors.push(format!(
+                    "action `{}` needs `exec` unless the application is D-Bus activatable",
+                    action.id
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// The text of the `.desktop` file.
+    pub fn to_desktop(&self, app_id: &str) -> String {
+        let mut text = String::from("[Desktop Entry]\nType=Application\n");
+
+        write_key(&mut text, "Name", &self.name);
+        if let Some(generic_name) = &self.generic_name {
+            write_key(&mut text, "GenericName", generic_name);
+        }
+        if let Some(comment) = &self.comment {
+            write_key(&mut text, "Comment", comment);
+        }
+        writeln!(text, "Exec={}", self.exec).unwrap();
+        if let Some(icon) = &self.icon {
+            write_key(&mut text, "Icon", icon);
+        }
+        text.push_str("Terminal=false\n");
+        if !self.categories.is_empty() {
+            writeln!(text, "Categories={}", list(&self.categories)).unwrap();
+        }
+        if !self.keywords.is_empty() {
+            writeln!(text, "Keywords={}", list(&self.keywords)).unwrap();
+        }
+        if self.dbus_activatable {
+            text.push_str("DBusActivatable=true\n");
+        }
+        if self.startup_notify {
+            text.push_str("StartupNotify=true\n");
+        }
+        if !self.actions.is_empty() {
+            let ids = self
+                .actions
+                .iter()
+                .map(|action| action.id.clone())
+                .collect::<Vec<_>>();
+
+            writeln!(text, "Actions={}", list(&ids)).unwrap();
+        }
+
+        for action in &self.actions {
+            let exec = action
+                .exec
+                .clone()
+                .unwrap_or_else(|| format!("gapplication action {} {}", app_id, action.id));
+
+            writeln!(text, "\n[Desktop Action {}]", action.id).unwrap();
+            write_key(&mut text, "Name", &action.name);
+            writeln!(text, "Exec={}", exec).unwrap();
+            if let Some(icon) = &action.icon {
+                write_key(&mut text, "Icon", icon);
+            }
+        }
+
+        text
+    }
+
+    /// Validates the entry, see [`Self::validate`], then writes it to
+    /// `applications/<app_id>.desktop` in `target_dir`, laid out like
+    /// `/usr/share` so that it can be installed from there. A D-Bus service
+    /// which starts the application is also written to
+    /// `dbus-1/services/<app_id>.service` if it is D-Bus activatable.
+    /// Returns the paths of the files.
+    pub fn install<P: AsRef<Path>>(
+        &self,
+        app_id: &str,
+        target_dir: P,
+    ) -> Result<Vec<PathBuf>, CompileError> {
+        let target_dir = target_dir.as_ref();
+        let desktop_file = target_dir
+            .join("applications")
+            .join(format!("{}.desktop", app_id));
+        let errors = self.validate(app_id);
+
+        if !errors.is_empty() {
+            return Err(CompileError::Invalid {
+                path: desktop_file,
+                errors,
+            });
+        }
+
+        fs::create_dir_all(desktop_file.parent().unwrap())?;
+        fs::write(&desktop_file, self.to_desktop(app_id))?;
+
+        if !self.dbus_activatable {
+            return Ok(vec![desktop_file]);
+        }
+
+        let service_file = target_dir
+            .join("dbus-1/services")
+            .join(format!("{}.service", app_id));
+
+        fs::create_dir_all(service_file.parent().unwrap())?;
+        fs::write(
+            &service_file,
+            format!(
+                "[D-BUS Service]\nName={}\nExec={} --gapplication-service\n",
+                app_id, self.exec
+            ),
+        )?;
+
+        Ok(vec![desktop_file, service_file])
+    }
+}
+
+fn write_key(text: &mut String, key: &str, value: &str) {
+    writeln!(text, "{}={}", key, escape(value)).unwrap();
+}
+
+/// Escapes a string value, which cannot have line breaks.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+/// The values of a list, each followed by `;`.
+fn list(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| format!("{};", escape(value).replace(';', "\\;")))
+        .collect()
+}
+
+/// Whether `name` is a well-known D-Bus name, which the file name of a D-Bus
+/// activatable entry has to be.
+pub(crate) fn is_bus_name(name: &str) -> bool {
+    let elements = name.split('.').collect::<Vec<_>>();
+
+    name.len() <= 255
+        && elements.len() >= 2
+        && elements.iter().all(|element| {
+            !element.is_empty()
+                && !element.starts_with(|c: char| c.is_ascii_digit())
+                && element
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        })
+}
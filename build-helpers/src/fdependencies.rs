@@ -1,47 +1,707 @@
+use crate::common::archive::{self, ArchiveFormat, ExtractOptions};
+use crate::common::cache;
+use crate::common::proxy::proxy_for;
 use crate::common::*;
+use crate::gresources::walk_dir;
+use glob::Pattern;
 use serde::Deserialize;
 use slug::slugify;
-use std::path::{Path, PathBuf};
+use std::{
+    env,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
 
+/// A dependency which is not a crate, whose files are copied into the output
+/// directory with `copy`.
 #[derive(Debug, Deserialize)]
 pub struct ForeignDependency {
-    git: String,
-    commit: String,
+    #[serde(flatten)]
+    source: ForeignSource,
+    /// The release the dependency is pinned to. It is recorded beside the
+    /// installed files, which are only fetched again when it changes, and
+    /// newer releases upstream are warned about unless Cargo is offline.
+    #[serde(default)]
+    version: Option<String>,
+    /// Commands run in the fetched directory before any files are copied
+    /// out of it, each given as the program and its arguments, such as
+    /// `["chmod", "+x", "bin/tool"]`. They are run without a shell, and with
+    /// only `PATH` kept from the environment.
+    #[serde(default, rename = "post-fetch")]
+    post_fetch: Vec<Vec<String>>,
     copy: GlobCopySet,
 }
 
+/// Where a foreign dependency comes from.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ForeignSource {
+    /// A repository checked out at `commit`.
+    Git { git: String, commit: String },
+    /// A `.tar.gz`, `.tar.xz` or `.zip` archive, which is downloaded once.
+    /// Only the files matching `extract` are extracted, or all of them if it
+    /// is empty, after the first `strip-components` directories are removed
+    /// from their paths. The archive is checked against `sha256` if it is
+    /// given.
+    Archive {
+        archive: String,
+        #[serde(default)]
+        sha256: Option<String>,
+        #[serde(default, rename = "strip-components")]
+        strip_components: usize,
+        #[serde(default)]
+        extract: Vec<String>,
+    },
+}
+
+/// Why a foreign dependency could not be fetched.
+#[derive(Debug)]
+pub enum UpdateError {
+    Git(git2::Error),
+    Io(io::Error),
+    /// The dependency is declared wrong, such as with an archive of an
+    /// unknown format.
+    Invalid(String),
+    /// Building offline from vendored dependencies, the file or directory
+    /// which was expected for the dependency is not there.
+    NotVendored(PathBuf),
+    /// A `post-fetch` command failed, or could not be run if there is no
+    /// status, with what it printed.
+    Hook {
+        command: String,
+        status: Option<ExitStatus>,
+        output: String,
+    },
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Git(error) => write!(f, "{}", error.message()),
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Invalid(message) => write!(f, "{}", message),
+            Self::NotVendored(path) => write!(f, "{} is missing", path.display()),
+            Self::Hook {
+                command,
+                status,
+                output,
+            } => {
+                match status {
+                    Some(status) => write!(f, "`{}` failed with exit status {}", command, status)?,
+                    None => write!(f, "`{}` could not be run", command)?,
+                }
+
+                for line in output.lines() {
+                    write!(f, "\n    {}", line)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for UpdateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Git(error) => Some(error),
+            Self::Io(error) => Some(error),
+            Self::Invalid(_) | Self::NotVendored(_) | Self::Hook { .. } => None,
+        }
+    }
+}
+
+impl From<git2::Error> for UpdateError {
+    fn from(error: git2::Error) -> Self {
+        Self::Git(error)
+    }
+}
+
+impl From<io::Error> for UpdateError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
 impl ForeignDependency {
-    pub fn update<P: AsRef<Path>>(&self, out_dir: P) -> bool {
-        let updated = git::update_repository(&self.git, &self.commit, self.clone_path(&out_dir));
+    /// Where the dependency comes from.
+    pub fn source(&self) -> &ForeignSource {
+        &self.source
+    }
+
+    /// Fetches the dependency, then copies its files if they changed.
+    /// Returns whether they did.
+    pub fn update<P: AsRef<Path>>(
+        &self,
+        out_dir: P,
+        proxy: Option<&str>,
+    ) -> Result<bool, UpdateError> {
+        let updated = self.fetch(&out_dir, proxy)?;
+
+        if updated {
+            self.copy_files(&out_dir);
+        }
+
+        Ok(updated)
+    }
+
+    /// Checks out the repository or downloads and extracts the archive, if
+    /// it is not already, without copying any files out of it. Returns
+    /// whether it changed.
+    ///
+    /// Connections go through `proxy` if it is given, or otherwise the one
+    /// from the environment, see [`proxy_for`].
+    pub fn fetch<P: AsRef<Path>>(
+        &self,
+        out_dir: P,
+        proxy: Option<&str>,
+    ) -> Result<bool, UpdateError> {
+        let stale = self.version.is_some() && self.installed_version(&out_dir) != self.version;
+
+        if let (
+            true,
+            ForeignSource::Archive {
+                archive, sha256, ..
+            },
+        ) = (stale, &self.source)
+        {
+            let (_, archive_file) = self.archive_file(archive, &out_dir)?;
+            // Without a checksum, the cached file can only be told apart
+            // from the new version by its URL, which may not have changed,
+            // so it is dropped when another version was installed before,
+            // rather than when the output directory was cleaned.
+            let replaced = self.installed_version(&out_dir).is_some();
+            let cached_file = (sha256.is_none() && replaced)
+                .then(|| cached_archive(archive, None))
+                .flatten();
+
+            // Removed so that the new version is downloaded.
+            for file in [Some(archive_file), cached_file].into_iter().flatten() {
+                match fs::remove_file(file) {
+                    Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                        return Err(error.into())
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let updated = match &self.source {
+            ForeignSource::Git { git, commit } => {
+                let proxy = proxy_for(git, proxy);
+
+                let mirror_dir =
+                    cache::cache_dir().map(|cache_dir| cache_dir.join("git").join(slugify(git)));
+
+                git::update_repository(
+                    git,
+                    commit,
+                    self.clone_path(&out_dir),
+                    proxy.as_deref(),
+                    mirror_dir.as_deref(),
+                )?
+            }
+            ForeignSource::Archive {
+                archive,
+                sha256,
+                strip_components,
+                extract,
+            } => {
+                let (format, archive_file) = self.archive_file(archive, &out_dir)?;
+                let options = extract_options(*strip_components, extract)?;
+                let proxy = proxy_for(archive, proxy);
+                let downloaded =
+                    download_cached(archive, sha256.as_deref(), &archive_file, proxy.as_deref())?;
+                let updated = downloaded || !self.clone_path(&out_dir).exists();
+
+                if updated {
+                    archive::extract(&archive_file, format, self.clone_path(&out_dir), &options)?;
+                }
+
+                updated
+            }
+        } || stale;
 
         if updated {
-            copy_globs(&self.copy, &self.clone_path(&out_dir), &out_dir);
+            // Fetched again next time if they fail, so that they are rerun.
+            if let Err(error) = self.run_hooks(&self.clone_path(&out_dir)) {
+                fs::remove_dir_all(self.clone_path(&out_dir))?;
+                return Err(error);
+            }
+        }
+
+        if let (true, Some(version)) = (stale, &self.version) {
+            fs::write(self.version_path(&out_dir), version)?;
+        }
+
+        if !is_offline() {
+            self.warn_if_outdated(proxy);
+        }
+
+        Ok(updated)
+    }
+
+    /// Installs the dependency from `vendor_dir` instead of the network,
+    /// where a repository is expected as the directory `name`, and an
+    /// archive by the file name in its URL. Archives are extracted as they
+    /// would be when downloaded. Returns the directory to copy files from.
+    ///
+    /// Repositories are copied into the output directory to run the
+    /// `post-fetch` commands in if there are any, so that the vendored
+    /// directory is not changed.
+    pub fn fetch_vendored<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        name: &str,
+        vendor_dir: P,
+        out_dir: Q,
+    ) -> Result<PathBuf, UpdateError> {
+        let vendor_dir = vendor_dir.as_ref();
+
+        match &self.source {
+            ForeignSource::Git { .. } => {
+                let vendored_dir = vendor_dir.join(name);
+
+                rerun_if_changed(&vendored_dir);
+
+                if !vendored_dir.is_dir() {
+                    return Err(UpdateError::NotVendored(vendored_dir));
+                } else if self.post_fetch.is_empty() {
+                    return Ok(vendored_dir);
+                }
+
+                let clone_path = self.clone_path(&out_dir);
+                let mut files = Vec::new();
+
+                if clone_path.exists() {
+                    fs::remove_dir_all(&clone_path)?;
+                }
+                walk_dir(&vendored_dir, Path::new(""), &mut files)?;
+
+                for file in files {
+                    fs::create_dir_all(clone_path.join(&file).parent().unwrap())?;
+                    fs::copy(vendored_dir.join(&file), clone_path.join(&file))?;
+                }
+
+                self.run_hooks(&clone_path)?;
+                Ok(clone_path)
+            }
+            ForeignSource::Archive {
+                archive,
+                sha256,
+                strip_components,
+                extract,
+            } => {
+                let (format, _) = self.archive_file(archive, &out_dir)?;
+                let file_name = archive.rsplit('/').next().unwrap_or(archive);
+                let vendored_file = vendor_dir.join(file_name);
+
+                rerun_if_changed(&vendored_file);
+
+                if !vendored_file.is_file() {
+                    return Err(UpdateError::NotVendored(vendored_file));
+                }
+                if let Some(sha256) = sha256 {
+                    check_sha256(&vendored_file, sha256)?;
+                }
+
+                let options = extract_options(*strip_components, extract)?;
+
+                archive::extract(&vendored_file, format, self.clone_path(&out_dir), &options)?;
+                self.run_hooks(&self.clone_path(&out_dir))?;
+                Ok(self.clone_path(&out_dir))
+            }
+        }
+    }
+
+    /// Runs the `post-fetch` commands in `dir` in order, stopping at the
+    /// first which fails.
+    fn run_hooks(&self, dir: &Path) -> Result<(), UpdateError> {
+        for args in &self.post_fetch {
+            let (program, args) = args.split_first().ok_or_else(|| {
+                UpdateError::Invalid("a `post-fetch` command is empty".to_owned())
+            })?;
+            let command = [program]
+                .into_iter()
+                .chain(args)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(" ");
+            // Paths to programs are relative to the fetched directory, like
+            // those in the arguments.
+            let program = match program.contains('/') {
+                true => dir.join(program),
+                false => PathBuf::from(program),
+            };
+            let output = Command::new(program)
+                .args(args)
+                .current_dir(dir)
+                .env_clear()
+                .envs(env::var_os("PATH").map(|path| ("PATH", path)))
+                .output()
+                .map_err(|error| UpdateError::Hook {
+                    command: command.clone(),
+                    status: None,
+                    output: error.to_string(),
+                })?;
+
+            if !output.status.success() {
+                return Err(UpdateError::Hook {
+                    command,
+                    status: Some(output.status),
+                    output: String::from_utf8_lossy(&output.stdout).into_owned()
+                        + &String::from_utf8_lossy(&output.stderr),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the files of the fetched dependency into the output directory.
+    pub fn copy_files<P: AsRef<Path>>(&self, out_dir: P) {
+        copy_globs(&self.copy, &self.clone_path(&out_dir), &out_dir);
+    }
+
+    /// Where the archive is downloaded to, beside where it is extracted.
+    fn archive_file<P: AsRef<Path>>(
+        &self,
+        archive: &str,
+        out_dir: P,
+    ) -> Result<(ArchiveFormat, PathBuf), UpdateError> {
+        let format = ArchiveFormat::from_name(archive).ok_or_else(|| {
+            UpdateError::Invalid(format!("{} is not a supported archive", archive))
+        })?;
+
+        Ok((
+            format,
+            self.clone_path(out_dir).with_extension(format.extension()),
+        ))
+    }
+
+    /// The version recorded when the dependency was last installed.
+    pub fn installed_version<P: AsRef<Path>>(&self, out_dir: P) -> Option<String> {
+        fs::read_to_string(self.version_path(out_dir))
+            .ok()
+            .map(|version| version.trim().to_owned())
+    }
+
+    fn version_path<P: AsRef<Path>>(&self, out_dir: P) -> PathBuf {
+        self.clone_path(out_dir).with_extension("version")
+    }
+
+    /// Prints a warning for Cargo if the repository has a release tagged
+    /// after the pinned version. Archives are only checked when they are
+    /// downloaded from GitHub, where their repository is known.
+    fn warn_if_outdated(&self, proxy: Option<&str>) {
+        let (version, url) = match (&self.version, self.repository_url()) {
+            (Some(version), Some(url)) => (version, url),
+            _ => return,
+        };
+
+        match git::remote_tags(&url, proxy_for(&url, proxy).as_deref()) {
+            Ok(tags) => {
+                if let Some(newer) = newer_version(version, tags.iter().map(String::as_str)) {
+                    println!(
+                        "cargo:warning={} has a newer release {} than the pinned {}",
+                        url, newer, version
+                    );
+                }
+            }
+            Err(error) => println!(
+                "cargo:warning=could not check {} for newer releases: {}",
+                url,
+                error.message()
+            ),
         }
+    }
+
+    fn repository_url(&self) -> Option<String> {
+        match &self.source {
+            ForeignSource::Git { git, .. } => Some(git.clone()),
+            ForeignSource::Archive { archive, .. } => {
+                let mut parts = archive.strip_prefix("https://github.com/")?.split('/');
 
-        updated
+                Some(format!(
+                    "https://github.com/{}/{}",
+                    parts.next()?,
+                    parts.next()?
+                ))
+            }
+        }
     }
 
+    /// Where the repository is checked out or the archive is extracted.
     pub fn clone_path<P: AsRef<Path>>(&self, out_dir: P) -> PathBuf {
-        out_dir.as_ref().join(slugify(&self.git))
+        let url = match &self.source {
+            ForeignSource::Git { git, .. } => git,
+            ForeignSource::Archive { archive, .. } => archive,
+        };
+
+        out_dir.as_ref().join(slugify(url))
+    }
+}
+
+/// Downloads `url` to `archive_file` if it is not there, or links it from the
+/// cache if it is in it, see [`cache`]. Downloads are checked against
+/// `sha256` if it is given, and added to the cache. Returns whether the file
+/// is new.
+fn download_cached(
+    url: &str,
+    sha256: Option<&str>,
+    archive_file: &Path,
+    proxy: Option<&str>,
+) -> Result<bool, UpdateError> {
+    if archive_file.exists() {
+        return Ok(false);
+    }
+
+    let cached_file = cached_archive(url, sha256);
+
+    if let Some(cached_file) = cached_file.as_ref().filter(|file| file.is_file()) {
+        cache::link_or_copy(cached_file, archive_file)?;
+        return Ok(true);
+    }
+
+    archive::download(url, archive_file, proxy)?;
+
+    if let Some(sha256) = sha256 {
+        if let Err(error) = check_sha256(archive_file, sha256) {
+            fs::remove_file(archive_file)?;
+            return Err(error);
+        }
+    }
+
+    // The cache only saves downloads, so the build goes on without it.
+    if let Some(cached_file) = cached_file {
+        if let Err(error) = cache::link_or_copy(archive_file, &cached_file) {
+            println!("cargo:warning=could not cache {}: {}", url, error);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Where the archive is kept in the cache, if there is one.
+fn cached_archive(url: &str, sha256: Option<&str>) -> Option<PathBuf> {
+    cache::cache_dir().map(|cache_dir| cache_dir.join("archives").join(cache::key(url, sha256)))
+}
+
+fn check_sha256(file: &Path, sha256: &str) -> Result<(), UpdateError> {
+    let actual = archive::sha256(file)?;
+
+    match actual.eq_ignore_ascii_case(sha256) {
+        true => Ok(()),
+        false => Err(UpdateError::Invalid(format!(
+            "{} has the SHA-256 checksum {}, but {} was expected",
+            file.display(),
+            actual,
+            sha256
+        ))),
     }
 }
 
+fn extract_options(
+    strip_components: usize,
+    extract: &[String],
+) -> Result<ExtractOptions, UpdateError> {
+    Ok(ExtractOptions {
+        strip_components,
+        include: extract
+            .iter()
+            .map(|pattern| {
+                Pattern::new(pattern)
+                    .map_err(|error| UpdateError::Invalid(format!("`{}`: {}", pattern, error)))
+            })
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Whether Cargo was told not to use the network, with `--offline` or
+/// `net.offline` in its configuration.
+fn is_offline() -> bool {
+    env::var("CARGO_NET_OFFLINE").is_ok_and(|offline| offline == "true")
+}
+
+/// The greatest of `tags` which is a later version than `current`, if any.
+/// Versions are compared by their dot-separated numbers, with an optional
+/// `v` in front, so tags of pre-releases and other names are left out.
+pub fn newer_version<'a, I: IntoIterator<Item = &'a str>>(
+    current: &str,
+    tags: I,
+) -> Option<&'a str> {
+    let current = parse_version(current)?;
+
+    tags.into_iter()
+        .filter_map(|tag| Some((parse_version(tag)?, tag)))
+        .filter(|(version, _)| *version > current)
+        .max()
+        .map(|(_, tag)| tag)
+}
+
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim_start_matches(['v', 'V'])
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect()
+}
+
 pub mod manifest {
-    use crate::fdependencies::ForeignDependency;
+    use crate::{
+        common::copy_globs,
+        fdependencies::{ForeignDependency, UpdateError},
+    };
     use serde::Deserialize;
-    use std::{collections::BTreeMap, path::Path};
+    use std::{
+        collections::BTreeMap,
+        env,
+        error::Error,
+        fmt,
+        path::{Path, PathBuf},
+        sync::{Mutex, PoisonError},
+        thread,
+    };
 
+    /// The variable which makes the foreign dependencies be installed from
+    /// the directory it names instead of the network, such as for packages
+    /// which are built offline. See [`ForeignDependency::fetch_vendored`].
+    pub const VENDOR_DIR_VAR: &str = "BUILD_HELPERS_VENDOR_DIR";
+
+    /// The foreign dependencies by their names, and the `proxy` to fetch
+    /// them through instead of the one from the environment.
     #[derive(Debug, Deserialize)]
-    pub struct ForeignDependenciesDetail(BTreeMap<String, ForeignDependency>);
+    pub struct ForeignDependenciesDetail {
+        #[serde(default)]
+        proxy: Option<String>,
+        #[serde(flatten)]
+        dependencies: BTreeMap<String, ForeignDependency>,
+    }
+
+    /// The dependencies which could not be fetched, by their names.
+    #[derive(Debug)]
+    pub struct UpdateErrors(pub Vec<(String, UpdateError)>);
+
+    impl fmt::Display for UpdateErrors {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                f,
+                "{} foreign dependencies could not be fetched",
+                self.0.len()
+            )?;
+
+            for (name, error) in &self.0 {
+                write!(f, "\n    {}: {}", name, error)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Error for UpdateErrors {}
 
     impl ForeignDependenciesDetail {
-        pub fn update_all<P: AsRef<Path>>(&self, out_dir: P) {
-            for (_, detail) in self.0.iter() {
-                let updated = detail.update(&out_dir);
+        /// Updates every dependency, fetching as many at once as Cargo runs
+        /// jobs, see [`Self::update_all_with`], or from the vendored
+        /// directory if [`VENDOR_DIR_VAR`] is set.
+        pub fn update_all<P: AsRef<Path>>(&self, out_dir: P) -> Result<(), UpdateErrors> {
+            println!("cargo:rerun-if-env-changed={}", VENDOR_DIR_VAR);
 
-                if !updated {
-                    continue;
+            if let Some(vendor_dir) = env::var_os(VENDOR_DIR_VAR) {
+                return self.vendor_all(PathBuf::from(vendor_dir), out_dir);
+            }
+
+            let jobs = env::var("NUM_JOBS")
+                .ok()
+                .and_then(|jobs| jobs.parse().ok())
+                .or_else(|| thread::available_parallelism().ok().map(usize::from))
+                .unwrap_or(1);
+
+            self.update_all_with(out_dir, jobs)
+        }
+
+        /// Fetches the dependencies on up to `jobs` threads, then copies the
+        /// files of those which changed. Every dependency is tried even if
+        /// some fail, and the errors of all of them are returned together.
+        pub fn update_all_with<P: AsRef<Path>>(
+            &self,
+            out_dir: P,
+            jobs: usize,
+        ) -> Result<(), UpdateErrors> {
+            let out_dir = out_dir.as_ref();
+            let queue = Mutex::new(self.dependencies.iter());
+            let results = Mutex::new(Vec::new());
+
+            thread::scope(|scope| {
+                for _ in 0..jobs.clamp(1, self.dependencies.len().max(1)) {
+                    scope.spawn(|| loop {
+                        let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                        let (name, detail) = match next {
+                            Some(next) => next,
+                            None => break,
+                        };
+                        let result = detail.fetch(out_dir, self.proxy.as_deref());
+
+                        results
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .push((name, detail, result));
+                    });
                 }
+            });
+
+            let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+            let mut errors = Vec::new();
+
+            // Copied in order, so that dependencies later by name overwrite
+            // the same files as they did before.
+            results.sort_by_key(|(name, _, _)| *name);
+
+            for (name, detail, result) in results {
+                match result {
+                    Ok(true) => detail.copy_files(out_dir),
+                    Ok(false) => (),
+                    Err(error) => errors.push((name.clone(), error)),
+                }
+            }
+
+            match errors.is_empty() {
+                true => Ok(()),
+                false => Err(UpdateErrors(errors)),
+            }
+        }
+
+        /// The dependencies by their names.
+        pub fn dependencies(&self) -> &BTreeMap<String, ForeignDependency> {
+            &self.dependencies
+        }
+
+        /// Installs every dependency from `vendor_dir` without using the
+        /// network, and copies their files. The errors of all of them are
+        /// returned together, so that every missing file is listed at once.
+        pub fn vendor_all<P: AsRef<Path>, Q: AsRef<Path>>(
+            &self,
+            vendor_dir: P,
+            out_dir: Q,
+        ) -> Result<(), UpdateErrors> {
+            let errors = self
+                .dependencies
+                .iter()
+                .filter_map(|(name, detail)| {
+                    match detail.fetch_vendored(name, &vendor_dir, &out_dir) {
+                        Ok(src_dir) => {
+                            copy_globs(&detail.copy, src_dir, &out_dir);
+                            None
+                        }
+                        Err(error) => Some((name.clone(), error)),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            match errors.is_empty() {
+                true => Ok(()),
+                false => Err(UpdateErrors(errors)),
             }
         }
     }
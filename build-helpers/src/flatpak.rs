@@ -0,0 +1,421 @@
+use crate::common::{rerun_if_changed, CompileError, GlobCopySet};
+use crate::fdependencies::{manifest::ForeignDependenciesDetail, ForeignSource};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The registry whose crates are vendored from their archives.
+const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+/// Where the archives of the crates are downloaded from.
+const CRATES_DOWNLOAD: &str = "https://static.crates.io/crates";
+
+/// The file with the sources of the crates, beside the manifest.
+pub const CARGO_SOURCES_FILE: &str = "cargo-sources.json";
+
+/// How the application is built with `flatpak-builder`, as it is read from
+/// the package metadata. The crates in `Cargo.lock` and the foreign
+/// dependencies are vendored, since nothing is downloaded while building.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FlatpakDetail {
+    #[serde(default = "default_runtime")]
+    pub runtime: String,
+    pub runtime_version: String,
+    #[serde(default = "default_sdk")]
+    pub sdk: String,
+    /// The SDK extensions, whose `bin` directories are added to `PATH`.
+    #[serde(default = "default_sdk_extensions")]
+    pub sdk_extensions: Vec<String>,
+    /// The binary target to build.
+    pub bin: String,
+    /// The name it is installed as, which the application is run by.
+    pub command: String,
+    #[serde(default)]
+    pub network: bool,
+    /// The names on the session bus which can be owned, besides those
+    /// starting with the application ID.
+    #[serde(default)]
+    pub own_names: Vec<String>,
+    /// The names on the session bus which can be talked to.
+    #[serde(default)]
+    pub talk_names: Vec<String>,
+    /// The names on the system bus which can be talked to.
+    #[serde(default)]
+    pub system_talk_names: Vec<String>,
+    /// The paths on the host which can be accessed, as `--filesystem` takes
+    /// them.
+    #[serde(default)]
+    pub filesystems: Vec<String>,
+    /// Variables set while building.
+    #[serde(default)]
+    pub build_env: BTreeMap<String, String>,
+    /// The files to copy into a directory below `/app` once built, by a glob
+    /// which can start with `$OUT_DIR` for those written by the build script.
+    #[serde(default)]
+    pub install: GlobCopySet,
+}
+
+fn default_runtime() -> String {
+    "org.gnome.Platform".to_owned()
+}
+
+fn default_sdk() -> String {
+    "org.gnome.Sdk".to_owned()
+}
+
+fn default_sdk_extensions() -> Vec<String> {
+    vec!["org.freedesktop.Sdk.Extension.rust-stable".to_owned()]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FlatpakManifest {
+    pub id: String,
+    pub runtime: String,
+    pub runtime_version: String,
+    pub sdk: String,
+    pub sdk_extensions: Vec<String>,
+    pub command: String,
+    pub finish_args: Vec<String>,
+    pub modules: Vec<FlatpakModule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FlatpakModule {
+    pub name: String,
+    pub buildsystem: String,
+    pub build_options: BuildOptions,
+    pub build_commands: Vec<String>,
+    pub sources: Vec<ModuleSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuildOptions {
+    pub append_path: String,
+    pub env: BTreeMap<String, String>,
+}
+
+/// A source of a module, or the name of a file with more of them.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ModuleSource {
+    File(String),
+    Source(FlatpakSource),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum FlatpakSource {
+    Dir {
+        path: PathBuf,
+        skip: Vec<String>,
+    },
+    Git {
+        url: String,
+        commit: String,
+        dest: String,
+    },
+    File {
+        url: String,
+        sha256: String,
+        dest: String,
+    },
+    Archive {
+        #[serde(rename = "archive-type")]
+        archive_type: String,
+        url: String,
+        sha256: String,
+        dest: String,
+    },
+    Inline {
+        contents: String,
+        dest: String,
+        #[serde(rename = "dest-filename")]
+        dest_filename: String,
+    },
+}
+
+impl FlatpakDetail {
+    /// The manifest of the application `app_id`, which is built from the
+    /// package `package` in `src_dir` as a module named like it. Finds what
+    /// `flatpak-builder` could not build, such as dependencies which cannot
+    /// be vendored.
+    pub fn to_manifest<P: AsRef<Path>>(
+        &self,
+        app_id: &str,
+        package: &str,
+        src_dir: P,
+        foreign_dependencies: &ForeignDependenciesDetail,
+    ) -> Result<FlatpakManifest, Vec<String>> {
+        let mut errors = Vec::new();
+        let build_dir = format!("/run/build/{}", package);
+        let out_dir = format!("target/release/build/{}-*/out", package);
+        let mut env = BTreeMap::from([
+            ("CARGO_HOME".to_owned(), format!("{}/cargo", build_dir)),
+            ("CARGO_NET_OFFLINE".to_owned(), "true".to_owned()),
+        ]);
+        let mut sources = vec![
+            ModuleSource::Source(FlatpakSource::Dir {
+                path: src_dir.as_ref().to_owned(),
+                skip: vec!["target".to_owned()],
+            }),
+            ModuleSource::File(CARGO_SOURCES_FILE.to_owned()),
+        ];
+        let mut build_commands = vec![
+            format!("cargo --offline build --release --bin {}", self.bin),
+            format!(
+                "install -Dm755 target/release/{} /app/bin/{}",
+                self.bin, self.command
+            ),
+        ];
+
+        if self.runtime_version.trim().is_empty() {
+            errors.push("`runtime-version` is empty".to_owned());
+        }
+        if self.command.is_empty() || self.command.contains('/') {
+            errors.push(format!("`{}` is not a command name", self.command));
+        }
+
+        if !foreign_dependencies.dependencies().is_empty() {
+            env.insert(
+                crate::fdependencies::manifest::VENDOR_DIR_VAR.to_owned(),
+                format!("{}/vendor", build_dir),
+            );
+        }
+
+        for (name, dependency) in foreign_dependencies.dependencies() {
+            match dependency.source() {
+                ForeignSource::Git { git, commit } => {
+                    sources.push(ModuleSource::Source(FlatpakSource::Git {
+                        url: git.clone(),
+                        commit: commit.clone(),
+                        dest: format!("vendor/{}", name),
+                    }))
+                }
+                // Vendored archives are found by the file names in their URLs,
+                // which Flatpak keeps.
+                ForeignSource::Archive {
+                    archive,
+                    sha256: Some(sha256),
+                    ..
+                } => sources.push(ModuleSource::Source(FlatpakSource::File {
+                    url: archive.clone(),
+                    sha256: sha256.clone(),
+                    dest: "vendor".to_owned(),
+                })),
+                // Flatpak needs the checksums of files.
+                ForeignSource::Archive { sha256: None, .. } => errors.push(format!(
+                    "foreign dependency `{}` is an archive without `sha256`, so it cannot be vendored",
+                    name
+                )),
+            }
+        }
+
+        for (src_glob, dest_part) in &self.install {
+            if Path::new(dest_part).is_absolute() || dest_part.split('/').any(|part| part == "..") {
+                errors.push(format!("`{}` is not a directory below `/app`", dest_part));
+                continue;
+            }
+
+            let src_glob = match src_glob.strip_prefix("$OUT_DIR") {
+                Some(rest) => format!("{}{}", out_dir, rest),
+                None => src_glob.clone(),
+            };
+
+            build_commands.push(format!(
+                "mkdir -p /app/{dest} && cp -r {src} /app/{dest}",
+                src = src_glob,
+                dest = dest_part
+            ));
+        }
+        env.extend(self.build_env.clone());
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(FlatpakManifest {
+            id: app_id.to_owned(),
+            runtime: self.runtime.clone(),
+            runtime_version: self.runtime_version.clone(),
+            sdk: self.sdk.clone(),
+            sdk_extensions: self.sdk_extensions.clone(),
+            command: self.command.clone(),
+            finish_args: self.finish_args(),
+            modules: vec![FlatpakModule {
+                name: package.to_owned(),
+                buildsystem: "simple".to_owned(),
+                build_options: BuildOptions {
+                    append_path: self
+                        .sdk_extensions
+                        .iter()
+                        .map(|extension| {
+                            let name = extension.rsplit('.').next().unwrap();
+
+                            format!("/usr/lib/sdk/{}/bin", name)
+                        })
+                        .collect::<Vec<_>>()
+                        .join(":"),
+                    env,
+                },
+                build_commands,
+                sources,
+            }],
+        })
+    }
+
+    /// The permissions of the sandbox, which are those of a GTK application
+    /// on Wayland or X11 with what it is given in the metadata.
+    pub fn finish_args(&self) -> Vec<String> {
+        let mut finish_args = [
+            "--share=ipc",
+            "--socket=fallback-x11",
+            "--socket=wayland",
+            "--device=dri",
+        ]
+        .map(str::to_owned)
+        .to_vec();
+
+        if self.network {
+            finish_args.push("--share=network".to_owned());
+        }
+
+        for (flag, values) in [
+            ("--own-name", &self.own_names),
+            ("--talk-name", &self.talk_names),
+            ("--system-talk-name", &self.system_talk_names),
+            ("--filesystem", &self.filesystems),
+        ] {
+            finish_args.extend(values.iter().map(|value| format!("{}={}", flag, value)));
+        }
+
+        finish_args
+    }
+
+    /// Writes the manifest, see [`Self::to_manifest`], to `<app_id>.json` in
+    /// `target_dir`, and the sources of the crates in the `Cargo.lock` of
+    /// `src_dir`, see [`cargo_sources`], to [`CARGO_SOURCES_FILE`] beside it.
+    /// Returns the paths of the files.
+    pub fn install<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        app_id: &str,
+        package: &str,
+        src_dir: P,
+        foreign_dependencies: &ForeignDependenciesDetail,
+        target_dir: Q,
+    ) -> Result<Vec<PathBuf>, CompileError> {
+        let src_dir = src_dir.as_ref();
+        let target_dir = target_dir.as_ref();
+        let manifest_file = target_dir.join(format!("{}.json", app_id));
+        let lock_file = src_dir.join("Cargo.lock");
+        let sources_file = target_dir.join(CARGO_SOURCES_FILE);
+
+        rerun_if_changed(&lock_file);
+
+        let manifest = self
+            .to_manifest(app_id, package, src_dir, foreign_dependencies)
+            .map_err(|errors| CompileError::Invalid {
+                path: manifest_file.clone(),
+                errors,
+            })?;
+        let sources = cargo_sources(&fs::read_to_string(&lock_file)?).map_err(|errors| {
+            CompileError::Invalid {
+                path: lock_file.clone(),
+                errors,
+            }
+        })?;
+
+        fs::create_dir_all(target_dir)?;
+        fs::write(
+            &manifest_file,
+            serde_json::to_string_pretty(&manifest).unwrap() + "\n",
+        )?;
+        fs::write(
+            &sources_file,
+            serde_json::to_string_pretty(&sources).unwrap() + "\n",
+        )?;
+
+        Ok(vec![manifest_file, sources_file])
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    checksum: Option<String>,
+}
+
+/// The sources which vendor the crates in the text of `Cargo.lock`, along
+/// with the Cargo configuration that builds from them. Only crates from
+/// crates.io can be vendored, and the packages in the workspace are left
+/// out.
+pub fn cargo_sources(lock_file: &str) -> Result<Vec<FlatpakSource>, Vec<String>> {
+    let lock = toml::from_str::<CargoLock>(lock_file).map_err(|error| vec![error.to_string()])?;
+    let mut errors = Vec::new();
+    let mut sources = Vec::new();
+
+    for package in &lock.package {
+        let dest = format!("cargo/vendor/{}-{}", package.name, package.version);
+
+        match (&package.source, &package.checksum) {
+            (None, _) => continue,
+            (Some(source), Some(checksum)) if source == CRATES_IO => {
+                sources.push(FlatpakSource::Archive {
+                    archive_type: "tar-gzip".to_owned(),
+                    url: format!(
+                        "{url}/{name}/{name}-{version}.crate",
+                        url = CRATES_DOWNLOAD,
+                        name = package.name,
+                        version = package.version
+                    ),
+                    sha256: checksum.clone(),
+                    dest: dest.clone(),
+                });
+                sources.push(FlatpakSource::Inline {
+                    contents: format!(r#"{{"package": "{}", "files": {{}}}}"#, checksum),
+                    dest,
+                    dest_filename: ".cargo-checksum.json".to_owned(),
+                });
+            }
+            (Some(source), _) => errors.push(format!(
+                "{} {} is from `{}`, which cannot be vendored",
+                package.name, package.version, source
+            )),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    sources.push(FlatpakSource::Inline {
+        contents: concat!(
+            "[source.vendored-sources]\n",
+            "directory = \"cargo/vendor\"\n",
+            "\n",
+            "[source.crates-io]\n",
+            "replace-with = \"vendored-sources\"\n",
+        )
+        .to_owned(),
+        dest: "cargo".to_owned(),
+        dest_filename: "config.toml".to_owned(),
+    });
+
+    Ok(sources)
+}
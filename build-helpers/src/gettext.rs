@@ -0,0 +1,251 @@
+use crate::common::{rerun_if_changed, run_compiler, CompileError};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Compiles the catalog of each language listed in `po_dir/LINGUAS` into
+/// `target_dir/<language>/LC_MESSAGES/<domain>.mo`, the layout gettext looks
+/// them up in, and tells Cargo to build again when any of them change.
+/// Returns the paths of the compiled catalogs.
+///
+/// They are compiled with `msgfmt`, or with [`write_mo`] if it is not
+/// installed.
+pub fn compile_translations<P: AsRef<Path>, Q: AsRef<Path>>(
+    po_dir: P,
+    domain: &str,
+    target_dir: Q,
+) -> Result<Vec<PathBuf>, CompileError> {
+    let po_dir = po_dir.as_ref();
+    let linguas = po_dir.join("LINGUAS");
+
+    rerun_if_changed(&linguas);
+
+    languages(&fs::read_to_string(&linguas)?)
+        .map(|language| {
+            let po_file = po_dir.join(format!("{}.po", language));
+            let mo_file = target_dir
+                .as_ref()
+                .join(language)
+                .join("LC_MESSAGES")
+                .join(format!("{}.mo", domain));
+
+            rerun_if_changed(&po_file);
+            fs::create_dir_all(mo_file.parent().unwrap())?;
+
+            let compiled = run_compiler(
+                Command::new("msgfmt")
+                    .arg("--check")
+                    .arg("--output-file")
+                    .arg(&mo_file)
+                    .arg(&po_file),
+            );
+
+            match compiled {
+                Err(CompileError::NotFound(_)) => write_mo(&po_file, &mo_file)?,
+                compiled => compiled?,
+            }
+
+            Ok(mo_file)
+        })
+        .collect()
+}
+
+/// The language codes in the text of a `LINGUAS` file, which are separated
+/// by blank space, with comments starting with `#`.
+fn languages(linguas: &str) -> impl Iterator<Item = &str> {
+    linguas
+        .lines()
+        .map(|line| line.split('#').next().unwrap())
+        .flat_map(str::split_whitespace)
+}
+
+/// Compiles a catalog without `msgfmt`. Fuzzy and untranslated messages are
+/// left out like `msgfmt` does, though the catalog is not checked.
+pub fn write_mo<P: AsRef<Path>, Q: AsRef<Path>>(po_file: P, mo_file: Q) -> io::Result<()> {
+    let messages = parse_po(&fs::read_to_string(po_file)?);
+
+    fs::write(mo_file, encode_mo(&messages))
+}
+
+/// One message of a catalog as it is read.
+#[derive(Debug, Default)]
+struct Entry {
+    fuzzy: bool,
+    context: Option<String>,
+    id: Option<String>,
+    id_plural: Option<String>,
+    /// The translations by their plural form, or only the first without one.
+    strings: BTreeMap<usize, String>,
+}
+
+/// The part of an entry which continued lines are appended to.
+#[derive(Debug, Copy, Clone)]
+enum Field {
+    Context,
+    Id,
+    IdPlural,
+    String(usize),
+}
+
+impl Entry {
+    fn field(&mut self, field: Field) -> &mut String {
+        match field {
+            Field::Context => self.context.get_or_insert_with(String::new),
+            Field::Id => self.id.get_or_insert_with(String::new),
+            Field::IdPlural => self.id_plural.get_or_insert_with(String::new),
+            Field::String(index) => self.strings.entry(index).or_default(),
+        }
+    }
+}
+
+/// The translated messages of a catalog, by their original strings as they
+/// are looked up. A context is joined to the `msgid` with `\x04`, and plural
+/// forms are joined with `\0`.
+fn parse_po(text: &str) -> BTreeMap<String, String> {
+    let mut entries = vec![Entry::default()];
+    let mut field = None;
+
+    for line in text.lines().map(str::trim) {
+        let entry = entries.last_mut().unwrap();
+
+        if let Some(flags) = line.strip_prefix("#,") {
+            if entry.id.is_some() {
+                entries.push(Entry::default());
+            }
+
+            let entry = entries.last_mut().unwrap();
+            entry.fuzzy = flags.split(',').any(|flag| flag.trim() == "fuzzy");
+            continue;
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else if line.starts_with('"') {
+            if let Some(field) = field {
+                entry.field(field).push_str(&unquote(line));
+            }
+            continue;
+        }
+
+        let (keyword, string) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let next = match keyword {
+            "msgctxt" => Field::Context,
+            "msgid" => Field::Id,
+            "msgid_plural" => Field::IdPlural,
+            "msgstr" => Field::String(0),
+            _ => match keyword
+                .strip_prefix("msgstr[")
+                .and_then(|index| index.strip_suffix(']'))
+                .and_then(|index| index.parse().ok())
+            {
+                Some(index) => Field::String(index),
+                None => continue,
+            },
+        };
+
+        // A new entry starts at its context or `msgid`.
+        if matches!(next, Field::Context | Field::Id) && entry.id.is_some() {
+            entries.push(Entry::default());
+        }
+
+        *entries.last_mut().unwrap().field(next) = unquote(string.trim());
+        field = Some(next);
+    }
+
+    entries
+        .into_iter()
+        .filter_map(|entry| {
+            let id = entry.id?;
+            let translated = !entry.strings.is_empty()
+                && entry.strings.values().all(|string| !string.is_empty());
+
+            // The header has the empty `msgid`, and is kept even while fuzzy.
+            if !translated || (entry.fuzzy && !id.is_empty()) {
+                return None;
+            }
+
+            let mut original = match entry.context {
+                Some(context) => format!("{}\x04{}", context, id),
+                None => id,
+            };
+            if let Some(id_plural) = entry.id_plural {
+                original = format!("{}\0{}", original, id_plural);
+            }
+
+            let strings = entry.strings.into_values().collect::<Vec<_>>();
+            Some((original, strings.join("\0")))
+        })
+        .collect()
+}
+
+/// Reads the text of a quoted string, such as `"Hello\n"`.
+fn unquote(quoted: &str) -> String {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|quoted| quoted.strip_suffix('"'))
+        .unwrap_or(quoted);
+    let mut string = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            string.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => string.push('\n'),
+            Some('t') => string.push('\t'),
+            Some('r') => string.push('\r'),
+            Some(c) => string.push(c),
+            None => (),
+        }
+    }
+
+    string
+}
+
+/// The binary catalog of `messages`, in the little-endian layout described
+/// in the gettext manual, without the optional hash table.
+fn encode_mo(messages: &BTreeMap<String, String>) -> Vec<u8> {
+    const HEADER_SIZE: usize = 28;
+
+    let count = messages.len();
+    let originals_offset = HEADER_SIZE;
+    let translations_offset = originals_offset + count * 8;
+    let hash_offset = translations_offset + count * 8;
+    let mut strings_offset = hash_offset;
+    let mut tables = Vec::new();
+    let mut strings = Vec::new();
+
+    // Originals are all listed first, sorted as the map is, then the
+    // translations in the same order.
+    for string in messages.keys().chain(messages.values()) {
+        tables.push((string.len() as u32, strings_offset as u32));
+        strings.extend_from_slice(string.as_bytes());
+        strings.push(0);
+        strings_offset += string.len() + 1;
+    }
+
+    let mut mo = Vec::with_capacity(strings_offset);
+
+    for word in [
+        0x950412de,
+        0,
+        count as u32,
+        originals_offset as u32,
+        translations_offset as u32,
+        0,
+        hash_offset as u32,
+    ] {
+        mo.extend_from_slice(&u32::to_le_bytes(word));
+    }
+    for (length, offset) in tables {
+        mo.extend_from_slice(&length.to_le_bytes());
+        mo.extend_from_slice(&offset.to_le_bytes());
+    }
+    mo.extend_from_slice(&strings);
+
+    mo
+}
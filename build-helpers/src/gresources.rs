@@ -1,5 +1,11 @@
+use crate::common::{rerun_if_changed, run_compiler, CompileError};
+use glob::{glob, MatchOptions, Pattern};
 use serde::Deserialize;
-use std::{fs, path::Path, process::Command};
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+    process::Command,
+};
 use strong_xml::{XmlRead, XmlWrite};
 use strum;
 
@@ -56,6 +62,219 @@ impl File {
             preprocess,
         }
     }
+
+    /// Fills in whether the file is compressed and how it is preprocessed,
+    /// where not given, by its extension. Text is compressed and XML has its
+    /// blank space stripped, while images and other formats which are already
+    /// compressed are left as they are.
+    pub fn with_defaults(mut self) -> Self {
+        let extension = Path::new(&self.path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        let (compressed, preprocess) = match extension.as_deref() {
+            Some("ui" | "xml" | "svg") => (Some(true), Some(Preprocess::XmlStripBlanks)),
+            Some("css" | "json" | "txt" | "js") => (Some(true), None),
+            _ => (None, None),
+        };
+
+        self.compressed = self.compressed.or(compressed);
+        self.preprocess = self.preprocess.or(preprocess);
+        self
+    }
+
+    /// Whether the path is a glob pattern, such as `icons/**/*.svg`, rather
+    /// than a single file.
+    pub fn is_glob(&self) -> bool {
+        self.path.contains(['*', '?', '['])
+    }
+
+    /// Expands a glob path into a file for each match below `src_dir`, in the
+    /// order they are matched, with the same attributes. Each alias is the
+    /// path from the first directory with a pattern, so `icons/**/*.svg`
+    /// matching `icons/actions/add.svg` is aliased `actions/add.svg`, or the
+    /// alias with `{}` replaced by it. Other paths are kept as they are.
+    pub fn expand<P: AsRef<Path>>(&self, src_dir: P) -> Vec<Self> {
+        if !self.is_glob() {
+            return vec![self.clone()];
+        }
+
+        let pattern = src_dir.as_ref().join(&self.path);
+        let base = glob_base(&pattern);
+
+        rerun_if_changed(&base);
+
+        glob(pattern.to_str().unwrap())
+            .unwrap()
+            .map(|file_path| {
+                let file_path = file_path.unwrap();
+                let alias = glob_alias(&base, &file_path, self.alias.as_deref().or(Some("{}")));
+
+                Self::new(
+                    file_path.to_str().unwrap().to_owned(),
+                    alias,
+                    self.compressed,
+                    self.preprocess.clone(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Collects the paths of the files below `dir_path`, from it.
+pub(crate) fn walk_dir(
+    dir_path: &Path,
+    relative: &Path,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir_path.join(relative))? {
+        let entry = entry?;
+        let relative = relative.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            walk_dir(dir_path, &relative, files)?;
+        } else {
+            files.push(relative);
+        }
+    }
+
+    Ok(())
+}
+
+/// The directories of a glob pattern before the first one with a pattern.
+pub(crate) fn glob_base(pattern: &Path) -> PathBuf {
+    pattern
+        .parent()
+        .unwrap_or(pattern)
+        .components()
+        .take_while(|component| match component {
+            Component::Normal(part) => !part.to_string_lossy().contains(['*', '?', '[']),
+            _ => true,
+        })
+        .collect()
+}
+
+/// The alias of `file_path` matched by a glob starting at `base`, with `{}`
+/// replaced by the path from `base`.
+pub(crate) fn glob_alias(base: &Path, file_path: &Path, alias: Option<&str>) -> Option<String> {
+    let relative = file_path.strip_prefix(base).unwrap_or(file_path);
+    // Aliases are resource paths, which are always separated by slashes.
+    let relative = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    alias.map(|alias| alias.replace("{}", &relative))
+}
+
+/// Which files [`GResource::from_dir`] adds, by glob patterns matched against
+/// their paths from the directory, such as `icons/**/*.svg`. A `*` does not
+/// match across directories, so `**/` is needed to match at any depth.
+#[derive(Debug, Default, Clone)]
+pub struct DirOptions {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl DirOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only adds files matching one of the included patterns, or every file
+    /// if none are given.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(Pattern::new(pattern).unwrap());
+        self
+    }
+
+    /// Leaves out files matching the pattern, even if they are included.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(Pattern::new(pattern).unwrap());
+        self
+    }
+
+    fn matches(&self, relative: &str) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        let matches = |pattern: &Pattern| pattern.matches_with(relative, options);
+
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+/// Builds [`GResources`] in a build script rather than writing the XML, with
+/// [`GResources::builder`]. Each [`prefix`](Self::prefix) starts a resource
+/// which the files after it are added to, and the other methods change the
+/// last added file.
+///
+/// ```
+/// use build_helpers::gresources::{GResources, Preprocess};
+///
+/// let gresources = GResources::builder()
+///     .prefix("/com/example/project/ui")
+///     .add_file("ui/window.ui")
+///     .compress(true)
+///     .preprocess(Preprocess::XmlStripBlanks)
+///     .prefix("/com/example/project/icons")
+///     .add_file("icons/**/*.svg")
+///     .alias("scalable/{}")
+///     .build();
+///
+/// assert_eq!(gresources.entries.len(), 2);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct GResourcesBuilder {
+    entries: Vec<GResource>,
+}
+
+impl GResourcesBuilder {
+    /// Starts a resource with the files after this under `prefix`.
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.entries.push(GResource::new(prefix.into()));
+        self
+    }
+
+    /// Adds a file, or a glob which is expanded when the resources are, see
+    /// [`File::expand`]. The prefix is `/` if none was given before it.
+    pub fn add_file<S: Into<String>>(mut self, path: S) -> Self {
+        if self.entries.is_empty() {
+            self = self.prefix("/");
+        }
+
+        let entry = self.entries.last_mut().unwrap();
+        entry.files.push(File::new(path.into(), None, None, None));
+        self
+    }
+
+    pub fn alias<S: Into<String>>(mut self, alias: S) -> Self {
+        self.last_file().alias = Some(alias.into());
+        self
+    }
+
+    pub fn compress(mut self, compressed: bool) -> Self {
+        self.last_file().compressed = Some(compressed);
+        self
+    }
+
+    pub fn preprocess(mut self, preprocess: Preprocess) -> Self {
+        self.last_file().preprocess = Some(preprocess);
+        self
+    }
+
+    pub fn build(self) -> GResources {
+        GResources::from_iter(self.entries)
+    }
+
+    fn last_file(&mut self) -> &mut File {
+        self.entries
+            .last_mut()
+            .and_then(|entry| entry.files.last_mut())
+            .expect("no file was added to change")
+    }
 }
 
 impl GResources {
@@ -63,29 +282,60 @@ impl GResources {
         Self::default()
     }
 
+    pub fn builder() -> GResourcesBuilder {
+        GResourcesBuilder::default()
+    }
+
+    /// Expands the glob paths of every resource, see [`File::expand`].
+    pub fn expand<P: AsRef<Path>>(&self, src_dir: P) -> Self {
+        Self::from_iter(self.entries.iter().map(|entry| entry.expand(&src_dir)))
+    }
+
     pub fn write<P: AsRef<Path>>(&self, dest_file: P) -> std::io::Result<()> {
         fs::write(dest_file, self.to_string().unwrap())
     }
 
+    /// Compiles the resources with files from the current directory, see
+    /// [`compile_resources`], panicking with the compiler's errors.
     pub fn compile<P: AsRef<Path>>(&self, dest_file: P) {
-        let dest_file = dest_file.as_ref();
-        let xml_path = dest_file.with_extension("").with_extension("gresource.xml");
+        if let Err(error) = compile_resources(self, ".", dest_file) {
+            panic!("{}", error);
+        }
+    }
+}
+
+/// Writes the XML of `gresources` next to `dest_file` and compiles it there,
+/// with the relative paths of the files loaded from `source_dir`. Returns
+/// the path of the compiled bundle, for `include_bytes!` or
+/// `gio::Resource::load`.
+pub fn compile_resources<P: AsRef<Path>, Q: AsRef<Path>>(
+    gresources: &GResources,
+    source_dir: P,
+    dest_file: Q,
+) -> Result<PathBuf, CompileError> {
+    let dest_file = dest_file.as_ref();
+    let xml_path = dest_file.with_extension("").with_extension("gresource.xml");
+
+    if let Some(dest_dir) = dest_file.parent() {
+        fs::create_dir_all(dest_dir)?;
+    }
 
-        self.write(&xml_path).unwrap();
+    gresources.write(&xml_path)?;
 
-        let status = Command::new("glib-compile-resources")
+    for file in gresources.entries.iter().flat_map(|entry| &entry.files) {
+        rerun_if_changed(source_dir.as_ref().join(&file.path));
+    }
+
+    run_compiler(
+        Command::new("glib-compile-resources")
+            .arg("--sourcedir")
+            .arg(source_dir.as_ref())
             .arg("--target")
             .arg(dest_file)
-            .arg(xml_path)
-            .status()
-            .unwrap();
+            .arg(&xml_path),
+    )?;
 
-        assert!(
-            status.success(),
-            "glib-compile-resources failed with exit status {}",
-            status
-        );
-    }
+    Ok(dest_file.to_owned())
 }
 
 impl FromIterator<GResource> for GResources {
@@ -110,13 +360,68 @@ impl GResource {
             files: Vec::from_iter(files),
         }
     }
+
+    /// Adds the files below `dir_path` which `options` allow, sorted by their
+    /// paths so that the compiled resources are the same on every build. Each
+    /// is aliased by its path from the directory, and is compressed and
+    /// preprocessed as suits its extension, see [`File::with_defaults`].
+    pub fn from_dir<P: AsRef<Path>>(
+        prefix: String,
+        dir_path: P,
+        options: &DirOptions,
+    ) -> io::Result<Self> {
+        let dir_path = dir_path.as_ref();
+        let mut relatives = Vec::new();
+
+        rerun_if_changed(dir_path);
+        walk_dir(dir_path, Path::new(""), &mut relatives)?;
+
+        let mut relatives = relatives
+            .into_iter()
+            .map(|relative| {
+                // Aliases are resource paths, which are always separated by slashes.
+                let alias = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                (alias, relative)
+            })
+            .filter(|(alias, _)| options.matches(alias))
+            .collect::<Vec<_>>();
+
+        relatives.sort();
+
+        let files = relatives.into_iter().map(|(alias, relative)| {
+            let file_path = dir_path.join(relative);
+            File::new(
+                file_path.to_str().unwrap().to_owned(),
+                Some(alias),
+                None,
+                None,
+            )
+            .with_defaults()
+        });
+
+        Ok(Self::from_iter(prefix, files))
+    }
+
+    /// Expands the glob paths of the files, see [`File::expand`].
+    pub fn expand<P: AsRef<Path>>(&self, src_dir: P) -> Self {
+        let files = self.files.iter().flat_map(|file| file.expand(&src_dir));
+        Self::from_iter(self.prefix.clone(), files)
+    }
 }
 
 pub mod manifest {
-    use crate::gresources::{File, GResource, GResources, Preprocess};
+    use crate::common::rerun_if_changed;
+    use crate::gresources::{glob_alias, glob_base, File, GResource, GResources, Preprocess};
     use glob::glob;
     use serde::Deserialize;
-    use std::{collections::BTreeMap, path::Path};
+    use std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    };
 
     #[derive(Debug, Deserialize)]
     pub struct GResourceFilesDetail {
@@ -134,6 +439,7 @@ pub mod manifest {
 
     pub struct GResourceFilesDetailIter<'a> {
         inner: &'a GResourceFilesDetail,
+        base: PathBuf,
         glob: glob::Paths,
     }
 
@@ -148,9 +454,15 @@ pub mod manifest {
 
     impl<'a> GResourceFilesDetailIter<'a> {
         pub fn new<P: AsRef<Path>>(inner: &'a GResourceFilesDetail, src_dir: P) -> Self {
+            let pattern = src_dir.as_ref().join(&inner.glob);
+            let base = glob_base(&pattern);
+
+            rerun_if_changed(&base);
+
             Self {
                 inner,
-                glob: glob(src_dir.as_ref().join(&inner.glob).to_str().unwrap()).unwrap(),
+                base,
+                glob: glob(pattern.to_str().unwrap()).unwrap(),
             }
         }
     }
@@ -160,12 +472,7 @@ pub mod manifest {
 
         fn next(&mut self) -> Option<Self::Item> {
             let file_path = self.glob.next()?.unwrap();
-            let file_name = file_path.file_name().unwrap().to_str().unwrap();
-            let alias = self
-                .inner
-                .alias
-                .clone()
-                .map(|alias| alias.replace("{}", file_name));
+            let alias = glob_alias(&self.base, &file_path, self.inner.alias.as_deref());
 
             Some(File::new(
                 file_path.to_str().to_owned().unwrap().to_string(),
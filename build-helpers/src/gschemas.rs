@@ -0,0 +1,259 @@
+use crate::common::{rerun_if_changed, run_compiler, CompileError};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use strong_xml::XmlRead;
+
+#[derive(Debug, Clone, PartialEq, XmlRead)]
+#[xml(tag = "schemalist")]
+pub struct SchemaList {
+    #[xml(child = "enum")]
+    pub enums: Vec<SchemaEnum>,
+    #[xml(child = "schema")]
+    pub schemas: Vec<Schema>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlRead)]
+#[xml(tag = "enum")]
+pub struct SchemaEnum {
+    #[xml(attr = "id")]
+    pub id: String,
+    #[xml(child = "value")]
+    pub values: Vec<EnumValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlRead)]
+#[xml(tag = "value")]
+pub struct EnumValue {
+    #[xml(attr = "nick")]
+    pub nick: String,
+    #[xml(attr = "value")]
+    pub value: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlRead)]
+#[xml(tag = "schema")]
+pub struct Schema {
+    #[xml(attr = "id")]
+    pub id: String,
+    #[xml(child = "key")]
+    pub keys: Vec<Key>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlRead)]
+#[xml(tag = "key")]
+pub struct Key {
+    #[xml(attr = "name")]
+    pub name: String,
+    /// The GVariant type, unless it is an enum.
+    #[xml(default, attr = "type")]
+    pub ty: Option<String>,
+    #[xml(default, attr = "enum")]
+    pub enum_id: Option<String>,
+    #[xml(default, flatten_text = "default")]
+    pub default: Option<String>,
+    #[xml(default, child = "range")]
+    pub range: Option<Range>,
+}
+
+#[derive(Debug, Clone, PartialEq, XmlRead)]
+#[xml(tag = "range")]
+pub struct Range {
+    #[xml(attr = "min")]
+    pub min: String,
+    #[xml(attr = "max")]
+    pub max: String,
+}
+
+impl SchemaList {
+    /// Finds the mistakes in the keys which would otherwise only be found
+    /// when the application reads them, or by `glib-compile-schemas` with
+    /// less pointed errors. Defaults of types other than the basic ones, such
+    /// as arrays and tuples, are left to the compiler.
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for schema in &self.schemas {
+            let mut names = HashSet::new();
+
+            for key in &schema.keys {
+                let mut error = |message: String| {
+                    errors.push(format!("{}: key `{}`: {}", schema.id, key.name, message))
+                };
+
+                if !names.insert(&key.name) {
+                    error("is defined more than once".to_owned());
+                }
+
+                match &key.default {
+                    Some(default) => {
+                        if let Err(message) = self.check_default(key, default.trim()) {
+                            error(message);
+                        }
+                    }
+                    None => error("has no default".to_owned()),
+                }
+
+                if let Some(range) = &key.range {
+                    if let Err(message) = check_range(key.ty.as_deref().unwrap_or_default(), range)
+                    {
+                        error(message);
+                    }
+                }
+            }
+        }
+
+        errors
+    }
+
+    fn check_default(&self, key: &Key, default: &str) -> Result<(), String> {
+        let ty = match (&key.ty, &key.enum_id) {
+            (Some(ty), None) => ty.as_str(),
+            (None, Some(enum_id)) => return self.check_enum_default(enum_id, default),
+            _ => return Err("needs either a `type` or an `enum`".to_owned()),
+        };
+
+        match ty {
+            "b" if !matches!(default, "true" | "false") => {
+                Err(format!("default {} is not `true` or `false`", default))
+            }
+            "s" if !is_quoted(default) => {
+                Err(format!("default {} is not a quoted string", default))
+            }
+            "d" if default.parse::<f64>().is_err() => {
+                Err(format!("default {} is not a number", default))
+            }
+            "y" | "n" | "q" | "i" | "u" | "x" | "t" => {
+                let value = parse_integer(ty, default)?;
+
+                match &key.range {
+                    Some(range) => {
+                        let (min, max) = (
+                            parse_integer(ty, &range.min)?,
+                            parse_integer(ty, &range.max)?,
+                        );
+
+                        // An inverted range is reported by itself.
+                        match min > max || (min..=max).contains(&value) {
+                            true => Ok(()),
+                            false => Err(format!(
+                                "default {} is outside the range {} to {}",
+                                default, range.min, range.max
+                            )),
+                        }
+                    }
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_enum_default(&self, enum_id: &str, default: &str) -> Result<(), String> {
+        let schema_enum = self
+            .enums
+            .iter()
+            .find(|schema_enum| schema_enum.id == enum_id)
+            .ok_or_else(|| format!("enum `{}` is not defined", enum_id))?;
+        let nick = default.trim_matches(|c| c == '\'' || c == '"');
+
+        match is_quoted(default) && schema_enum.values.iter().any(|value| value.nick == nick) {
+            true => Ok(()),
+            false => Err(format!(
+                "default {} is not one of the quoted nicks of `{}`",
+                default, enum_id
+            )),
+        }
+    }
+}
+
+fn check_range(ty: &str, range: &Range) -> Result<(), String> {
+    match ty {
+        "y" | "n" | "q" | "i" | "u" | "x" | "t" => {
+            match parse_integer(ty, &range.min)? <= parse_integer(ty, &range.max)? {
+                true => Ok(()),
+                false => Err(format!(
+                    "range minimum {} is above its maximum {}",
+                    range.min, range.max
+                )),
+            }
+        }
+        "d" => Ok(()),
+        _ => Err(format!("a range is not allowed for type `{}`", ty)),
+    }
+}
+
+/// Reads an integer of the GVariant type `ty`, checking that it fits.
+fn parse_integer(ty: &str, text: &str) -> Result<i128, String> {
+    let (min, max) = match ty {
+        "y" => (u8::MIN as i128, u8::MAX as i128),
+        "n" => (i16::MIN as i128, i16::MAX as i128),
+        "q" => (u16::MIN as i128, u16::MAX as i128),
+        "i" => (i32::MIN as i128, i32::MAX as i128),
+        "u" => (u32::MIN as i128, u32::MAX as i128),
+        "x" => (i64::MIN as i128, i64::MAX as i128),
+        _ => (u64::MIN as i128, u64::MAX as i128),
+    };
+
+    match text.trim().parse::<i128>() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        Ok(_) => Err(format!("{} does not fit in type `{}`", text, ty)),
+        Err(_) => Err(format!("{} is not an integer", text)),
+    }
+}
+
+fn is_quoted(text: &str) -> bool {
+    text.len() >= 2
+        && ((text.starts_with('\'') && text.ends_with('\''))
+            || (text.starts_with('"') && text.ends_with('"')))
+}
+
+/// Validates the `*.gschema.xml` files in `source_dir`, see
+/// [`SchemaList::validate`], then compiles them into `gschemas.compiled` in
+/// `target_dir` so that they can be used without being installed. Returns
+/// the path of the compiled schemas, and tells Cargo to build again when
+/// any of them change.
+pub fn compile_schemas<P: AsRef<Path>, Q: AsRef<Path>>(
+    source_dir: P,
+    target_dir: Q,
+) -> Result<PathBuf, CompileError> {
+    let source_dir = source_dir.as_ref();
+    let target_dir = target_dir.as_ref();
+    let mut schema_files = fs::read_dir(source_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The directory is watched so that added schemas are compiled too.
+    rerun_if_changed(source_dir);
+    schema_files.retain(|path| path.to_string_lossy().ends_with(".gschema.xml"));
+    schema_files.sort();
+
+    for schema_file in &schema_files {
+        let errors = match SchemaList::from_str(&fs::read_to_string(schema_file)?) {
+            Ok(schema_list) => schema_list.validate(),
+            Err(error) => vec![error.to_string()],
+        };
+
+        if !errors.is_empty() {
+            return Err(CompileError::Invalid {
+                path: schema_file.clone(),
+                errors,
+            });
+        }
+    }
+
+    fs::create_dir_all(target_dir)?;
+
+    run_compiler(
+        Command::new("glib-compile-schemas")
+            .arg("--strict")
+            .arg("--targetdir")
+            .arg(target_dir)
+            .arg(source_dir),
+    )?;
+
+    Ok(target_dir.join("gschemas.compiled"))
+}
@@ -0,0 +1,81 @@
+use crate::common::{rerun_if_changed, CompileError};
+use resvg::{tiny_skia, usvg};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The sizes of the `hicolor` icon theme which application icons are
+/// rendered at, each also at twice the scale for `@2` directories.
+pub const HICOLOR_SIZES: [u32; 8] = [16, 24, 32, 48, 64, 128, 256, 512];
+
+/// Renders the icon `svg_file` into `target_dir` in the layout of the
+/// `hicolor` icon theme, as `<size>x<size>/apps/<name>.png` for each of
+/// [`HICOLOR_SIZES`] and again in `<size>x<size>@2` at twice the pixels, with
+/// the SVG itself in `scalable/apps`. The directory can be installed into
+/// `/usr/share/icons/hicolor`, or added to the resources under the
+/// application's `icons` path where GTK finds them. Returns the paths of the
+/// files, and tells Cargo to build again when the SVG changes.
+pub fn render_icon<P: AsRef<Path>, Q: AsRef<Path>>(
+    svg_file: P,
+    name: &str,
+    target_dir: Q,
+) -> Result<Vec<PathBuf>, CompileError> {
+    let svg_file = svg_file.as_ref();
+    let target_dir = target_dir.as_ref();
+    let invalid = |error: String| CompileError::Invalid {
+        path: svg_file.to_owned(),
+        errors: vec![error],
+    };
+
+    rerun_if_changed(svg_file);
+
+    let svg = fs::read(svg_file)?;
+    let tree = usvg::Tree::from_data(&svg, &usvg::Options::default())
+        .map_err(|error| invalid(error.to_string()))?;
+    let mut icon_files = Vec::new();
+
+    for size in HICOLOR_SIZES {
+        for (dir_name, pixels) in [
+            (format!("{0}x{0}", size), size),
+            (format!("{0}x{0}@2", size), size * 2),
+        ] {
+            let icon_file = target_dir
+                .join(dir_name)
+                .join("apps")
+                .join(format!("{}.png", name));
+
+            fs::create_dir_all(icon_file.parent().unwrap())?;
+            fs::write(&icon_file, render_png(&tree, pixels).map_err(invalid)?)?;
+            icon_files.push(icon_file);
+        }
+    }
+
+    let scalable_file = target_dir
+        .join("scalable/apps")
+        .join(format!("{}.svg", name));
+
+    fs::create_dir_all(scalable_file.parent().unwrap())?;
+    fs::copy(svg_file, &scalable_file)?;
+    icon_files.push(scalable_file);
+
+    Ok(icon_files)
+}
+
+/// Renders the SVG `tree` as a square PNG of `pixels`, scaled to fit and
+/// centered if it is not square itself.
+fn render_png(tree: &usvg::Tree, pixels: u32) -> Result<Vec<u8>, String> {
+    let mut pixmap = tiny_skia::Pixmap::new(pixels, pixels)
+        .ok_or_else(|| format!("{} pixels is not a size to render at", pixels))?;
+    let size = tree.size();
+    let scale = pixels as f32 / size.width().max(size.height());
+    let transform = tiny_skia::Transform::from_translate(
+        (pixels as f32 - size.width() * scale) / 2.0,
+        (pixels as f32 - size.height() * scale) / 2.0,
+    )
+    .pre_scale(scale, scale);
+
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    pixmap.encode_png().map_err(|error| error.to_string())
+}
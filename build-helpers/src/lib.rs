@@ -1,6 +1,16 @@
+pub mod appstream;
+pub mod blueprint;
 pub mod common;
+pub mod completions;
+pub mod desktop;
 pub mod fdependencies;
+pub mod flatpak;
+pub mod gettext;
 pub mod gresources;
+pub mod gschemas;
+pub mod icons;
+pub mod metadata;
+pub mod pkgconfig;
 
 pub mod manifest {
     pub use crate::fdependencies::manifest::*;
@@ -8,8 +18,18 @@ pub mod manifest {
 }
 
 pub mod prelude {
+    pub use crate::appstream::*;
+    pub use crate::blueprint::*;
     pub use crate::common::*;
+    pub use crate::completions::*;
+    pub use crate::desktop::*;
     pub use crate::fdependencies::*;
+    pub use crate::flatpak::*;
+    pub use crate::gettext::*;
     pub use crate::gresources::*;
+    pub use crate::gschemas::*;
+    pub use crate::icons::*;
     pub use crate::manifest::*;
+    pub use crate::metadata::*;
+    pub use crate::pkgconfig::*;
 }
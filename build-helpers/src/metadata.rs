@@ -0,0 +1,93 @@
+use crate::common::{rerun_if_changed, CompileError};
+use serde::de::DeserializeOwned;
+use serde_path_to_error::Segment;
+use std::{fmt::Write, fs, path::Path};
+
+/// The table of the manifest which the metadata is under.
+const METADATA_TABLE: &str = "package.metadata";
+
+/// Reads the `[package.metadata]` of the manifest `manifest_file` into `T`,
+/// which declares the sections the build script uses. All of them are
+/// checked before anything is built, and each key which `T` does not have, as
+/// well as a value of the wrong type or a missing field, is reported by its
+/// path in the manifest, such as `package.metadata.gresources.ui.prefix`.
+/// Sections which `T` does not have are left to the other tools which read
+/// them, such as `cargo-deb`, and keys in tables flattened into maps cannot
+/// be told apart from entries. Cargo is told to rerun the build script when
+/// the manifest changes.
+pub fn read_metadata<T: DeserializeOwned, P: AsRef<Path>>(
+    manifest_file: P,
+) -> Result<T, CompileError> {
+    let manifest_file = manifest_file.as_ref();
+    let invalid = |errors| CompileError::Invalid {
+        path: manifest_file.to_owned(),
+        errors,
+    };
+
+    rerun_if_changed(manifest_file);
+
+    let manifest = toml::from_str::<toml::Value>(&fs::read_to_string(manifest_file)?)
+        .map_err(|error| invalid(vec![error.to_string()]))?;
+    let metadata = manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .cloned()
+        .ok_or_else(|| invalid(vec![format!("`{}` is missing", METADATA_TABLE)]))?;
+    let mut errors = Vec::new();
+    let mut ignored = |path: serde_ignored::Path| {
+        if !matches!(
+            path,
+            serde_ignored::Path::Map {
+                parent: serde_ignored::Path::Root,
+                ..
+            }
+        ) {
+            errors.push(format!("`{}` is not a known key", ignored_path(&path)));
+        }
+    };
+    let result =
+        serde_path_to_error::deserialize(serde_ignored::Deserializer::new(metadata, &mut ignored));
+
+    match result {
+        Ok(metadata) if errors.is_empty() => Ok(metadata),
+        Ok(_) => Err(invalid(errors)),
+        Err(error) => {
+            let mut path = METADATA_TABLE.to_owned();
+
+            for segment in error.path().iter() {
+                match segment {
+                    Segment::Seq { index } => write!(path, "[{}]", index).unwrap(),
+                    Segment::Map { key } => write!(path, ".{}", key).unwrap(),
+                    Segment::Enum { variant } => write!(path, ".{}", variant).unwrap(),
+                    Segment::Unknown => path.push_str(".?"),
+                }
+            }
+
+            let message = error.inner().to_string();
+            // The path toml adds is less precise than the one given here.
+            let message = match message.rfind(" for key `") {
+                Some(index) => &message[..index],
+                None => &message,
+            };
+
+            errors.insert(0, format!("`{}`: {}", path, message));
+
+            Err(invalid(errors))
+        }
+    }
+}
+
+/// The path in the manifest of a key which was not deserialized, written
+/// like those of [`serde_path_to_error`].
+fn ignored_path(path: &serde_ignored::Path) -> String {
+    match path {
+        serde_ignored::Path::Root => METADATA_TABLE.to_owned(),
+        serde_ignored::Path::Seq { parent, index } => {
+            format!("{}[{}]", ignored_path(parent), index)
+        }
+        serde_ignored::Path::Map { parent, key } => format!("{}.{}", ignored_path(parent), key),
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_path(parent),
+    }
+}
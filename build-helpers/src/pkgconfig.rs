@@ -0,0 +1,205 @@
+use std::{env, error::Error, fmt, fs, io, process::Command};
+
+/// A library which has to be installed to build, as it is found by
+/// `pkg-config`.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemLibrary<'a> {
+    /// The name of its `.pc` file, such as `gtk4`.
+    pub module: &'a str,
+    pub min_version: &'a str,
+    /// The packages with its headers, by the `ID` of each distribution in
+    /// `/etc/os-release`.
+    pub packages: &'a [(&'a str, &'a str)],
+}
+
+/// A library which is not installed, or is older than required.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingLibrary {
+    pub module: String,
+    pub min_version: String,
+    /// The version which is installed, if any.
+    pub version: Option<String>,
+    /// The package to install for the distribution being built on.
+    pub package: Option<String>,
+}
+
+/// Why the system libraries could not be found.
+#[derive(Debug)]
+pub enum LibraryError {
+    /// `pkg-config` itself is not installed.
+    PkgConfigNotFound,
+    Io(io::Error),
+    Missing {
+        libraries: Vec<MissingLibrary>,
+        /// The command to install the packages with on the distribution.
+        install_command: Option<&'static str>,
+    },
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::PkgConfigNotFound => write!(
+                f,
+                "pkg-config was not found, it has to be installed and in PATH to build"
+            ),
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Missing {
+                libraries,
+                install_command,
+            } => {
+                write!(f, "libraries required to build are missing")?;
+
+                for library in libraries {
+                    match &library.version {
+                        Some(version) => write!(
+                            f,
+                            "\n    {} {} is installed, but at least {} is required",
+                            library.module, version, library.min_version
+                        )?,
+                        None => write!(
+                            f,
+                            "\n    {} {} or later is not installed",
+                            library.module, library.min_version
+                        )?,
+                    }
+                }
+
+                let packages = libraries
+                    .iter()
+                    .filter_map(|library| library.package.as_deref())
+                    .collect::<Vec<_>>();
+
+                match (install_command, packages.len() == libraries.len()) {
+                    (Some(install_command), true) => write!(
+                        f,
+                        "\ninstall them with:\n    {} {}",
+                        install_command,
+                        packages.join(" ")
+                    ),
+                    _ => write!(
+                        f,
+                        "\ninstall the development packages of your distribution which provide them, \
+                        or set PKG_CONFIG_PATH to where their .pc files are"
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl Error for LibraryError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The commands which install packages, by the `ID` of each distribution.
+const INSTALL_COMMANDS: [(&str, &str); 7] = [
+    ("debian", "sudo apt install"),
+    ("ubuntu", "sudo apt install"),
+    ("fedora", "sudo dnf install"),
+    ("arch", "sudo pacman -S"),
+    ("opensuse", "sudo zypper install"),
+    ("alpine", "sudo apk add"),
+    ("nixos", "nix-shell -p"),
+];
+
+/// Checks with `pkg-config` that each of `libraries` is installed at its
+/// minimum version, so that a missing one is reported by its package for
+/// the distribution, instead of by the linker or a `-sys` crate.
+pub fn check_libraries(libraries: &[SystemLibrary]) -> Result<(), LibraryError> {
+    println!("cargo:rerun-if-env-changed=PKG_CONFIG");
+    println!("cargo:rerun-if-env-changed=PKG_CONFIG_PATH");
+
+    let distributions = match fs::read_to_string("/etc/os-release") {
+        Ok(os_release) => distributions(&os_release),
+        Err(_) => Vec::new(),
+    };
+    let mut missing = Vec::new();
+
+    for library in libraries {
+        let version = module_version(library.module)?;
+
+        if version.is_some() && at_least_version(library.module, library.min_version)? {
+            continue;
+        }
+
+        missing.push(MissingLibrary {
+            module: library.module.to_owned(),
+            min_version: library.min_version.to_owned(),
+            version,
+            package: distributions.iter().find_map(|distribution| {
+                library
+                    .packages
+                    .iter()
+                    .find(|(id, _)| id == distribution)
+                    .map(|(_, package)| (*package).to_owned())
+            }),
+        });
+    }
+
+    match missing.is_empty() {
+        true => Ok(()),
+        false => Err(LibraryError::Missing {
+            libraries: missing,
+            install_command: distributions.iter().find_map(|distribution| {
+                INSTALL_COMMANDS
+                    .iter()
+                    .find(|(id, _)| id == distribution)
+                    .map(|(_, command)| *command)
+            }),
+        }),
+    }
+}
+
+/// The `ID` of the distribution in the text of `/etc/os-release`, followed by
+/// those in `ID_LIKE` which it is derived from.
+pub fn distributions(os_release: &str) -> Vec<String> {
+    let value = |key: &str| {
+        os_release
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(|value| value.trim().trim_matches(['"', '\'']))
+            .unwrap_or_default()
+    };
+
+    value("ID")
+        .split_whitespace()
+        .chain(value("ID_LIKE").split_whitespace())
+        // The variants of openSUSE have IDs like `opensuse-tumbleweed`.
+        .map(|id| id.split('-').next().unwrap().to_owned())
+        .collect()
+}
+
+fn module_version(module: &str) -> Result<Option<String>, LibraryError> {
+    let output = pkg_config().arg("--modversion").arg(module).output();
+
+    match output {
+        Ok(output) if output.status.success() => Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_owned(),
+        )),
+        Ok(_) => Ok(None),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            Err(LibraryError::PkgConfigNotFound)
+        }
+        Err(error) => Err(LibraryError::Io(error)),
+    }
+}
+
+fn at_least_version(module: &str, min_version: &str) -> Result<bool, LibraryError> {
+    pkg_config()
+        .arg(format!("--atleast-version={}", min_version))
+        .arg(module)
+        .status()
+        .map(|status| status.success())
+        .map_err(LibraryError::Io)
+}
+
+/// The `pkg-config` named by `PKG_CONFIG`, like the `pkg-config` crate uses.
+fn pkg_config() -> Command {
+    Command::new(env::var_os("PKG_CONFIG").unwrap_or_else(|| "pkg-config".into()))
+}
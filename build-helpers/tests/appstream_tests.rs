@@ -0,0 +1,214 @@
+use build_helpers::appstream::*;
+use build_helpers::common::CompileError;
+use std::fs;
+use std::path::Path;
+use strong_xml::XmlWrite;
+
+static APP_ID: &str = "com.example.Project";
+
+fn example_detail() -> AppStreamDetail {
+    AppStreamDetail {
+        name: "Project".to_owned(),
+        summary: "Does things & more".to_owned(),
+        description: vec!["It does things.".to_owned()],
+        developer: "Someone".to_owned(),
+        developer_id: Some("com.example".to_owned()),
+        metadata_license: "CC0-1.0".to_owned(),
+        project_license: Some("Apache-2.0".to_owned()),
+        homepage: Some("https://example.com".to_owned()),
+        bugtracker: None,
+        screenshots: vec![ScreenshotDetail {
+            image: "https://example.com/main.png".to_owned(),
+            caption: Some("The window".to_owned()),
+        }],
+        releases: vec![
+            ReleaseDetail {
+                version: "1.1.0".to_owned(),
+                date: "2024-02-01".to_owned(),
+                notes: vec!["Fixed things".to_owned()],
+            },
+            ReleaseDetail {
+                version: "1.0.0".to_owned(),
+                date: "2024-01-01".to_owned(),
+                notes: Vec::new(),
+            },
+        ],
+        changelog: None,
+    }
+}
+
+#[test]
+fn test_to_string() {
+    let component = example_detail().to_component(APP_ID, ".").unwrap();
+
+    assert_eq!(
+        component.to_string().unwrap(),
+        concat!(
+            r#"<component type="desktop-application">"#,
+            r#"<developer id="com.example"><name>Someone</name></developer>"#,
+            "<description><p>It does things.</p></description>",
+            r#"<launchable type="desktop-id">com.example.Project.desktop</launchable>"#,
+            r#"<url type="homepage">https://example.com</url>"#,
+            "<screenshots>",
+            r#"<screenshot type="default">"#,
+            "<image>https://example.com/main.png</image>",
+            "<caption>The window</caption>",
+            "</screenshot>",
+            "</screenshots>",
+            r#"<content_rating type="oars-1.1"/>"#,
+            "<releases>",
+            r#"<release version="1.1.0" date="2024-02-01">"#,
+            "<description><ul><li>Fixed things</li></ul></description>",
+            "</release>",
+            r#"<release version="1.0.0" date="2024-01-01"/>"#,
+            "</releases>",
+            // The order of the elements does not matter.
+            "<id>com.example.Project</id>",
+            "<metadata_license>CC0-1.0</metadata_license>",
+            "<project_license>Apache-2.0</project_license>",
+            "<name>Project</name>",
+            "<summary>Does things &amp; more</summary>",
+            "</component>",
+        )
+    );
+}
+
+#[test]
+fn test_validate() {
+    let mut detail = example_detail();
+
+    assert!(detail
+        .to_component(APP_ID, ".")
+        .unwrap()
+        .validate()
+        .is_empty());
+
+    detail.summary = "Does things.".to_owned();
+    detail.metadata_license = "GPL-3.0".to_owned();
+    detail.releases[1].date = "2024-03-01".to_owned();
+    detail.releases[0].date = "1 Feb 2024".to_owned();
+
+    assert_eq!(
+        detail
+            .to_component("project", ".")
+            .unwrap()
+            .validate()
+            .len(),
+        // The ID, the license, the summary and the date.
+        4
+    );
+
+    detail.releases[0].date = "2024-02-01".to_owned();
+
+    assert!(detail
+        .to_component(APP_ID, ".")
+        .unwrap()
+        .validate()
+        .iter()
+        .any(|error| error.contains("newest first")));
+}
+
+#[test]
+fn test_recommendations() {
+    let mut detail = example_detail();
+
+    assert!(detail
+        .to_component(APP_ID, ".")
+        .unwrap()
+        .recommendations()
+        .is_empty());
+
+    detail.screenshots.clear();
+    detail.releases.clear();
+
+    assert_eq!(
+        detail
+            .to_component(APP_ID, ".")
+            .unwrap()
+            .recommendations()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn test_parse_changelog() {
+    let releases = parse_changelog(concat!(
+        "# Changelog\n",
+        "\n",
+        "## [Unreleased]\n",
+        "- Not yet\n",
+        "\n",
+        "## [1.1.0] - 2024-02-01\n",
+        "### Fixed\n",
+        "- One thing\n",
+        "- Another\n",
+        "\n",
+        "## v1.0.0 (2024-01-01)\n",
+        "\n",
+        "[1.1.0]: https://example.com/compare/v1.0.0...v1.1.0\n",
+    ));
+
+    assert_eq!(
+        releases,
+        [
+            Release {
+                version: "1.1.0".to_owned(),
+                date: Some("2024-02-01".to_owned()),
+                description: Some(Description {
+                    blocks: vec![
+                        Block::Paragraph(Paragraph {
+                            text: "Fixed".to_owned()
+                        }),
+                        Block::List(List {
+                            items: vec!["One thing".to_owned(), "Another".to_owned()]
+                        }),
+                    ]
+                }),
+            },
+            Release {
+                version: "1.0.0".to_owned(),
+                date: Some("2024-01-01".to_owned()),
+                description: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_install_changelog() {
+    let src_dir = Path::new("target/tmp/appstream");
+    let target_dir = src_dir.join("share");
+    let mut detail = example_detail();
+
+    fs::create_dir_all(src_dir).unwrap();
+    fs::write(
+        src_dir.join("CHANGELOG.md"),
+        "## [2.0.0] - 2024-05-01\n- Everything\n",
+    )
+    .unwrap();
+    detail.changelog = Some("CHANGELOG.md".to_owned());
+
+    let metainfo_file = detail.install(APP_ID, src_dir, &target_dir).unwrap();
+    let text = fs::read_to_string(&metainfo_file).unwrap();
+
+    assert_eq!(
+        metainfo_file,
+        target_dir.join("metainfo/com.example.Project.metainfo.xml")
+    );
+    assert!(text.starts_with("<?xml"));
+    assert!(text.contains(r#"<release version="2.0.0" date="2024-05-01">"#));
+    assert!(!text.contains("1.1.0"));
+}
+
+#[test]
+fn test_install_invalid() {
+    let mut detail = example_detail();
+
+    detail.description.clear();
+
+    assert!(matches!(
+        detail.install(APP_ID, ".", "target/tmp/share-invalid"),
+        Err(CompileError::Invalid { .. })
+    ));
+}
@@ -0,0 +1,147 @@
+use build_helpers::common::archive::*;
+use glob::Pattern;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use test_case::test_case;
+
+static TEMP_DIR: &str = "target/tmp/archive";
+
+/// The files of every example archive, under a top directory like those of
+/// release tarballs.
+static FILES: [(&str, &str); 3] = [
+    ("project-1.0/README.md", "Read me."),
+    ("project-1.0/flags/4x3/de.svg", "<svg/>"),
+    ("project-1.0/flags/1x1/de.svg", "<svg/>"),
+];
+
+fn write_tar<W: Write>(writer: W) -> W {
+    let mut builder = tar::Builder::new(writer);
+
+    for (path, contents) in FILES {
+        let mut header = tar::Header::new_gnu();
+
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, path, contents.as_bytes())
+            .unwrap();
+    }
+
+    builder.into_inner().unwrap()
+}
+
+fn write_archive(format: ArchiveFormat) -> PathBuf {
+    let archive_file = Path::new(TEMP_DIR).join(format!("project-1.0.{}", format.extension()));
+
+    fs::create_dir_all(TEMP_DIR).unwrap();
+
+    match format {
+        ArchiveFormat::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(
+                File::create(&archive_file).unwrap(),
+                flate2::Compression::default(),
+            );
+            write_tar(encoder).finish().unwrap();
+        }
+        ArchiveFormat::TarXz => {
+            let tar = write_tar(Vec::new());
+            let mut xz = Vec::new();
+
+            lzma_rs::xz_compress(&mut tar.as_slice(), &mut xz).unwrap();
+            fs::write(&archive_file, xz).unwrap();
+        }
+        ArchiveFormat::Zip => {
+            let mut writer = zip::ZipWriter::new(File::create(&archive_file).unwrap());
+
+            for (path, contents) in FILES {
+                writer
+                    .start_file(path, zip::write::SimpleFileOptions::default())
+                    .unwrap();
+                writer.write_all(contents.as_bytes()).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+    }
+
+    archive_file
+}
+
+#[test_case("https://example.com/project-1.0.tar.gz", Some(ArchiveFormat::TarGz) ; "test tar gz")]
+#[test_case("project.TGZ", Some(ArchiveFormat::TarGz) ; "test tgz")]
+#[test_case("project-1.0.tar.xz", Some(ArchiveFormat::TarXz) ; "test tar xz")]
+#[test_case("project-1.0.zip", Some(ArchiveFormat::Zip) ; "test zip")]
+#[test_case("project-1.0.tar.bz2", None ; "test unsupported")]
+fn test_format_from_name(name: &str, expected: Option<ArchiveFormat>) {
+    assert_eq!(ArchiveFormat::from_name(name), expected);
+}
+
+#[test_case(ArchiveFormat::TarGz ; "test extract tar gz")]
+#[test_case(ArchiveFormat::TarXz ; "test extract tar xz")]
+#[test_case(ArchiveFormat::Zip ; "test extract zip")]
+fn test_extract(format: ArchiveFormat) {
+    let archive_file = write_archive(format);
+    let dest_dir = Path::new(TEMP_DIR).join(format.extension());
+    let options = ExtractOptions {
+        strip_components: 1,
+        include: vec![Pattern::new("flags/**/*.svg").unwrap()],
+    };
+    let files = extract(&archive_file, format, &dest_dir, &options).unwrap();
+
+    assert_eq!(
+        files,
+        [
+            dest_dir.join("flags/1x1/de.svg"),
+            dest_dir.join("flags/4x3/de.svg"),
+        ]
+    );
+    assert_eq!(fs::read_to_string(&files[0]).unwrap(), "<svg/>");
+    assert!(!dest_dir.join("README.md").exists());
+}
+
+#[test]
+fn test_extract_everything() {
+    let archive_file = write_archive(ArchiveFormat::Zip);
+    let dest_dir = Path::new(TEMP_DIR).join("everything");
+    let files = extract(
+        &archive_file,
+        ArchiveFormat::Zip,
+        &dest_dir,
+        &ExtractOptions::default(),
+    )
+    .unwrap();
+
+    assert_eq!(files.len(), FILES.len());
+    assert!(dest_dir.join("project-1.0/README.md").is_file());
+}
+
+#[test]
+fn test_strip_too_many_components() {
+    let archive_file = write_archive(ArchiveFormat::TarGz);
+    let dest_dir = Path::new(TEMP_DIR).join("stripped");
+    let options = ExtractOptions {
+        strip_components: 2,
+        ..ExtractOptions::default()
+    };
+    let files = extract(&archive_file, ArchiveFormat::TarGz, &dest_dir, &options).unwrap();
+
+    // The README is not in a directory deep enough to be kept.
+    assert_eq!(
+        files,
+        [dest_dir.join("1x1/de.svg"), dest_dir.join("4x3/de.svg")]
+    );
+}
+
+#[test]
+fn test_sha256() {
+    let file = Path::new(TEMP_DIR).join("abc.txt");
+
+    fs::create_dir_all(TEMP_DIR).unwrap();
+    fs::write(&file, "abc").unwrap();
+
+    assert_eq!(
+        sha256(&file).unwrap(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+}
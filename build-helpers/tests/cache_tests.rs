@@ -0,0 +1,128 @@
+use build_helpers::common::cache::*;
+use build_helpers::common::git;
+use build_helpers::manifest::ForeignDependenciesDetail;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+static CACHE_DIR: &str = "target/tmp/cache";
+
+#[test]
+fn test_key() {
+    assert_eq!(
+        key("https://example.com/flags.zip", Some("ABC123")),
+        "https-example-com-flags-zip-abc123"
+    );
+    assert_eq!(
+        key("https://example.com/flags.zip", None),
+        "https-example-com-flags-zip"
+    );
+}
+
+#[test]
+fn test_link_or_copy() {
+    let src_file = Path::new("target/tmp/cache-link/src.txt");
+    let dest_file = Path::new("target/tmp/cache-link/nested/dest.txt");
+
+    if src_file.parent().unwrap().exists() {
+        fs::remove_dir_all(src_file.parent().unwrap()).unwrap();
+    }
+
+    fs::create_dir_all(src_file.parent().unwrap()).unwrap();
+    fs::write(src_file, "new").unwrap();
+    fs::create_dir_all(dest_file.parent().unwrap()).unwrap();
+    fs::write(dest_file, "old").unwrap();
+
+    link_or_copy(src_file, dest_file).unwrap();
+
+    assert_eq!(fs::read_to_string(dest_file).unwrap(), "new");
+}
+
+// The variable is only changed by this test, so that it does not race.
+#[test]
+fn test_cached_archive() {
+    let url = "https://example.invalid/flags.zip";
+    let out_dir = Path::new("target/tmp/cached");
+    let cached_file = Path::new(CACHE_DIR).join("archives").join(key(url, None));
+
+    env::set_var(CACHE_DIR_VAR, CACHE_DIR);
+    assert_eq!(cache_dir().as_deref(), Some(Path::new(CACHE_DIR)));
+
+    fs::create_dir_all(cached_file.parent().unwrap()).unwrap();
+    if out_dir.exists() {
+        fs::remove_dir_all(out_dir).unwrap();
+    }
+
+    let mut writer = zip::ZipWriter::new(File::create(&cached_file).unwrap());
+    writer
+        .start_file("de.svg", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(b"<svg/>").unwrap();
+    writer.finish().unwrap();
+
+    let detail: ForeignDependenciesDetail = toml::from_str::<toml::Value>(&format!(
+        "[flags]\narchive = \"{}\"\ncopy = [[\"*.svg\", \"assets/flags\"]]\n",
+        url
+    ))
+    .unwrap()
+    .try_into()
+    .unwrap();
+
+    // The host does not exist, so it can only have come from the cache.
+    detail.update_all_with(out_dir, 1).unwrap();
+
+    assert!(out_dir.join("assets/flags/de.svg").is_file());
+
+    env::set_var(CACHE_DIR_VAR, "");
+    assert_eq!(cache_dir(), None);
+}
+
+#[test]
+fn test_git_mirror() {
+    let tmp_dir = Path::new("target/tmp/git-mirror");
+
+    if tmp_dir.exists() {
+        fs::remove_dir_all(tmp_dir).unwrap();
+    }
+
+    let origin_dir = tmp_dir.join("origin");
+    let mirror_dir = tmp_dir.join("mirror");
+    let repository = git2::Repository::init(&origin_dir).unwrap();
+
+    fs::write(origin_dir.join("de.svg"), "<svg/>").unwrap();
+
+    let mut index = repository.index().unwrap();
+    index.add_path(Path::new("de.svg")).unwrap();
+    let tree = repository.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+    let commit = repository
+        .commit(Some("HEAD"), &signature, &signature, "Add", &tree, &[])
+        .unwrap()
+        .to_string();
+    let url = fs::canonicalize(&origin_dir).unwrap();
+    let url = url.to_str().unwrap();
+
+    git::update_repository(url, &commit, tmp_dir.join("first"), None, Some(&mirror_dir)).unwrap();
+
+    // The origin is gone, so the second clone can only come from the mirror.
+    fs::remove_dir_all(&origin_dir).unwrap();
+    git::update_repository(
+        url,
+        &commit,
+        tmp_dir.join("second"),
+        None,
+        Some(&mirror_dir),
+    )
+    .unwrap();
+
+    assert!(tmp_dir.join("second/de.svg").is_file());
+    assert_eq!(
+        git2::Repository::open(tmp_dir.join("second"))
+            .unwrap()
+            .find_remote("origin")
+            .unwrap()
+            .url(),
+        Some(url)
+    );
+}
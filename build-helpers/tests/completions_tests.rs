@@ -0,0 +1,28 @@
+use build_helpers::completions::*;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn test_install_completions() {
+    let target_dir = Path::new("target/tmp/completions/share");
+    let files = install_completions("project", target_dir).unwrap();
+
+    assert_eq!(
+        files,
+        [
+            target_dir.join("bash-completion/completions/project"),
+            target_dir.join("zsh/site-functions/_project"),
+            target_dir.join("fish/vendor_completions.d/project.fish"),
+        ]
+    );
+
+    for file in &files {
+        let text = fs::read_to_string(file).unwrap();
+
+        assert!(text.contains("COMPLETE="), "{}", file.display());
+    }
+
+    assert!(fs::read_to_string(&files[1])
+        .unwrap()
+        .starts_with("#compdef project"));
+}
@@ -0,0 +1,116 @@
+use build_helpers::common::CompileError;
+use build_helpers::desktop::*;
+use std::fs;
+use std::path::Path;
+
+static APP_ID: &str = "com.example.Project";
+
+fn example_entry() -> DesktopEntry {
+    DesktopEntry {
+        name: "Project".to_owned(),
+        generic_name: None,
+        comment: Some("Does things;\nwell".to_owned()),
+        exec: "project".to_owned(),
+        icon: Some(APP_ID.to_owned()),
+        categories: vec!["Network".to_owned(), "GTK".to_owned()],
+        keywords: vec!["vpn".to_owned()],
+        dbus_activatable: true,
+        startup_notify: false,
+        actions: vec![
+            DesktopAction {
+                id: "quick-connect".to_owned(),
+                name: "Quick Connect".to_owned(),
+                exec: None,
+                icon: None,
+            },
+            DesktopAction {
+                id: "preferences".to_owned(),
+                name: "Preferences".to_owned(),
+                exec: Some("project --preferences".to_owned()),
+                icon: None,
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_to_desktop() {
+    assert_eq!(
+        example_entry().to_desktop(APP_ID),
+        concat!(
+            "[Desktop Entry]\n",
+            "Type=Application\n",
+            "Name=Project\n",
+            "Comment=Does things;\\nwell\n",
+            "Exec=project\n",
+            "Icon=com.example.Project\n",
+            "Terminal=false\n",
+            "Categories=Network;GTK;\n",
+            "Keywords=vpn;\n",
+            "DBusActivatable=true\n",
+            "Actions=quick-connect;preferences;\n",
+            "\n",
+            "[Desktop Action quick-connect]\n",
+            "Name=Quick Connect\n",
+            "Exec=gapplication action com.example.Project quick-connect\n",
+            "\n",
+            "[Desktop Action preferences]\n",
+            "Name=Preferences\n",
+            "Exec=project --preferences\n",
+        )
+    );
+}
+
+#[test]
+fn test_validate() {
+    let mut entry = example_entry();
+
+    assert!(entry.validate(APP_ID).is_empty());
+
+    entry.name = String::new();
+    entry.categories = vec!["GTK".to_owned()];
+    entry.actions[1].id = "quick-connect".to_owned();
+
+    assert_eq!(
+        entry.validate("project").len(),
+        // The name, the categories, the D-Bus name and the repeated action.
+        4
+    );
+
+    entry.dbus_activatable = false;
+
+    assert!(entry
+        .validate(APP_ID)
+        .iter()
+        .any(|error| error.contains("needs `exec`")));
+}
+
+#[test]
+fn test_install() {
+    let target_dir = Path::new("target/tmp/share");
+    let files = example_entry().install(APP_ID, target_dir).unwrap();
+
+    assert_eq!(
+        files,
+        [
+            target_dir.join("applications/com.example.Project.desktop"),
+            target_dir.join("dbus-1/services/com.example.Project.service"),
+        ]
+    );
+    assert_eq!(
+        fs::read_to_string(&files[1]).unwrap(),
+        "[D-BUS Service]\nName=com.example.Project\nExec=project --gapplication-service\n"
+    );
+}
+
+#[test]
+fn test_install_invalid() {
+    let mut entry = example_entry();
+
+    entry.exec = String::new();
+
+    assert!(matches!(
+        entry.install(APP_ID, "target/tmp/share-invalid"),
+        Err(CompileError::Invalid { .. })
+    ));
+}
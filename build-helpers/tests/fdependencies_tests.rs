@@ -0,0 +1,167 @@
+use build_helpers::fdependencies::{newer_version, UpdateError};
+use build_helpers::manifest::ForeignDependenciesDetail;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use test_case::test_case;
+
+#[test_case("6.6.6", &["v6.6.5", "v6.6.6"], None ; "test pinned is latest")]
+#[test_case("6.6.6", &["v6.6.6", "v6.10.0", "v6.7.0"], Some("v6.10.0") ; "test compares numerically")]
+#[test_case("v1.2", &["1.2.1", "1.3-beta"], Some("1.2.1") ; "test leaves out pre-releases")]
+#[test_case("1.0", &["latest", "nightly"], None ; "test leaves out names")]
+#[test_case("main", &["v1.0.0"], None ; "test pinned is not a version")]
+fn test_newer_version(current: &str, tags: &[&str], expected: Option<&str>) {
+    assert_eq!(newer_version(current, tags.iter().copied()), expected);
+}
+
+/// Reads the dependencies as the manifest is by `read_metadata`, through a
+/// value.
+fn parse_detail(toml: &str) -> ForeignDependenciesDetail {
+    toml::from_str::<toml::Value>(toml)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+#[test]
+fn test_update_all_collects_errors() {
+    let detail = parse_detail(
+        r#"
+        [flags]
+        archive = "https://example.com/flags.rar"
+        copy = []
+
+        [icons]
+        archive = "https://example.com/icons.zip"
+        extract = ["icons/[*.svg"]
+        copy = []
+        "#,
+    );
+    let errors = detail
+        .update_all_with("target/tmp/fdependencies", 2)
+        .unwrap_err();
+    let names = errors
+        .0
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+
+    assert_eq!(names, ["flags", "icons"]);
+    assert!(matches!(errors.0[0].1, UpdateError::Invalid(_)));
+}
+
+/// Writes a zip archive with the one file `path` in it.
+fn write_zip(archive_file: &Path, path: &str) {
+    fs::create_dir_all(archive_file.parent().unwrap()).unwrap();
+
+    let mut writer = zip::ZipWriter::new(File::create(archive_file).unwrap());
+    writer
+        .start_file(path, zip::write::SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(b"<svg/>").unwrap();
+    writer.finish().unwrap();
+}
+
+#[test]
+fn test_vendor_all() {
+    let vendor_dir = Path::new("target/tmp/vendor");
+    let out_dir = Path::new("target/tmp/vendored");
+    let detail = parse_detail(
+        r#"
+        [flags]
+        archive = "https://example.com/releases/flags-1.0.zip"
+        strip-components = 1
+        copy = [["flags/*.svg", "assets/flags"]]
+
+        [icons]
+        git = "https://example.com/icons"
+        commit = "1c50b81a5802532638f1fff93d50cfdd9975f09d"
+        copy = [["*.svg", "assets/icons"]]
+        "#,
+    );
+
+    write_zip(&vendor_dir.join("flags-1.0.zip"), "flags-1.0/flags/de.svg");
+
+    let errors = detail.vendor_all(vendor_dir, out_dir).unwrap_err();
+
+    // The archive is still installed while the repository is missing.
+    assert!(out_dir.join("assets/flags/de.svg").is_file());
+    assert_eq!(errors.0.len(), 1);
+    assert_eq!(errors.0[0].0, "icons");
+    assert!(
+        matches!(&errors.0[0].1, UpdateError::NotVendored(path) if *path == vendor_dir.join("icons"))
+    );
+}
+
+#[test]
+fn test_post_fetch() {
+    let vendor_dir = Path::new("target/tmp/vendor-hooks");
+    let out_dir = Path::new("target/tmp/vendored-hooks");
+    let detail = parse_detail(
+        r#"
+        [flags]
+        archive = "https://example.com/flags.zip"
+        post-fetch = [["sh", "-c", "cp de.svg de-copy.svg"], ["touch", "converted"]]
+        copy = [["*", "assets/flags"]]
+        "#,
+    );
+
+    write_zip(&vendor_dir.join("flags.zip"), "de.svg");
+    detail.vendor_all(vendor_dir, out_dir).unwrap();
+
+    assert!(out_dir.join("assets/flags/de-copy.svg").is_file());
+    assert!(out_dir.join("assets/flags/converted").is_file());
+}
+
+#[test]
+fn test_post_fetch_failure() {
+    let vendor_dir = Path::new("target/tmp/vendor-failing-hooks");
+    let detail = parse_detail(
+        r#"
+        [flags]
+        archive = "https://example.com/flags.zip"
+        post-fetch = [["sh", "-c", "echo converting; echo broken >&2; exit 3"]]
+        copy = []
+        "#,
+    );
+
+    write_zip(&vendor_dir.join("flags.zip"), "de.svg");
+
+    let errors = detail
+        .vendor_all(vendor_dir, "target/tmp/vendored-failing-hooks")
+        .unwrap_err();
+
+    match &errors.0[0].1 {
+        UpdateError::Hook {
+            command,
+            status,
+            output,
+        } => {
+            assert_eq!(command, "sh -c echo converting; echo broken >&2; exit 3");
+            assert_eq!(status.and_then(|status| status.code()), Some(3));
+            assert_eq!(output, "converting\nbroken\n");
+        }
+        error => panic!("unexpected error: {}", error),
+    }
+}
+
+#[test]
+fn test_vendored_checksum() {
+    let vendor_dir = Path::new("target/tmp/vendor-checksum");
+    let detail = parse_detail(
+        r#"
+        [flags]
+        archive = "https://example.com/flags.zip"
+        sha256 = "0000000000000000000000000000000000000000000000000000000000000000"
+        copy = []
+        "#,
+    );
+
+    write_zip(&vendor_dir.join("flags.zip"), "de.svg");
+
+    let errors = detail
+        .vendor_all(vendor_dir, "target/tmp/vendored-checksum")
+        .unwrap_err();
+
+    assert!(matches!(&errors.0[0].1, UpdateError::Invalid(message) if message.contains("SHA-256")));
+}
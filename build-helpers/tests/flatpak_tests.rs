@@ -0,0 +1,175 @@
+use build_helpers::flatpak::*;
+use build_helpers::manifest::ForeignDependenciesDetail;
+
+static APP_ID: &str = "com.example.Project";
+
+fn parse<T: serde::de::DeserializeOwned>(toml: &str) -> T {
+    toml::from_str::<toml::Value>(toml)
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+fn example_detail() -> FlatpakDetail {
+    parse(
+        r#"
+        runtime-version = "47"
+        bin = "gui"
+        command = "project"
+        network = true
+        system-talk-names = ["org.freedesktop.login1"]
+        install = [["$OUT_DIR/share/*", "share"]]
+
+        [build-env]
+        PROJECT_LOCALEDIR = "/app/share/locale"
+        "#,
+    )
+}
+
+#[test]
+fn test_finish_args() {
+    assert_eq!(
+        example_detail().finish_args(),
+        [
+            "--share=ipc",
+            "--socket=fallback-x11",
+            "--socket=wayland",
+            "--device=dri",
+            "--share=network",
+            "--system-talk-name=org.freedesktop.login1",
+        ]
+    );
+}
+
+#[test]
+fn test_to_manifest() {
+    let foreign_dependencies = parse::<ForeignDependenciesDetail>(
+        r#"
+        [flags]
+        git = "https://example.com/flags"
+        commit = "abc123"
+        copy = []
+
+        [icons]
+        archive = "https://example.com/releases/icons-1.0.zip"
+        sha256 = "d2fabcfbdc87f4758337ca535fb41a6d701b65693ce38287d856d1674551ec9b"
+        copy = []
+        "#,
+    );
+    let manifest = example_detail()
+        .to_manifest(APP_ID, "project", "/src/project", &foreign_dependencies)
+        .unwrap();
+    let module = &manifest.modules[0];
+
+    assert_eq!(manifest.runtime, "org.gnome.Platform");
+    assert_eq!(
+        module.build_options.append_path,
+        "/usr/lib/sdk/rust-stable/bin"
+    );
+    assert_eq!(
+        module.build_options.env["BUILD_HELPERS_VENDOR_DIR"],
+        "/run/build/project/vendor"
+    );
+    assert_eq!(
+        module.build_options.env["PROJECT_LOCALEDIR"],
+        "/app/share/locale"
+    );
+    assert_eq!(
+        module.build_commands,
+        [
+            "cargo --offline build --release --bin gui",
+            "install -Dm755 target/release/gui /app/bin/project",
+            "mkdir -p /app/share && cp -r target/release/build/project-*/out/share/* /app/share",
+        ]
+    );
+    assert_eq!(
+        module.sources[2],
+        ModuleSource::Source(FlatpakSource::Git {
+            url: "https://example.com/flags".to_owned(),
+            commit: "abc123".to_owned(),
+            dest: "vendor/flags".to_owned(),
+        })
+    );
+    assert_eq!(
+        module.sources[3],
+        ModuleSource::Source(FlatpakSource::File {
+            url: "https://example.com/releases/icons-1.0.zip".to_owned(),
+            sha256: "d2fabcfbdc87f4758337ca535fb41a6d701b65693ce38287d856d1674551ec9b".to_owned(),
+            dest: "vendor".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn test_to_manifest_invalid() {
+    let foreign_dependencies = parse::<ForeignDependenciesDetail>(
+        r#"
+        [flags]
+        archive = "https://example.com/flags.zip"
+        copy = []
+        "#,
+    );
+    let mut detail = example_detail();
+
+    detail
+        .install
+        .push(("data/*.xml".to_owned(), "/usr/share".to_owned()));
+
+    assert_eq!(
+        detail
+            .to_manifest(APP_ID, "project", "/src/project", &foreign_dependencies)
+            .unwrap_err()
+            .len(),
+        // The archive and the install directory.
+        2
+    );
+}
+
+#[test]
+fn test_cargo_sources() {
+    let sources = cargo_sources(
+        r#"
+        version = 3
+
+        [[package]]
+        name = "project"
+        version = "0.1.0"
+
+        [[package]]
+        name = "glob"
+        version = "0.3.1"
+        source = "registry+https://github.com/rust-lang/crates.io-index"
+        checksum = "d2fabcfbdc87f4758337ca535fb41a6d701b65693ce38287d856d1674551ec9b"
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(sources.len(), 3);
+    assert_eq!(
+        sources[0],
+        FlatpakSource::Archive {
+            archive_type: "tar-gzip".to_owned(),
+            url: "https://static.crates.io/crates/glob/glob-0.3.1.crate".to_owned(),
+            sha256: "d2fabcfbdc87f4758337ca535fb41a6d701b65693ce38287d856d1674551ec9b".to_owned(),
+            dest: "cargo/vendor/glob-0.3.1".to_owned(),
+        }
+    );
+}
+
+#[test]
+fn test_cargo_sources_git() {
+    let errors = cargo_sources(
+        r#"
+        [[package]]
+        name = "ksni"
+        version = "0.2.0"
+        source = "git+https://example.com/ksni#abc123"
+        "#,
+    )
+    .unwrap_err();
+
+    assert_eq!(
+        errors,
+        ["ksni 0.2.0 is from `git+https://example.com/ksni#abc123`, which cannot be vendored"]
+    );
+}
@@ -0,0 +1,29 @@
+use build_helpers::gettext::*;
+use std::fs;
+use std::path::Path;
+
+static PO_DIR: &str = "tests/files/po";
+
+/// `de.mo` was compiled with `msgfmt --no-hash`, which leaves out the
+/// optional hash table like [`write_mo`] does.
+#[test]
+fn test_write_mo() {
+    let mo_file = Path::new("target/tmp/write_mo/de.mo");
+
+    fs::create_dir_all(mo_file.parent().unwrap()).unwrap();
+    write_mo(Path::new(PO_DIR).join("de.po"), mo_file).unwrap();
+
+    assert_eq!(
+        fs::read(mo_file).unwrap(),
+        fs::read(Path::new(PO_DIR).join("de.mo")).unwrap()
+    );
+}
+
+#[test]
+fn test_compile_translations() {
+    let target_dir = Path::new("target/tmp/locale");
+    let compiled = compile_translations(PO_DIR, "example", target_dir).unwrap();
+
+    assert_eq!(compiled, [target_dir.join("de/LC_MESSAGES/example.mo")]);
+    assert!(compiled[0].is_file());
+}
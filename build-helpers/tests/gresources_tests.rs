@@ -1,6 +1,8 @@
+use build_helpers::blueprint::compile_blueprints;
 use build_helpers::gresources::*;
 use once_cell::sync::Lazy;
 use std::fmt::Debug;
+use std::path::Path;
 use strong_xml::{XmlRead, XmlWrite};
 use test_case::test_case;
 
@@ -109,3 +111,162 @@ where
     let xml = data.to_string().unwrap();
     assert_eq!(expected, xml);
 }
+
+#[test_case("assets/**/*.png", None, &[
+    ("assets/bar/baz_1.png", "bar/baz_1.png"),
+    ("assets/bar/baz_2.png", "bar/baz_2.png"),
+    ("assets/bar/baz_3.png", "bar/baz_3.png"),
+    ("assets/foo/baz_1.png", "foo/baz_1.png"),
+    ("assets/foo/baz_2.png", "foo/baz_2.png"),
+    ("assets/foo/baz_3.png", "foo/baz_3.png"),
+] ; "test expand recursive glob")]
+#[test_case("assets/foo/baz_*.png", Some("images/{}"), &[
+    ("assets/foo/baz_1.png", "images/baz_1.png"),
+    ("assets/foo/baz_2.png", "images/baz_2.png"),
+    ("assets/foo/baz_3.png", "images/baz_3.png"),
+] ; "test expand glob with alias")]
+fn test_expand_glob(path: &str, alias: Option<&str>, expected: &[(&str, &str)]) {
+    let files_dir = Path::new("tests/files");
+    let file = File::new(path.to_owned(), alias.map(str::to_owned), Some(true), None);
+    let gresource = GResource::from_iter("/com/example/project/res".to_owned(), [file]);
+    let expected = expected
+        .iter()
+        .map(|(path, alias)| {
+            File::new(
+                files_dir.join(path).to_str().unwrap().to_owned(),
+                Some(alias.to_string()),
+                Some(true),
+                None,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(gresource.expand(files_dir).files, expected);
+}
+
+#[test]
+fn test_expand_keeps_plain_paths() {
+    let file = File::new("foo/bar/baz_1.png".to_owned(), None, None, None);
+
+    assert!(!file.is_glob());
+    assert_eq!(file.expand("tests/files"), [file]);
+}
+
+#[test]
+fn test_builder() {
+    let gresources = GResources::builder()
+        .prefix("/com/example/project/res")
+        .add_file("foo/bar/baz_1.png")
+        .add_file("foo/bar/baz_5.png")
+        .alias("image_5.png")
+        .compress(true)
+        .preprocess(Preprocess::ToPixData)
+        .build();
+
+    assert_eq!(
+        gresources.to_string().unwrap(),
+        concat!(
+            r#"<gresources><gresource prefix="/com/example/project/res">"#,
+            r#"<file>foo/bar/baz_1.png</file>"#,
+            r#"<file alias="image_5.png" compressed="true" preprocess="to-pixdata">foo/bar/baz_5.png</file>"#,
+            r#"</gresource></gresources>"#,
+        )
+    );
+}
+
+#[test]
+fn test_builder_default_prefix() {
+    let gresources = GResources::builder().add_file("foo/bar/baz_1.png").build();
+
+    assert_eq!(gresources.entries[0].prefix, "/");
+}
+
+#[test]
+#[should_panic]
+fn test_builder_change_without_file() {
+    GResources::builder().prefix("/").compress(true);
+}
+
+#[test]
+fn test_from_dir() {
+    let dir_path = Path::new("tests/files/dir");
+    let options = DirOptions::new().exclude("*.txt");
+    let gresource =
+        GResource::from_dir("/com/example/project/res".to_owned(), dir_path, &options).unwrap();
+    let file = |path: &str, compressed, preprocess| {
+        File::new(
+            dir_path.join(path).to_str().unwrap().to_owned(),
+            Some(path.to_owned()),
+            compressed,
+            preprocess,
+        )
+    };
+
+    assert_eq!(
+        gresource.files,
+        [
+            file(
+                "icons/add.svg",
+                Some(true),
+                Some(Preprocess::XmlStripBlanks)
+            ),
+            file(
+                "icons/remove.svg",
+                Some(true),
+                Some(Preprocess::XmlStripBlanks)
+            ),
+            file("style.css", Some(true), None),
+            file("window.ui", Some(true), Some(Preprocess::XmlStripBlanks)),
+        ]
+    );
+}
+
+#[test_case(&["*.ui"], &[], &["window.ui"] ; "test from dir include")]
+#[test_case(&["**/*.svg"], &["**/remove.svg"], &["icons/add.svg"] ; "test from dir include and exclude")]
+#[test_case(&[], &["icons/*"], &["notes.txt", "style.css", "window.ui"] ; "test from dir exclude")]
+fn test_from_dir_filters(include: &[&str], exclude: &[&str], expected: &[&str]) {
+    let options = include.iter().fold(DirOptions::new(), |options, pattern| {
+        options.include(pattern)
+    });
+    let options = exclude
+        .iter()
+        .fold(options, |options, pattern| options.exclude(pattern));
+    let gresource = GResource::from_dir("/".to_owned(), "tests/files/dir", &options).unwrap();
+    let aliases = gresource
+        .files
+        .iter()
+        .map(|file| file.alias.as_deref().unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(aliases, expected);
+}
+
+#[test]
+fn test_with_defaults_keeps_given() {
+    let file = File::new("foo/bar/baz.svg".to_owned(), None, Some(false), None).with_defaults();
+
+    assert_eq!(file.compressed, Some(false));
+    assert_eq!(file.preprocess, Some(Preprocess::XmlStripBlanks));
+}
+
+#[test]
+fn test_compile_missing_file() {
+    let gresources = GResources::builder().add_file("missing.ui").build();
+    let dest_file = Path::new("target/tmp/missing.gresource");
+
+    assert!(compile_resources(&gresources, "tests/files", dest_file).is_err());
+    assert!(dest_file.with_extension("gresource.xml").exists());
+}
+
+#[test]
+fn test_compile_without_blueprints() {
+    let gresource = compile_blueprints(
+        "/com/example/project/ui".to_owned(),
+        "tests/files/dir",
+        "target/tmp/blueprints",
+    )
+    .unwrap();
+
+    assert_eq!(gresource.prefix, "/com/example/project/ui");
+    assert!(gresource.files.is_empty());
+}
@@ -0,0 +1,95 @@
+use build_helpers::gschemas::*;
+use strong_xml::XmlRead;
+use test_case::test_case;
+
+static SCHEMA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<schemalist gettext-domain="example">
+    <enum id="com.example.Theme">
+        <value nick="light" value="0"/>
+        <value nick="dark" value="1"/>
+    </enum>
+    <schema id="com.example" path="/com/example/">
+        <key name="interval" type="u">
+            <range min="1" max="60"/>
+            <default>2</default>
+            <summary>Interval</summary>
+        </key>
+        <key name="enabled" type="b">
+            <default>true</default>
+        </key>
+        <key name="name" type="s">
+            <default>''</default>
+        </key>
+        <key name="theme" enum="com.example.Theme">
+            <default>'dark'</default>
+        </key>
+        <key name="servers" type="as">
+            <default>[]</default>
+        </key>
+    </schema>
+</schemalist>
+"#;
+
+fn key(attrs: &str, children: &str) -> String {
+    SCHEMA.replace(
+        r#"<key name="servers" type="as">"#,
+        &format!(
+            r#"<key name="broken" {}>{}</key><key name="servers" type="as">"#,
+            attrs, children
+        ),
+    )
+}
+
+#[test]
+fn test_valid_schema() {
+    let schema_list = SchemaList::from_str(SCHEMA).unwrap();
+
+    assert_eq!(schema_list.schemas[0].keys.len(), 5);
+    assert_eq!(schema_list.validate(), Vec::<String>::new());
+}
+
+#[test]
+fn test_app_schema() {
+    let schema = include_str!("../../data/com.github.spikespaz.nordvpn-viking.gschema.xml");
+
+    assert_eq!(
+        SchemaList::from_str(schema).unwrap().validate(),
+        Vec::<String>::new()
+    );
+}
+
+#[test_case(r#"type="b""#, "<default>yes</default>", "default yes is not `true` or `false`" ; "test invalid boolean")]
+#[test_case(r#"type="s""#, "<default>hello</default>", "default hello is not a quoted string" ; "test unquoted string")]
+#[test_case(r#"type="y""#, "<default>300</default>", "300 does not fit in type `y`" ; "test integer overflow")]
+#[test_case(r#"type="i""#, r#"<range min="0" max="10"/><default>11</default>"#, "default 11 is outside the range 0 to 10" ; "test outside range")]
+#[test_case(r#"type="i""#, r#"<range min="10" max="0"/><default>5</default>"#, "range minimum 10 is above its maximum 0" ; "test inverted range")]
+#[test_case(r#"type="s""#, r#"<range min="a" max="b"/><default>''</default>"#, "a range is not allowed for type `s`" ; "test range on string")]
+#[test_case(r#"enum="com.example.Theme""#, "<default>'blue'</default>", "default 'blue' is not one of the quoted nicks of `com.example.Theme`" ; "test unknown nick")]
+#[test_case(r#"enum="com.example.Color""#, "<default>'red'</default>", "enum `com.example.Color` is not defined" ; "test unknown enum")]
+#[test_case(r#"type="b""#, "", "has no default" ; "test missing default")]
+#[test_case("", "<default>true</default>", "needs either a `type` or an `enum`" ; "test missing type")]
+fn test_invalid_key(attrs: &str, children: &str, expected: &str) {
+    let schema_list = SchemaList::from_str(&key(attrs, children)).unwrap();
+
+    assert_eq!(
+        schema_list.validate(),
+        [format!("com.example: key `broken`: {}", expected)]
+    );
+}
+
+#[test]
+fn test_duplicate_key() {
+    let schema = SCHEMA.replace(r#"name="name""#, r#"name="enabled""#);
+
+    assert_eq!(
+        SchemaList::from_str(&schema).unwrap().validate(),
+        ["com.example: key `enabled`: is defined more than once"]
+    );
+}
+
+#[test]
+fn test_compile_app_schemas() {
+    let compiled = compile_schemas("../data", "target/tmp/schemas").unwrap();
+
+    assert!(compiled.is_file());
+}
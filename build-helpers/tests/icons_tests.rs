@@ -0,0 +1,39 @@
+use build_helpers::icons::*;
+use std::path::Path;
+
+#[test]
+fn test_render_app_icon() {
+    let target_dir = Path::new("target/tmp/icons/hicolor");
+    let icon_files = render_icon(
+        "../data/icons/com.github.spikespaz.nordvpn-viking.svg",
+        "com.github.spikespaz.nordvpn-viking",
+        target_dir,
+    )
+    .unwrap();
+
+    assert_eq!(icon_files.len(), HICOLOR_SIZES.len() * 2 + 1);
+    assert!(target_dir
+        .join("48x48@2/apps/com.github.spikespaz.nordvpn-viking.png")
+        .is_file());
+    assert!(target_dir
+        .join("scalable/apps/com.github.spikespaz.nordvpn-viking.svg")
+        .is_file());
+
+    for icon_file in icon_files {
+        assert!(icon_file.is_file(), "{} is missing", icon_file.display());
+    }
+}
+
+#[test]
+fn test_render_invalid_svg() {
+    let error = render_icon(
+        "tests/files/dir/style.css",
+        "style",
+        "target/tmp/icons/invalid",
+    )
+    .unwrap_err();
+
+    assert!(error
+        .to_string()
+        .starts_with("tests/files/dir/style.css is invalid"));
+}
@@ -0,0 +1,117 @@
+use build_helpers::common::{CompileError, GlobCopySet};
+use build_helpers::manifest::*;
+use build_helpers::metadata::read_metadata;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use test_case::test_case;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Metadata {
+    copy_files: GlobCopySet,
+    gresources: GResourcesDetail,
+}
+
+static VALID_METADATA: &str = r#"
+[package.metadata]
+copy-files = [["ui/*.ui", "assets/ui"]]
+
+[package.metadata.gresources.ui]
+prefix = "/com/example/Project/ui"
+
+[[package.metadata.gresources.ui.files]]
+glob = "assets/ui/*.ui"
+compressed = true
+"#;
+
+fn write_manifest(name: &str, metadata: &str) -> PathBuf {
+    let manifest_file = Path::new("target/tmp/metadata").join(format!("{}.toml", name));
+
+    fs::create_dir_all(manifest_file.parent().unwrap()).unwrap();
+    fs::write(
+        &manifest_file,
+        format!(
+            "[package]\nname = \"project\"\nversion = \"0.1.0\"\n{}",
+            metadata
+        ),
+    )
+    .unwrap();
+
+    manifest_file
+}
+
+#[test]
+fn test_read_metadata() {
+    let manifest_file = write_manifest("valid", VALID_METADATA);
+    let metadata = read_metadata::<Metadata, _>(manifest_file).unwrap();
+
+    assert_eq!(
+        metadata.copy_files,
+        [("ui/*.ui".to_owned(), "assets/ui".to_owned())]
+    );
+    assert_eq!(
+        metadata
+            .gresources
+            .to_gresources("target/tmp/metadata")
+            .entries[0]
+            .prefix,
+        "/com/example/Project/ui"
+    );
+}
+
+#[test]
+fn test_read_metadata_other_tools() {
+    let manifest_file = write_manifest(
+        "other-tools",
+        &format!(
+            "{}\n[package.metadata.deb]\nsection = \"net\"\n",
+            VALID_METADATA
+        ),
+    );
+
+    assert!(read_metadata::<Metadata, _>(manifest_file).is_ok());
+}
+
+#[test_case(
+    "unknown-key",
+    "compressed = true",
+    "compresed = true",
+    &["`package.metadata.gresources.ui.files[0].compresed` is not a known key"];
+    "unknown key"
+)]
+#[test_case(
+    "wrong-type",
+    "compressed = true",
+    "compressed = \"yes\"",
+    &["`package.metadata.gresources.ui.files[0].compressed`: invalid type: string \"yes\", expected a boolean"];
+    "wrong type"
+)]
+#[test_case(
+    "missing-field",
+    "prefix = \"/com/example/Project/ui\"",
+    "",
+    &["`package.metadata.gresources.ui`: missing field `prefix`"];
+    "missing field"
+)]
+fn test_read_metadata_invalid(name: &str, from: &str, to: &str, expected: &[&str]) {
+    let manifest_file = write_manifest(name, &VALID_METADATA.replace(from, to));
+
+    match read_metadata::<Metadata, _>(&manifest_file) {
+        Err(CompileError::Invalid { path, errors }) => {
+            assert_eq!(path, manifest_file);
+            assert_eq!(errors, expected);
+        }
+        result => panic!("expected the metadata to be invalid, got {:?}", result),
+    }
+}
+
+#[test]
+fn test_read_metadata_missing() {
+    let manifest_file = write_manifest("missing", "");
+
+    assert!(matches!(
+        read_metadata::<Metadata, _>(manifest_file),
+        Err(CompileError::Invalid { .. })
+    ));
+}
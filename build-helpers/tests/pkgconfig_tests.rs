@@ -0,0 +1,76 @@
+use build_helpers::pkgconfig::*;
+use std::env;
+use test_case::test_case;
+
+#[test_case("ID=debian\n", &["debian"] ; "test id")]
+#[test_case("NAME=\"Linux Mint\"\nID=linuxmint\nID_LIKE=\"ubuntu debian\"\n", &["linuxmint", "ubuntu", "debian"] ; "test id like")]
+#[test_case("ID=\"opensuse-tumbleweed\"\nID_LIKE=\"opensuse suse\"\n", &["opensuse", "opensuse", "suse"] ; "test variant")]
+#[test_case("NAME=Unknown\n", &[] ; "test no id")]
+fn test_distributions(os_release: &str, expected: &[&str]) {
+    assert_eq!(distributions(os_release), expected);
+}
+
+// The variable is only changed by this test, so that it does not race.
+#[test]
+fn test_check_libraries() {
+    env::set_var("PKG_CONFIG_PATH", "tests/files/pkgconfig");
+
+    let library = |module, min_version| SystemLibrary {
+        module,
+        min_version,
+        packages: &[],
+    };
+
+    assert!(check_libraries(&[library("viking-test", "1.0")]).is_ok());
+
+    match check_libraries(&[
+        library("viking-test", "1.2.0"),
+        library("viking-test", "1.10"),
+        library("viking-missing", "1.0"),
+    ]) {
+        Err(LibraryError::Missing { libraries, .. }) => {
+            let versions = libraries
+                .iter()
+                .map(|library| (library.module.as_str(), library.version.as_deref()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                versions,
+                [("viking-test", Some("1.2.0")), ("viking-missing", None)]
+            );
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_missing_message() {
+    let error = LibraryError::Missing {
+        libraries: vec![
+            MissingLibrary {
+                module: "gtk4".to_owned(),
+                min_version: "4.4".to_owned(),
+                version: Some("4.0.3".to_owned()),
+                package: Some("libgtk-4-dev".to_owned()),
+            },
+            MissingLibrary {
+                module: "libadwaita-1".to_owned(),
+                min_version: "1.0".to_owned(),
+                version: None,
+                package: Some("libadwaita-1-dev".to_owned()),
+            },
+        ],
+        install_command: Some("sudo apt install"),
+    };
+
+    assert_eq!(
+        error.to_string(),
+        concat!(
+            "libraries required to build are missing\n",
+            "    gtk4 4.0.3 is installed, but at least 4.4 is required\n",
+            "    libadwaita-1 1.0 or later is not installed\n",
+            "install them with:\n",
+            "    sudo apt install libgtk-4-dev libadwaita-1-dev",
+        )
+    );
+}
@@ -0,0 +1,11 @@
+use build_helpers::common::progress::format_bytes;
+use test_case::test_case;
+
+#[test_case(0, "0 B" ; "test zero")]
+#[test_case(1023, "1023 B" ; "test bytes")]
+#[test_case(1536, "1.5 KiB" ; "test kibibytes")]
+#[test_case(5 * 1024 * 1024, "5.0 MiB" ; "test mebibytes")]
+#[test_case(3 * 1024 * 1024 * 1024 * 1024 * 1024, "3072.0 TiB" ; "test largest unit")]
+fn test_format_bytes(bytes: u64, expected: &str) {
+    assert_eq!(format_bytes(bytes), expected);
+}
@@ -0,0 +1,43 @@
+use build_helpers::common::proxy::*;
+use std::env;
+use test_case::test_case;
+
+#[test_case("example.com", "example.com", true ; "test exact host")]
+#[test_case("cdn.example.com", ".example.com", true ; "test subdomain")]
+#[test_case("cdn.example.com", "localhost, example.com", true ; "test list")]
+#[test_case("Example.COM", "example.com", true ; "test case insensitive")]
+#[test_case("notexample.com", "example.com", false ; "test other domain")]
+#[test_case("github.com", "*", true ; "test wildcard")]
+#[test_case("github.com", "", false ; "test empty")]
+fn test_is_excluded(host: &str, no_proxy: &str, expected: bool) {
+    assert_eq!(is_excluded(host, no_proxy), expected);
+}
+
+// The variables are only changed by this test, so that they do not race.
+#[test]
+fn test_proxy_for() {
+    env::set_var("HTTPS_PROXY", "http://proxy.example.com:3128");
+    env::set_var("http_proxy", "http://plain.example.com:3128");
+    env::set_var("NO_PROXY", "internal.example.com");
+
+    assert_eq!(
+        proxy_for("https://github.com/lipis/flag-icons", None).as_deref(),
+        Some("http://proxy.example.com:3128")
+    );
+    assert_eq!(
+        proxy_for("http://mirror.example.org/flags.zip", None).as_deref(),
+        Some("http://plain.example.com:3128")
+    );
+    assert_eq!(
+        proxy_for("https://user@internal.example.com:8443/flags.zip", None),
+        None
+    );
+    assert_eq!(
+        proxy_for(
+            "https://internal.example.com/flags.zip",
+            Some("socks5://localhost:1080")
+        )
+        .as_deref(),
+        Some("socks5://localhost:1080")
+    );
+}
@@ -1,32 +1,148 @@
 use build_helpers::prelude::*;
-use cargo_toml::Manifest;
 use serde::Deserialize;
 use std::env;
 use std::path::PathBuf;
 
+const APP_ID: &str = "com.github.spikespaz.nordvpn-viking";
+
+/// Where the templates compiled from blueprints are, alongside the others.
+const UI_PREFIX: &str = "/com/github/spikespaz/nordvpn-viking/ui";
+
+const ICONS_PREFIX: &str = "/com/github/spikespaz/nordvpn-viking/icons";
+
+/// The libraries linked by the GTK crates, at the versions they require.
+const SYSTEM_LIBRARIES: [SystemLibrary; 3] = [
+    SystemLibrary {
+        module: "glib-2.0",
+        min_version: "2.66",
+        packages: &[
+            ("debian", "libglib2.0-dev"),
+            ("ubuntu", "libglib2.0-dev"),
+            ("fedora", "glib2-devel"),
+            ("arch", "glib2"),
+            ("opensuse", "glib2-devel"),
+            ("alpine", "glib-dev"),
+            ("nixos", "glib"),
+        ],
+    },
+    SystemLibrary {
+        module: "gtk4",
+        min_version: "4.0",
+        packages: &[
+            ("debian", "libgtk-4-dev"),
+            ("ubuntu", "libgtk-4-dev"),
+            ("fedora", "gtk4-devel"),
+            ("arch", "gtk4"),
+            ("opensuse", "gtk4-devel"),
+            ("alpine", "gtk4.0-dev"),
+            ("nixos", "gtk4"),
+        ],
+    },
+    SystemLibrary {
+        module: "libadwaita-1",
+        min_version: "1.0",
+        packages: &[
+            ("debian", "libadwaita-1-dev"),
+            ("ubuntu", "libadwaita-1-dev"),
+            ("fedora", "libadwaita-devel"),
+            ("arch", "libadwaita"),
+            ("opensuse", "libadwaita-devel"),
+            ("alpine", "libadwaita-dev"),
+            ("nixos", "libadwaita"),
+        ],
+    },
+];
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct Metadata {
+    appstream: AppStreamDetail,
     copy_files: GlobCopySet,
+    desktop_entry: DesktopEntry,
+    flatpak: FlatpakDetail,
     foreign_dependencies: ForeignDependenciesDetail,
     gresources: GResourcesDetail,
 }
 
 fn main() {
+    check_libraries(&SYSTEM_LIBRARIES).unwrap_or_else(|error| panic!("{}", error));
+
     let curr_dir = env::current_dir().unwrap();
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
-    let manifest: Manifest<Metadata> = Manifest::from_path_with_metadata("Cargo.toml").unwrap();
-    let metadata = manifest.package.unwrap().metadata.unwrap();
-
-    println!("Output directory: {:?}", out_dir);
-    // println!("Manifest: {:#?}", manifest);
+    // Checked as a whole before anything is built, so that a mistake in it
+    // is reported by its key rather than by whichever step panics first.
+    let metadata: Metadata =
+        read_metadata("Cargo.toml").unwrap_or_else(|error| panic!("{}", error));
 
     copy_globs(&metadata.copy_files, &curr_dir, &out_dir);
 
-    metadata.foreign_dependencies.update_all(&out_dir);
+    metadata
+        .foreign_dependencies
+        .update_all(&out_dir)
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    let mut gresources = metadata.gresources.to_gresources(&out_dir);
+    let blueprints = compile_blueprints(
+        UI_PREFIX.to_owned(),
+        curr_dir.join("src/bin/gui/ui"),
+        out_dir.join("assets/blueprints"),
+    )
+    .unwrap_or_else(|error| panic!("{}", error));
+
+    if !blueprints.files.is_empty() {
+        gresources.entries.push(blueprints);
+    }
+
+    // GTK finds the icons under the application's `icons` path, while
+    // packages install the same directory into `/usr/share/icons/hicolor`.
+    let icons_dir = out_dir.join("icons/hicolor");
+    render_icon(
+        curr_dir.join(format!("data/icons/{}.svg", APP_ID)),
+        APP_ID,
+        &icons_dir,
+    )
+    .unwrap_or_else(|error| panic!("{}", error));
+    gresources.entries.push(
+        GResource::from_dir(ICONS_PREFIX.to_owned(), &icons_dir, &DirOptions::new()).unwrap(),
+    );
+
+    gresources.compile(out_dir.join("assets/compiled.gresource"));
+
+    // Laid out like `/usr/share`, to be installed by packages.
+    metadata
+        .desktop_entry
+        .install(APP_ID, out_dir.join("share"))
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    install_completions("viking", out_dir.join("share"))
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    let mut appstream = metadata.appstream;
+    appstream
+        .project_license
+        .get_or_insert_with(|| env::var("CARGO_PKG_LICENSE").unwrap());
+    appstream
+        .install(APP_ID, &curr_dir, out_dir.join("share"))
+        .unwrap_or_else(|error| panic!("{}", error));
 
     metadata
-        .gresources
-        .to_gresources(&out_dir)
-        .compile(out_dir.join("assets/compiled.gresource"));
+        .flatpak
+        .install(
+            APP_ID,
+            &env::var("CARGO_PKG_NAME").unwrap(),
+            &curr_dir,
+            &metadata.foreign_dependencies,
+            out_dir.join("flatpak"),
+        )
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    compile_schemas(curr_dir.join("data"), out_dir.join("schemas"))
+        .unwrap_or_else(|error| panic!("{}", error));
+
+    compile_translations(
+        curr_dir.join("po"),
+        &env::var("CARGO_PKG_NAME").unwrap(),
+        out_dir.join("locale"),
+    )
+    .unwrap_or_else(|error| panic!("{}", error));
 }
@@ -0,0 +1,162 @@
+//! Starting the application on login with an XDG autostart entry, as
+//! described by the Desktop Application Autostart Specification.
+
+use crate::config::xdg_base_dir;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The argument which starts the application in the tray without a window.
+pub const HIDDEN_ARG: &str = "--hidden";
+
+const FILE_NAME: &str = "com.github.spikespaz.nordvpn-viking.desktop";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Autostart {
+    path: PathBuf,
+}
+
+impl Autostart {
+    /// The entry at `$XDG_CONFIG_HOME/autostart`, or under `~/.config` if the
+    /// variable is not set.
+    pub fn new() -> Self {
+        Self::with_path(
+            xdg_base_dir("XDG_CONFIG_HOME", ".config")
+                .join("autostart")
+                .join(FILE_NAME),
+        )
+    }
+
+    pub fn with_path<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the entry exists and has not been disabled by the desktop's
+    /// own startup settings.
+    pub fn is_enabled(&self) -> bool {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => !text.lines().any(|line| {
+                matches!(
+                    line.trim(),
+                    "Hidden=true" | "X-GNOME-Autostart-enabled=false"
+                )
+            }),
+            Err(_) => false,
+        }
+    }
+
+    /// Writes an entry which runs the current executable with
+    /// [`HIDDEN_ARG`], so that only the tray comes up after login.
+    pub fn enable(&self) -> io::Result<()> {
+        self.enable_with(&env::current_exe()?, &[HIDDEN_ARG])
+    }
+
+    pub fn enable_with(&self, program: &Path, args: &[&str]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let exec = [program.to_string_lossy().as_ref()]
+            .into_iter()
+            .chain(args.iter().copied())
+            .map(quote_exec)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        fs::write(
+            &self.path,
+            format!(
+                "[Desktop Entry]\n\
+                 Type=Application\n\
+                 Name=NordVPN Viking\n\
+                 Comment=Keep the VPN status in the tray\n\
+                 Exec={}\n\
+                 Icon=network-vpn\n\
+                 Terminal=false\n\
+                 X-GNOME-Autostart-enabled=true\n",
+                exec
+            ),
+        )
+    }
+
+    /// Removes the entry, which is not an error if there is none.
+    pub fn disable(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) -> io::Result<()> {
+        match enabled {
+            true => self.enable(),
+            false => self.disable(),
+        }
+    }
+}
+
+impl Default for Autostart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Quotes an argument of the `Exec` key if it has reserved characters.
+fn quote_exec(arg: &str) -> String {
+    const RESERVED: &[char] = &[
+        ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(',
+        ')', '`',
+    ];
+
+    if !arg.is_empty() && !arg.contains(RESERVED) {
+        return arg.replace('%', "%%");
+    }
+
+    let mut quoted = String::from('"');
+
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+
+    quoted.push('"');
+    quoted.replace('%', "%%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Autostart;
+    use std::path::Path;
+
+    #[test]
+    fn test_autostart() {
+        let dir = std::env::temp_dir().join(format!(
+            "nordvpn-viking-autostart-test-{}",
+            std::process::id()
+        ));
+        let autostart = Autostart::with_path(dir.join("viking.desktop"));
+
+        assert!(!autostart.is_enabled());
+        autostart.disable().unwrap();
+
+        autostart
+            .enable_with(Path::new("/opt/Nord VPN/viking"), &["--hidden"])
+            .unwrap();
+        assert!(autostart.is_enabled());
+
+        let text = std::fs::read_to_string(autostart.path()).unwrap();
+        assert!(text.contains("Exec=\"/opt/Nord VPN/viking\" --hidden\n"));
+
+        autostart.set_enabled(false).unwrap();
+        assert!(!autostart.is_enabled());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,401 @@
+//! How the application behaves, which can be set by hand in `config.toml`
+//! in the XDG config directory. The keys which are set there take precedence
+//! over the preferences, and the file is read again whenever it changes.
+//!
+//! ```toml
+//! default-target = "country:Germany"
+//! poll-interval = 5
+//!
+//! [notifications]
+//! enabled = true
+//! expiry-reminder = 14
+//!
+//! [watchdog]
+//! enabled = true
+//! max-attempts = 10
+//! ```
+
+use crate::config::xdg_dir;
+use crate::nordvpn::{ConnectOption, RetryPolicy};
+use serde::Deserialize;
+use serde_path_to_error::Segment;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+/// How often the file is checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Behavior {
+    /// What Quick Connect connects to, such as `country:Germany`.
+    #[serde(with = "crate::profile::optional")]
+    pub default_target: Option<ConnectOption>,
+    /// How often the status is polled, in seconds, from 1 to 60.
+    pub poll_interval: Option<u32>,
+    pub notifications: NotificationBehavior,
+    pub watchdog: WatchdogBehavior,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NotificationBehavior {
+    /// Whether desktop notifications are shown at all.
+    pub enabled: Option<bool>,
+    /// How many days before the subscription expires to be reminded, from 0
+    /// for no reminder to 60.
+    pub expiry_reminder: Option<u32>,
+}
+
+/// Settings of the [`Watchdog`](crate::nordvpn::Watchdog) which reconnects
+/// dropped connections.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct WatchdogBehavior {
+    /// Whether dropped connections are reconnected.
+    pub enabled: Option<bool>,
+    /// How often the connection is checked, in seconds.
+    pub interval: Option<u64>,
+    /// How many times reconnecting is attempted before giving up.
+    pub max_attempts: Option<u32>,
+    /// The delay before the second attempt, in seconds, which doubles after
+    /// each attempt.
+    pub retry_delay: Option<u64>,
+    /// The upper bound for the delay between attempts, in seconds.
+    pub max_retry_delay: Option<u64>,
+}
+
+#[derive(Debug, Error)]
+pub enum BehaviorError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("`{key}` {message}")]
+    Invalid { key: String, message: String },
+}
+
+impl Behavior {
+    /// `$XDG_CONFIG_HOME/nordvpn-viking/config.toml`, or under `~/.config` if
+    /// the variable is not set.
+    pub fn path() -> PathBuf {
+        xdg_dir("XDG_CONFIG_HOME", ".config").join("config.toml")
+    }
+
+    /// Reads the file at the default path, where nothing is set if it does
+    /// not exist.
+    pub fn load() -> Result<Self, BehaviorError> {
+        Self::load_from(Self::path())
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self, BehaviorError> {
+        match fs::read_to_string(path) {
+            Ok(text) => text.parse(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// The first value which is out of its range, if any.
+    pub fn validate(&self) -> Result<(), BehaviorError> {
+        let checks = [
+            ("poll-interval", self.poll_interval.map(u64::from), 1, 60),
+            (
+                "notifications.expiry-reminder",
+                self.notifications.expiry_reminder.map(u64::from),
+                0,
+                60,
+            ),
+            ("watchdog.interval", self.watchdog.interval, 1, u64::MAX),
+            (
+                "watchdog.max-attempts",
+                self.watchdog.max_attempts.map(u64::from),
+                1,
+                u64::MAX,
+            ),
+        ];
+
+        for (key, value, min, max) in checks {
+            match value {
+                Some(value) if value < min => {
+                    return Err(invalid(key, format!("must be at least {}", min)))
+                }
+                Some(value) if value > max => {
+                    return Err(invalid(key, format!("must be at most {}", max)))
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Behavior {
+    type Err = BehaviorError;
+
+    /// Parses and validates the text of the file, naming the key of the
+    /// first value which is wrong, or which is not known.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut unknown = None;
+        let mut ignored = |path: serde_ignored::Path| {
+            unknown.get_or_insert_with(|| ignored_key(&path));
+        };
+        let mut deserializer = toml::Deserializer::new(text);
+        let result = serde_path_to_error::deserialize(serde_ignored::Deserializer::new(
+            &mut deserializer,
+            &mut ignored,
+        ));
+
+        let behavior: Self = match result {
+            Ok(behavior) => behavior,
+            Err(error) => {
+                let mut key = String::new();
+
+                for segment in error.path().iter() {
+                    match segment {
+                        Segment::Seq { index } => write!(key, "[{}]", index).unwrap(),
+                        Segment::Map { key: name } | Segment::Enum { variant: name } => {
+                            if !key.is_empty() {
+                                key.push('.');
+                            }
+                            key.push_str(name);
+                        }
+                        Segment::Unknown => key.push_str(".?"),
+                    }
+                }
+
+                let message = error.inner().to_string();
+                // The key toml adds is less precise than the one given here.
+                let message = match message.rfind(" for key `") {
+                    Some(index) => &message[..index],
+                    None => &message,
+                };
+
+                return Err(invalid(key, message.to_owned()));
+            }
+        };
+
+        if let Some(key) = unknown {
+            return Err(invalid(key, "is not a known key".to_owned()));
+        }
+
+        behavior.validate()?;
+
+        Ok(behavior)
+    }
+}
+
+impl WatchdogBehavior {
+    /// How often the connection is checked, or `default` if it is not set.
+    pub fn interval_or(&self, default: Duration) -> Duration {
+        self.interval.map(Duration::from_secs).unwrap_or(default)
+    }
+
+    /// `policy` with the values which are set replaced.
+    pub fn policy(&self, mut policy: RetryPolicy) -> RetryPolicy {
+        if let Some(max_attempts) = self.max_attempts {
+            policy.max_attempts = max_attempts;
+        }
+        if let Some(retry_delay) = self.retry_delay {
+            policy.initial_delay = Duration::from_secs(retry_delay);
+        }
+        if let Some(max_retry_delay) = self.max_retry_delay {
+            policy.max_delay = Duration::from_secs(max_retry_delay);
+        }
+        policy
+    }
+}
+
+fn invalid<S: Into<String>>(key: S, message: String) -> BehaviorError {
+    BehaviorError::Invalid {
+        key: key.into(),
+        message,
+    }
+}
+
+/// The key of a value which was not deserialized, such as `watchdog.retries`.
+fn ignored_key(path: &serde_ignored::Path) -> String {
+    match path {
+        serde_ignored::Path::Root => String::new(),
+        serde_ignored::Path::Seq { parent, index } => format!("{}[{}]", ignored_key(parent), index),
+        serde_ignored::Path::Map { parent, key } => match ignored_key(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{}.{}", parent, key),
+        },
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => ignored_key(parent),
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).ok()?.modified().ok()
+}
+
+/// Checks the file on a background thread and sends what it sets whenever
+/// it is changed, created or removed. Checking stops when the watcher is
+/// dropped.
+pub struct BehaviorWatcher {
+    events: Receiver<Result<Behavior, BehaviorError>>,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BehaviorWatcher {
+    /// Watches the file at the default path.
+    pub fn new() -> Self {
+        Self::with_path(Behavior::path(), RELOAD_INTERVAL)
+    }
+
+    /// Watches the file at `path`, which is checked every `interval`.
+    pub fn with_path<P: Into<PathBuf>>(path: P, interval: Duration) -> Self {
+        let path = path.into();
+        let (event_sender, events) = mpsc::channel();
+        let (stop, stop_receiver) = mpsc::channel::<()>();
+        // The file as it is now was read by whoever started watching.
+        let mut previous = modified(&path);
+
+        let thread = thread::spawn(move || loop {
+            match stop_receiver.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => (),
+                _ => return,
+            }
+
+            let current = modified(&path);
+
+            if current != previous {
+                previous = current;
+
+                if event_sender.send(Behavior::load_from(&path)).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self {
+            events,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// The receiving end of the event channel. A file which cannot be read
+    /// is sent as an error and does not stop the watcher.
+    pub fn events(&self) -> &Receiver<Result<Behavior, BehaviorError>> {
+        &self.events
+    }
+}
+
+impl Default for BehaviorWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for BehaviorWatcher {
+    fn drop(&mut self) {
+        // Disconnecting the stop channel wakes the thread immediately.
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Behavior, BehaviorError, BehaviorWatcher};
+    use crate::nordvpn::{ConnectOption, RetryPolicy};
+    use std::time::Duration;
+
+    fn invalid_key(text: &str) -> String {
+        match text.parse::<Behavior>() {
+            Err(BehaviorError::Invalid { key, .. }) => key,
+            result => panic!("expected the config to be invalid, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let behavior: Behavior = concat!(
+            "default-target = \"country:Germany\"\n",
+            "poll-interval = 5\n",
+            "[notifications]\n",
+            "expiry-reminder = 14\n",
+            "[watchdog]\n",
+            "enabled = true\n",
+            "max-attempts = 10\n",
+            "retry-delay = 1\n",
+        )
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            behavior.default_target,
+            Some(ConnectOption::Country("Germany".to_owned()))
+        );
+        assert_eq!(behavior.poll_interval, Some(5));
+        assert_eq!(behavior.notifications.enabled, None);
+        assert_eq!(behavior.notifications.expiry_reminder, Some(14));
+        assert_eq!(behavior.watchdog.enabled, Some(true));
+        assert_eq!(
+            behavior.watchdog.interval_or(Duration::from_secs(3)),
+            Duration::from_secs(3)
+        );
+        assert_eq!(
+            behavior.watchdog.policy(RetryPolicy::default()),
+            RetryPolicy::default()
+                .max_attempts(10)
+                .initial_delay(Duration::from_secs(1))
+        );
+        assert_eq!("".parse::<Behavior>().unwrap(), Behavior::default());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(invalid_key("poll-intervall = 5"), "poll-intervall");
+        assert_eq!(invalid_key("[watchdog]\nretries = 5"), "watchdog.retries");
+        assert_eq!(
+            invalid_key("[notifications]\nenabled = \"yes\""),
+            "notifications.enabled"
+        );
+        assert_eq!(
+            invalid_key("default-target = \"nowhere\""),
+            "default-target"
+        );
+        assert_eq!(invalid_key("poll-interval = 0"), "poll-interval");
+        assert_eq!(
+            invalid_key("[notifications]\nexpiry-reminder = 90"),
+            "notifications.expiry-reminder"
+        );
+    }
+
+    #[test]
+    fn test_watcher() {
+        let dir = std::env::temp_dir().join(format!(
+            "nordvpn-viking-behavior-test-{}",
+            std::process::id()
+        ));
+        let path = dir.join("config.toml");
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let watcher = BehaviorWatcher::with_path(&path, Duration::from_millis(5));
+        let event = || watcher.events().recv_timeout(Duration::from_secs(5));
+
+        std::fs::write(&path, "poll-interval = 10\n").unwrap();
+        assert_eq!(event().unwrap().unwrap().poll_interval, Some(10));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(event().unwrap().unwrap(), Behavior::default());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
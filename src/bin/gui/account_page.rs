@@ -0,0 +1,364 @@
+use crate::i18n::{gettext, gettext_f};
+use crate::preferences::Preferences;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::login::LoginListener;
+use nordvpn_viking::nordvpn::{self, Account};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// How often the session is checked while waiting for the browser.
+const LOGIN_POLL: Duration = Duration::from_secs(2);
+
+/// How long to wait for the login to be finished in the browser.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+
+glib::wrapper! {
+    pub struct VikingAccountPage(ObjectSubclass<imp::VikingAccountPage>)
+    @extends gtk::Widget, gtk::Box,
+    @implements gtk::Orientable;
+}
+
+/// Sent from background threads to the page on the main thread.
+pub enum Message {
+    /// The account, or `None` when not logged in.
+    Account(Result<Option<Account>, String>),
+    /// The URL to log in at, or `None` if already logged in.
+    LoginUrl(Result<Option<String>, String>),
+    /// The session appeared while waiting for the browser.
+    LoggedIn(Account),
+    LoginTimedOut,
+    LoggedOut(Result<bool, String>),
+}
+
+impl VikingAccountPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingAccountPage`")
+    }
+
+    /// Calls `callback` once logging in from the page has finished.
+    pub fn connect_logged_in<F: Fn(&Account) + 'static>(&self, callback: F) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        imp.logged_in_callbacks
+            .borrow_mut()
+            .push(Box::new(callback));
+    }
+
+    /// Calls `callback` whenever the page finds that there is no session.
+    pub fn connect_logged_out<F: Fn() + 'static>(&self, callback: F) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        imp.logged_out_callbacks
+            .borrow_mut()
+            .push(Box::new(callback));
+    }
+
+    fn setup(&self) {
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |message| {
+                page.handle(message);
+                glib::Continue(true)
+            }),
+        );
+
+        imp.sender.replace(Some(sender));
+        imp.login_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| page.login()));
+        imp.logout_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| page.logout()));
+        // The reminders are sent by the application, see `reminders`.
+        Preferences::new().bind("expiry-reminder", &imp.reminder_spin.get(), "value");
+        // The session may have changed from the terminal while hidden.
+        self.connect_map(|page| page.reload());
+
+        self.reload();
+    }
+
+    pub fn reload(&self) {
+        self.run(|| Message::Account(nordvpn::account().map_err(|error| error.to_string())));
+    }
+
+    /// Asks the CLI for a login URL, which is opened in the browser.
+    fn login(&self) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        imp.login_button.set_sensitive(false);
+        imp.error_label.set_visible(false);
+        self.run(|| Message::LoginUrl(nordvpn::login().map_err(|error| error.to_string())));
+    }
+
+    fn logout(&self) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        imp.logout_button.set_sensitive(false);
+        imp.error_label.set_visible(false);
+        self.run(|| Message::LoggedOut(nordvpn::logout().map_err(|error| error.to_string())));
+    }
+
+    /// Runs a blocking call on another thread, sending its message back.
+    fn run<F>(&self, call: F)
+    where
+        F: FnOnce() -> Message + Send + 'static,
+    {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        if let Some(sender) = imp.sender.borrow().clone() {
+            thread::spawn(move || {
+                sender.send(call()).ok();
+            });
+        }
+    }
+
+    fn handle(&self, message: Message) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        match message {
+            Message::Account(Ok(account)) => self.update(account.as_ref()),
+            Message::LoginUrl(Ok(Some(url))) => self.wait_for_login(&url),
+            // Logged in from somewhere else in the meantime.
+            Message::LoginUrl(Ok(None)) => self.reload(),
+            Message::LoggedIn(account) => {
+                self.close_login_dialog();
+                self.update(Some(&account));
+
+                for callback in imp.logged_in_callbacks.borrow().iter() {
+                    callback(&account);
+                }
+            }
+            Message::LoginTimedOut => {
+                self.close_login_dialog();
+                self.show_error(&gettext(
+                    "Timed out waiting for the login to finish in the browser.",
+                ));
+            }
+            Message::LoggedOut(Ok(_)) => self.update(None),
+            Message::Account(Err(error))
+            | Message::LoginUrl(Err(error))
+            | Message::LoggedOut(Err(error)) => self.show_error(&error),
+        }
+    }
+
+    /// Opens `url` in the default browser, and shows a dialog while polling
+    /// for the session until it exists or the dialog is cancelled. The page
+    /// is sent back to a [`LoginListener`] if one can be started, which
+    /// finishes the login as soon as the browser returns.
+    fn wait_for_login(&self, url: &str) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+        let window = self
+            .root()
+            .and_then(|root| root.downcast::<gtk::Window>().ok());
+        let sender = match imp.sender.borrow().clone() {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        let listener = LoginListener::bind()
+            .map_err(|error| warn!("Failed to listen for the login: {}", error))
+            .ok();
+        let url = match &listener {
+            Some(listener) => listener.rewrite(url).unwrap_or_else(|_| url.to_owned()),
+            None => url.to_owned(),
+        };
+
+        gtk::show_uri(window.as_ref(), &url, 0);
+
+        let dialog = gtk::MessageDialog::new(
+            window.as_ref(),
+            gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+            gtk::MessageType::Other,
+            gtk::ButtonsType::Cancel,
+            &gettext("Waiting for Login"),
+        );
+        let spinner = gtk::Spinner::new();
+        let link = gtk::LinkButton::with_label(&url, &gettext("Open the login page again"));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        dialog.set_secondary_text(Some(&gettext(
+            "Finish logging in to NordVPN in your browser. This will continue on its own once you have.",
+        )));
+        spinner.start();
+        if let Ok(area) = dialog.message_area().downcast::<gtk::Box>() {
+            area.append(&spinner);
+            area.append(&link);
+        }
+        dialog.connect_response(
+            glib::clone!(@weak self as page, @strong cancelled => move |_, _| {
+                let imp = imp::VikingAccountPage::from_instance(&page);
+
+                cancelled.store(true, Ordering::SeqCst);
+                imp.login_button.set_sensitive(true);
+                page.close_login_dialog();
+            }),
+        );
+        dialog.present();
+        imp.login_dialog.replace(Some(dialog));
+
+        thread::spawn(move || {
+            let started = Instant::now();
+
+            while !cancelled.load(Ordering::SeqCst) {
+                if started.elapsed() >= LOGIN_TIMEOUT {
+                    sender.send(Message::LoginTimedOut).ok();
+                    break;
+                }
+
+                // The CLI may be briefly unavailable while the session is
+                // being created, so errors are only retried.
+                if let Ok(Some(account)) = nordvpn::account() {
+                    sender.send(Message::LoggedIn(account)).ok();
+                    break;
+                }
+
+                let callback = match &listener {
+                    Some(listener) => listener.wait(LOGIN_POLL, &cancelled),
+                    None => {
+                        thread::sleep(LOGIN_POLL);
+                        continue;
+                    }
+                };
+
+                // The session is then found straight away by the next poll.
+                match callback {
+                    Ok(Some(callback)) => {
+                        if let Err(error) = nordvpn::login_callback(&callback) {
+                            warn!("Failed to finish the login: {}", error);
+                        }
+                    }
+                    Ok(None) => (),
+                    Err(error) => warn!("Failed to answer the browser: {}", error),
+                }
+            }
+        });
+    }
+
+    fn close_login_dialog(&self) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        if let Some(dialog) = imp.login_dialog.take() {
+            dialog.destroy();
+        }
+    }
+
+    fn show_error(&self, error: &str) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        imp.error_label.set_label(error);
+        imp.error_label.set_visible(true);
+        imp.login_button.set_sensitive(true);
+        imp.logout_button.set_sensitive(true);
+        if imp.account_stack.visible_child_name().as_deref() == Some("loading") {
+            imp.account_stack.set_visible_child_name("logged_out");
+        }
+    }
+
+    fn update(&self, account: Option<&Account>) {
+        let imp = imp::VikingAccountPage::from_instance(self);
+
+        imp.error_label.set_visible(false);
+        imp.login_button.set_sensitive(true);
+        imp.logout_button.set_sensitive(true);
+
+        let account = match account {
+            Some(account) => account,
+            None => {
+                imp.account_stack.set_visible_child_name("logged_out");
+
+                for callback in imp.logged_out_callbacks.borrow().iter() {
+                    callback();
+                }
+                return;
+            }
+        };
+
+        imp.email_label.set_label(&account.email);
+        let expires = account.expires.format("%Y-%m-%d");
+
+        imp.subscription_label.set_label(&match account.active {
+            true => gettext_f("Active until {}", &[&expires]),
+            false => gettext_f("Inactive until {}", &[&expires]),
+        });
+        imp.dedicated_ip_label
+            .set_label(&match &account.dedicated_ip {
+                Some(servers) if !servers.is_empty() => servers.join(", "),
+                Some(_) => gettext("Active, no server selected"),
+                None => gettext("None"),
+            });
+        imp.account_stack.set_visible_child_name("logged_in");
+    }
+}
+
+impl Default for VikingAccountPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+mod imp {
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use nordvpn_viking::nordvpn::Account;
+    use std::cell::RefCell;
+
+    type LoggedInCallback = Box<dyn Fn(&Account)>;
+    type LoggedOutCallback = Box<dyn Fn()>;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/account_page.ui")]
+    pub struct VikingAccountPage {
+        #[template_child]
+        pub account_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub login_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub email_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub subscription_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub dedicated_ip_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub reminder_spin: TemplateChild<gtk::SpinButton>,
+        #[template_child]
+        pub logout_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub error_label: TemplateChild<gtk::Label>,
+        pub sender: RefCell<Option<glib::Sender<super::Message>>>,
+        pub login_dialog: RefCell<Option<gtk::MessageDialog>>,
+        pub logged_in_callbacks: RefCell<Vec<LoggedInCallback>>,
+        pub logged_out_callbacks: RefCell<Vec<LoggedOutCallback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingAccountPage {
+        const NAME: &'static str = "VikingAccountPage";
+        type Type = super::VikingAccountPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingAccountPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingAccountPage {}
+    impl BoxImpl for VikingAccountPage {}
+}
@@ -0,0 +1,29 @@
+//! The keyboard shortcuts of every action, kept in one place so that the
+//! shortcuts window, menus and buttons all show the same keys.
+
+use gtk::prelude::*;
+use gtk::Application;
+
+/// Detailed action names and their accelerators.
+pub const ACCELS: &[(&str, &[&str])] = &[
+    ("app.quit", &["<primary>q"]),
+    ("win.command-palette", &["<primary>k"]),
+    ("win.quick-connect", &["<primary><shift>k"]),
+    ("win.disconnect", &["<primary>d"]),
+    ("win.search", &["<primary>f"]),
+    ("win.show-shortcuts", &["<primary>question"]),
+    ("win.show-page::connect_page", &["<alt>1"]),
+    ("win.show-page::map_page", &["<alt>2"]),
+    ("win.show-page::history_page", &["<alt>3"]),
+    ("win.show-page::account_page", &["<alt>4"]),
+    ("win.show-page::settings_page", &["<alt>5"]),
+    ("win.show-page::allowlist_page", &["<alt>6"]),
+    ("win.show-page::meshnet_page", &["<alt>7"]),
+    ("win.show-page::fileshare_page", &["<alt>8"]),
+];
+
+pub fn set_accels(app: &Application) {
+    for (action, accels) in ACCELS {
+        app.set_accels_for_action(action, accels);
+    }
+}
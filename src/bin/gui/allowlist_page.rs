@@ -0,0 +1,350 @@
+use crate::i18n::{gettext, gettext_f};
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::nordvpn::allowlist::{
+    self, Allowlist, AllowlistEntry, InvalidAllowlistEntry, PortRange, Subnet,
+};
+use nordvpn_viking::nordvpn::{self, CliResult, Protocol};
+use std::thread;
+
+glib::wrapper! {
+    pub struct VikingAllowlistPage(ObjectSubclass<imp::VikingAllowlistPage>)
+    @extends gtk::Widget, gtk::Box,
+    @implements gtk::Orientable;
+}
+
+/// What the entry is parsed as, in the order of the kind dropdown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum EntryKind {
+    Port,
+    Range,
+    Subnet,
+}
+
+const KINDS: [EntryKind; 3] = [EntryKind::Port, EntryKind::Range, EntryKind::Subnet];
+/// The protocols in the order of the protocol dropdown, `None` being both.
+const PROTOCOLS: [Option<Protocol>; 3] = [None, Some(Protocol::Udp), Some(Protocol::Tcp)];
+
+/// The allowlist and whether LAN discovery is enabled, as read from the CLI.
+type Loaded = (Allowlist, bool);
+
+impl EntryKind {
+    fn placeholder(self) -> &'static str {
+        match self {
+            Self::Port => "22",
+            Self::Range => "8000-8080",
+            Self::Subnet => "192.168.1.0/24",
+        }
+    }
+
+    fn parse(
+        self,
+        text: &str,
+        protocol: Option<Protocol>,
+    ) -> Result<AllowlistEntry, InvalidAllowlistEntry> {
+        let text = text.trim();
+        let port = |text: &str| {
+            text.trim().parse::<u16>().map_err(|_| {
+                InvalidAllowlistEntry(gettext_f(
+                    "`{}` is not a port from 1 to 65535",
+                    &[&text.trim()],
+                ))
+            })
+        };
+
+        Ok(match self {
+            Self::Port => AllowlistEntry::Ports(PortRange::single(port(text)?, protocol)?),
+            Self::Range => {
+                let (start, end) = text
+                    .split_once(|c: char| c == '-' || c == ':' || c.is_whitespace())
+                    .ok_or_else(|| {
+                        InvalidAllowlistEntry(gettext("a range is two ports like 8000-8080"))
+                    })?;
+
+                AllowlistEntry::Ports(PortRange::new(port(start)?, port(end)?, protocol)?)
+            }
+            Self::Subnet => AllowlistEntry::Subnet(text.parse::<Subnet>()?),
+        })
+    }
+}
+
+impl VikingAllowlistPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingAllowlistPage`")
+    }
+
+    fn setup(&self) {
+        let imp = imp::VikingAllowlistPage::from_instance(self);
+
+        imp.kind_dropdown
+            .connect_selected_notify(glib::clone!(@weak self as page => move |_| {
+                page.validate();
+            }));
+        imp.protocol_dropdown.connect_selected_notify(
+            glib::clone!(@weak self as page => move |_| {
+                page.validate();
+            }),
+        );
+        imp.entry
+            .connect_changed(glib::clone!(@weak self as page => move |_| {
+                page.validate();
+            }));
+        imp.entry
+            .connect_activate(glib::clone!(@weak self as page => move |_| page.add()));
+        imp.add_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| page.add()));
+        imp.clear_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| {
+                page.run(allowlist::clear);
+            }));
+
+        self.validate();
+        self.run(|| Ok(()));
+    }
+
+    fn kind(&self) -> EntryKind {
+        let imp = imp::VikingAllowlistPage::from_instance(self);
+
+        KINDS[imp.kind_dropdown.selected() as usize]
+    }
+
+    /// Parses the entry as the selected kind, showing why it is invalid or
+    /// why the CLI would refuse it.
+    fn validate(&self) -> Option<AllowlistEntry> {
+        let imp = imp::VikingAllowlistPage::from_instance(self);
+        let kind = self.kind();
+        let text = imp.entry.text();
+
+        imp.entry.set_placeholder_text(Some(kind.placeholder()));
+        imp.protocol_dropdown
+            .set_sensitive(kind != EntryKind::Subnet);
+
+        let protocol = PROTOCOLS[imp.protocol_dropdown.selected() as usize];
+        let (entry, message) = match kind.parse(&text, protocol) {
+            _ if text.trim().is_empty() => (None, None),
+            Ok(AllowlistEntry::Subnet(subnet))
+                if imp.lan_discovery.get() && subnet.is_private() =>
+            {
+                let message = format!(
+                    "{} overlaps the local network, which LAN discovery already allows. \
+                     Turn LAN discovery off in the settings to allowlist it.",
+                    subnet
+                );
+                (None, Some(message))
+            }
+            Ok(entry) => (Some(entry), None),
+            Err(error) => (None, Some(error.to_string())),
+        };
+
+        if message.is_some() {
+            imp.entry.add_css_class("error");
+        } else {
+            imp.entry.remove_css_class("error");
+        }
+        imp.validation_label
+            .set_label(message.as_deref().unwrap_or_default());
+        imp.validation_label.set_visible(message.is_some());
+        imp.add_button.set_sensitive(entry.is_some());
+
+        entry
+    }
+
+    fn add(&self) {
+        let imp = imp::VikingAllowlistPage::from_instance(self);
+
+        if let Some(entry) = self.validate() {
+            imp.entry.set_text("");
+            self.run(move || allowlist::add(&entry));
+        }
+    }
+
+    /// Makes the edit in the background, then reads the allowlist back so the
+    /// page shows what the daemon actually uses.
+    fn run<F>(&self, edit: F)
+    where
+        F: FnOnce() -> CliResult<()> + Send + 'static,
+    {
+        let imp = imp::VikingAllowlistPage::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        self.set_sensitive(false);
+        imp.error_label.set_visible(false);
+
+        thread::spawn(move || {
+            let load = || -> CliResult<Loaded> {
+                let lan_discovery = nordvpn::settings()?.lan_discovery;
+                Ok((allowlist::list()?, lan_discovery.unwrap_or_default()))
+            };
+            // Whatever the edit did, the page should reflect the CLI.
+            let result = edit()
+                .and_then(|_| load())
+                .map_err(|error| (error.to_string(), load().ok()));
+
+            sender.send(result).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |result: Result<Loaded, (String, Option<Loaded>)>| {
+                page.set_sensitive(true);
+
+                match result {
+                    Ok(loaded) => page.update(loaded),
+                    Err((error, loaded)) => {
+                        if let Some(loaded) = loaded {
+                            page.update(loaded);
+                        }
+                        page.show_error(&error);
+                    }
+                }
+
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    fn show_error(&self, error: &str) {
+        let imp = imp::VikingAllowlistPage::from_instance(self);
+
+        imp.error_label.set_label(error);
+        imp.error_label.set_visible(true);
+    }
+
+    fn update(&self, (allowlist, lan_discovery): Loaded) {
+        let imp = imp::VikingAllowlistPage::from_instance(self);
+
+        while let Some(row) = imp.list_box.first_child() {
+            imp.list_box.remove(&row);
+        }
+
+        let ports = allowlist.ports.iter().copied().map(AllowlistEntry::Ports);
+        let subnets = allowlist
+            .subnets
+            .iter()
+            .copied()
+            .map(AllowlistEntry::Subnet);
+
+        for entry in ports.chain(subnets) {
+            self.append_row(entry);
+        }
+
+        let conflicts = match lan_discovery {
+            true => allowlist.lan_conflicts(),
+            false => Vec::new(),
+        };
+        let conflicts = conflicts
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        imp.lan_warning_label.set_label(&format!(
+            "LAN discovery is enabled, so it decides whether local traffic bypasses \
+             the VPN instead of {}.",
+            conflicts.join(", ")
+        ));
+        imp.lan_warning_label.set_visible(!conflicts.is_empty());
+        imp.clear_button
+            .set_sensitive(!allowlist.ports.is_empty() || !allowlist.subnets.is_empty());
+
+        imp.lan_discovery.set(lan_discovery);
+        self.validate();
+    }
+
+    fn append_row(&self, entry: AllowlistEntry) {
+        let imp = imp::VikingAllowlistPage::from_instance(self);
+        let title = gtk::Label::new(Some(&entry.to_string()));
+        let subtitle = gtk::Label::new(Some(&match entry {
+            AllowlistEntry::Ports(PortRange { start, end, .. }) if start == end => gettext("Port"),
+            AllowlistEntry::Ports(_) => gettext("Port Range"),
+            AllowlistEntry::Subnet(_) => gettext("Subnet"),
+        }));
+        let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let remove = gtk::Button::from_icon_name(Some("list-remove-symbolic"));
+
+        title.set_xalign(0.0);
+        subtitle.set_xalign(0.0);
+        subtitle.add_css_class("dim-label");
+        subtitle.add_css_class("caption");
+        labels.set_hexpand(true);
+        labels.append(&title);
+        labels.append(&subtitle);
+        remove.set_valign(gtk::Align::Center);
+        remove.set_tooltip_text(Some(&gettext("Remove")));
+        remove.add_css_class("flat");
+        remove.connect_clicked(glib::clone!(@weak self as page => move |_| {
+            page.run(move || allowlist::remove(&entry));
+        }));
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&labels);
+        content.append(&remove);
+        imp.list_box.append(&content);
+    }
+}
+
+impl Default for VikingAllowlistPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+mod imp {
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use std::cell::Cell;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/allowlist_page.ui")]
+    pub struct VikingAllowlistPage {
+        #[template_child]
+        pub error_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub lan_warning_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub kind_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub protocol_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub add_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub validation_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub clear_button: TemplateChild<gtk::Button>,
+        /// Whether LAN discovery was enabled when the allowlist was last read.
+        pub lan_discovery: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingAllowlistPage {
+        const NAME: &'static str = "VikingAllowlistPage";
+        type Type = super::VikingAllowlistPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingAllowlistPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingAllowlistPage {}
+    impl BoxImpl for VikingAllowlistPage {}
+}
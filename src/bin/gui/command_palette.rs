@@ -0,0 +1,356 @@
+use crate::actions::ACCELS;
+use crate::i18n::gettext;
+use crate::main_window::VikingApplicationWindow;
+use crate::server_browser::{self, BrowserEntry};
+use crate::watchdog;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, glib};
+use nordvpn_viking::config::Config;
+use nordvpn_viking::nordapi::ServerFilters;
+use nordvpn_viking::nordvpn::ConnectOption;
+use nordvpn_viking::profile::Profile;
+use std::thread;
+
+/// The most rows shown at once, as only the best matches are of use.
+const MAX_ROWS: usize = 50;
+
+glib::wrapper! {
+    pub struct VikingCommandPalette(ObjectSubclass<imp::VikingCommandPalette>)
+    @extends gtk::Widget, gtk::Window,
+    @implements gtk::Root;
+}
+
+/// What happens when an item is chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// A window or application action, with the page to show for
+    /// `win.show-page`.
+    Action(&'static str, Option<&'static str>),
+    Connect(ConnectOption),
+    Profile(Profile),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteItem {
+    pub title: String,
+    pub subtitle: String,
+    pub command: Command,
+    /// Whether the item is only listed when searched for, as there are too
+    /// many countries and cities to browse here.
+    pub searched_only: bool,
+}
+
+impl VikingCommandPalette {
+    pub fn new(parent: &VikingApplicationWindow) -> Self {
+        glib::Object::new(&[("transient-for", parent)])
+            .expect("Failed to create `VikingCommandPalette`")
+    }
+
+    fn setup(&self) {
+        let imp = imp::VikingCommandPalette::from_instance(self);
+        let config = Config::load().unwrap_or_default();
+        let favorites = config.favorites.into_iter().map(|favorite| PaletteItem {
+            title: favorite.name,
+            subtitle: gettext("Favorite"),
+            command: Command::Connect(favorite.option),
+            searched_only: false,
+        });
+        let profiles = config.profiles.into_iter().map(|profile| PaletteItem {
+            title: profile.name.clone(),
+            subtitle: gettext("Profile"),
+            command: Command::Profile(profile),
+            searched_only: false,
+        });
+
+        imp.items.replace(
+            actions()
+                .into_iter()
+                .chain(favorites)
+                .chain(profiles)
+                .collect(),
+        );
+
+        imp.search_entry.connect_search_changed(
+            glib::clone!(@weak self as palette => move |_| palette.filter()),
+        );
+        imp.search_entry
+            .connect_activate(glib::clone!(@weak self as palette => move |_| {
+                let imp = imp::VikingCommandPalette::from_instance(&palette);
+
+                if let Some(row) = imp.list_box.selected_row() {
+                    palette.run(row.index());
+                }
+            }));
+        imp.search_entry
+            .connect_stop_search(glib::clone!(@weak self as palette => move |_| palette.close()));
+        imp.list_box.connect_row_activated(
+            glib::clone!(@weak self as palette => move |_, row| palette.run(row.index())),
+        );
+
+        // The arrow keys move through the list while typing.
+        let keys = gtk::EventControllerKey::new();
+
+        keys.connect_key_pressed(
+            glib::clone!(@weak self as palette => @default-return gtk::Inhibit(false), move |_, key, _, _| {
+                match key {
+                    gdk::keys::constants::Up => palette.move_selection(-1),
+                    gdk::keys::constants::Down => palette.move_selection(1),
+                    _ => return gtk::Inhibit(false),
+                }
+
+                gtk::Inhibit(true)
+            }),
+        );
+        imp.search_entry.add_controller(&keys);
+
+        self.load();
+        self.filter();
+    }
+
+    /// Adds the countries and cities once they are loaded, in the
+    /// background since they come from the API.
+    fn load(&self) {
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        let api = crate::api();
+
+        thread::spawn(move || {
+            if let Ok(servers) = api.servers(&ServerFilters::default()) {
+                sender.send(server_browser::entries(&servers)).ok();
+            }
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as palette => @default-return glib::Continue(false), move |entries: Vec<BrowserEntry>| {
+                let imp = imp::VikingCommandPalette::from_instance(&palette);
+                let places = entries
+                    .into_iter()
+                    .filter(|entry| !matches!(entry.option, ConnectOption::Server(_)))
+                    .map(|entry| PaletteItem {
+                        title: entry.title,
+                        subtitle: entry.subtitle,
+                        command: Command::Connect(entry.option),
+                        searched_only: true,
+                    });
+
+                imp.items.borrow_mut().extend(places);
+                palette.filter();
+
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    /// Lists the best matches of the search, or without one everything that
+    /// is not only listed when searched for.
+    fn filter(&self) {
+        let imp = imp::VikingCommandPalette::from_instance(self);
+        let query = imp.search_entry.text();
+        let items = imp.items.borrow();
+
+        let mut matches = items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| match query.is_empty() {
+                true => (!item.searched_only).then_some((0, index)),
+                false => {
+                    server_browser::fuzzy_score(&query, &item.title).map(|score| (score, index))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        matches.sort_by_key(|(score, index)| (-score, *index));
+        matches.truncate(MAX_ROWS);
+
+        while let Some(row) = imp.list_box.first_child() {
+            imp.list_box.remove(&row);
+        }
+
+        let shown = matches
+            .into_iter()
+            .map(|(_, index)| items[index].clone())
+            .collect::<Vec<_>>();
+
+        for item in &shown {
+            imp.list_box.append(&item_row(item));
+        }
+
+        drop(items);
+        imp.shown.replace(shown);
+        imp.list_box
+            .select_row(imp.list_box.row_at_index(0).as_ref());
+    }
+
+    fn move_selection(&self, step: i32) {
+        let imp = imp::VikingCommandPalette::from_instance(self);
+        let index = imp
+            .list_box
+            .selected_row()
+            .map_or(0, |row| row.index() + step);
+
+        if let Some(row) = imp.list_box.row_at_index(index) {
+            imp.list_box.select_row(Some(&row));
+        }
+    }
+
+    fn run(&self, index: i32) {
+        let imp = imp::VikingCommandPalette::from_instance(self);
+        let window = self
+            .transient_for()
+            .and_then(|window| window.downcast::<VikingApplicationWindow>().ok());
+        let item = imp.shown.borrow().get(index as usize).cloned();
+
+        self.close();
+
+        let (window, item) = match (window, item) {
+            (Some(window), Some(item)) => (window, item),
+            _ => return,
+        };
+
+        match item.command {
+            Command::Action(name, target) => {
+                WidgetExt::activate_action(
+                    &window,
+                    name,
+                    target.map(ToVariant::to_variant).as_ref(),
+                );
+            }
+            Command::Connect(option) => window.connect_to(option),
+            Command::Profile(profile) => {
+                watchdog::connecting(profile.target.as_ref());
+                WidgetExt::activate_action(
+                    &window,
+                    "win.show-page",
+                    Some(&"connect_page".to_variant()),
+                );
+                thread::spawn(move || profile.apply());
+            }
+        }
+    }
+}
+
+fn item_row(item: &PaletteItem) -> gtk::ListBoxRow {
+    let title = gtk::Label::new(Some(&item.title));
+    let subtitle = gtk::Label::new(Some(&item.subtitle));
+    let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+    let row = gtk::ListBoxRow::new();
+
+    title.set_xalign(0.0);
+    subtitle.set_xalign(0.0);
+    subtitle.add_css_class("dim-label");
+    subtitle.add_css_class("caption");
+    labels.set_hexpand(true);
+    labels.append(&title);
+    labels.append(&subtitle);
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.append(&labels);
+
+    if let Some(accel) = accel_label(&item.command) {
+        let shortcut = gtk::Label::new(Some(&accel));
+
+        shortcut.add_css_class("dim-label");
+        content.append(&shortcut);
+    }
+
+    row.set_child(Some(&content));
+
+    row
+}
+
+/// The first shortcut of an action, as shown to the user.
+fn accel_label(command: &Command) -> Option<String> {
+    let detailed = match command {
+        Command::Action(name, Some(target)) => format!("{}::{}", name, target),
+        Command::Action(name, None) => name.to_string(),
+        _ => return None,
+    };
+    let (_, accels) = ACCELS.iter().find(|(action, _)| *action == detailed)?;
+    let (key, mods) = gtk::accelerator_parse(accels.first()?)?;
+
+    gtk::accelerator_get_label(key, mods).map(Into::into)
+}
+
+/// The actions of the window and the application, in the order they are
+/// listed without a search.
+fn actions() -> Vec<PaletteItem> {
+    let action = |title: String, name, target| PaletteItem {
+        title,
+        subtitle: gettext("Action"),
+        command: Command::Action(name, target),
+        searched_only: false,
+    };
+    let page = |title: String, page| PaletteItem {
+        title,
+        subtitle: gettext("Page"),
+        command: Command::Action("win.show-page", Some(page)),
+        searched_only: false,
+    };
+
+    vec![
+        action(gettext("Quick Connect"), "win.quick-connect", None),
+        action(gettext("Disconnect"), "win.disconnect", None),
+        action(gettext("Search Servers"), "win.search", None),
+        page(gettext("Connect"), "connect_page"),
+        page(gettext("Map"), "map_page"),
+        page(gettext("History"), "history_page"),
+        page(gettext("Account"), "account_page"),
+        page(gettext("Settings"), "settings_page"),
+        page(gettext("Allowlist"), "allowlist_page"),
+        page(gettext("Meshnet"), "meshnet_page"),
+        page(gettext("Files"), "fileshare_page"),
+        action(gettext("DNS Leak Test"), "win.dns-leak-test", None),
+        action(gettext("Export Statistics…"), "win.export", None),
+        action(gettext("Keyboard Shortcuts"), "win.show-shortcuts", None),
+        action(gettext("Quit"), "app.quit", None),
+    ]
+}
+
+mod imp {
+    use super::PaletteItem;
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/command_palette.ui")]
+    pub struct VikingCommandPalette {
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        pub items: RefCell<Vec<PaletteItem>>,
+        /// The items in `list_box`, in order.
+        pub shown: RefCell<Vec<PaletteItem>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingCommandPalette {
+        const NAME: &'static str = "VikingCommandPalette";
+        type Type = super::VikingCommandPalette;
+        type ParentType = gtk::Window;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingCommandPalette {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingCommandPalette {}
+    impl WindowImpl for VikingCommandPalette {}
+}
@@ -0,0 +1,79 @@
+//! Runs the watchdog, the scheduler, the recorder and the D-Bus service
+//! without a window or tray, such as from the systemd user service, under a
+//! name of its own so that the application can still be started beside it.
+
+use crate::dbus_service;
+use crate::network_monitor;
+use crate::preferences::Preferences;
+use crate::recorder;
+use crate::scheduler;
+use crate::status_monitor;
+use crate::watchdog;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use nordvpn_viking::systemd::{UserService, BUS_NAME};
+use std::env;
+use std::process;
+use tracing::warn;
+
+/// The argument which writes, enables and starts the systemd user service.
+pub const INSTALL_ARG: &str = "--install-service";
+
+/// The argument which stops, disables and removes the systemd user service.
+pub const UNINSTALL_ARG: &str = "--uninstall-service";
+
+/// Runs until the daemon is stopped, and exits with a failure if another is
+/// already running.
+pub fn run() {
+    let app = gio::Application::new(Some(BUS_NAME), gio::ApplicationFlags::IS_SERVICE);
+
+    app.connect_startup(|app| {
+        Preferences::new().watch_behavior();
+        status_monitor::spawn();
+        watchdog::spawn(app);
+        scheduler::spawn(app);
+        recorder::spawn();
+
+        if let Err(error) = network_monitor::watch() {
+            warn!("Failed to watch the network for changes: {}", error);
+        }
+
+        // Nothing activates the daemon, which would otherwise exit when idle.
+        app.hold();
+
+        if let Some(connection) = app.dbus_connection() {
+            let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+            if let Err(error) = dbus_service::register(&connection, sender) {
+                warn!("Failed to register the D-Bus service: {}", error);
+            }
+
+            receiver.attach(None, |action| {
+                crate::perform(action);
+                glib::Continue(true)
+            });
+        }
+    });
+
+    // The arguments were read already, and are not options of the daemon.
+    process::exit(app.run_with_args(&env::args().take(1).collect::<Vec<_>>()));
+}
+
+/// Installs or uninstalls the systemd user service, for [`INSTALL_ARG`] and
+/// [`UNINSTALL_ARG`], and exits with a failure if that failed.
+pub fn set_installed(installed: bool) {
+    let service = UserService::new();
+    let result = match installed {
+        true => service.install(),
+        false => service.uninstall(),
+    };
+
+    match result {
+        Ok(()) if installed => println!("Started {}", service.path().display()),
+        Ok(()) => println!("Removed {}", service.path().display()),
+        Err(error) => {
+            eprintln!("The service could not be changed: {}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! The `org.nordvpn.Viking` interface, which lets shell extensions, scripts
+//! and other applications read the connection status and control the VPN
+//! through the application.
+//!
+//! It is exported on the application's own connection, so it can be reached
+//! at `com.github.spikespaz.nordvpn-viking` while the application runs, and
+//! at `com.github.spikespaz.nordvpn-viking.Daemon` while the daemon does:
+//!
+//! ```sh
+//! gdbus call --session --dest com.github.spikespaz.nordvpn-viking \
+//!     --object-path /com/github/spikespaz/nordvpn_viking \
+//!     --method org.nordvpn.Viking.Connect country:Germany
+//! ```
+
+use crate::status_monitor;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use nordvpn_viking::nordvpn::{ConnectOption, Status, StatusEvent};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+pub const OBJECT_PATH: &str = "/com/github/spikespaz/nordvpn_viking";
+pub const INTERFACE_NAME: &str = "org.nordvpn.Viking";
+
+const INTERFACE: &str = r#"
+<node>
+    <interface name="org.nordvpn.Viking">
+        <method name="Connect">
+            <arg type="s" name="target" direction="in"/>
+        </method>
+        <method name="Disconnect"/>
+        <method name="QuickConnect"/>
+        <method name="SetTarget">
+            <arg type="s" name="target" direction="in"/>
+        </method>
+        <method name="ExpectDisconnect"/>
+        <property name="Status" type="s" access="read"/>
+        <property name="Server" type="s" access="read"/>
+        <property name="Transfer" type="(tt)" access="read"/>
+    </interface>
+</node>
+"#;
+
+/// Requests from other applications which have to be handled on the GTK
+/// main thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServiceAction {
+    Connect(ConnectOption),
+    Disconnect,
+    QuickConnect,
+    /// What the user connected to elsewhere, or `None` for the CLI's choice,
+    /// for the watchdog to reconnect to.
+    SetTarget(Option<ConnectOption>),
+    /// The user is about to disconnect elsewhere, which the watchdog should
+    /// not undo.
+    ExpectDisconnect,
+}
+
+/// Exports the interface, and announces changes to its properties as the
+/// [`status_monitor`] sees them.
+pub fn register(
+    connection: &gio::DBusConnection,
+    actions: glib::Sender<ServiceAction>,
+) -> Result<(), glib::Error> {
+    let interface = gio::DBusNodeInfo::for_xml(INTERFACE)?
+        .lookup_interface(INTERFACE_NAME)
+        .expect("Failed to find the service interface");
+    let status = Arc::new(Mutex::new(None));
+    let actions = Mutex::new(actions);
+
+    let watched = status.clone();
+    let signals = connection.clone();
+    status_monitor::subscribe(move |event| {
+        let mut status = watched.lock().unwrap();
+        let changed = match event {
+            Ok(StatusEvent::Connected(next) | StatusEvent::ServerChanged(next)) => {
+                *status = Some(next.clone());
+                ["Status", "Server", "Transfer"].as_slice()
+            }
+            Ok(StatusEvent::Disconnected) => {
+                *status = None;
+                ["Status", "Server", "Transfer"].as_slice()
+            }
+            Ok(StatusEvent::TransferUpdated(transfer)) => match status.as_mut() {
+                Some(status) => {
+                    status.transfer = *transfer;
+                    ["Transfer"].as_slice()
+                }
+                None => return glib::Continue(true),
+            },
+            Err(_) => return glib::Continue(true),
+        };
+        let changed = changed
+            .iter()
+            .map(|name| (name.to_string(), property(status.as_ref(), name)))
+            .collect::<HashMap<_, _>>();
+
+        // Nobody may be listening, which is not an error worth reporting.
+        signals
+            .emit_signal(
+                None,
+                OBJECT_PATH,
+                "org.freedesktop.DBus.Properties",
+                "PropertiesChanged",
+                Some(&(INTERFACE_NAME, changed, Vec::<String>::new()).to_variant()),
+            )
+            .ok();
+
+        glib::Continue(true)
+    });
+
+    connection.register_object(
+        OBJECT_PATH,
+        &interface,
+        move |_, _, _, _, method, parameters, invocation| {
+            let send = |action| {
+                // The receiver only goes away when the application is quitting.
+                actions.lock().unwrap().send(action).ok();
+            };
+
+            match method {
+                "Connect" => {
+                    let target = parameters.get::<(String,)>().map(|(target,)| target);
+
+                    match target.as_deref().unwrap_or_default().parse() {
+                        Ok(option) => send(ServiceAction::Connect(option)),
+                        Err(error) => {
+                            invocation.return_dbus_error(
+                                "org.freedesktop.DBus.Error.InvalidArgs",
+                                &error.to_string(),
+                            );
+                            return;
+                        }
+                    }
+                }
+                "Disconnect" => send(ServiceAction::Disconnect),
+                "QuickConnect" => send(ServiceAction::QuickConnect),
+                "SetTarget" => {
+                    let target = parameters.get::<(String,)>().map(|(target,)| target);
+
+                    match target.as_deref().unwrap_or_default() {
+                        "" => send(ServiceAction::SetTarget(None)),
+                        target => match target.parse() {
+                            Ok(option) => send(ServiceAction::SetTarget(Some(option))),
+                            Err(error) => {
+                                invocation.return_dbus_error(
+                                    "org.freedesktop.DBus.Error.InvalidArgs",
+                                    &error.to_string(),
+                                );
+                                return;
+                            }
+                        },
+                    }
+                }
+                "ExpectDisconnect" => send(ServiceAction::ExpectDisconnect),
+                _ => (),
+            }
+
+            invocation.return_value(None);
+        },
+        move |_, _, _, _, name| property(status.lock().unwrap().as_ref(), name),
+        // The properties are read-only, so this is never called.
+        |_, _, _, _, _, _| false,
+    )?;
+
+    Ok(())
+}
+
+/// The value of the property `name` while connected with `status`.
+fn property(status: Option<&Status>, name: &str) -> glib::Variant {
+    match name {
+        "Status" => match status {
+            Some(_) => "Connected",
+            None => "Disconnected",
+        }
+        .to_variant(),
+        "Server" => status
+            .map(|status| status.hostname.as_str())
+            .unwrap_or_default()
+            .to_variant(),
+        "Transfer" => status
+            .map(|status| {
+                (
+                    status.transfer.received.get_bytes() as u64,
+                    status.transfer.sent.get_bytes() as u64,
+                )
+            })
+            .unwrap_or_default()
+            .to_variant(),
+        _ => unreachable!("unknown property `{}`", name),
+    }
+}
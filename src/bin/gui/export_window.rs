@@ -0,0 +1,175 @@
+use crate::i18n::{gettext, gettext_f};
+use chrono::{Datelike, Local};
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::export::{self, DateRange, ExportData, ExportFormat, ExportResult};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::thread;
+
+/// The choices of the data dropdown, in order.
+const DATA: [(ExportData, &str); 2] = [
+    (ExportData::History, "history"),
+    (ExportData::Usage, "usage"),
+];
+
+/// The choices of the format dropdown, in order.
+const FORMATS: [ExportFormat; 2] = [ExportFormat::Csv, ExportFormat::Json];
+
+glib::wrapper! {
+    pub struct VikingExportWindow(ObjectSubclass<imp::VikingExportWindow>)
+    @extends gtk::Widget, gtk::Window,
+    @implements gtk::Root;
+}
+
+impl VikingExportWindow {
+    pub fn new<P: IsA<gtk::Window>>(parent: &P) -> Self {
+        glib::Object::new(&[("transient-for", parent)])
+            .expect("Failed to create `VikingExportWindow`")
+    }
+
+    fn setup(&self) {
+        let imp = imp::VikingExportWindow::from_instance(self);
+
+        imp.export_button
+            .connect_clicked(glib::clone!(@weak self as window => move |_| window.choose_file()));
+    }
+
+    fn selection(&self) -> (ExportData, &'static str, ExportFormat, DateRange) {
+        let imp = imp::VikingExportWindow::from_instance(self);
+        let (data, name) = DATA[imp.data_dropdown.selected() as usize % DATA.len()];
+        let format = FORMATS[imp.format_dropdown.selected() as usize % FORMATS.len()];
+        let today = Local::now().date_naive();
+        let range = match imp.range_dropdown.selected() {
+            0 => DateRange::last_days(7, today),
+            1 => DateRange::last_days(30, today),
+            2 => DateRange::new(today.with_day(1), Some(today)),
+            3 => DateRange::last_days(365, today),
+            _ => DateRange::all(),
+        };
+
+        (data, name, format, range)
+    }
+
+    fn choose_file(&self) {
+        let imp = imp::VikingExportWindow::from_instance(self);
+        let (_, name, format, _) = self.selection();
+        let chooser = gtk::FileChooserNative::new(
+            Some(&gettext("Export Statistics")),
+            Some(self),
+            gtk::FileChooserAction::Save,
+            Some(&gettext("_Export")),
+            None,
+        );
+
+        chooser.set_modal(true);
+        chooser.set_current_name(&format!(
+            "nordvpn-{}-{}.{}",
+            name,
+            Local::now().format("%Y-%m-%d"),
+            format.extension()
+        ));
+        chooser.connect_response(
+            glib::clone!(@weak self as window => move |chooser, response| {
+                let path = chooser.file().and_then(|file| file.path());
+
+                if let (gtk::ResponseType::Accept, Some(path)) = (response, path) {
+                    window.export(path);
+                }
+
+                let imp = imp::VikingExportWindow::from_instance(&window);
+                imp.chooser.replace(None);
+            }),
+        );
+        chooser.show();
+        // The dialog is gone once the last reference is.
+        imp.chooser.replace(Some(chooser));
+    }
+
+    fn export(&self, path: PathBuf) {
+        let imp = imp::VikingExportWindow::from_instance(self);
+        let (data, _, format, range) = self.selection();
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        imp.export_button.set_sensitive(false);
+        imp.status_label.set_label(&gettext("Exporting…"));
+        imp.status_label.set_visible(true);
+
+        thread::spawn(move || {
+            let result = (|| -> ExportResult<()> {
+                let file = File::create(&path)?;
+                export::export(BufWriter::new(file), data, format, range)
+            })();
+
+            sender
+                .send(result.map(|_| path).map_err(|error| error.to_string()))
+                .ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as window => @default-return glib::Continue(false), move |result| {
+                let imp = imp::VikingExportWindow::from_instance(&window);
+
+                imp.export_button.set_sensitive(true);
+                match result {
+                    Ok(path) => imp
+                        .status_label
+                        .set_label(&gettext_f("Exported to {}", &[&path.display()])),
+                    Err(error) => imp.status_label.set_label(&error),
+                }
+                glib::Continue(false)
+            }),
+        );
+    }
+}
+
+mod imp {
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use std::cell::RefCell;
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/export_window.ui")]
+    pub struct VikingExportWindow {
+        #[template_child]
+        pub data_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub range_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub format_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub status_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub export_button: TemplateChild<gtk::Button>,
+        pub chooser: RefCell<Option<gtk::FileChooserNative>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingExportWindow {
+        const NAME: &'static str = "VikingExportWindow";
+        type Type = super::VikingExportWindow;
+        type ParentType = gtk::Window;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingExportWindow {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingExportWindow {}
+    impl WindowImpl for VikingExportWindow {}
+}
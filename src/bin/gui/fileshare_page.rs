@@ -0,0 +1,389 @@
+use crate::i18n::{gettext, gettext_f, ngettext_f};
+use crate::meshnet_page::row_content;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{gdk, gio, glib};
+use nordvpn_viking::nordvpn::fileshare::{self, FileTransfer, TransferStatus, Transfers};
+use nordvpn_viking::nordvpn::meshnet::{self, Peer, PeerStatus};
+use nordvpn_viking::nordvpn::{CliError, CliResult};
+use std::path::PathBuf;
+use std::thread;
+
+glib::wrapper! {
+    pub struct VikingFilesharePage(ObjectSubclass<imp::VikingFilesharePage>)
+    @extends gtk::Widget, gtk::Box,
+    @implements gtk::Orientable;
+}
+
+/// How often the transfers are read again while the page is shown.
+const REFRESH_SECONDS: u32 = 2;
+
+/// The peers and transfers, or `None` if meshnet is turned off.
+type Loaded = Option<(Vec<Peer>, Transfers)>;
+
+impl VikingFilesharePage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingFilesharePage`")
+    }
+
+    fn setup(&self) {
+        self.connect_map(|page| page.run(|| Ok(())));
+
+        // Transfers arrive and progress on their own. This leaves the page
+        // usable, and the rows are only rebuilt when something changed.
+        glib::timeout_add_seconds_local(
+            REFRESH_SECONDS,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move || {
+                let imp = imp::VikingFilesharePage::from_instance(&page);
+
+                if page.is_mapped() && !imp.loading.get() {
+                    page.load(|| Ok(()));
+                }
+                glib::Continue(true)
+            }),
+        );
+    }
+
+    /// Makes the change in the background, then reads the peers and
+    /// transfers back so the page shows what the daemon actually has.
+    fn run<F>(&self, edit: F)
+    where
+        F: FnOnce() -> CliResult<()> + Send + 'static,
+    {
+        self.set_sensitive(false);
+        self.load(edit);
+    }
+
+    fn load<F>(&self, edit: F)
+    where
+        F: FnOnce() -> CliResult<()> + Send + 'static,
+    {
+        let imp = imp::VikingFilesharePage::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        imp.loading.set(true);
+
+        thread::spawn(move || {
+            let load = || -> CliResult<Loaded> {
+                let peers = match meshnet::peers() {
+                    Ok(peers) => peers,
+                    Err(CliError::MeshnetDisabled) => return Ok(None),
+                    Err(error) => return Err(error),
+                };
+                let peers = peers.local.into_iter().chain(peers.external).collect();
+
+                Ok(Some((peers, fileshare::transfers()?)))
+            };
+            // Whatever the change did, the page should reflect the CLI.
+            let result = edit()
+                .and_then(|_| load())
+                .map_err(|error| (error.to_string(), load().ok()));
+
+            sender.send(result).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |result: Result<Loaded, (String, Option<Loaded>)>| {
+                let imp = imp::VikingFilesharePage::from_instance(&page);
+
+                imp.loading.set(false);
+                imp.error_label.set_visible(false);
+                page.set_sensitive(true);
+
+                match result {
+                    Ok(loaded) => page.update(loaded),
+                    Err((error, loaded)) => {
+                        if let Some(loaded) = loaded {
+                            page.update(loaded);
+                        }
+                        imp.error_label.set_label(&error);
+                        imp.error_label.set_visible(true);
+                    }
+                }
+
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    fn update(&self, loaded: Loaded) {
+        let imp = imp::VikingFilesharePage::from_instance(self);
+
+        // Rebuilding the rows would end a drag onto one of them.
+        if imp.loaded.borrow().as_ref() == Some(&loaded) {
+            return;
+        }
+        imp.loaded.replace(Some(loaded.clone()));
+
+        for list_box in [
+            &*imp.peers_list_box,
+            &*imp.incoming_list_box,
+            &*imp.outgoing_list_box,
+        ] {
+            while let Some(row) = list_box.first_child() {
+                list_box.remove(&row);
+            }
+        }
+
+        imp.disabled_box.set_visible(loaded.is_none());
+        imp.enabled_box.set_visible(loaded.is_some());
+
+        let (peers, transfers) = match loaded {
+            Some(loaded) => loaded,
+            None => return,
+        };
+
+        for peer in &peers {
+            imp.peers_list_box.append(&self.peer_row(peer));
+        }
+
+        // The newest transfers are listed last by the CLI.
+        for transfer in transfers.incoming.iter().rev() {
+            imp.incoming_list_box
+                .append(&self.transfer_row(transfer, true));
+        }
+
+        for transfer in transfers.outgoing.iter().rev() {
+            imp.outgoing_list_box
+                .append(&self.transfer_row(transfer, false));
+        }
+    }
+
+    fn send(&self, hostname: &str, paths: Vec<PathBuf>) {
+        let hostname = hostname.to_owned();
+
+        self.run(move || fileshare::send(&hostname, paths).map(drop));
+    }
+
+    fn peer_row(&self, peer: &Peer) -> gtk::ListBoxRow {
+        let online = peer.status == PeerStatus::Connected;
+        let content = row_content(
+            peer.nickname.as_deref().unwrap_or(&peer.hostname),
+            &match online {
+                true => gettext("Online"),
+                false => gettext("Offline"),
+            },
+        );
+        let choose = gtk::Button::from_icon_name(Some("document-send-symbolic"));
+        // Only one file is given for each drop by the bindings of GTK 4.4,
+        // the chooser allows several.
+        let target = gtk::DropTarget::new(gio::File::static_type(), gdk::DragAction::COPY);
+        let row = gtk::ListBoxRow::new();
+        let hostname = peer.hostname.clone();
+
+        choose.set_valign(gtk::Align::Center);
+        choose.set_tooltip_text(Some(&gettext("Send Files…")));
+        choose.add_css_class("flat");
+        choose.connect_clicked(
+            glib::clone!(@weak self as page, @strong hostname => move |_| {
+                page.choose_files(&hostname);
+            }),
+        );
+        target.connect_drop(
+            glib::clone!(@weak self as page => @default-return false, move |_, value, _, _| {
+                match value.get::<gio::File>().ok().and_then(|file| file.path()) {
+                    Some(path) => {
+                        page.send(&hostname, vec![path]);
+                        true
+                    }
+                    None => false,
+                }
+            }),
+        );
+        content.append(&choose);
+        row.set_activatable(false);
+        row.set_sensitive(online);
+        row.set_child(Some(&content));
+        row.add_controller(&target);
+
+        row
+    }
+
+    fn choose_files(&self, hostname: &str) {
+        let imp = imp::VikingFilesharePage::from_instance(self);
+        let chooser = gtk::FileChooserNative::new(
+            Some(&gettext_f("Send Files to {}", &[&hostname])),
+            self.root()
+                .and_then(|root| root.downcast::<gtk::Window>().ok())
+                .as_ref(),
+            gtk::FileChooserAction::Open,
+            Some(&gettext("_Send")),
+            None,
+        );
+        let hostname = hostname.to_owned();
+
+        chooser.set_modal(true);
+        chooser.set_select_multiple(true);
+        chooser.connect_response(
+            glib::clone!(@weak self as page => move |chooser, response| {
+                let imp = imp::VikingFilesharePage::from_instance(&page);
+
+                if response == gtk::ResponseType::Accept {
+                    let paths = chooser
+                        .files()
+                        .map(|files| {
+                            (0..files.n_items())
+                                .filter_map(|index| files.item(index))
+                                .filter_map(|file| file.downcast::<gio::File>().ok())
+                                .filter_map(|file| file.path())
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default();
+
+                    if !paths.is_empty() {
+                        page.send(&hostname, paths);
+                    }
+                }
+
+                imp.chooser.replace(None);
+            }),
+        );
+        chooser.show();
+        // The dialog is gone once the last reference is.
+        imp.chooser.replace(Some(chooser));
+    }
+
+    fn transfer_row(&self, transfer: &FileTransfer, incoming: bool) -> gtk::Box {
+        let mut details = vec![status_label(&transfer.status)];
+
+        if let Some(files) = transfer.files {
+            details.push(ngettext_f("{} file", "{} files", files, &[&files]));
+        }
+        if let Some(transferred) = &transfer.transferred {
+            details.push(transferred.clone());
+        }
+        if let Some(created) = &transfer.created {
+            details.push(created.clone());
+        }
+
+        let content = row_content(&transfer.peer, &details.join(" · "));
+
+        if transfer.status == TransferStatus::InProgress {
+            let progress = gtk::ProgressBar::new();
+            let fraction = transfer.transferred.as_deref().and_then(percent);
+
+            progress.set_valign(gtk::Align::Center);
+            progress.set_width_request(80);
+            match fraction {
+                Some(fraction) => progress.set_fraction(fraction),
+                None => progress.pulse(),
+            }
+            content.append(&progress);
+        }
+
+        let mut actions = Vec::new();
+
+        match transfer.status {
+            TransferStatus::Requested if incoming => {
+                actions.push(("object-select-symbolic", gettext("Accept"), true));
+                actions.push(("window-close-symbolic", gettext("Decline"), false));
+            }
+            TransferStatus::Requested | TransferStatus::InProgress => {
+                actions.push(("process-stop-symbolic", gettext("Cancel"), false));
+            }
+            _ => (),
+        }
+
+        for (icon, tooltip, accept) in actions {
+            let button = gtk::Button::from_icon_name(Some(icon));
+            let id = transfer.id.clone();
+
+            button.set_valign(gtk::Align::Center);
+            button.set_tooltip_text(Some(&tooltip));
+            button.add_css_class("flat");
+            button.connect_clicked(glib::clone!(@weak self as page => move |_| {
+                let id = id.clone();
+
+                page.run(move || match accept {
+                    true => fileshare::accept(&id, None),
+                    false => fileshare::cancel(&id),
+                });
+            }));
+            content.append(&button);
+        }
+
+        content
+    }
+}
+
+impl Default for VikingFilesharePage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn status_label(status: &TransferStatus) -> String {
+    match status {
+        TransferStatus::Requested => gettext("Waiting"),
+        TransferStatus::InProgress => gettext("In Progress"),
+        TransferStatus::Completed => gettext("Completed"),
+        TransferStatus::Canceled => gettext("Canceled"),
+        TransferStatus::Failed => gettext("Failed"),
+        TransferStatus::Other(status) => status.clone(),
+    }
+}
+
+/// The fraction done from a column such as `45%` or `45.5 %`.
+fn percent(transferred: &str) -> Option<f64> {
+    let percent = transferred.trim().strip_suffix('%')?.trim();
+
+    percent
+        .parse::<f64>()
+        .ok()
+        .map(|percent| (percent / 100.0).clamp(0.0, 1.0))
+}
+
+mod imp {
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/fileshare_page.ui")]
+    pub struct VikingFilesharePage {
+        #[template_child]
+        pub error_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub disabled_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub enabled_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub peers_list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub incoming_list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub outgoing_list_box: TemplateChild<gtk::ListBox>,
+        pub chooser: RefCell<Option<gtk::FileChooserNative>>,
+        /// Whether the CLI is being read, so that refreshes do not pile up.
+        pub loading: Cell<bool>,
+        /// What the rows were last built from.
+        pub loaded: RefCell<Option<super::Loaded>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingFilesharePage {
+        const NAME: &'static str = "VikingFilesharePage";
+        type Type = super::VikingFilesharePage;
+        type ParentType = gtk::Box;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingFilesharePage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingFilesharePage {}
+    impl BoxImpl for VikingFilesharePage {}
+}
@@ -0,0 +1,41 @@
+//! Country flags from the `flag-icons` set, which is compiled into the
+//! resource bundle so they can be shown without network access.
+
+use gtk::gio;
+
+const FLAGS_PREFIX: &str = "/com/github/spikespaz/nordvpn-viking/flags";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FlagShape {
+    /// A 1:1 flag, for icons next to list rows.
+    Square,
+    /// A 4:3 flag, for larger illustrations.
+    Rectangle,
+}
+
+/// The resource path of the flag for an ISO 3166-1 alpha-2 country code, or
+/// `None` if the bundle has no flag for it.
+pub fn flag_path(code: &str, shape: FlagShape) -> Option<String> {
+    let shape = match shape {
+        FlagShape::Square => "square",
+        FlagShape::Rectangle => "rectangle",
+    };
+    // NordVPN uses `UK` where the flag set follows ISO with `GB`.
+    let code = match code.to_ascii_lowercase().as_str() {
+        "uk" => "gb".to_owned(),
+        code => code.to_owned(),
+    };
+    let path = format!("{}/{}/{}.svg", FLAGS_PREFIX, shape, code);
+
+    gio::resources_get_info(&path, gio::ResourceLookupFlags::NONE)
+        .ok()
+        .map(|_| path)
+}
+
+/// An image of the flag, or a generic icon if the bundle has no flag.
+pub fn flag_image(code: &str, shape: FlagShape) -> gtk::Image {
+    match flag_path(code, shape) {
+        Some(path) => gtk::Image::from_resource(&path),
+        None => gtk::Image::from_icon_name(Some("mark-location-symbolic")),
+    }
+}
@@ -0,0 +1,265 @@
+use crate::i18n::{gettext, gettext_f};
+use byte_unit::Byte;
+use chrono::Local;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::history::{History, HistoryEntry};
+use nordvpn_viking::nordvpn::ConnectOption;
+use nordvpn_viking::stats::{Period, Stats, StatsResult};
+
+glib::wrapper! {
+    pub struct VikingHistoryPage(ObjectSubclass<imp::VikingHistoryPage>)
+    @extends gtk::Widget, gtk::Box,
+    @implements gtk::Orientable;
+}
+
+impl VikingHistoryPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingHistoryPage`")
+    }
+
+    /// Calls `callback` with the server of every entry the user reconnects to.
+    pub fn connect_target_activated<F: Fn(&ConnectOption) + 'static>(&self, callback: F) {
+        let imp = imp::VikingHistoryPage::from_instance(self);
+
+        imp.callbacks.borrow_mut().push(Box::new(callback));
+    }
+
+    fn setup(&self) {
+        let imp = imp::VikingHistoryPage::from_instance(self);
+
+        // Connections may have ended while the page was hidden.
+        self.connect_map(|page| {
+            page.reload();
+            page.reload_usage();
+        });
+        imp.clear_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| {
+                let imp = imp::VikingHistoryPage::from_instance(&page);
+
+                match History::new().clear() {
+                    Ok(()) => page.reload(),
+                    Err(error) => imp.status_label.set_label(&error.to_string()),
+                }
+            }));
+    }
+
+    fn reload(&self) {
+        let imp = imp::VikingHistoryPage::from_instance(self);
+
+        while let Some(row) = imp.list_box.first_child() {
+            imp.list_box.remove(&row);
+        }
+
+        let entries = match History::new().load() {
+            Ok(entries) => entries,
+            Err(error) => {
+                imp.status_label.set_label(&error.to_string());
+                imp.status_label.set_visible(true);
+                return;
+            }
+        };
+
+        for entry in entries.iter().rev() {
+            imp.list_box.append(&self.entry_row(entry));
+        }
+
+        imp.status_label.set_label(&gettext("No connections yet"));
+        imp.status_label.set_visible(entries.is_empty());
+        imp.clear_button.set_sensitive(!entries.is_empty());
+    }
+
+    fn reload_usage(&self) {
+        let imp = imp::VikingHistoryPage::from_instance(self);
+        let labels = [
+            &*imp.today_label,
+            &*imp.week_label,
+            &*imp.month_label,
+            &*imp.countries_label,
+        ];
+
+        match usage() {
+            Ok(usage) => {
+                for (label, text) in labels.iter().zip(usage) {
+                    label.set_label(&text);
+                }
+            }
+            Err(error) => {
+                for label in labels {
+                    label.set_label(&gettext("Unknown"));
+                }
+                imp.countries_label.set_label(&error.to_string());
+            }
+        }
+    }
+
+    fn entry_row(&self, entry: &HistoryEntry) -> gtk::ListBoxRow {
+        let title = gtk::Label::new(Some(&format!(
+            "{} ({}, {})",
+            entry.hostname, entry.city, entry.country
+        )));
+        let subtitle = gtk::Label::new(Some(&describe(entry)));
+        let reconnect = gtk::Button::from_icon_name(Some("view-refresh-symbolic"));
+        let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let row = gtk::ListBoxRow::new();
+        let option = entry.option();
+
+        title.set_xalign(0.0);
+        subtitle.set_xalign(0.0);
+        subtitle.add_css_class("dim-label");
+        subtitle.add_css_class("caption");
+        reconnect.set_valign(gtk::Align::Center);
+        reconnect.set_tooltip_text(Some(&gettext("Connect again")));
+        reconnect.add_css_class("flat");
+        reconnect.connect_clicked(glib::clone!(@weak self as page => move |_| {
+            let imp = imp::VikingHistoryPage::from_instance(&page);
+
+            for callback in imp.callbacks.borrow().iter() {
+                callback(&option);
+            }
+        }));
+        labels.set_hexpand(true);
+        labels.append(&title);
+        labels.append(&subtitle);
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&labels);
+        content.append(&reconnect);
+        row.set_activatable(false);
+        row.set_child(Some(&content));
+
+        row
+    }
+}
+
+impl Default for VikingHistoryPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The totals of today, this week and this month, and the countries with
+/// the most carried this month.
+fn usage() -> StatsResult<[String; 4]> {
+    let stats = Stats::open()?;
+    let today = Local::now().date_naive();
+    let total = |period: Period| -> StatsResult<String> {
+        let totals = stats.totals(period, today, today)?;
+
+        Ok(match totals.first() {
+            Some(total) => format_transfer(total.received, total.sent),
+            None => gettext("Nothing yet"),
+        })
+    };
+    let month = Period::Month.start_of(today);
+    let countries = stats
+        .by_country(month, today)?
+        .iter()
+        .take(3)
+        .map(|country| {
+            format!(
+                "{} ({})",
+                country.country,
+                Byte::from_bytes((country.received + country.sent) as u128)
+                    .get_appropriate_unit(true)
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok([
+        total(Period::Day)?,
+        total(Period::Week)?,
+        total(Period::Month)?,
+        match countries.is_empty() {
+            true => gettext("Nothing yet"),
+            false => countries.join(", "),
+        },
+    ])
+}
+
+fn format_transfer(received: u64, sent: u64) -> String {
+    gettext_f(
+        "{} received, {} sent",
+        &[
+            &Byte::from_bytes(received as u128).get_appropriate_unit(true),
+            &Byte::from_bytes(sent as u128).get_appropriate_unit(true),
+        ],
+    )
+}
+
+/// When and for how long the connection was up, and how much it carried.
+fn describe(entry: &HistoryEntry) -> String {
+    let duration = match entry.disconnected {
+        Some(disconnected) => {
+            let minutes = (disconnected - entry.connected).num_minutes();
+            format!("{}h {:02}m", minutes / 60, minutes % 60)
+        }
+        None => gettext("ongoing"),
+    };
+
+    format!(
+        "{}, {}, {}",
+        entry.connected.format("%Y-%m-%d %H:%M"),
+        duration,
+        format_transfer(entry.received, entry.sent)
+    )
+}
+
+mod imp {
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use nordvpn_viking::nordvpn::ConnectOption;
+    use std::cell::RefCell;
+
+    type Callback = Box<dyn Fn(&ConnectOption)>;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/history_page.ui")]
+    pub struct VikingHistoryPage {
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub status_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub clear_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub today_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub week_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub month_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub countries_label: TemplateChild<gtk::Label>,
+        pub callbacks: RefCell<Vec<Callback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingHistoryPage {
+        const NAME: &'static str = "VikingHistoryPage";
+        type Type = super::VikingHistoryPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingHistoryPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingHistoryPage {}
+    impl BoxImpl for VikingHistoryPage {}
+}
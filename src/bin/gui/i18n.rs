@@ -0,0 +1,101 @@
+//! Translation of the interface with gettext. Strings in the templates are
+//! marked `translatable`, and those in code go through [`gettext`],
+//! [`gettext_f`] and [`ngettext_f`], so that `xgettext` can extract them with
+//! `--keyword=gettext_f --keyword=ngettext_f:1,2`.
+
+use gettextrs::LocaleCategory;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+pub use gettextrs::gettext;
+
+/// The name of the translation catalogs, `nordvpn-viking.mo`.
+pub const GETTEXT_PACKAGE: &str = env!("CARGO_PKG_NAME");
+
+/// Where the catalogs are installed, which packagers can change by setting
+/// `NORDVPN_VIKING_LOCALEDIR` when building.
+pub const LOCALEDIR: &str = match option_env!("NORDVPN_VIKING_LOCALEDIR") {
+    Some(dir) => dir,
+    None => "/usr/share/locale",
+};
+
+/// Where the build script compiles the catalogs in `po`, which are used when
+/// none are installed, such as when running from the source tree.
+const BUILD_LOCALEDIR: &str = concat!(env!("OUT_DIR"), "/locale");
+
+/// Sets the locale from the environment and binds the translations, which
+/// must happen before any strings are translated or templates are built.
+///
+/// The `language` from the config, such as `de` or `pt_BR`, is preferred
+/// over the languages of the environment.
+pub fn init(language: Option<&str>) -> io::Result<()> {
+    if let Some(language) = language.filter(|language| !language.is_empty()) {
+        // Read by gettext before `LC_ALL`, `LC_MESSAGES` and `LANG`.
+        env::set_var("LANGUAGE", language);
+    }
+
+    gettextrs::setlocale(LocaleCategory::LcAll, "");
+    gettextrs::bindtextdomain(GETTEXT_PACKAGE, localedir())?;
+    gettextrs::bind_textdomain_codeset(GETTEXT_PACKAGE, "UTF-8")?;
+    gettextrs::textdomain(GETTEXT_PACKAGE)?;
+
+    Ok(())
+}
+
+/// The languages which have a catalog installed, sorted by code.
+pub fn languages() -> Vec<String> {
+    languages_in(localedir())
+}
+
+/// The installed catalogs, or else the ones compiled by the build.
+fn localedir() -> &'static str {
+    match languages_in(LOCALEDIR).is_empty() && !languages_in(BUILD_LOCALEDIR).is_empty() {
+        true => BUILD_LOCALEDIR,
+        false => LOCALEDIR,
+    }
+}
+
+fn languages_in(dir: &str) -> Vec<String> {
+    let mut languages = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| {
+            let catalog = PathBuf::from("LC_MESSAGES").join(format!("{}.mo", GETTEXT_PACKAGE));
+            entry.path().join(catalog).is_file()
+        })
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+
+    languages.sort();
+    languages
+}
+
+/// Translates `format` and replaces each `{}` in it with the next of `args`.
+pub fn gettext_f(format: &str, args: &[&dyn Display]) -> String {
+    fill(&gettext(format), args)
+}
+
+/// Translates the form of `singular` or `plural` used for `n` in the current
+/// language, and replaces each `{}` in it with the next of `args`.
+pub fn ngettext_f(singular: &str, plural: &str, n: u32, args: &[&dyn Display]) -> String {
+    fill(&gettextrs::ngettext(singular, plural, n), args)
+}
+
+fn fill(format: &str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = format.split("{}");
+    let mut text = parts.next().unwrap_or_default().to_owned();
+
+    for part in parts {
+        if let Some(arg) = args.next() {
+            text += &arg.to_string();
+        }
+        text += part;
+    }
+
+    text
+}
@@ -0,0 +1,191 @@
+use crate::i18n::{gettext, ngettext_f};
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::dns_leak::{self, DnsLeakReport, Resolver};
+use std::thread;
+
+glib::wrapper! {
+    pub struct VikingLeakTestWindow(ObjectSubclass<imp::VikingLeakTestWindow>)
+    @extends gtk::Widget, gtk::Window,
+    @implements gtk::Root;
+}
+
+impl VikingLeakTestWindow {
+    /// A window which starts the test as soon as it is created.
+    pub fn new<P: IsA<gtk::Window>>(parent: &P) -> Self {
+        glib::Object::new(&[("transient-for", parent)])
+            .expect("Failed to create `VikingLeakTestWindow`")
+    }
+
+    fn setup(&self) {
+        let imp = imp::VikingLeakTestWindow::from_instance(self);
+
+        imp.run_button
+            .connect_clicked(glib::clone!(@weak self as window => move |_| window.run()));
+        self.run();
+    }
+
+    fn run(&self) {
+        let imp = imp::VikingLeakTestWindow::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        while let Some(row) = imp.list_box.first_child() {
+            imp.list_box.remove(&row);
+        }
+
+        imp.list_box.set_visible(false);
+        imp.run_button.set_sensitive(false);
+        imp.spinner.start();
+        imp.icon_stack.set_visible_child_name("running");
+        imp.result_label.set_label(&gettext("Testing…"));
+        imp.detail_label.set_label(&gettext(
+            "Looking up test hostnames through every configured resolver",
+        ));
+
+        thread::spawn(move || {
+            sender
+                .send(dns_leak::check().map_err(|error| error.to_string()))
+                .ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as window => @default-return glib::Continue(false), move |report| {
+                window.show(report);
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    fn show(&self, report: Result<DnsLeakReport, String>) {
+        let imp = imp::VikingLeakTestWindow::from_instance(self);
+
+        imp.spinner.stop();
+        imp.icon_stack.set_visible_child_name("finished");
+        imp.run_button.set_sensitive(true);
+
+        let report = match report {
+            Ok(report) => report,
+            Err(error) => {
+                imp.result_image
+                    .set_icon_name(Some("dialog-warning-symbolic"));
+                imp.result_label.set_label(&gettext("The Test Failed"));
+                imp.detail_label.set_label(&error);
+                return;
+            }
+        };
+        let leaks = report.leaks().count();
+
+        if report.passed() {
+            imp.result_image
+                .set_icon_name(Some("security-high-symbolic"));
+            imp.result_label.set_label(&gettext("No DNS Leaks"));
+            imp.detail_label.set_label(&gettext(
+                "Every lookup went through NordVPN or your custom DNS.",
+            ));
+        } else {
+            imp.result_image
+                .set_icon_name(Some("security-low-symbolic"));
+            imp.result_label.set_label(&gettext("DNS Is Leaking"));
+            imp.detail_label.set_label(&ngettext_f(
+                "{} of {} resolver is outside of the VPN, and can see the sites you visit.",
+                "{} of {} resolvers are outside of the VPN, and can see the sites you visit.",
+                report.resolvers.len() as u32,
+                &[&leaks, &report.resolvers.len()],
+            ));
+        }
+
+        if let Some(public_ip) = &report.public_ip {
+            imp.list_box
+                .append(&resolver_row(&gettext("Public Address"), public_ip));
+        }
+        for resolver in &report.resolvers {
+            let title = match resolver.trusted {
+                true => gettext("Resolver"),
+                false => gettext("Leaking Resolver"),
+            };
+
+            imp.list_box.append(&resolver_row(&title, resolver));
+        }
+
+        imp.list_box.set_visible(true);
+    }
+}
+
+fn resolver_row(title: &str, resolver: &Resolver) -> gtk::Box {
+    let title = gtk::Label::new(Some(&format!("{} {}", title, resolver.ip)));
+    let details = [resolver.network.as_deref(), resolver.country.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    let subtitle = gtk::Label::new(Some(&details.join(", ")));
+    let content = gtk::Box::new(gtk::Orientation::Vertical, 2);
+
+    title.set_xalign(0.0);
+    if !resolver.trusted {
+        title.add_css_class("error");
+    }
+    subtitle.set_xalign(0.0);
+    subtitle.set_wrap(true);
+    subtitle.add_css_class("dim-label");
+    subtitle.add_css_class("caption");
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.append(&title);
+    content.append(&subtitle);
+
+    content
+}
+
+mod imp {
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+
+    #[derive(Debug, Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/leak_test_window.ui")]
+    pub struct VikingLeakTestWindow {
+        #[template_child]
+        pub icon_stack: TemplateChild<gtk::Stack>,
+        #[template_child]
+        pub spinner: TemplateChild<gtk::Spinner>,
+        #[template_child]
+        pub result_image: TemplateChild<gtk::Image>,
+        #[template_child]
+        pub result_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub detail_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub run_button: TemplateChild<gtk::Button>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingLeakTestWindow {
+        const NAME: &'static str = "VikingLeakTestWindow";
+        type Type = super::VikingLeakTestWindow;
+        type ParentType = gtk::Window;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingLeakTestWindow {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingLeakTestWindow {}
+    impl WindowImpl for VikingLeakTestWindow {}
+}
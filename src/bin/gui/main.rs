@@ -1,22 +1,281 @@
+mod account_page;
+mod actions;
+mod allowlist_page;
+mod command_palette;
+mod daemon;
+mod dbus_service;
+mod export_window;
+mod fileshare_page;
+mod flags;
+mod history_page;
+mod i18n;
+mod leak_test_window;
 mod main_window;
+mod meshnet_page;
+mod network_monitor;
+mod palette;
+mod preferences;
+mod recorder;
+mod reminders;
 mod resources;
+mod scheduler;
+mod search_provider;
+mod server_browser;
+mod settings_page;
+mod speed_graph;
+mod status_monitor;
+mod status_page;
+mod tray;
+mod watchdog;
+mod world_map;
 
+use dbus_service::ServiceAction;
 use gtk::prelude::*;
+use gtk::{gio, glib, Application};
+use i18n::gettext;
 use main_window::VikingApplicationWindow;
+use nordvpn_viking::autostart::HIDDEN_ARG;
+use nordvpn_viking::logging;
+use nordvpn_viking::nordapi::{ApiCache, NordApi};
+use nordvpn_viking::nordvpn::{self, ConnectOption};
+use nordvpn_viking::systemd::DAEMON_ARG;
+use preferences::Preferences;
+use search_provider::SearchAction;
+use std::env;
+use std::thread;
+use tracing::warn;
+use tray::{TrayAction, VikingTray};
+
+/// The option taking a target to connect to, from a new or running
+/// instance.
+const CONNECT_OPTION: &str = "connect";
 
 fn main() {
+    logging::init();
+
+    let preferences = Preferences::new();
+
+    preferences.import_config();
+
+    let language = preferences.language();
+
+    if let Err(error) = i18n::init(language.as_deref()) {
+        warn!("Failed to set up translations: {}", error);
+    }
+
+    // Read before GTK, which would forward them to a running instance.
+    match env::args().nth(1).as_deref() {
+        Some(DAEMON_ARG) => return daemon::run(),
+        Some(daemon::INSTALL_ARG) => return daemon::set_installed(true),
+        Some(daemon::UNINSTALL_ARG) => return daemon::set_installed(false),
+        _ => (),
+    }
+
     resources::init();
 
-    let application = gtk::Application::new(
+    // Launching again forwards the command line to the running instance.
+    let application = Application::new(
         Some("com.github.spikespaz.nordvpn-viking"),
-        Default::default(),
+        gio::ApplicationFlags::HANDLES_COMMAND_LINE,
     );
 
-    application.connect_activate(|app| {
-        let window = VikingApplicationWindow::new(app);
+    application.add_main_option(
+        HIDDEN_ARG.trim_start_matches('-'),
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        &gettext("Start in the tray without opening a window"),
+        None,
+    );
+    application.add_main_option(
+        CONNECT_OPTION,
+        glib::Char::from(b'\0'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        &gettext("Connect to a country, city or server, such as “de” or “country:Germany”"),
+        Some(&gettext("TARGET")),
+    );
+
+    application.connect_startup(|app| {
+        // Registers the adaptive widgets used by the templates.
+        adw::init();
+        // Found in the resources when the icon is not installed.
+        gtk::Window::set_default_icon_name("com.github.spikespaz.nordvpn-viking");
+        Preferences::new().watch();
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        let quit = gio::SimpleAction::new("quit", None);
+
+        quit.connect_activate(glib::clone!(@weak app => move |_, _| app.quit()));
+        app.add_action(&quit);
+
+        // Activated from the actions of the desktop entry, without a window.
+        let quick_connect = gio::SimpleAction::new("quick-connect", None);
+        let disconnect = gio::SimpleAction::new("disconnect", None);
+
+        quick_connect
+            .connect_activate(|_, _| connect_in_background(Preferences::new().default_target()));
+        disconnect.connect_activate(|_, _| {
+            watchdog::disconnecting();
+            thread::spawn(nordvpn::disconnect);
+        });
+        app.add_action(&quick_connect);
+        app.add_action(&disconnect);
+        actions::set_accels(app);
+
+        status_monitor::spawn();
+        VikingTray::spawn(sender);
+
+        // While the daemon runs, it reconnects, keeps the schedule and
+        // records the history and transfer totals.
+        let deferred = app
+            .dbus_connection()
+            .is_some_and(|connection| watchdog::defer_to_daemon(&connection));
+
+        if !deferred {
+            watchdog::spawn(app.upcast_ref());
+            scheduler::spawn(app.upcast_ref());
+            recorder::spawn();
+        }
+        reminders::spawn(app);
+
+        if let Err(error) = network_monitor::watch() {
+            warn!("Failed to watch the network for changes: {}", error);
+        }
+
+        // The tray keeps the application running after the window is closed.
+        app.hold();
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak app => @default-return glib::Continue(false), move |action| {
+                match action {
+                    TrayAction::ShowWindow => {
+                        present_window(&app);
+                    }
+                    TrayAction::Quit => app.quit(),
+                }
+
+                glib::Continue(true)
+            }),
+        );
+
+        if let Some(connection) = app.dbus_connection() {
+            let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+            if let Err(error) = search_provider::register(&connection, sender) {
+                warn!("Failed to register the search provider: {}", error);
+            }
 
-        window.show();
+            receiver.attach(
+                None,
+                glib::clone!(@weak app => @default-return glib::Continue(false), move |action| {
+                    match action {
+                        SearchAction::Connect(option) => present_window(&app).connect_to(option),
+                        SearchAction::Search(terms) => present_window(&app).search(&terms),
+                    }
+
+                    glib::Continue(true)
+                }),
+            );
+
+            let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+            if let Err(error) = dbus_service::register(&connection, sender) {
+                warn!("Failed to register the D-Bus service: {}", error);
+            }
+
+            receiver.attach(None, |action| {
+                perform(action);
+                glib::Continue(true)
+            });
+        }
+    });
+
+    application.connect_command_line(|app, command_line| {
+        let options = command_line.options_dict();
+        let hidden = options.contains(HIDDEN_ARG.trim_start_matches('-'));
+        let target = options
+            .lookup_value(CONNECT_OPTION, None)
+            .and_then(|target| target.get::<String>())
+            .map(|target| parse_target(&target));
+
+        match (target, hidden) {
+            (Some(option), false) => present_window(app).connect_to(option),
+            (Some(option), true) => connect_in_background(Some(option)),
+            // Started on login, so only the tray comes up.
+            (None, true) => (),
+            (None, false) => app.activate(),
+        }
+
+        0
+    });
+
+    application.connect_activate(|app| {
+        present_window(app);
     });
 
     application.run();
 }
+
+fn present_window(app: &Application) -> VikingApplicationWindow {
+    let window = app
+        .active_window()
+        .and_then(|window| window.downcast().ok())
+        .unwrap_or_else(|| VikingApplicationWindow::new(app));
+
+    window.present();
+    window
+}
+
+/// A client of the API which reuses its responses for as long as the
+/// preferences say, for everything that lists servers.
+pub fn api() -> NordApi {
+    NordApi::new().with_cache(ApiCache::new(Preferences::new().api_cache_ttl()))
+}
+
+/// Connects without showing the window, to `option` or else the fastest
+/// server matching the preferences.
+fn connect_in_background(option: Option<ConnectOption>) {
+    watchdog::connecting(option.as_ref());
+
+    match option {
+        Some(option) => thread::spawn(move || {
+            nordvpn::connect(Some(&option)).ok();
+        }),
+        None => thread::spawn(tray::quick_connect),
+    };
+}
+
+/// Does what was asked for over D-Bus, by the application or the daemon.
+fn perform(action: ServiceAction) {
+    match action {
+        ServiceAction::Connect(option) => connect_in_background(Some(option)),
+        ServiceAction::Disconnect => {
+            watchdog::disconnecting();
+            thread::spawn(nordvpn::disconnect);
+        }
+        // Like the button on the status page, this goes to the preferred
+        // target if there is one.
+        ServiceAction::QuickConnect => connect_in_background(Preferences::new().default_target()),
+        ServiceAction::SetTarget(option) => watchdog::connecting(option.as_ref()),
+        ServiceAction::ExpectDisconnect => watchdog::disconnecting(),
+    }
+}
+
+/// Reads the target given with `--connect`, either as written by
+/// [`ConnectOption`]'s `Display` or as the CLI takes it, such as `de`,
+/// `Germany` or `de123`.
+fn parse_target(text: &str) -> ConnectOption {
+    let text = text.trim();
+
+    text.parse().unwrap_or_else(|_| {
+        if text.len() == 2 && text.chars().all(|c| c.is_ascii_alphabetic()) {
+            ConnectOption::CountryCode(text.to_owned())
+        } else if text.chars().any(|c| c.is_ascii_digit()) {
+            ConnectOption::Server(text.to_owned())
+        } else {
+            ConnectOption::Country(text.to_owned())
+        }
+    })
+}
@@ -1,4 +1,11 @@
+use crate::command_palette::VikingCommandPalette;
+use crate::export_window::VikingExportWindow;
+use crate::leak_test_window::VikingLeakTestWindow;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
 use gtk::{gio, glib};
+use nordvpn_viking::nordvpn::{self, ConnectOption};
+use std::thread;
 
 glib::wrapper! {
     pub struct VikingApplicationWindow(ObjectSubclass<imp::VikingApplicationWindow>)
@@ -11,16 +18,287 @@ impl VikingApplicationWindow {
         glib::Object::new(&[("application", app)])
             .expect("Failed to create `VikingApplicationWindow`")
     }
+
+    /// Connects from the status page, showing it for the progress.
+    pub fn connect_to(&self, option: ConnectOption) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+
+        self.show_status();
+        imp.status_page.connect(Some(option));
+    }
+
+    /// Shows the server browser searching for `text`.
+    pub fn search(&self, text: &str) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+
+        self.show_servers();
+        imp.server_browser.set_search(text);
+    }
+
+    /// Shows the status page, navigating back from the server browser when
+    /// the window is too narrow for both.
+    fn show_status(&self) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+
+        imp.pages_stack.set_visible_child_name("connect_page");
+        imp.connect_leaflet.set_visible_child_name("status");
+    }
+
+    fn show_servers(&self) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+
+        imp.pages_stack.set_visible_child_name("connect_page");
+        imp.connect_leaflet.set_visible_child_name("servers");
+    }
+
+    /// Moves the page switcher to the bottom and makes the server browser a
+    /// page of its own when the window is narrow, as on phones and in tiling
+    /// window managers.
+    fn setup_layout(&self) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+        let connect_leaflet = imp.connect_leaflet.get();
+        let switcher_bar = imp.switcher_bar.get();
+
+        imp.switcher_title.connect_title_visible_notify(
+            glib::clone!(@weak switcher_bar => move |switcher_title| {
+                switcher_bar.set_reveal(switcher_title.is_title_visible());
+            }),
+        );
+
+        imp.connect_leaflet.connect_folded_notify(
+            glib::clone!(@weak self as window => move |_| window.update_navigation()),
+        );
+        imp.connect_leaflet.connect_visible_child_notify(
+            glib::clone!(@weak self as window => move |_| window.update_navigation()),
+        );
+        imp.pages_stack.connect_visible_child_notify(
+            glib::clone!(@weak self as window => move |_| window.update_navigation()),
+        );
+
+        imp.back_button
+            .connect_clicked(glib::clone!(@weak connect_leaflet => move |_| {
+                connect_leaflet.navigate(adw::NavigationDirection::Back);
+            }));
+        imp.servers_button
+            .connect_clicked(glib::clone!(@weak self as window => move |_| window.show_servers()));
+
+        self.update_navigation();
+    }
+
+    /// Shows the buttons to navigate between the status and the server
+    /// browser only while they are folded on the connect page.
+    fn update_navigation(&self) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+        let folded = imp.connect_leaflet.is_folded()
+            && imp.pages_stack.visible_child_name().as_deref() == Some("connect_page");
+        let servers = imp.connect_leaflet.visible_child_name().as_deref() == Some("servers");
+
+        imp.back_button.set_visible(folded && servers);
+        imp.servers_button.set_visible(folded && !servers);
+    }
+
+    /// Warns while the kill switch blocks traffic because the VPN is
+    /// disconnected, with the buttons to get out of it.
+    fn setup_killswitch(&self) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+
+        imp.status_page.connect_status_changed(
+            glib::clone!(@weak self as window => move |_| window.check_killswitch()),
+        );
+        // The kill switch may have been changed on the Settings page.
+        imp.pages_stack.connect_visible_child_notify(
+            glib::clone!(@weak self as window => move |_| window.check_killswitch()),
+        );
+        imp.killswitch_button
+            .connect_clicked(glib::clone!(@weak self as window => move |_| {
+                window.run_killswitch(|| {
+                    nordvpn::settings()?.set_killswitch(false)?;
+                    Ok(())
+                });
+            }));
+    }
+
+    fn check_killswitch(&self) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+
+        if imp.status_page.is_connected() {
+            imp.killswitch_bar.set_revealed(false);
+            return;
+        }
+
+        self.run_killswitch(|| Ok(()));
+    }
+
+    /// Runs `call` in the background, then shows the banner if the kill
+    /// switch is still enabled while disconnected.
+    fn run_killswitch<F>(&self, call: F)
+    where
+        F: FnOnce() -> nordvpn::CliResult<()> + Send + 'static,
+    {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        imp.killswitch_button.set_sensitive(false);
+
+        thread::spawn(move || {
+            // The banner stays hidden if the settings cannot be read.
+            let blocking = call()
+                .and_then(|_| nordvpn::settings())
+                .is_ok_and(|settings| settings.killswitch);
+            sender.send(blocking).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as window => @default-return glib::Continue(false), move |blocking| {
+                let imp = imp::VikingApplicationWindow::from_instance(&window);
+
+                imp.killswitch_button.set_sensitive(true);
+                imp.killswitch_bar
+                    .set_revealed(blocking && !imp.status_page.is_connected());
+
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    /// Adds the window actions, whose accelerators are in
+    /// [`ACCELS`](crate::actions::ACCELS).
+    fn setup_actions(&self) {
+        let imp = imp::VikingApplicationWindow::from_instance(self);
+        let status_page = imp.status_page.get();
+        let server_browser = imp.server_browser.get();
+        let pages_stack = imp.pages_stack.get();
+
+        let quick_connect = gio::SimpleAction::new("quick-connect", None);
+        quick_connect.connect_activate(
+            glib::clone!(@weak self as window, @weak status_page => move |_, _| {
+                // Progress and errors are shown on the status page.
+                window.show_status();
+                status_page.quick_connect();
+            }),
+        );
+
+        let disconnect = gio::SimpleAction::new("disconnect", None);
+        disconnect.connect_activate(
+            glib::clone!(@weak self as window, @weak status_page => move |_, _| {
+                window.show_status();
+                status_page.disconnect();
+            }),
+        );
+
+        let search = gio::SimpleAction::new("search", None);
+        search.connect_activate(
+            glib::clone!(@weak self as window, @weak server_browser => move |_, _| {
+                window.show_servers();
+                server_browser.focus_search();
+            }),
+        );
+
+        let show_page = gio::SimpleAction::new("show-page", Some(&String::static_variant_type()));
+        show_page.connect_activate(glib::clone!(@weak pages_stack => move |_, page| {
+            if let Some(page) = page.and_then(|page| page.str()) {
+                pages_stack.set_visible_child_name(page);
+            }
+        }));
+
+        let show_shortcuts = gio::SimpleAction::new("show-shortcuts", None);
+        show_shortcuts.connect_activate(glib::clone!(@weak self as window => move |_, _| {
+            window.show_shortcuts();
+        }));
+
+        let leak_test = gio::SimpleAction::new("dns-leak-test", None);
+        leak_test.connect_activate(glib::clone!(@weak self as window => move |_, _| {
+            VikingLeakTestWindow::new(&window).present();
+        }));
+
+        let export = gio::SimpleAction::new("export", None);
+        export.connect_activate(glib::clone!(@weak self as window => move |_, _| {
+            VikingExportWindow::new(&window).present();
+        }));
+
+        let command_palette = gio::SimpleAction::new("command-palette", None);
+        command_palette.connect_activate(glib::clone!(@weak self as window => move |_, _| {
+            VikingCommandPalette::new(&window).present();
+        }));
+
+        for action in [
+            quick_connect,
+            disconnect,
+            search,
+            show_page,
+            show_shortcuts,
+            leak_test,
+            export,
+            command_palette,
+        ] {
+            self.add_action(&action);
+        }
+    }
+
+    fn show_shortcuts(&self) {
+        let builder = gtk::Builder::from_resource(
+            "/com/github/spikespaz/nordvpn-viking/ui/shortcuts_window.ui",
+        );
+        let shortcuts = builder
+            .object::<gtk::ShortcutsWindow>("shortcuts_window")
+            .expect("Failed to find `shortcuts_window`");
+
+        shortcuts.set_transient_for(Some(self));
+        shortcuts.set_application(self.application().as_ref());
+        shortcuts.present();
+    }
 }
 
 mod imp {
+    use crate::account_page::VikingAccountPage;
+    use crate::allowlist_page::VikingAllowlistPage;
+    use crate::fileshare_page::VikingFilesharePage;
+    use crate::history_page::VikingHistoryPage;
+    use crate::meshnet_page::VikingMeshnetPage;
+    use crate::preferences::Preferences;
+    use crate::server_browser::VikingServerBrowser;
+    use crate::settings_page::VikingSettingsPage;
+    use crate::status_page::VikingStatusPage;
+    use crate::world_map::VikingWorldMap;
     use gtk::prelude::*;
     use gtk::subclass::prelude::*;
     use gtk::{glib, CompositeTemplate};
 
     #[derive(Debug, Default, CompositeTemplate)]
     #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/application_window.ui")]
-    pub struct VikingApplicationWindow {}
+    pub struct VikingApplicationWindow {
+        #[template_child]
+        pub switcher_title: TemplateChild<adw::ViewSwitcherTitle>,
+        #[template_child]
+        pub switcher_bar: TemplateChild<adw::ViewSwitcherBar>,
+        #[template_child]
+        pub back_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub servers_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub pages_stack: TemplateChild<adw::ViewStack>,
+        #[template_child]
+        pub connect_leaflet: TemplateChild<adw::Leaflet>,
+        #[template_child]
+        pub status_page: TemplateChild<VikingStatusPage>,
+        #[template_child]
+        pub server_browser: TemplateChild<VikingServerBrowser>,
+        #[template_child]
+        pub history_page: TemplateChild<VikingHistoryPage>,
+        #[template_child]
+        pub world_map: TemplateChild<VikingWorldMap>,
+        #[template_child]
+        pub account_page: TemplateChild<VikingAccountPage>,
+        #[template_child]
+        pub meshnet_page: TemplateChild<VikingMeshnetPage>,
+        #[template_child]
+        pub reset_map_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub killswitch_bar: TemplateChild<gtk::InfoBar>,
+        #[template_child]
+        pub killswitch_button: TemplateChild<gtk::Button>,
+    }
 
     #[glib::object_subclass]
     impl ObjectSubclass for VikingApplicationWindow {
@@ -29,6 +307,16 @@ mod imp {
         type ParentType = gtk::ApplicationWindow;
 
         fn class_init(class: &mut Self::Class) {
+            // The template refers to these types, so they have to be registered.
+            VikingStatusPage::static_type();
+            VikingServerBrowser::static_type();
+            VikingWorldMap::static_type();
+            VikingHistoryPage::static_type();
+            VikingSettingsPage::static_type();
+            VikingAllowlistPage::static_type();
+            VikingAccountPage::static_type();
+            VikingMeshnetPage::static_type();
+            VikingFilesharePage::static_type();
             Self::bind_template(class);
             // UtilityCallbacks::bind_template_callbacks(klass);
         }
@@ -41,11 +329,73 @@ mod imp {
     impl ObjectImpl for VikingApplicationWindow {
         fn constructed(&self, obj: &Self::Type) {
             self.parent_constructed(obj);
+
+            obj.setup_actions();
+            obj.setup_layout();
+            obj.setup_killswitch();
+
+            let status_page = self.status_page.get();
+            let pages_stack = self.pages_stack.get();
+            self.server_browser.connect_target_activated(
+                glib::clone!(@weak obj as window, @weak status_page => move |option| {
+                    window.show_status();
+                    status_page.connect(Some(option.clone()));
+                }),
+            );
+            self.history_page.connect_target_activated(
+                glib::clone!(@weak status_page => move |option| {
+                    status_page.connect(Some(option.clone()));
+                }),
+            );
+
+            let world_map = self.world_map.get();
+            self.world_map.connect_target_activated(
+                glib::clone!(@weak obj as window, @weak status_page => move |option| {
+                    window.show_status();
+                    status_page.connect(Some(option.clone()));
+                }),
+            );
+            self.meshnet_page.connect_target_activated(
+                glib::clone!(@weak obj as window, @weak status_page => move |option| {
+                    window.show_status();
+                    status_page.connect(Some(option.clone()));
+                }),
+            );
+            self.reset_map_button
+                .connect_clicked(glib::clone!(@weak world_map => move |_| world_map.reset_view()));
+            status_page.connect_status_changed(
+                glib::clone!(@weak world_map => move |status| world_map.set_connected(status)),
+            );
+
+            // Nothing else works without a session, so logging in comes first.
+            self.account_page
+                .connect_logged_out(glib::clone!(@weak pages_stack => move || {
+                    pages_stack.set_visible_child_name("account_page");
+                }));
+            self.account_page
+                .connect_logged_in(glib::clone!(@weak pages_stack => move |_| {
+                    pages_stack.set_visible_child_name("account_page");
+                }));
         }
     }
 
     impl WidgetImpl for VikingApplicationWindow {}
-    impl WindowImpl for VikingApplicationWindow {}
+    impl WindowImpl for VikingApplicationWindow {
+        /// Hides the window instead when running in the background, so that
+        /// the pages keep watching the connection and recording history.
+        fn close_request(&self, window: &Self::Type) -> gtk::Inhibit {
+            if Preferences::new().run_in_background() {
+                window.hide();
+                return gtk::Inhibit(true);
+            }
+
+            if let Some(app) = window.application() {
+                app.quit();
+            }
+
+            self.parent_close_request(window)
+        }
+    }
     impl ApplicationWindowImpl for VikingApplicationWindow {}
 
     // struct UtilityCallbacks {}
@@ -0,0 +1,421 @@
+use crate::i18n::gettext;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::nordvpn::meshnet::{self, Invites, Peer, PeerStatus, Peers, Permission};
+use nordvpn_viking::nordvpn::{CliError, CliResult, ConnectOption};
+use std::net::IpAddr;
+use std::thread;
+
+glib::wrapper! {
+    pub struct VikingMeshnetPage(ObjectSubclass<imp::VikingMeshnetPage>)
+    @extends gtk::Widget, gtk::Box,
+    @implements gtk::Orientable;
+}
+
+/// The peers and invitations, or `None` if meshnet is turned off.
+type Loaded = Option<(Peers, Invites)>;
+
+/// The permissions a peer can be given, in the order of their toggles.
+const PERMISSIONS: [Permission; 4] = [
+    Permission::Incoming,
+    Permission::Routing,
+    Permission::Local,
+    Permission::Fileshare,
+];
+
+/// What can be done with a pending invitation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum InviteAction {
+    Accept,
+    Decline,
+    Revoke,
+}
+
+impl InviteAction {
+    fn icon_name(self) -> &'static str {
+        match self {
+            Self::Accept => "object-select-symbolic",
+            Self::Decline => "window-close-symbolic",
+            Self::Revoke => "list-remove-symbolic",
+        }
+    }
+
+    fn tooltip(self) -> String {
+        match self {
+            Self::Accept => gettext("Accept"),
+            Self::Decline => gettext("Decline"),
+            Self::Revoke => gettext("Revoke"),
+        }
+    }
+
+    fn apply(self, email: &str) -> CliResult<()> {
+        match self {
+            Self::Accept => meshnet::accept_invite(email),
+            Self::Decline => meshnet::deny_invite(email),
+            Self::Revoke => meshnet::revoke_invite(email),
+        }
+    }
+}
+
+impl VikingMeshnetPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingMeshnetPage`")
+    }
+
+    /// Calls `callback` with the peer the user chooses to route traffic
+    /// through.
+    pub fn connect_target_activated<F: Fn(&ConnectOption) + 'static>(&self, callback: F) {
+        let imp = imp::VikingMeshnetPage::from_instance(self);
+
+        imp.callbacks.borrow_mut().push(Box::new(callback));
+    }
+
+    fn setup(&self) {
+        let imp = imp::VikingMeshnetPage::from_instance(self);
+
+        // Peers come online and invitations arrive while the page is hidden.
+        self.connect_map(|page| page.run(|| Ok(())));
+        imp.enable_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| {
+                page.run(|| meshnet::set_enabled(true));
+            }));
+        imp.disable_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| {
+                page.run(|| meshnet::set_enabled(false));
+            }));
+        imp.invite_entry
+            .connect_changed(glib::clone!(@weak self as page => move |entry| {
+                let imp = imp::VikingMeshnetPage::from_instance(&page);
+
+                imp.invite_button.set_sensitive(entry.text().contains('@'));
+            }));
+        imp.invite_entry
+            .connect_activate(glib::clone!(@weak self as page => move |_| page.invite()));
+        imp.invite_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| page.invite()));
+    }
+
+    fn invite(&self) {
+        let imp = imp::VikingMeshnetPage::from_instance(self);
+        let email = imp.invite_entry.text().trim().to_owned();
+
+        if email.contains('@') {
+            imp.invite_entry.set_text("");
+            self.run(move || meshnet::send_invite(&email));
+        }
+    }
+
+    /// Makes the change in the background, then reads the peers and
+    /// invitations back so the page shows what the daemon actually has.
+    fn run<F>(&self, edit: F)
+    where
+        F: FnOnce() -> CliResult<()> + Send + 'static,
+    {
+        let imp = imp::VikingMeshnetPage::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        self.set_sensitive(false);
+        imp.error_label.set_visible(false);
+
+        thread::spawn(move || {
+            let load = || -> CliResult<Loaded> {
+                match meshnet::peers() {
+                    Ok(peers) => Ok(Some((peers, meshnet::invites()?))),
+                    Err(CliError::MeshnetDisabled) => Ok(None),
+                    Err(error) => Err(error),
+                }
+            };
+            // Whatever the change did, the page should reflect the CLI.
+            let result = edit()
+                .and_then(|_| load())
+                .map_err(|error| (error.to_string(), load().ok()));
+
+            sender.send(result).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |result: Result<Loaded, (String, Option<Loaded>)>| {
+                page.set_sensitive(true);
+
+                match result {
+                    Ok(loaded) => page.update(loaded),
+                    Err((error, loaded)) => {
+                        if let Some(loaded) = loaded {
+                            page.update(loaded);
+                        }
+                        page.show_error(&error);
+                    }
+                }
+
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    fn show_error(&self, error: &str) {
+        let imp = imp::VikingMeshnetPage::from_instance(self);
+
+        imp.error_label.set_label(error);
+        imp.error_label.set_visible(true);
+    }
+
+    fn update(&self, loaded: Loaded) {
+        let imp = imp::VikingMeshnetPage::from_instance(self);
+
+        for list_box in [
+            &*imp.device_list_box,
+            &*imp.peers_list_box,
+            &*imp.invites_list_box,
+        ] {
+            while let Some(row) = list_box.first_child() {
+                list_box.remove(&row);
+            }
+        }
+
+        imp.disabled_box.set_visible(loaded.is_none());
+        imp.enabled_box.set_visible(loaded.is_some());
+
+        let (peers, invites) = match loaded {
+            Some(loaded) => loaded,
+            None => return,
+        };
+
+        let device = &peers.this_device;
+        imp.device_list_box.append(&row_content(
+            &device.hostname,
+            &describe(device.ip, &device.os, &device.distribution),
+        ));
+
+        for peer in peers.local.iter().chain(&peers.external) {
+            imp.peers_list_box.append(&self.peer_row(peer));
+        }
+
+        for email in &invites.received {
+            let row = self.invite_row(
+                email,
+                &gettext("Received"),
+                &[InviteAction::Accept, InviteAction::Decline],
+            );
+
+            imp.invites_list_box.append(&row);
+        }
+
+        for email in &invites.sent {
+            let row = self.invite_row(email, &gettext("Sent"), &[InviteAction::Revoke]);
+
+            imp.invites_list_box.append(&row);
+        }
+    }
+
+    fn peer_row(&self, peer: &Peer) -> gtk::Box {
+        let status = match peer.status {
+            PeerStatus::Connected => gettext("Online"),
+            PeerStatus::Disconnected => gettext("Offline"),
+        };
+        let content = row_content(
+            peer.nickname.as_deref().unwrap_or(&peer.hostname),
+            &format!(
+                "{} · {}",
+                status,
+                describe(peer.ip, &peer.os, &peer.distribution)
+            ),
+        );
+        let route = gtk::Button::from_icon_name(Some("network-vpn-symbolic"));
+        let permissions = gtk::MenuButton::new();
+        let popover = gtk::Popover::new();
+        let toggles = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        let remove = gtk::Button::with_label(&gettext("Remove Peer"));
+        let option = ConnectOption::MeshnetPeer(peer.hostname.clone());
+
+        route.set_valign(gtk::Align::Center);
+        route.set_tooltip_text(Some(&gettext("Route traffic through this peer")));
+        route.set_sensitive(peer.status == PeerStatus::Connected);
+        route.add_css_class("flat");
+        route.connect_clicked(glib::clone!(@weak self as page => move |_| {
+            let imp = imp::VikingMeshnetPage::from_instance(&page);
+
+            for callback in imp.callbacks.borrow().iter() {
+                callback(&option);
+            }
+        }));
+
+        for permission in PERMISSIONS {
+            let (allowed, label) = match permission {
+                Permission::Incoming => (
+                    peer.permissions.incoming,
+                    gettext("Allow incoming connections"),
+                ),
+                Permission::Routing => (
+                    peer.permissions.routing,
+                    gettext("Allow routing traffic through this device"),
+                ),
+                Permission::Local => (
+                    peer.permissions.local,
+                    gettext("Allow access to the local network"),
+                ),
+                Permission::Fileshare => {
+                    (peer.permissions.fileshare, gettext("Allow sending files"))
+                }
+            };
+            let toggle = gtk::CheckButton::with_label(&label);
+            let hostname = peer.hostname.clone();
+
+            toggle.set_active(allowed);
+            toggle.connect_toggled(glib::clone!(@weak self as page => move |toggle| {
+                let hostname = hostname.clone();
+                let allowed = toggle.is_active();
+
+                page.run(move || {
+                    meshnet::set_permission(&hostname, permission, allowed).map(|_| ())
+                });
+            }));
+            toggles.append(&toggle);
+        }
+
+        let hostname = peer.hostname.clone();
+
+        remove.set_margin_top(6);
+        remove.add_css_class("destructive-action");
+        remove.connect_clicked(glib::clone!(@weak self as page => move |_| {
+            let hostname = hostname.clone();
+
+            page.run(move || meshnet::remove_peer(&hostname));
+        }));
+        toggles.append(&remove);
+        toggles.set_margin_top(6);
+        toggles.set_margin_bottom(6);
+        toggles.set_margin_start(6);
+        toggles.set_margin_end(6);
+        popover.set_child(Some(&toggles));
+        permissions.set_icon_name("emblem-system-symbolic");
+        permissions.set_tooltip_text(Some(&gettext("Permissions")));
+        permissions.set_valign(gtk::Align::Center);
+        permissions.set_popover(Some(&popover));
+        permissions.add_css_class("flat");
+        content.append(&route);
+        content.append(&permissions);
+
+        content
+    }
+
+    fn invite_row(&self, email: &str, direction: &str, actions: &[InviteAction]) -> gtk::Box {
+        let content = row_content(email, direction);
+
+        for &action in actions {
+            let button = gtk::Button::from_icon_name(Some(action.icon_name()));
+            let email = email.to_owned();
+
+            button.set_valign(gtk::Align::Center);
+            button.set_tooltip_text(Some(&action.tooltip()));
+            button.add_css_class("flat");
+            button.connect_clicked(glib::clone!(@weak self as page => move |_| {
+                let email = email.clone();
+
+                page.run(move || action.apply(&email));
+            }));
+            content.append(&button);
+        }
+
+        content
+    }
+}
+
+impl Default for VikingMeshnetPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The address and system of a device, such as `100.64.0.2 · linux (Fedora)`.
+fn describe(ip: IpAddr, os: &str, distribution: &Option<String>) -> String {
+    match distribution {
+        Some(distribution) => format!("{} · {} ({})", ip, os, distribution),
+        None => format!("{} · {}", ip, os),
+    }
+}
+
+/// A row with a title and a dimmed subtitle, to which buttons are appended.
+pub fn row_content(title: &str, subtitle: &str) -> gtk::Box {
+    let title = gtk::Label::new(Some(title));
+    let subtitle = gtk::Label::new(Some(subtitle));
+    let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+
+    title.set_xalign(0.0);
+    subtitle.set_xalign(0.0);
+    subtitle.add_css_class("dim-label");
+    subtitle.add_css_class("caption");
+    labels.set_hexpand(true);
+    labels.append(&title);
+    labels.append(&subtitle);
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.append(&labels);
+
+    content
+}
+
+mod imp {
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use nordvpn_viking::nordvpn::ConnectOption;
+    use std::cell::RefCell;
+
+    type Callback = Box<dyn Fn(&ConnectOption)>;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/meshnet_page.ui")]
+    pub struct VikingMeshnetPage {
+        #[template_child]
+        pub error_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub disabled_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub enable_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub enabled_box: TemplateChild<gtk::Box>,
+        #[template_child]
+        pub device_list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub peers_list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub invite_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub invite_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub invites_list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub disable_button: TemplateChild<gtk::Button>,
+        pub callbacks: RefCell<Vec<Callback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingMeshnetPage {
+        const NAME: &'static str = "VikingMeshnetPage";
+        type Type = super::VikingMeshnetPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingMeshnetPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingMeshnetPage {}
+    impl BoxImpl for VikingMeshnetPage {}
+}
@@ -0,0 +1,83 @@
+//! Tells the watchdog about changes to the network as they happen, from the
+//! signals of NetworkManager and logind on the system bus, so that the VPN is
+//! re-established after resuming or switching networks without waiting for
+//! the next poll.
+
+use crate::watchdog;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use once_cell::sync::OnceCell;
+
+const NM_NAME: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const LOGIND_MANAGER: &str = "org.freedesktop.login1.Manager";
+
+/// `NM_STATE_CONNECTED_GLOBAL`, when there is access to the internet.
+const NM_STATE_CONNECTED_GLOBAL: u32 = 70;
+
+/// Subscriptions only last as long as the connection they were made on.
+static CONNECTION: OnceCell<gio::DBusConnection> = OnceCell::new();
+
+/// Subscribes to the signals on the system bus. Without NetworkManager the
+/// network is assumed to be online, and the watchdog polls as usual.
+pub fn watch() -> Result<(), glib::Error> {
+    let connection = gio::bus_get_sync(gio::BusType::System, None::<&gio::Cancellable>)?;
+
+    if let Some(state) = nm_state(&connection) {
+        watchdog::set_online(state == NM_STATE_CONNECTED_GLOBAL);
+    }
+
+    connection.signal_subscribe(
+        Some(NM_NAME),
+        Some(NM_NAME),
+        Some("StateChanged"),
+        Some(NM_PATH),
+        None,
+        gio::DBusSignalFlags::NONE,
+        |_, _, _, _, _, parameters| {
+            if let Some((state,)) = parameters.get::<(u32,)>() {
+                watchdog::set_online(state == NM_STATE_CONNECTED_GLOBAL);
+            }
+        },
+    );
+
+    connection.signal_subscribe(
+        Some(LOGIND_NAME),
+        Some(LOGIND_MANAGER),
+        Some("PrepareForSleep"),
+        None,
+        None,
+        gio::DBusSignalFlags::NONE,
+        |_, _, _, _, _, parameters| {
+            // Sent with `false` after resuming.
+            if let Some((false,)) = parameters.get::<(bool,)>() {
+                watchdog::check_now();
+            }
+        },
+    );
+
+    CONNECTION.set(connection).ok();
+
+    Ok(())
+}
+
+/// The current state of NetworkManager, or `None` if it is not running.
+fn nm_state(connection: &gio::DBusConnection) -> Option<u32> {
+    let reply = connection
+        .call_sync(
+            Some(NM_NAME),
+            NM_PATH,
+            "org.freedesktop.DBus.Properties",
+            "Get",
+            Some(&(NM_NAME, "State").to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+            None::<&gio::Cancellable>,
+        )
+        .ok()?;
+    let (value,) = reply.get::<(glib::Variant,)>()?;
+
+    value.get::<u32>()
+}
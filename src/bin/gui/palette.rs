@@ -0,0 +1,57 @@
+//! The colors of the custom drawn widgets, which follow the light or dark
+//! style chosen by the desktop or on the Settings page.
+
+use gtk::glib;
+use gtk::prelude::*;
+
+/// A color as red, green and blue between 0 and 1.
+pub type Rgb = (f64, f64, f64);
+
+/// A color with a variant which stays readable on each style, taken from the
+/// GNOME palette.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Shade {
+    pub light: Rgb,
+    pub dark: Rgb,
+}
+
+pub const BLUE: Shade = Shade {
+    light: (0.21, 0.52, 0.89),
+    dark: (0.47, 0.68, 0.93),
+};
+pub const ORANGE: Shade = Shade {
+    light: (0.90, 0.38, 0.00),
+    dark: (1.00, 0.64, 0.28),
+};
+pub const GREEN: Shade = Shade {
+    light: (0.18, 0.76, 0.49),
+    dark: (0.34, 0.89, 0.54),
+};
+
+impl Shade {
+    /// The variant for the current style.
+    pub fn get(self) -> Rgb {
+        match is_dark() {
+            true => self.dark,
+            false => self.light,
+        }
+    }
+}
+
+/// Whether the dark style is in use, from the system preference unless it
+/// is overridden by the `theme` preference.
+pub fn is_dark() -> bool {
+    adw::StyleManager::default().is_some_and(|style_manager| style_manager.is_dark())
+}
+
+/// Redraws `widget` whenever the style switches between light and dark,
+/// since drawn colors are not updated by the stylesheet.
+pub fn redraw_on_change<W: IsA<gtk::Widget>>(widget: &W) {
+    if let Some(style_manager) = adw::StyleManager::default() {
+        let widget = widget.as_ref();
+
+        style_manager.connect_dark_notify(glib::clone!(@weak widget => move |_| {
+            widget.queue_draw();
+        }));
+    }
+}
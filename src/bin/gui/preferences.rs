@@ -0,0 +1,307 @@
+//! The preferences of the application, kept with GSettings so that widgets
+//! can be bound to them and they can be changed with `gsettings` or dconf.
+//!
+//! Favorites, profiles and the schedule are structured data, so they stay in
+//! the [`Config`] file.
+
+use crate::watchdog;
+use chrono::NaiveDate;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use nordvpn_viking::autostart::Autostart;
+use nordvpn_viking::behavior::{Behavior, BehaviorWatcher};
+use nordvpn_viking::config::Config;
+use nordvpn_viking::nordvpn::ConnectOption;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+pub const SCHEMA_ID: &str = "com.github.spikespaz.nordvpn-viking";
+
+/// Where the build script compiles the schema, which is used when it has not
+/// been installed, such as when running from the source tree.
+const SCHEMA_DIR: &str = concat!(env!("OUT_DIR"), "/schemas");
+
+/// The choices of the `theme` key, by their values in the schema.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Theme {
+    System = 0,
+    Light = 1,
+    Dark = 2,
+}
+
+#[derive(Debug, Clone)]
+pub struct Preferences {
+    settings: gio::Settings,
+}
+
+impl Theme {
+    /// In the order of the theme dropdown.
+    pub const ALL: [Self; 3] = [Self::System, Self::Light, Self::Dark];
+
+    fn color_scheme(self) -> adw::ColorScheme {
+        match self {
+            Self::System => adw::ColorScheme::Default,
+            Self::Light => adw::ColorScheme::ForceLight,
+            Self::Dark => adw::ColorScheme::ForceDark,
+        }
+    }
+}
+
+impl Preferences {
+    /// Opens the installed schema, or else the one compiled by the build.
+    pub fn new() -> Self {
+        let default = gio::SettingsSchemaSource::default();
+        let schema = default
+            .as_ref()
+            .and_then(|source| source.lookup(SCHEMA_ID, true))
+            .or_else(|| {
+                gio::SettingsSchemaSource::from_directory(SCHEMA_DIR, default.as_ref(), false)
+                    .ok()?
+                    .lookup(SCHEMA_ID, false)
+            })
+            .expect("Failed to find the GSettings schema");
+
+        Self {
+            settings: gio::Settings::new_full(&schema, None::<&gio::SettingsBackend>, None),
+        }
+    }
+
+    /// Binds `property` of `object` to `key` both ways.
+    pub fn bind<P: IsA<glib::Object>>(&self, key: &str, object: &P, property: &str) {
+        self.settings.bind(key, object, property).build();
+    }
+
+    /// Calls `callback` whenever `key` is changed, from here or elsewhere.
+    pub fn connect_changed<F: Fn(&Self) + 'static>(
+        &self,
+        key: &str,
+        callback: F,
+    ) -> glib::SignalHandlerId {
+        self.settings
+            .connect_changed(Some(key), move |settings, _| {
+                callback(&Self {
+                    settings: settings.clone(),
+                })
+            })
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.settings.uint("poll-interval") as u64)
+    }
+
+    /// How long responses of the API are reused before asking it again.
+    pub fn api_cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.settings.uint("api-cache-ttl") as u64 * 60)
+    }
+
+    /// How often the server browser refreshes the load of the servers.
+    pub fn load_refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.settings.uint("load-refresh-interval") as u64 * 60)
+    }
+
+    pub fn notifications(&self) -> bool {
+        self.settings.boolean("notifications")
+    }
+
+    pub fn autostart(&self) -> bool {
+        self.settings.boolean("autostart")
+    }
+
+    pub fn theme(&self) -> Theme {
+        match self.settings.enum_("theme") {
+            1 => Theme::Light,
+            2 => Theme::Dark,
+            _ => Theme::System,
+        }
+    }
+
+    pub fn set_theme(&self, theme: Theme) -> Result<(), glib::BoolError> {
+        self.settings.set_enum("theme", theme as i32)
+    }
+
+    /// What Quick Connect connects to, or `None` for the fastest server.
+    pub fn default_target(&self) -> Option<ConnectOption> {
+        self.settings.string("default-target").parse().ok()
+    }
+
+    pub fn set_default_target(
+        &self,
+        target: Option<&ConnectOption>,
+    ) -> Result<(), glib::BoolError> {
+        let target = target.map(ToString::to_string).unwrap_or_default();
+
+        self.settings.set_string("default-target", &target)
+    }
+
+    pub fn run_in_background(&self) -> bool {
+        self.settings.boolean("run-in-background")
+    }
+
+    pub fn auto_reconnect(&self) -> bool {
+        self.settings.boolean("auto-reconnect")
+    }
+
+    /// The language of the interface, such as `de`, or `None` to follow the
+    /// environment.
+    pub fn language(&self) -> Option<String> {
+        Some(self.settings.string("language").to_string()).filter(|language| !language.is_empty())
+    }
+
+    pub fn set_language(&self, language: Option<&str>) -> Result<(), glib::BoolError> {
+        self.settings
+            .set_string("language", language.unwrap_or_default())
+    }
+
+    /// How many days before the subscription expires to be reminded, or 0
+    /// for no reminder.
+    pub fn expiry_reminder(&self) -> u32 {
+        self.settings.uint("expiry-reminder")
+    }
+
+    /// The expiration date which the last reminder was sent for.
+    pub fn expiry_reminded(&self) -> Option<NaiveDate> {
+        self.settings.string("expiry-reminded").parse().ok()
+    }
+
+    pub fn set_expiry_reminded(&self, expires: NaiveDate) -> Result<(), glib::BoolError> {
+        self.settings
+            .set_string("expiry-reminded", &expires.to_string())
+    }
+
+    /// Copies the preferences which earlier versions kept in the [`Config`]
+    /// file, and whether there is an autostart entry, the first time.
+    pub fn import_config(&self) {
+        if self.settings.boolean("config-imported") {
+            return;
+        }
+
+        let config = fs::read_to_string(Config::path())
+            .ok()
+            .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+            .unwrap_or_default();
+
+        for (field, key) in [
+            ("run_in_background", "run-in-background"),
+            ("auto_reconnect", "auto-reconnect"),
+        ] {
+            if let Some(value) = config[field].as_bool() {
+                self.settings.set_boolean(key, value).ok();
+            }
+        }
+        if let Some(language) = config["language"].as_str() {
+            self.settings.set_string("language", language).ok();
+        }
+
+        self.settings
+            .set_boolean("autostart", Autostart::new().is_enabled())
+            .ok();
+        self.settings.set_boolean("config-imported", true).ok();
+    }
+
+    /// Sets the keys which the [`Behavior`] file sets, so that it takes
+    /// precedence over the Settings page.
+    pub fn apply_behavior(&self, behavior: &Behavior) {
+        if let Some(target) = &behavior.default_target {
+            self.set_default_target(Some(target)).ok();
+        }
+        if let Some(interval) = behavior.poll_interval {
+            self.settings.set_uint("poll-interval", interval).ok();
+        }
+        if let Some(enabled) = behavior.notifications.enabled {
+            self.settings.set_boolean("notifications", enabled).ok();
+        }
+        if let Some(days) = behavior.notifications.expiry_reminder {
+            self.settings.set_uint("expiry-reminder", days).ok();
+        }
+        if let Some(enabled) = behavior.watchdog.enabled {
+            self.settings.set_boolean("auto-reconnect", enabled).ok();
+        }
+    }
+
+    /// Applies the theme, the autostart entry and the [`Behavior`] file, now
+    /// and whenever they are changed. The handlers hold on to the settings,
+    /// so they last as long as the application.
+    pub fn watch(&self) {
+        let preferences = self.clone();
+
+        self.watch_behavior();
+
+        self.apply_theme();
+        self.connect_changed("theme", move |_| preferences.apply_theme());
+        self.connect_changed("autostart", |preferences| {
+            let autostart = Autostart::new();
+            let enabled = preferences.autostart();
+
+            if autostart.is_enabled() == enabled {
+                return;
+            }
+
+            if let Err(error) = autostart.set_enabled(enabled) {
+                warn!("The autostart entry could not be changed: {}", error);
+                // Puts the switches back to what the desktop will do.
+                preferences
+                    .settings
+                    .set_boolean("autostart", autostart.is_enabled())
+                    .ok();
+            }
+        });
+    }
+
+    /// Applies the [`Behavior`] file now and again whenever it changes,
+    /// keeping what was set before while it is invalid. The daemon only
+    /// watches this, having no theme or autostart entry to apply.
+    pub fn watch_behavior(&self) {
+        match Behavior::load() {
+            Ok(behavior) => self.apply_behavior(&behavior),
+            Err(error) => warn!(
+                "The config file {:?} is invalid: {}",
+                Behavior::path(),
+                error
+            ),
+        }
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        // The watcher is dropped with the application.
+        thread::spawn(move || {
+            let watcher = BehaviorWatcher::new();
+
+            for event in watcher.events() {
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@strong self as preferences => move |event| {
+                match event {
+                    Ok(behavior) => {
+                        preferences.apply_behavior(&behavior);
+                        watchdog::configure(&behavior.watchdog);
+                    }
+                    Err(error) => {
+                        warn!("The config file {:?} is invalid: {}", Behavior::path(), error);
+                    }
+                }
+
+                glib::Continue(true)
+            }),
+        );
+    }
+
+    fn apply_theme(&self) {
+        if let Some(style_manager) = adw::StyleManager::default() {
+            style_manager.set_color_scheme(self.theme().color_scheme());
+        }
+    }
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,40 @@
+//! Records the connection history and the transfer totals for the whole
+//! application, whether or not a window is open. While the daemon runs, it
+//! records instead.
+
+use crate::status_monitor;
+use gtk::glib;
+use nordvpn_viking::history::{History, HistoryRecorder};
+use nordvpn_viking::stats::Stats;
+use std::cell::RefCell;
+use tracing::warn;
+
+/// Starts recording the events of the [`status_monitor`].
+pub fn spawn() {
+    let recorder = RefCell::new(HistoryRecorder::default());
+    // `None` once the database failed, rather than warning at every poll.
+    let stats = RefCell::new(
+        Stats::open()
+            .inspect_err(|error| warn!("Failed to open the transfer totals: {}", error))
+            .ok(),
+    );
+
+    status_monitor::subscribe(move |event| {
+        if let Ok(event) = event {
+            let finished = recorder.borrow_mut().record(event);
+
+            if let Some(Err(error)) = finished.map(|entry| History::new().append(&entry)) {
+                warn!("Failed to save the connection history: {}", error);
+            }
+
+            let recorded = stats.borrow_mut().as_mut().map(|stats| stats.record(event));
+
+            if let Some(Err(error)) = recorded {
+                warn!("Failed to save the transfer totals: {}", error);
+                stats.replace(None);
+            }
+        }
+
+        glib::Continue(true)
+    });
+}
@@ -0,0 +1,69 @@
+//! Reminds the user with a desktop notification when the subscription is
+//! about to expire, checked in the background for the whole application.
+
+use crate::i18n::{gettext, ngettext_f};
+use crate::preferences::Preferences;
+use chrono::{Local, NaiveDate};
+use gtk::prelude::*;
+use gtk::{gio, glib, Application};
+use nordvpn_viking::nordvpn;
+use std::thread;
+use std::time::Duration;
+
+/// How often the expiration date is read, as it only changes on renewal.
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Starts checking the expiration date of the account.
+pub fn spawn(app: &Application) {
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+    thread::spawn(move || loop {
+        // Not being logged in or the CLI failing are shown on the account page.
+        if let Ok(Some(account)) = nordvpn::account() {
+            if sender.send(account.expires).is_err() {
+                break;
+            }
+        }
+
+        thread::sleep(CHECK_INTERVAL);
+    });
+
+    receiver.attach(
+        None,
+        glib::clone!(@weak app => @default-return glib::Continue(false), move |expires| {
+            remind(&app, expires);
+            glib::Continue(true)
+        }),
+    );
+}
+
+/// Sends the reminder once for each expiration date, when it is within the
+/// days chosen on the account page.
+fn remind(app: &Application, expires: NaiveDate) {
+    let preferences = Preferences::new();
+    let days = match preferences.expiry_reminder() {
+        0 => return,
+        days => days as i64,
+    };
+    let left = (expires - Local::now().date_naive()).num_days();
+
+    if left > days || preferences.expiry_reminded() == Some(expires) {
+        return;
+    }
+
+    let body = match left {
+        left if left < 0 => gettext("Your NordVPN subscription has expired."),
+        0 => gettext("Your NordVPN subscription expires today."),
+        left => ngettext_f(
+            "Your NordVPN subscription expires tomorrow.",
+            "Your NordVPN subscription expires in {} days.",
+            left as u32,
+            &[&left],
+        ),
+    };
+    let notification = gio::Notification::new(&gettext("Subscription Expiring"));
+
+    notification.set_body(Some(&body));
+    app.send_notification(Some("expiry-reminder"), &notification);
+    preferences.set_expiry_reminded(expires).ok();
+}
@@ -0,0 +1,62 @@
+//! Runs the scheduled connections for the whole application, whether or not
+//! a window is open, and tells the user with desktop notifications.
+
+use crate::i18n::{gettext, gettext_f};
+use crate::preferences::Preferences;
+use crate::watchdog;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use nordvpn_viking::schedule::{ScheduleEvent, Scheduler};
+use std::thread;
+
+/// Starts the scheduler, which should be after the watchdog so that the
+/// watchdog knows about scheduled disconnects.
+pub fn spawn(app: &gio::Application) {
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+    // The scheduler stops when this thread drops it with the application.
+    thread::spawn(move || {
+        let scheduler = Scheduler::new(watchdog::handle());
+
+        for event in scheduler.events() {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver.attach(
+        None,
+        glib::clone!(@weak app => @default-return glib::Continue(false), move |event| {
+            notify(&app, &event);
+            glib::Continue(true)
+        }),
+    );
+}
+
+fn notify(app: &gio::Application, event: &ScheduleEvent) {
+    if !Preferences::new().notifications() {
+        return;
+    }
+
+    let (title, body) = match &event.result {
+        Ok(()) => (
+            gettext("Scheduled Connection"),
+            gettext_f(
+                "{} at {}.",
+                &[&event.rule.action, &event.rule.time.format("%H:%M")],
+            ),
+        ),
+        Err(error) => (
+            gettext("Scheduled Connection Failed"),
+            gettext_f(
+                "Could not {}: {}",
+                &[&event.rule.action.to_string().to_lowercase(), &error],
+            ),
+        ),
+    };
+    let notification = gio::Notification::new(&title);
+
+    notification.set_body(Some(&body));
+    app.send_notification(None, &notification);
+}
@@ -0,0 +1,180 @@
+//! The `org.gnome.Shell.SearchProvider2` interface, which offers countries
+//! and cities to connect to when they are typed in the GNOME Shell overview.
+//!
+//! GNOME Shell finds the provider through
+//! `data/com.github.spikespaz.nordvpn-viking.search-provider.ini`, which has
+//! to be installed to `/usr/share/gnome-shell/search-providers`.
+
+use crate::i18n::gettext_f;
+use crate::server_browser::{self, BrowserEntry};
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use nordvpn_viking::nordapi::ServerFilters;
+use nordvpn_viking::nordvpn::ConnectOption;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const OBJECT_PATH: &str = "/com/github/spikespaz/nordvpn_viking/SearchProvider";
+
+const INTERFACE: &str = r#"
+<node>
+    <interface name="org.gnome.Shell.SearchProvider2">
+        <method name="GetInitialResultSet">
+            <arg type="as" name="terms" direction="in"/>
+            <arg type="as" name="results" direction="out"/>
+        </method>
+        <method name="GetSubsearchResultSet">
+            <arg type="as" name="previous_results" direction="in"/>
+            <arg type="as" name="terms" direction="in"/>
+            <arg type="as" name="results" direction="out"/>
+        </method>
+        <method name="GetResultMetas">
+            <arg type="as" name="identifiers" direction="in"/>
+            <arg type="aa{sv}" name="metas" direction="out"/>
+        </method>
+        <method name="ActivateResult">
+            <arg type="s" name="identifier" direction="in"/>
+            <arg type="as" name="terms" direction="in"/>
+            <arg type="u" name="timestamp" direction="in"/>
+        </method>
+        <method name="LaunchSearch">
+            <arg type="as" name="terms" direction="in"/>
+            <arg type="u" name="timestamp" direction="in"/>
+        </method>
+    </interface>
+</node>
+"#;
+
+/// The most results offered at once, as the overview only shows a few.
+const MAX_RESULTS: usize = 10;
+
+/// Requests from the overview which have to be handled on the GTK main
+/// thread.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchAction {
+    Connect(ConnectOption),
+    /// Open the window with the terms in the server search.
+    Search(String),
+}
+
+/// Exports the provider on the application's own connection, so it shares
+/// the well-known name that GNOME Shell activates.
+pub fn register(
+    connection: &gio::DBusConnection,
+    actions: glib::Sender<SearchAction>,
+) -> Result<(), glib::Error> {
+    let interface = gio::DBusNodeInfo::for_xml(INTERFACE)?
+        .lookup_interface("org.gnome.Shell.SearchProvider2")
+        .expect("Failed to find the search provider interface");
+    let entries = Arc::new(Mutex::new(Vec::new()));
+    let actions = Mutex::new(actions);
+
+    let loaded = entries.clone();
+    let api = crate::api();
+    thread::spawn(move || {
+        if let Ok(servers) = api.servers(&ServerFilters::default()) {
+            let places = server_browser::entries(&servers)
+                .into_iter()
+                .filter(|entry| !matches!(entry.option, ConnectOption::Server(_)));
+
+            loaded.lock().unwrap().extend(places);
+        }
+    });
+
+    connection.register_object(
+        OBJECT_PATH,
+        &interface,
+        move |_, _, _, _, method, parameters, invocation| {
+            let entries = entries.lock().unwrap();
+            let send = |action| {
+                // The receiver only goes away when the application is quitting.
+                actions.lock().unwrap().send(action).ok();
+            };
+
+            let reply = match method {
+                "GetInitialResultSet" => parameters
+                    .get::<(Vec<String>,)>()
+                    .map(|(terms,)| (search(&entries, &terms),).to_variant()),
+                "GetSubsearchResultSet" => parameters
+                    .get::<(Vec<String>, Vec<String>)>()
+                    .map(|(_, terms)| (search(&entries, &terms),).to_variant()),
+                "GetResultMetas" => parameters
+                    .get::<(Vec<String>,)>()
+                    .map(|(ids,)| (metas(&entries, &ids),).to_variant()),
+                "ActivateResult" => {
+                    if let Some((id, _, _)) = parameters.get::<(String, Vec<String>, u32)>() {
+                        if let Ok(option) = id.parse() {
+                            send(SearchAction::Connect(option));
+                        }
+                    }
+                    None
+                }
+                "LaunchSearch" => {
+                    if let Some((terms, _)) = parameters.get::<(Vec<String>, u32)>() {
+                        send(SearchAction::Search(terms.join(" ")));
+                    }
+                    None
+                }
+                _ => None,
+            };
+
+            invocation.return_value(reply.as_ref());
+        },
+        // The interface has no properties, so these are never called.
+        |_, _, _, _, _| glib::Variant::from_tuple(&[]),
+        |_, _, _, _, _, _| false,
+    )?;
+
+    Ok(())
+}
+
+/// The identifiers of the entries where every term starts a word of the
+/// title, countries first.
+fn search(entries: &[BrowserEntry], terms: &[String]) -> Vec<String> {
+    let terms = terms
+        .iter()
+        .map(|term| term.to_lowercase())
+        .collect::<Vec<_>>();
+
+    entries
+        .iter()
+        .filter(|entry| {
+            let title = entry.title.to_lowercase();
+            let words = title
+                .split(|c: char| !c.is_alphanumeric())
+                .collect::<Vec<_>>();
+
+            terms
+                .iter()
+                .all(|term| words.iter().any(|word| word.starts_with(term.as_str())))
+        })
+        .take(MAX_RESULTS)
+        .map(|entry| entry.option.to_string())
+        .collect()
+}
+
+fn metas(entries: &[BrowserEntry], ids: &[String]) -> Vec<HashMap<String, glib::Variant>> {
+    ids.iter()
+        .filter_map(|id| {
+            let entry = entries
+                .iter()
+                .find(|entry| entry.option.to_string() == *id)?;
+            let mut meta = HashMap::new();
+
+            meta.insert("id".to_owned(), id.to_variant());
+            meta.insert("name".to_owned(), entry.title.to_variant());
+            meta.insert(
+                "description".to_owned(),
+                gettext_f(
+                    "Connect to this {} with NordVPN",
+                    &[&entry.subtitle.to_lowercase()],
+                )
+                .to_variant(),
+            );
+            meta.insert("gicon".to_owned(), "network-vpn".to_variant());
+
+            Some(meta)
+        })
+        .collect()
+}
@@ -0,0 +1,719 @@
+use crate::flags::{self, FlagShape};
+use crate::i18n::{gettext, gettext_f};
+use crate::preferences::Preferences;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::config::Config;
+use nordvpn_viking::latency::{self, Latency, LatencyOptions};
+use nordvpn_viking::nordapi::{ApiResult, NordApi, Server, ServerFilters, ServerLoads};
+use nordvpn_viking::nordvpn::{ConnectOption, ListEntry};
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The most rows shown at once, since building thousands is slow.
+const MAX_ROWS: usize = 200;
+
+/// How long a measured latency is shown before it is measured again.
+const LATENCY_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// How often to check whether the loads are due to be refreshed.
+const LOAD_CHECK_INTERVAL: u32 = 60;
+
+glib::wrapper! {
+    pub struct VikingServerBrowser(ObjectSubclass<imp::VikingServerBrowser>)
+    @extends gtk::Widget, gtk::Box,
+    @implements gtk::Orientable;
+}
+
+/// Something in the list which can be connected to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrowserEntry {
+    pub title: String,
+    pub subtitle: String,
+    /// The country code, for the flag shown beside the entry.
+    pub country_code: Option<String>,
+    pub option: ConnectOption,
+    /// The server whose latency is shown beside the entry, which for a
+    /// country or city is its least loaded server.
+    pub probe: Option<String>,
+    /// The load of the server, or of the least loaded server of a country or
+    /// city, as a percentage.
+    pub load: Option<u8>,
+}
+
+/// How the listed entries are ordered, in the order of the sort drop-down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// How well the entry matches the search.
+    Relevance,
+    Latency,
+    Load,
+}
+
+impl SortOrder {
+    pub const ALL: [Self; 3] = [Self::Relevance, Self::Latency, Self::Load];
+}
+
+/// A measured latency, or `None` if the server did not respond.
+type Measurement = (Instant, Option<Duration>);
+
+impl VikingServerBrowser {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingServerBrowser`")
+    }
+
+    /// Calls `callback` with the target of every entry the user activates.
+    pub fn connect_target_activated<F: Fn(&ConnectOption) + 'static>(&self, callback: F) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+
+        imp.callbacks.borrow_mut().push(Box::new(callback));
+    }
+
+    pub fn focus_search(&self) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+
+        imp.search_entry.grab_focus();
+    }
+
+    pub fn set_search(&self, text: &str) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+
+        imp.search_entry.set_text(text);
+        imp.search_entry.set_position(-1);
+        self.focus_search();
+    }
+
+    fn load(&self) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        let api = crate::api();
+
+        thread::spawn(move || {
+            sender.send(api.servers(&ServerFilters::default())).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as browser => @default-return glib::Continue(false), move |servers: ApiResult<Vec<Server>>| {
+                let imp = imp::VikingServerBrowser::from_instance(&browser);
+
+                match servers {
+                    Ok(servers) => {
+                        imp.entries.replace(entries(&servers));
+                        imp.servers.replace(servers);
+                        browser.filter();
+                        browser.refresh_loads();
+                    }
+                    Err(error) => imp.status_label.set_label(&error.to_string()),
+                }
+
+                glib::Continue(false)
+            }),
+        );
+
+        imp.search_entry.connect_search_changed(
+            glib::clone!(@weak self as browser => move |_| browser.filter()),
+        );
+        imp.sort_dropdown.connect_selected_notify(
+            glib::clone!(@weak self as browser => move |_| browser.filter()),
+        );
+        imp.refresh_button.connect_clicked(
+            glib::clone!(@weak self as browser => move |_| browser.refresh_loads()),
+        );
+        glib::timeout_add_seconds_local(
+            LATENCY_TTL.as_secs() as u32,
+            glib::clone!(@weak self as browser => @default-return glib::Continue(false), move || {
+                browser.measure_shown();
+                glib::Continue(true)
+            }),
+        );
+        glib::timeout_add_seconds_local(
+            LOAD_CHECK_INTERVAL,
+            glib::clone!(@weak self as browser => @default-return glib::Continue(false), move || {
+                let imp = imp::VikingServerBrowser::from_instance(&browser);
+                let stale = match imp.loads.borrow_mut().as_mut() {
+                    Some(loads) => {
+                        loads.set_interval(Preferences::new().load_refresh_interval());
+                        loads.is_stale()
+                    }
+                    // Still refreshing.
+                    None => false,
+                };
+
+                if stale && !imp.servers.borrow().is_empty() {
+                    browser.refresh_loads();
+                }
+
+                glib::Continue(true)
+            }),
+        );
+        imp.list_box.connect_row_activated(
+            glib::clone!(@weak self as browser => move |_, row| browser.activate(false, row.index())),
+        );
+        imp.favorites_list.connect_row_activated(
+            glib::clone!(@weak self as browser => move |_, row| browser.activate(true, row.index())),
+        );
+
+        imp.loads.replace(Some(ServerLoads::new(
+            // Uncached, since the loads change by the minute.
+            NordApi::new(),
+            Preferences::new().load_refresh_interval(),
+        )));
+        imp.config.replace(Config::load().unwrap_or_default());
+        self.filter();
+    }
+
+    fn sort_order(&self) -> SortOrder {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+
+        SortOrder::ALL
+            .get(imp.sort_dropdown.selected() as usize)
+            .copied()
+            .unwrap_or(SortOrder::Relevance)
+    }
+
+    /// Fetches the current load of every server in the background and
+    /// updates the entries with them.
+    fn refresh_loads(&self) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        // Taken while refreshing, so that only one refresh runs at a time.
+        let mut loads = match imp.loads.take() {
+            Some(loads) => loads,
+            None => return,
+        };
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        imp.refresh_button.set_sensitive(false);
+        thread::spawn(move || {
+            let result = loads.refresh();
+
+            sender.send((loads, result)).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as browser => @default-return glib::Continue(false), move |(loads, result): (ServerLoads, ApiResult<()>)| {
+                let imp = imp::VikingServerBrowser::from_instance(&browser);
+
+                match result {
+                    Ok(()) => {
+                        let mut servers = imp.servers.borrow_mut();
+
+                        loads.apply(&mut servers);
+                        imp.entries.replace(entries(&servers));
+                        drop(servers);
+                        browser.filter();
+                    }
+                    Err(error) => {
+                        imp.status_label.set_label(&error.to_string());
+                        imp.status_label.set_visible(true);
+                    }
+                }
+
+                imp.loads.replace(Some(loads));
+                imp.refresh_button.set_sensitive(true);
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    fn filter(&self) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        let query = imp.search_entry.text();
+        let entries = imp.entries.borrow();
+
+        let mut matches = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                if query.is_empty() {
+                    // Servers are only listed once searched for.
+                    match entry.option {
+                        ConnectOption::Server(_) => None,
+                        _ => Some((0, index)),
+                    }
+                } else {
+                    fuzzy_score(&query, &entry.title).map(|score| (score, index))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        match self.sort_order() {
+            SortOrder::Relevance => matches.sort_by_key(|(score, index)| (-score, *index)),
+            SortOrder::Latency => {
+                let latencies = imp.latencies.borrow();
+                // Unmeasured entries and those which did not respond go last.
+                let latency = |index: usize| {
+                    entries[index]
+                        .probe
+                        .as_ref()
+                        .and_then(|probe| latencies.get(probe))
+                        .and_then(|(_, latency)| *latency)
+                        .unwrap_or(Duration::MAX)
+                };
+
+                matches.sort_by_key(|(score, index)| (latency(*index), -score, *index));
+            }
+            SortOrder::Load => matches.sort_by_key(|(score, index)| {
+                (entries[*index].load.unwrap_or(u8::MAX), -score, *index)
+            }),
+        }
+        matches.truncate(MAX_ROWS);
+
+        let matches = matches
+            .into_iter()
+            .map(|(_, index)| entries[index].clone())
+            .collect::<Vec<_>>();
+        // Favorites are only listed separately while not searching.
+        let favorites = match query.is_empty() {
+            true => self.favorites(&entries),
+            false => Vec::new(),
+        };
+
+        imp.latency_labels.borrow_mut().clear();
+        self.fill(&imp.list_box, &matches);
+        self.fill(&imp.favorites_list, &favorites);
+        imp.favorites_label.set_visible(!favorites.is_empty());
+        imp.favorites_list.set_visible(!favorites.is_empty());
+
+        imp.status_label
+            .set_label(&match (entries.is_empty(), matches.is_empty()) {
+                (true, _) => gettext("Loading servers…"),
+                (false, true) => gettext("Nothing matches the search"),
+                (false, false) => String::new(),
+            });
+        imp.status_label.set_visible(matches.is_empty());
+        drop(entries);
+        imp.shown.replace(matches);
+        imp.shown_favorites.replace(favorites);
+        self.measure_shown();
+    }
+
+    /// Measures the latency of every shown entry which has not been measured
+    /// recently, in the background and a few at a time so that the rows fill
+    /// in as results arrive.
+    fn measure_shown(&self) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        let hostnames = {
+            let latencies = imp.latencies.borrow();
+            let mut pending = imp.pending.borrow_mut();
+            let shown = imp.shown.borrow();
+            let favorites = imp.shown_favorites.borrow();
+
+            shown
+                .iter()
+                .chain(favorites.iter())
+                .filter_map(|entry| entry.probe.clone())
+                .filter(|probe| {
+                    latencies
+                        .get(probe)
+                        .is_none_or(|(at, _)| at.elapsed() > LATENCY_TTL)
+                })
+                .filter(|probe| pending.insert(probe.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        if hostnames.is_empty() {
+            return;
+        }
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        thread::spawn(move || {
+            let options = LatencyOptions::default();
+
+            for batch in hostnames.chunks(options.parallelism) {
+                if sender
+                    .send(Some(latency::measure(batch, &options)))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+
+            sender.send(None).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as browser => @default-return glib::Continue(false), move |batch: Option<Vec<Latency>>| {
+                let imp = imp::VikingServerBrowser::from_instance(&browser);
+
+                match batch {
+                    Some(batch) => {
+                        for latency in batch {
+                            imp.pending.borrow_mut().remove(&latency.hostname);
+                            imp.latencies
+                                .borrow_mut()
+                                .insert(latency.hostname.clone(), (Instant::now(), latency.duration()));
+                        }
+
+                        browser.update_latencies();
+                        glib::Continue(true)
+                    }
+                    None => {
+                        // Sorting only once every result is in keeps the rows
+                        // from jumping around while they are measured.
+                        if browser.sort_order() == SortOrder::Latency {
+                            browser.filter();
+                        }
+
+                        glib::Continue(false)
+                    }
+                }
+            }),
+        );
+    }
+
+    fn update_latencies(&self) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        let latencies = imp.latencies.borrow();
+
+        for (probe, label) in imp.latency_labels.borrow().iter() {
+            label.set_label(&latency_text(latencies.get(probe)));
+        }
+    }
+
+    /// The starred entries, using the loaded entry where there is one so
+    /// that it has a flag.
+    fn favorites(&self, entries: &[BrowserEntry]) -> Vec<BrowserEntry> {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        let config = imp.config.borrow();
+
+        config
+            .favorites
+            .iter()
+            .map(|favorite| {
+                entries
+                    .iter()
+                    .find(|entry| entry.option == favorite.option)
+                    .cloned()
+                    .unwrap_or_else(|| BrowserEntry {
+                        title: favorite.name.clone(),
+                        subtitle: gettext("Favorite"),
+                        country_code: None,
+                        option: favorite.option.clone(),
+                        probe: None,
+                        load: None,
+                    })
+            })
+            .collect()
+    }
+
+    fn fill(&self, list: &gtk::ListBox, entries: &[BrowserEntry]) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        let config = imp.config.borrow();
+
+        while let Some(row) = list.first_child() {
+            list.remove(&row);
+        }
+
+        for entry in entries {
+            let star = gtk::ToggleButton::new();
+
+            star.set_active(config.is_favorite(&entry.option));
+            star.set_icon_name(match star.is_active() {
+                true => "starred-symbolic",
+                false => "non-starred-symbolic",
+            });
+            star.set_valign(gtk::Align::Center);
+            star.set_tooltip_text(Some(&gettext("Favorite")));
+            star.add_css_class("flat");
+            star.connect_toggled(
+                glib::clone!(@weak self as browser, @strong entry => move |_| {
+                    browser.toggle_favorite(&entry);
+                }),
+            );
+
+            let latency = gtk::Label::new(None);
+
+            if let Some(probe) = &entry.probe {
+                latency.set_label(&latency_text(imp.latencies.borrow().get(probe)));
+                imp.latency_labels
+                    .borrow_mut()
+                    .push((probe.clone(), latency.clone()));
+            }
+
+            list.append(&entry_row(entry, &latency, &star));
+        }
+    }
+
+    fn toggle_favorite(&self, entry: &BrowserEntry) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        let mut config = imp.config.borrow_mut();
+
+        config.toggle_favorite(&entry.title, entry.option.clone());
+
+        if let Err(error) = config.save() {
+            imp.status_label.set_label(&error.to_string());
+            imp.status_label.set_visible(true);
+        }
+
+        drop(config);
+        // Rebuilding the rows would destroy the clicked button while its
+        // handler is still running.
+        glib::idle_add_local_once(glib::clone!(@weak self as browser => move || browser.filter()));
+    }
+
+    fn activate(&self, favorite: bool, row: i32) {
+        let imp = imp::VikingServerBrowser::from_instance(self);
+        let shown = match favorite {
+            true => imp.shown_favorites.borrow(),
+            false => imp.shown.borrow(),
+        };
+        let option = match shown.get(row as usize) {
+            Some(entry) => entry.option.clone(),
+            None => return,
+        };
+
+        drop(shown);
+
+        for callback in imp.callbacks.borrow().iter() {
+            callback(&option);
+        }
+    }
+}
+
+impl Default for VikingServerBrowser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Every country, city and server among `servers`, in that order.
+pub fn entries(servers: &[Server]) -> Vec<BrowserEntry> {
+    // The least loaded server of each, as `(load, hostname)`.
+    let mut countries = BTreeMap::new();
+    let mut cities = BTreeMap::new();
+    let least_loaded = |current: &mut (u8, String), server: &Server| {
+        if server.load < current.0 {
+            *current = (server.load, server.hostname.clone());
+        }
+    };
+
+    for server in servers {
+        if let Some(country) = server.country() {
+            let least = (server.load, server.hostname.clone());
+
+            least_loaded(
+                countries
+                    .entry((country.name.clone(), country.code.clone()))
+                    .or_insert_with(|| least.clone()),
+                server,
+            );
+
+            if let Some(city) = &country.city {
+                least_loaded(
+                    cities
+                        .entry((
+                            country.name.clone(),
+                            country.code.clone(),
+                            city.name.clone(),
+                        ))
+                        .or_insert(least),
+                    server,
+                );
+            }
+        }
+    }
+
+    let countries = countries
+        .into_iter()
+        .map(|((country, code), (load, probe))| BrowserEntry {
+            title: country.clone(),
+            subtitle: gettext("Country"),
+            country_code: Some(code),
+            option: ConnectOption::Country(ListEntry::new(&country).id),
+            probe: Some(probe),
+            load: Some(load),
+        });
+    let cities = cities
+        .into_iter()
+        .map(|((country, code, city), (load, probe))| BrowserEntry {
+            title: format!("{}, {}", city, country),
+            subtitle: gettext("City"),
+            country_code: Some(code),
+            option: ConnectOption::CountryCity(
+                ListEntry::new(&country).id,
+                ListEntry::new(&city).id,
+            ),
+            probe: Some(probe),
+            load: Some(load),
+        });
+    let servers = servers.iter().map(|server| BrowserEntry {
+        title: server.name.clone(),
+        subtitle: gettext_f("{}, {}% load", &[&server.hostname, &server.load]),
+        country_code: server.country().map(|country| country.code.clone()),
+        option: ConnectOption::server_from_hostname(&server.hostname),
+        probe: Some(server.hostname.clone()),
+        load: Some(server.load),
+    });
+
+    countries.chain(cities).chain(servers).collect()
+}
+
+fn latency_text(measurement: Option<&Measurement>) -> String {
+    match measurement {
+        Some((_, Some(latency))) => gettext_f("{} ms", &[&latency.as_millis()]),
+        Some((_, None)) => gettext("No response"),
+        None => "…".to_owned(),
+    }
+}
+
+fn entry_row(
+    entry: &BrowserEntry,
+    latency: &gtk::Label,
+    star: &gtk::ToggleButton,
+) -> gtk::ListBoxRow {
+    let title = gtk::Label::new(Some(&entry.title));
+    let subtitle = gtk::Label::new(Some(&entry.subtitle));
+    let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+    let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+    let row = gtk::ListBoxRow::new();
+
+    title.set_xalign(0.0);
+    subtitle.set_xalign(0.0);
+    subtitle.add_css_class("dim-label");
+    subtitle.add_css_class("caption");
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    labels.append(&title);
+    labels.append(&subtitle);
+
+    if let Some(code) = &entry.country_code {
+        // Countries stand out from their cities and servers with wider flags.
+        let shape = match entry.option {
+            ConnectOption::Country(_) => FlagShape::Rectangle,
+            _ => FlagShape::Square,
+        };
+        let flag = flags::flag_image(code, shape);
+
+        flag.set_pixel_size(24);
+        content.append(&flag);
+    }
+
+    labels.set_hexpand(true);
+    latency.add_css_class("dim-label");
+    latency.add_css_class("numeric");
+    content.append(&labels);
+    content.append(latency);
+    content.append(star);
+    row.set_child(Some(&content));
+
+    row
+}
+
+/// Scores how well `query` matches `text` when its characters appear in
+/// order but not necessarily next to each other, or `None` if they do not
+/// all appear. Consecutive characters and those at the start of a word score
+/// higher, and skipped characters lower.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut query = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .peekable();
+    let mut score = 0;
+    let mut previous = None;
+    let mut consecutive = false;
+
+    for (index, c) in text.chars().enumerate() {
+        let wanted = match query.peek() {
+            Some(wanted) => *wanted,
+            None => break,
+        };
+
+        if c.to_lowercase().eq(wanted.to_lowercase()) {
+            let word_start = previous.is_none_or(|previous: char| !previous.is_alphanumeric());
+
+            score += 1 + if consecutive { 4 } else { 0 } + if word_start { 6 } else { 0 };
+            consecutive = true;
+            query.next();
+        } else {
+            score -= if index == 0 { 0 } else { 1 };
+            consecutive = false;
+        }
+
+        previous = Some(c);
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
+mod imp {
+    use super::ConnectOption;
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use nordvpn_viking::config::Config;
+    use nordvpn_viking::nordapi::{Server, ServerLoads};
+    use std::cell::RefCell;
+    use std::collections::{HashMap, HashSet};
+
+    type Callback = Box<dyn Fn(&ConnectOption)>;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/server_browser.ui")]
+    pub struct VikingServerBrowser {
+        #[template_child]
+        pub search_entry: TemplateChild<gtk::SearchEntry>,
+        #[template_child]
+        pub sort_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub refresh_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub favorites_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub favorites_list: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub status_label: TemplateChild<gtk::Label>,
+        /// The servers the entries were made from, with their loads updated.
+        pub servers: RefCell<Vec<Server>>,
+        /// `None` while the loads are being refreshed.
+        pub loads: RefCell<Option<ServerLoads>>,
+        pub entries: RefCell<Vec<super::BrowserEntry>>,
+        /// The entry of each row in `list_box`.
+        pub shown: RefCell<Vec<super::BrowserEntry>>,
+        /// The entry of each row in `favorites_list`.
+        pub shown_favorites: RefCell<Vec<super::BrowserEntry>>,
+        pub config: RefCell<Config>,
+        /// Latencies by hostname, kept across searches.
+        pub latencies: RefCell<HashMap<String, super::Measurement>>,
+        /// The hostnames being measured.
+        pub pending: RefCell<HashSet<String>>,
+        /// The latency label of each shown row, to fill in as results arrive.
+        pub latency_labels: RefCell<Vec<(String, gtk::Label)>>,
+        pub callbacks: RefCell<Vec<Callback>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingServerBrowser {
+        const NAME: &'static str = "VikingServerBrowser";
+        type Type = super::VikingServerBrowser;
+        type ParentType = gtk::Box;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingServerBrowser {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.load();
+        }
+    }
+
+    impl WidgetImpl for VikingServerBrowser {}
+    impl BoxImpl for VikingServerBrowser {}
+}
@@ -0,0 +1,1052 @@
+use crate::i18n::{self, gettext, gettext_f};
+use crate::preferences::{Preferences, Theme};
+use crate::watchdog;
+use chrono::{Local, NaiveTime, Weekday};
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::config::Config;
+use nordvpn_viking::nordvpn::{self, CliResult, ConnectOption, Protocol, Settings, Technology};
+use nordvpn_viking::profile::Profile;
+use nordvpn_viking::schedule::{Rule, ScheduledAction};
+use std::net::IpAddr;
+use std::thread;
+
+glib::wrapper! {
+    pub struct VikingSettingsPage(ObjectSubclass<imp::VikingSettingsPage>)
+    @extends gtk::Widget, gtk::Box,
+    @implements gtk::Orientable;
+}
+
+/// The on or off settings, in the order they are listed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Toggle {
+    Firewall,
+    Killswitch,
+    ThreatProtection,
+    Obfuscate,
+    PostQuantum,
+    Autoconnect,
+    Notify,
+    Ipv6,
+    LanDiscovery,
+}
+
+/// One change made on the page, applied with the setters of [`Settings`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Technology(Technology),
+    Protocol(Protocol),
+    Toggle(Toggle, bool),
+    /// Custom DNS servers, or `None` to use NordVPN's.
+    Dns(Option<Vec<IpAddr>>),
+}
+
+/// The days chosen from the dropdown, in the order they are listed.
+const DAYS: [&[Weekday]; 3] = [
+    &[],
+    &[
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ],
+    &[Weekday::Sat, Weekday::Sun],
+];
+
+const TECHNOLOGIES: [Technology; 2] = [Technology::NordLynx, Technology::OpenVpn];
+const PROTOCOLS: [Protocol; 2] = [Protocol::Udp, Protocol::Tcp];
+
+impl Toggle {
+    const ALL: [Self; 9] = [
+        Self::Firewall,
+        Self::Killswitch,
+        Self::ThreatProtection,
+        Self::Obfuscate,
+        Self::PostQuantum,
+        Self::Autoconnect,
+        Self::Notify,
+        Self::Ipv6,
+        Self::LanDiscovery,
+    ];
+
+    fn title(self) -> String {
+        match self {
+            Self::Firewall => gettext("Firewall"),
+            Self::Killswitch => gettext("Kill Switch"),
+            Self::ThreatProtection => gettext("Threat Protection Lite"),
+            Self::Obfuscate => gettext("Obfuscation"),
+            Self::PostQuantum => gettext("Post-Quantum Encryption"),
+            Self::Autoconnect => gettext("Auto-Connect"),
+            Self::Notify => gettext("Notifications"),
+            Self::Ipv6 => gettext("IPv6"),
+            Self::LanDiscovery => gettext("LAN Discovery"),
+        }
+    }
+
+    fn subtitle(self) -> String {
+        match self {
+            Self::Firewall => gettext("Manage the system firewall while connected"),
+            Self::Killswitch => gettext("Block all traffic while the VPN is down"),
+            Self::ThreatProtection => gettext("Block ads and malicious sites with NordVPN's DNS"),
+            Self::Obfuscate => gettext("Disguise OpenVPN traffic where VPNs are blocked"),
+            Self::PostQuantum => gettext("Protect the key exchange against quantum computers"),
+            Self::Autoconnect => gettext("Connect when the system starts"),
+            Self::Notify => gettext("Show desktop notifications from the daemon"),
+            Self::Ipv6 => gettext("Allow IPv6 traffic through the VPN"),
+            Self::LanDiscovery => gettext("Reach devices on the local network while connected"),
+        }
+    }
+
+    /// The current value, or `None` if the setting does not apply.
+    fn get(self, settings: &Settings) -> Option<bool> {
+        match self {
+            Self::Firewall => Some(settings.firewall),
+            Self::Killswitch => Some(settings.killswitch),
+            Self::ThreatProtection => Some(settings.cybersec),
+            Self::Obfuscate => settings.obfuscate,
+            Self::PostQuantum => settings.post_quantum,
+            Self::Autoconnect => Some(settings.autoconnect),
+            Self::Notify => Some(settings.notify),
+            Self::Ipv6 => Some(settings.ipv6),
+            Self::LanDiscovery => settings.lan_discovery,
+        }
+    }
+}
+
+impl Change {
+    fn apply(&self, settings: &mut Settings) -> CliResult<()> {
+        match self.clone() {
+            Self::Technology(technology) => settings.set_technology(technology)?,
+            Self::Protocol(protocol) => settings.set_protocol(protocol)?,
+            Self::Toggle(toggle, enabled) => match toggle {
+                Toggle::Firewall => settings.set_firewall(enabled)?,
+                Toggle::Killswitch => settings.set_killswitch(enabled)?,
+                Toggle::ThreatProtection => settings.set_cybersec(enabled)?,
+                Toggle::Obfuscate => settings.set_obfuscate(enabled)?,
+                Toggle::PostQuantum => settings.set_post_quantum(enabled)?,
+                Toggle::Autoconnect => settings.set_autoconnect(enabled)?,
+                Toggle::Notify => settings.set_notify(enabled)?,
+                Toggle::Ipv6 => settings.set_ipv6(enabled)?,
+                Toggle::LanDiscovery => settings.set_lan_discovery(enabled)?,
+            },
+            Self::Dns(addresses) => settings.set_dns(addresses)?,
+        };
+
+        Ok(())
+    }
+
+    /// Whether NordVPN reconnects to apply the change while connected, which
+    /// briefly interrupts the connection.
+    fn reconnects(&self) -> bool {
+        matches!(
+            self,
+            Self::Technology(_)
+                | Self::Protocol(_)
+                | Self::Toggle(Toggle::Obfuscate | Toggle::PostQuantum, _)
+        )
+    }
+
+    /// Whether the settings read back from the CLI reflect the change.
+    fn is_applied(&self, settings: &Settings) -> bool {
+        match self {
+            Self::Technology(technology) => settings.technology == *technology,
+            Self::Protocol(protocol) => {
+                settings.protocol.is_none_or(|current| current == *protocol)
+            }
+            Self::Toggle(toggle, enabled) => toggle
+                .get(settings)
+                .is_none_or(|current| current == *enabled),
+            Self::Dns(addresses) => {
+                let current = settings.dns.as_ref();
+
+                match addresses {
+                    Some(addresses) => current.is_some_and(|current| {
+                        addresses.iter().all(|address| current.contains(address))
+                    }),
+                    None => current.is_none(),
+                }
+            }
+        }
+    }
+}
+
+impl VikingSettingsPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingSettingsPage`")
+    }
+
+    fn setup(&self) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+
+        imp.technology.connect_selected_notify(
+            glib::clone!(@weak self as page => move |dropdown| {
+                let technology = TECHNOLOGIES[dropdown.selected() as usize];
+                page.change(Change::Technology(technology));
+            }),
+        );
+        self.append_row(
+            &gettext("Technology"),
+            &gettext("The VPN protocol used for new connections"),
+            &imp.technology.get(),
+        );
+
+        imp.protocol
+            .connect_selected_notify(glib::clone!(@weak self as page => move |dropdown| {
+                let protocol = PROTOCOLS[dropdown.selected() as usize];
+                page.change(Change::Protocol(protocol));
+            }));
+        self.append_row(
+            &gettext("Protocol"),
+            &gettext("The transport used by OpenVPN"),
+            &imp.protocol.get(),
+        );
+
+        for toggle in Toggle::ALL {
+            let switch = gtk::Switch::new();
+
+            switch.set_valign(gtk::Align::Center);
+            switch.connect_active_notify(glib::clone!(@weak self as page => move |switch| {
+                page.change(Change::Toggle(toggle, switch.is_active()));
+            }));
+            self.append_row(&toggle.title(), &toggle.subtitle(), &switch);
+            imp.switches.borrow_mut().push((toggle, switch));
+        }
+
+        imp.dns_entry
+            .connect_activate(glib::clone!(@weak self as page => move |entry| {
+                page.change_dns(&entry.text());
+            }));
+        self.append_row(
+            &gettext("Custom DNS"),
+            &gettext("Up to three addresses separated by commas, or empty for NordVPN's"),
+            &imp.dns_entry.get(),
+        );
+
+        // These are not daemon settings, so they are bound to the preferences.
+        let preferences = Preferences::new();
+
+        let autostart = gtk::Switch::new();
+
+        autostart.set_valign(gtk::Align::Center);
+        preferences.bind("autostart", &autostart, "active");
+        self.append_row(
+            &gettext("Start on Login"),
+            &gettext("Show NordVPN Viking in the tray after logging in"),
+            &autostart,
+        );
+
+        let background = gtk::Switch::new();
+
+        background.set_valign(gtk::Align::Center);
+        preferences.bind("run-in-background", &background, "active");
+        self.append_row(
+            &gettext("Run in Background"),
+            &gettext("Keep the tray and connection watching running when the window is closed"),
+            &background,
+        );
+
+        let reconnect = gtk::Switch::new();
+
+        reconnect.set_valign(gtk::Align::Center);
+        preferences.bind("auto-reconnect", &reconnect, "active");
+        self.append_row(
+            &gettext("Auto-Reconnect"),
+            &gettext("Reconnect when the connection drops without being asked to"),
+            &reconnect,
+        );
+
+        let notifications = gtk::Switch::new();
+
+        notifications.set_valign(gtk::Align::Center);
+        preferences.bind("notifications", &notifications, "active");
+        self.append_row(
+            &gettext("Desktop Notifications"),
+            &gettext("Announce reconnections and scheduled connections"),
+            &notifications,
+        );
+
+        let interval = gtk::SpinButton::with_range(1.0, 60.0, 1.0);
+
+        interval.set_valign(gtk::Align::Center);
+        preferences.bind("poll-interval", &interval, "value");
+        self.append_row(
+            &gettext("Poll Interval"),
+            &gettext("Seconds between reading the connection status, used after restarting"),
+            &interval,
+        );
+
+        let cache_ttl = gtk::SpinButton::with_range(1.0, 1440.0, 1.0);
+
+        cache_ttl.set_valign(gtk::Align::Center);
+        preferences.bind("api-cache-ttl", &cache_ttl, "value");
+        self.append_row(
+            &gettext("Server List Lifetime"),
+            &gettext("Minutes the server list is reused before asking NordVPN again"),
+            &cache_ttl,
+        );
+
+        let load_interval = gtk::SpinButton::with_range(1.0, 120.0, 1.0);
+
+        load_interval.set_valign(gtk::Align::Center);
+        preferences.bind("load-refresh-interval", &load_interval, "value");
+        self.append_row(
+            &gettext("Load Refresh Interval"),
+            &gettext("Minutes between refreshing the load of the servers in the browser"),
+            &load_interval,
+        );
+
+        let follows_system = adw::StyleManager::default()
+            .is_some_and(|style_manager| style_manager.system_supports_color_schemes());
+        let theme = gtk::DropDown::from_strings(&[
+            &gettext("System Default"),
+            &gettext("Light"),
+            &gettext("Dark"),
+        ]);
+
+        theme.set_valign(gtk::Align::Center);
+        theme.set_selected(
+            Theme::ALL
+                .iter()
+                .position(|theme| *theme == preferences.theme())
+                .unwrap_or_default() as u32,
+        );
+        theme.connect_selected_notify(
+            glib::clone!(@weak self as page, @strong preferences => move |dropdown| {
+                let theme = Theme::ALL[dropdown.selected() as usize % Theme::ALL.len()];
+
+                if let Err(error) = preferences.set_theme(theme) {
+                    page.show_error(&gettext_f("The preference could not be saved: {}", &[&error]));
+                }
+            }),
+        );
+        preferences.connect_changed(
+            "theme",
+            glib::clone!(@weak theme => move |preferences| {
+                let selected = Theme::ALL
+                    .iter()
+                    .position(|theme| *theme == preferences.theme())
+                    .unwrap_or_default() as u32;
+
+                if theme.selected() != selected {
+                    theme.set_selected(selected);
+                }
+            }),
+        );
+        self.append_row(
+            &gettext("Theme"),
+            &match follows_system {
+                true => gettext("Whether the interface is light, dark, or follows the system"),
+                false => gettext(
+                    "Whether the interface is light or dark, since the desktop does not choose",
+                ),
+            },
+            &theme,
+        );
+
+        imp.default_target.set_valign(gtk::Align::Center);
+        imp.default_target.connect_selected_notify(
+            glib::clone!(@weak self as page, @strong preferences => move |dropdown| {
+                let imp = imp::VikingSettingsPage::from_instance(&page);
+                // The first choice is the fastest server.
+                let target = (dropdown.selected() as usize)
+                    .checked_sub(1)
+                    .and_then(|index| imp.targets.borrow().get(index).cloned());
+
+                if target == preferences.default_target() {
+                    return;
+                }
+
+                if let Err(error) = preferences.set_default_target(target.as_ref()) {
+                    page.show_error(&gettext_f("The preference could not be saved: {}", &[&error]));
+                }
+            }),
+        );
+        self.append_row(
+            &gettext("Quick Connect Target"),
+            &gettext("What Quick Connect connects to, from the favorites"),
+            &imp.default_target,
+        );
+
+        let languages = i18n::languages();
+        let current = preferences.language();
+        let system = gettext("System Default");
+        let language = gtk::DropDown::from_strings(
+            &[system.as_str()]
+                .into_iter()
+                .chain(languages.iter().map(String::as_str))
+                .collect::<Vec<_>>(),
+        );
+
+        language.set_valign(gtk::Align::Center);
+        language.set_selected(
+            current
+                .and_then(|current| languages.iter().position(|language| *language == current))
+                .map_or(0, |index| index as u32 + 1),
+        );
+        language.connect_selected_notify(
+            glib::clone!(@weak self as page, @strong preferences => move |dropdown| {
+                // The first choice follows the environment.
+                let language = (dropdown.selected() as usize)
+                    .checked_sub(1)
+                    .and_then(|index| languages.get(index));
+
+                if let Err(error) = preferences.set_language(language.map(String::as_str)) {
+                    page.show_error(&gettext_f("The preference could not be saved: {}", &[&error]));
+                }
+            }),
+        );
+        self.append_row(
+            &gettext("Language"),
+            &gettext("The language of the interface, used after restarting"),
+            &language,
+        );
+
+        imp.profile_entry
+            .connect_changed(glib::clone!(@weak self as page => move |entry| {
+                let imp = imp::VikingSettingsPage::from_instance(&page);
+
+                imp.save_profile_button
+                    .set_sensitive(!entry.text().trim().is_empty());
+            }));
+        imp.save_profile_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| page.save_profile()));
+        imp.add_rule_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| page.add_rule()));
+        imp.time_entry
+            .connect_activate(glib::clone!(@weak self as page => move |_| page.add_rule()));
+        // The config file may have been edited while the page was hidden.
+        self.connect_map(|page| {
+            page.reload_profiles();
+            page.reload_schedule();
+            page.reload_targets();
+        });
+
+        self.reload_profiles();
+        self.reload_schedule();
+        self.reload_targets();
+        self.refresh();
+    }
+
+    fn reload_profiles(&self) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+
+        while let Some(row) = imp.profiles_box.first_child() {
+            imp.profiles_box.remove(&row);
+        }
+
+        let profiles = Config::load().unwrap_or_default().profiles;
+
+        imp.profiles_box.set_visible(!profiles.is_empty());
+
+        for profile in profiles {
+            imp.profiles_box.append(&self.profile_row(profile));
+        }
+    }
+
+    fn profile_row(&self, profile: Profile) -> gtk::Box {
+        let title = gtk::Label::new(Some(&profile.name));
+        let subtitle = gtk::Label::new(Some(&describe(&profile)));
+        let apply = gtk::Button::with_label(&gettext("Connect"));
+        let remove = gtk::Button::from_icon_name(Some("user-trash-symbolic"));
+        let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let name = profile.name.clone();
+
+        title.set_xalign(0.0);
+        subtitle.set_xalign(0.0);
+        subtitle.set_wrap(true);
+        subtitle.add_css_class("dim-label");
+        subtitle.add_css_class("caption");
+        apply.set_valign(gtk::Align::Center);
+        apply.connect_clicked(glib::clone!(@weak self as page => move |_| {
+            page.apply_profile(profile.clone());
+        }));
+        remove.set_valign(gtk::Align::Center);
+        remove.set_tooltip_text(Some(&gettext("Remove the profile")));
+        remove.add_css_class("flat");
+        remove.connect_clicked(glib::clone!(@weak self as page => move |_| {
+            let mut config = Config::load().unwrap_or_default();
+
+            config.remove_profile(&name);
+
+            match config.save() {
+                Ok(()) => page.reload_profiles(),
+                Err(error) => {
+                    page.show_error(&gettext_f("The profile could not be removed: {}", &[&error]))
+                }
+            }
+        }));
+        labels.set_hexpand(true);
+        labels.append(&title);
+        labels.append(&subtitle);
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&labels);
+        content.append(&apply);
+        content.append(&remove);
+
+        content
+    }
+
+    /// Lists the favorites as choices for Quick Connect, keeping the current
+    /// choice even if it has since been unstarred.
+    fn reload_targets(&self) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+        let current = Preferences::new().default_target();
+        let favorites = Config::load().unwrap_or_default().favorites;
+        let mut names = vec![gettext("Fastest Server")];
+        let mut targets = Vec::new();
+
+        for favorite in favorites {
+            names.push(favorite.name);
+            targets.push(favorite.option);
+        }
+        if let Some(current) = current
+            .as_ref()
+            .filter(|current| !targets.contains(current))
+        {
+            names.push(current.to_string());
+            targets.push(current.clone());
+        }
+
+        let selected = current
+            .and_then(|current| targets.iter().position(|target| *target == current))
+            .map_or(0, |index| index as u32 + 1);
+
+        // Replaced first so that the handler reads the new list.
+        imp.targets.replace(targets);
+        imp.default_target.set_model(Some(&gtk::StringList::new(
+            &names.iter().map(String::as_str).collect::<Vec<_>>(),
+        )));
+        imp.default_target.set_selected(selected);
+    }
+
+    fn reload_schedule(&self) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+        let config = Config::load().unwrap_or_default();
+
+        // Profiles and favorites can be scheduled as well.
+        let actions = [ScheduledAction::Connect(None), ScheduledAction::Disconnect]
+            .into_iter()
+            .chain(
+                config
+                    .profiles
+                    .iter()
+                    .map(|profile| ScheduledAction::Profile(profile.name.clone())),
+            )
+            .chain(
+                config
+                    .favorites
+                    .iter()
+                    .map(|favorite| ScheduledAction::Connect(Some(favorite.option.clone()))),
+            )
+            .collect::<Vec<_>>();
+        let labels = actions
+            .iter()
+            .map(ScheduledAction::to_string)
+            .collect::<Vec<_>>();
+
+        imp.action_dropdown.set_model(Some(&gtk::StringList::new(
+            &labels.iter().map(String::as_str).collect::<Vec<_>>(),
+        )));
+        imp.schedule_actions.replace(actions);
+
+        while let Some(row) = imp.schedule_box.first_child() {
+            imp.schedule_box.remove(&row);
+        }
+
+        imp.schedule_box.set_visible(!config.schedule.is_empty());
+
+        for (index, rule) in config.schedule.iter().enumerate() {
+            imp.schedule_box.append(&self.rule_row(index, rule));
+        }
+    }
+
+    fn rule_row(&self, index: usize, rule: &Rule) -> gtk::Box {
+        let title = gtk::Label::new(Some(&format!(
+            "{} {}",
+            rule.time.format("%H:%M"),
+            rule.action
+        )));
+        let subtitle = gtk::Label::new(None);
+        let enabled = gtk::Switch::new();
+        let remove = gtk::Button::from_icon_name(Some("user-trash-symbolic"));
+        let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let days = DAYS
+            .iter()
+            .position(|days| *days == rule.days.as_slice())
+            .map_or_else(
+                || gettext("Some days"),
+                |index| match index {
+                    0 => gettext("Every day"),
+                    1 => gettext("Weekdays"),
+                    _ => gettext("Weekends"),
+                },
+            );
+
+        subtitle.set_label(&match rule.next_run(&Local::now()) {
+            Some(next) if rule.enabled => {
+                gettext_f("{}, next {}", &[&days, &next.format("%a %H:%M")])
+            }
+            _ => days,
+        });
+        title.set_xalign(0.0);
+        subtitle.set_xalign(0.0);
+        subtitle.add_css_class("dim-label");
+        subtitle.add_css_class("caption");
+        enabled.set_valign(gtk::Align::Center);
+        enabled.set_active(rule.enabled);
+        enabled.connect_active_notify(glib::clone!(@weak self as page => move |switch| {
+            page.edit_schedule(|schedule| {
+                if let Some(rule) = schedule.get_mut(index) {
+                    rule.enabled = switch.is_active();
+                }
+            });
+        }));
+        remove.set_valign(gtk::Align::Center);
+        remove.set_tooltip_text(Some(&gettext("Remove the rule")));
+        remove.add_css_class("flat");
+        remove.connect_clicked(glib::clone!(@weak self as page => move |_| {
+            page.edit_schedule(|schedule| {
+                if index < schedule.len() {
+                    schedule.remove(index);
+                }
+            });
+        }));
+        labels.set_hexpand(true);
+        labels.append(&title);
+        labels.append(&subtitle);
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&labels);
+        content.append(&enabled);
+        content.append(&remove);
+
+        content
+    }
+
+    fn add_rule(&self) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+        let time = match NaiveTime::parse_from_str(imp.time_entry.text().trim(), "%H:%M") {
+            Ok(time) => time,
+            Err(_) => {
+                self.show_error(&gettext(
+                    "The time should be in 24-hour form, such as 22:00",
+                ));
+                return;
+            }
+        };
+        let action = match imp
+            .schedule_actions
+            .borrow()
+            .get(imp.action_dropdown.selected() as usize)
+        {
+            Some(action) => action.clone(),
+            None => return,
+        };
+        let mut rule = Rule::new(time, action);
+
+        rule.days = DAYS
+            .get(imp.days_dropdown.selected() as usize)
+            .map_or_else(Vec::new, |days| days.to_vec());
+        imp.time_entry.set_text("");
+        imp.error_label.set_visible(false);
+        self.edit_schedule(|schedule| schedule.push(rule));
+    }
+
+    /// Changes the rules in the config, which the scheduler reads again
+    /// before the next run.
+    fn edit_schedule<F: FnOnce(&mut Vec<Rule>)>(&self, edit: F) {
+        let mut config = Config::load().unwrap_or_default();
+
+        edit(&mut config.schedule);
+
+        match config.save() {
+            Ok(()) => self.reload_schedule(),
+            Err(error) => {
+                self.show_error(&gettext_f("The schedule could not be saved: {}", &[&error]))
+            }
+        }
+    }
+
+    /// Saves the settings and the current location as a profile, replacing
+    /// any profile by the same name.
+    fn save_profile(&self) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+        let name = imp.profile_entry.text().trim().to_owned();
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        imp.save_profile_button.set_sensitive(false);
+        imp.error_label.set_visible(false);
+
+        thread::spawn(move || {
+            let profile = || -> CliResult<Profile> {
+                // The CLI takes names with underscores instead of spaces.
+                let target = nordvpn::status()?.map(|status| {
+                    ConnectOption::CountryCity(
+                        status.country.replace(' ', "_"),
+                        status.city.replace(' ', "_"),
+                    )
+                });
+
+                Ok(Profile::from_settings(name, target, &nordvpn::settings()?))
+            };
+
+            sender
+                .send(profile().map_err(|error| error.to_string()))
+                .ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |profile: Result<Profile, String>| {
+                let imp = imp::VikingSettingsPage::from_instance(&page);
+                let mut config = Config::load().unwrap_or_default();
+                let saved = profile.and_then(|profile| {
+                    config.set_profile(profile);
+                    config.save().map_err(|error| error.to_string())
+                });
+
+                match saved {
+                    Ok(()) => {
+                        imp.profile_entry.set_text("");
+                        page.reload_profiles();
+                    }
+                    Err(error) => {
+                        page.show_error(&gettext_f("The profile could not be saved: {}", &[&error]));
+                        imp.save_profile_button.set_sensitive(true);
+                    }
+                }
+
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    fn apply_profile(&self, profile: Profile) {
+        watchdog::connecting(profile.target.as_ref());
+        self.run(move || {
+            profile.apply()?;
+            Ok((nordvpn::settings()?, true))
+        });
+    }
+
+    fn append_row<W: IsA<gtk::Widget>>(&self, title: &str, subtitle: &str, widget: &W) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+        let title = gtk::Label::new(Some(title));
+        let subtitle = gtk::Label::new(Some(subtitle));
+        let labels = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+
+        title.set_xalign(0.0);
+        subtitle.set_xalign(0.0);
+        subtitle.set_wrap(true);
+        subtitle.add_css_class("dim-label");
+        subtitle.add_css_class("caption");
+        labels.set_hexpand(true);
+        labels.append(&title);
+        labels.append(&subtitle);
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.append(&labels);
+        content.append(widget);
+        imp.list_box.append(&content);
+    }
+
+    fn change_dns(&self, text: &str) {
+        let addresses = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|address| !address.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<IpAddr>, _>>();
+
+        match addresses {
+            Ok(addresses) if addresses.is_empty() => self.change(Change::Dns(None)),
+            Ok(addresses) => self.change(Change::Dns(Some(addresses))),
+            Err(error) => self.show_error(&gettext_f("Invalid DNS address: {}", &[&error])),
+        }
+    }
+
+    /// Applies the change, asking first if it would make NordVPN reconnect.
+    fn change(&self, change: Change) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+
+        if imp.updating.get() {
+            return;
+        }
+
+        if !change.reconnects() {
+            return self.apply(change);
+        }
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        imp.list_box.set_sensitive(false);
+
+        thread::spawn(move || {
+            // Asking is harmless if the status could not be read.
+            let connected = !matches!(nordvpn::status(), Ok(None));
+            sender.send(connected).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |connected| {
+                let imp = imp::VikingSettingsPage::from_instance(&page);
+
+                imp.list_box.set_sensitive(true);
+
+                match connected {
+                    true => page.confirm_reconnect(change.clone()),
+                    false => page.apply(change.clone()),
+                }
+
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    /// Asks whether to apply a change which reconnects, putting the widget
+    /// back if not.
+    fn confirm_reconnect(&self, change: Change) {
+        let window = self
+            .root()
+            .and_then(|root| root.downcast::<gtk::Window>().ok());
+        let dialog = gtk::MessageDialog::new(
+            window.as_ref(),
+            gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::None,
+            &gettext("Reconnect to Apply the Change?"),
+        );
+
+        dialog.set_secondary_text(Some(&gettext(
+            "NordVPN reconnects to apply this setting, so the connection is briefly interrupted.",
+        )));
+        dialog.add_button(&gettext("_Cancel"), gtk::ResponseType::Cancel);
+        dialog.add_button(&gettext("_Reconnect"), gtk::ResponseType::Accept);
+        dialog.set_default_response(gtk::ResponseType::Accept);
+        dialog.connect_response(glib::clone!(@weak self as page => move |dialog, response| {
+            dialog.destroy();
+
+            match response {
+                gtk::ResponseType::Accept => page.apply(change.clone()),
+                _ => page.refresh(),
+            }
+        }));
+        dialog.present();
+    }
+
+    /// Applies the change in the background, then reads every setting back
+    /// so the page shows what the daemon actually uses.
+    fn apply(&self, change: Change) {
+        self.run(move || {
+            let mut settings = nordvpn::settings()?;
+            change.apply(&mut settings)?;
+
+            let settings = nordvpn::settings()?;
+            let applied = change.is_applied(&settings);
+            Ok((settings, applied))
+        });
+    }
+
+    fn refresh(&self) {
+        self.run(|| Ok((nordvpn::settings()?, true)));
+    }
+
+    fn run<F>(&self, call: F)
+    where
+        F: FnOnce() -> CliResult<(Settings, bool)> + Send + 'static,
+    {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        imp.list_box.set_sensitive(false);
+        imp.error_label.set_visible(false);
+
+        thread::spawn(move || {
+            sender.send(call().map_err(|error| error.to_string())).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |result: Result<(Settings, bool), String>| {
+                let imp = imp::VikingSettingsPage::from_instance(&page);
+
+                imp.list_box.set_sensitive(true);
+
+                match result {
+                    Ok((settings, applied)) => {
+                        page.update(&settings);
+
+                        if !applied {
+                            page.show_error(&gettext("NordVPN did not apply the change"));
+                        }
+                    }
+                    Err(error) => page.show_error(&error),
+                }
+
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    fn show_error(&self, error: &str) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+
+        imp.error_label.set_label(error);
+        imp.error_label.set_visible(true);
+    }
+
+    fn update(&self, settings: &Settings) {
+        let imp = imp::VikingSettingsPage::from_instance(self);
+
+        // Setting the widgets must not be mistaken for the user changing them.
+        imp.updating.set(true);
+
+        let technology = TECHNOLOGIES
+            .iter()
+            .position(|technology| *technology == settings.technology);
+        imp.technology
+            .set_selected(technology.unwrap_or_default() as u32);
+
+        let protocol = settings
+            .protocol
+            .and_then(|protocol| PROTOCOLS.iter().position(|current| *current == protocol));
+        imp.protocol
+            .set_selected(protocol.unwrap_or_default() as u32);
+        // NordLynx only runs over UDP, so the CLI does not list a protocol.
+        imp.protocol.set_sensitive(settings.protocol.is_some());
+
+        for (toggle, switch) in imp.switches.borrow().iter() {
+            let value = toggle.get(settings);
+
+            switch.set_active(value.unwrap_or_default());
+            switch.set_sensitive(value.is_some());
+        }
+
+        let dns = settings
+            .dns
+            .iter()
+            .flatten()
+            .map(IpAddr::to_string)
+            .collect::<Vec<_>>();
+        imp.dns_entry.set_text(&dns.join(", "));
+
+        imp.updating.set(false);
+    }
+}
+
+/// The location and settings of the profile, such as `Berlin, Germany over
+/// NORDLYNX with the kill switch`.
+fn describe(profile: &Profile) -> String {
+    let mut text = match &profile.target {
+        Some(ConnectOption::CountryCity(country, city)) => {
+            format!("{}, {}", city, country).replace('_', " ")
+        }
+        Some(option) => option.to_string(),
+        None => gettext("Any server"),
+    };
+
+    if let Some(technology) = profile.technology {
+        text = gettext_f("{} over {}", &[&text, &technology]);
+    }
+    if profile.killswitch == Some(true) {
+        text = gettext_f("{} with the kill switch", &[&text]);
+    }
+    if let Some(dns) = profile.dns.as_ref().filter(|dns| !dns.is_empty()) {
+        let dns = dns.iter().map(IpAddr::to_string).collect::<Vec<_>>();
+        text = gettext_f("{}, DNS {}", &[&text, &dns.join(", ")]);
+    }
+
+    text
+}
+
+impl Default for VikingSettingsPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+mod imp {
+    use super::Toggle;
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use nordvpn_viking::nordvpn::ConnectOption;
+    use nordvpn_viking::schedule::ScheduledAction;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/settings_page.ui")]
+    pub struct VikingSettingsPage {
+        #[template_child]
+        pub list_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub error_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub technology: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub protocol: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub dns_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub profile_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub save_profile_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub profiles_box: TemplateChild<gtk::ListBox>,
+        #[template_child]
+        pub time_entry: TemplateChild<gtk::Entry>,
+        #[template_child]
+        pub days_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub action_dropdown: TemplateChild<gtk::DropDown>,
+        #[template_child]
+        pub add_rule_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub schedule_box: TemplateChild<gtk::ListBox>,
+        /// The actions listed in `action_dropdown`, in order.
+        pub schedule_actions: RefCell<Vec<ScheduledAction>>,
+        pub default_target: gtk::DropDown,
+        /// The targets listed in `default_target` after the fastest server.
+        pub targets: RefCell<Vec<ConnectOption>>,
+        pub switches: RefCell<Vec<(Toggle, gtk::Switch)>>,
+        /// Set while the widgets are being updated from the CLI.
+        pub updating: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingSettingsPage {
+        const NAME: &'static str = "VikingSettingsPage";
+        type Type = super::VikingSettingsPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(class: &mut Self::Class) {
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingSettingsPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingSettingsPage {}
+    impl BoxImpl for VikingSettingsPage {}
+}
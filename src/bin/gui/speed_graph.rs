@@ -0,0 +1,211 @@
+use crate::palette::{self, Shade};
+use byte_unit::Byte;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{cairo, glib};
+use nordvpn_viking::nordvpn::TransferRate;
+use std::time::{Duration, Instant};
+
+/// The colors of the received and sent lines.
+const RECEIVED_COLOR: Shade = palette::BLUE;
+const SENT_COLOR: Shade = palette::ORANGE;
+
+/// The space left of the plot for the scale labels.
+const MARGIN: f64 = 64.0;
+
+glib::wrapper! {
+    pub struct VikingSpeedGraph(ObjectSubclass<imp::VikingSpeedGraph>)
+    @extends gtk::Widget, gtk::DrawingArea;
+}
+
+impl VikingSpeedGraph {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingSpeedGraph`")
+    }
+
+    /// How far back the graph reaches.
+    pub fn set_window(&self, window: Duration) {
+        let imp = imp::VikingSpeedGraph::from_instance(self);
+
+        imp.window.set(window);
+        self.trim();
+        self.queue_draw();
+    }
+
+    pub fn push(&self, rate: TransferRate) {
+        let imp = imp::VikingSpeedGraph::from_instance(self);
+
+        imp.samples.borrow_mut().push((Instant::now(), rate));
+        self.trim();
+        self.queue_draw();
+    }
+
+    /// How long ago the latest rate was pushed.
+    pub fn last_sample_age(&self) -> Option<Duration> {
+        let imp = imp::VikingSpeedGraph::from_instance(self);
+
+        imp.samples.borrow().last().map(|(at, _)| at.elapsed())
+    }
+
+    pub fn clear(&self) {
+        let imp = imp::VikingSpeedGraph::from_instance(self);
+
+        imp.samples.borrow_mut().clear();
+        self.queue_draw();
+    }
+
+    fn trim(&self) {
+        let imp = imp::VikingSpeedGraph::from_instance(self);
+        let window = imp.window.get();
+
+        imp.samples
+            .borrow_mut()
+            .retain(|(at, _)| at.elapsed() <= window);
+    }
+
+    fn draw(&self, cr: &cairo::Context, width: f64, height: f64) -> Result<(), cairo::Error> {
+        let imp = imp::VikingSpeedGraph::from_instance(self);
+        let samples = imp.samples.borrow();
+        let window = imp.window.get().as_secs_f64();
+        let foreground = self.style_context().color();
+        let peak = samples
+            .iter()
+            .map(|(_, rate)| rate.received.max(rate.sent))
+            .fold(0.0, f64::max);
+        let scale = nice_ceiling(peak);
+        let plot_width = (width - MARGIN).max(1.0);
+
+        // The scale, with gridlines at the top, middle and bottom.
+        cr.set_line_width(1.0);
+        cr.set_font_size(10.0);
+
+        for step in 0..=2 {
+            let y = (height - 1.0) * f64::from(step) / 2.0 + 0.5;
+            let value = scale * f64::from(2 - step) / 2.0;
+            let label = format!(
+                "{}/s",
+                Byte::from_bytes(value as u128).get_appropriate_unit(false)
+            );
+
+            cr.set_source_rgba(
+                foreground.red.into(),
+                foreground.green.into(),
+                foreground.blue.into(),
+                0.15,
+            );
+            cr.move_to(MARGIN, y);
+            cr.line_to(width, y);
+            cr.stroke()?;
+
+            cr.set_source_rgba(
+                foreground.red.into(),
+                foreground.green.into(),
+                foreground.blue.into(),
+                0.6,
+            );
+            cr.move_to(0.0, (y + 4.0).clamp(10.0, height));
+            cr.show_text(&label)?;
+        }
+
+        // The newest sample is at the right edge.
+        let point = |(at, value): (Instant, f64)| {
+            let age = at.elapsed().as_secs_f64().min(window);
+            let x = MARGIN + plot_width * (1.0 - age / window);
+            let y = (height - 1.0) * (1.0 - value / scale) + 0.5;
+            (x, y)
+        };
+
+        for (color, value) in [
+            (
+                RECEIVED_COLOR,
+                (|rate: &TransferRate| rate.received) as fn(&TransferRate) -> f64,
+            ),
+            (SENT_COLOR, |rate: &TransferRate| rate.sent),
+        ] {
+            let mut points = samples.iter().map(|(at, rate)| point((*at, value(rate))));
+
+            if let Some((x, y)) = points.next() {
+                let (red, green, blue) = color.get();
+
+                cr.set_source_rgb(red, green, blue);
+                cr.set_line_width(2.0);
+                cr.move_to(x, y);
+
+                for (x, y) in points {
+                    cr.line_to(x, y);
+                }
+
+                cr.stroke()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for VikingSpeedGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The smallest of 1, 2 or 5 times a power of ten that is at least `value`,
+/// so that the scale reads as round numbers. Never below 1 KB/s, so an idle
+/// connection does not magnify noise.
+fn nice_ceiling(value: f64) -> f64 {
+    let value = value.max(1000.0);
+    let magnitude = 10_f64.powf(value.log10().floor());
+
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|nice| *nice >= value)
+        .unwrap_or(10.0 * magnitude)
+}
+
+mod imp {
+    use gtk::glib;
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use nordvpn_viking::nordvpn::TransferRate;
+    use std::cell::{Cell, RefCell};
+    use std::time::{Duration, Instant};
+
+    pub struct VikingSpeedGraph {
+        pub samples: RefCell<Vec<(Instant, TransferRate)>>,
+        pub window: Cell<Duration>,
+    }
+
+    impl Default for VikingSpeedGraph {
+        fn default() -> Self {
+            Self {
+                samples: RefCell::default(),
+                window: Cell::new(Duration::from_secs(60)),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingSpeedGraph {
+        const NAME: &'static str = "VikingSpeedGraph";
+        type Type = super::VikingSpeedGraph;
+        type ParentType = gtk::DrawingArea;
+    }
+
+    impl ObjectImpl for VikingSpeedGraph {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.set_content_height(120);
+            obj.set_draw_func(|area, cr, width, height| {
+                let graph = area.downcast_ref::<super::VikingSpeedGraph>().unwrap();
+                // A failed draw only leaves this frame incomplete.
+                graph.draw(cr, f64::from(width), f64::from(height)).ok();
+            });
+            crate::palette::redraw_on_change(obj);
+        }
+    }
+
+    impl WidgetImpl for VikingSpeedGraph {}
+    impl DrawingAreaImpl for VikingSpeedGraph {}
+}
@@ -0,0 +1,104 @@
+//! Polls the connection status once for the whole application, whether or
+//! not a window is open, and hands each change to everything that shows or
+//! records it, such as the tray, the status page and the D-Bus service.
+
+use crate::preferences::Preferences;
+use gtk::glib;
+use nordvpn_viking::nordvpn::{CliResult, Status, StatusEvent, StatusWatcher};
+use std::cell::{Cell, RefCell};
+use std::thread;
+use std::time::Duration;
+
+type Subscriber = Box<dyn Fn(&CliResult<StatusEvent>) -> glib::Continue>;
+
+// Only touched on the main thread, where the events are handed out.
+thread_local! {
+    static SUBSCRIBERS: RefCell<Vec<Subscriber>> = const { RefCell::new(Vec::new()) };
+    /// The status as of the last event, for those subscribing later.
+    static STATUS: RefCell<Option<Status>> = const { RefCell::new(None) };
+    static INTERVAL: Cell<Option<Duration>> = const { Cell::new(None) };
+}
+
+/// Starts polling at the interval from the preferences. Only the first call
+/// does anything.
+pub fn spawn() {
+    if INTERVAL.with(Cell::get).is_some() {
+        return;
+    }
+
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    let interval = Preferences::new().poll_interval();
+
+    INTERVAL.with(|cell| cell.set(Some(interval)));
+
+    thread::spawn(move || {
+        let watcher = StatusWatcher::new(interval);
+
+        for event in watcher.events() {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver.attach(None, |event| {
+        dispatch(&event);
+        glib::Continue(true)
+    });
+}
+
+/// How often the status is polled, or the interval from the preferences if
+/// polling has not started.
+pub fn poll_interval() -> Duration {
+    INTERVAL
+        .with(Cell::get)
+        .unwrap_or_else(|| Preferences::new().poll_interval())
+}
+
+/// Calls `callback` with every event from now on, until it returns
+/// `glib::Continue(false)`. If already connected, it is first called with
+/// that status as if it had just connected.
+pub fn subscribe<F>(callback: F)
+where
+    F: Fn(&CliResult<StatusEvent>) -> glib::Continue + 'static,
+{
+    let current = STATUS.with(|status| status.borrow().clone());
+
+    if let Some(status) = current {
+        if !callback(&Ok(StatusEvent::Connected(status))).0 {
+            return;
+        }
+    }
+
+    SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().push(Box::new(callback)));
+}
+
+fn dispatch(event: &CliResult<StatusEvent>) {
+    STATUS.with(|status| {
+        let mut status = status.borrow_mut();
+
+        match event {
+            Ok(StatusEvent::Connected(next) | StatusEvent::ServerChanged(next)) => {
+                *status = Some(next.clone())
+            }
+            Ok(StatusEvent::Disconnected) => *status = None,
+            Ok(StatusEvent::TransferUpdated(transfer)) => {
+                if let Some(status) = status.as_mut() {
+                    status.transfer = *transfer;
+                }
+            }
+            Err(_) => (),
+        }
+    });
+
+    // Taken out while calling them, so that they may subscribe others.
+    let mut subscribers = SUBSCRIBERS.with(|subscribers| subscribers.take());
+
+    subscribers.retain(|subscriber| subscriber(event).0);
+    SUBSCRIBERS.with(|added| {
+        let mut added = added.borrow_mut();
+
+        subscribers.append(&mut added);
+        *added = subscribers;
+    });
+}
@@ -0,0 +1,449 @@
+use crate::i18n::{gettext, gettext_f};
+use crate::preferences::Preferences;
+use crate::status_monitor;
+use crate::watchdog;
+use gtk::glib;
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use nordvpn_viking::config::Config;
+use nordvpn_viking::fastest;
+use nordvpn_viking::nordapi::{Insights, NordApi};
+use nordvpn_viking::nordvpn::{self, CliResult, ConnectOption, Status, StatusEvent, TransferRate};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait after the connection changes before looking up the
+/// public address, so that the routes have settled.
+const INSIGHTS_DELAY: Duration = Duration::from_secs(2);
+
+glib::wrapper! {
+    pub struct VikingStatusPage(ObjectSubclass<imp::VikingStatusPage>)
+    @extends gtk::Widget, gtk::Box,
+    @implements gtk::Orientable;
+}
+
+/// Sent from background threads to the page on the main thread.
+pub enum Message {
+    /// A connect or disconnect attempt finished, with the error if it failed.
+    Finished(Result<(), String>),
+    /// The public address as of the lookup numbered by the first field.
+    Insights(u64, Result<Insights, String>),
+}
+
+impl VikingStatusPage {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingStatusPage`")
+    }
+
+    /// Calls `callback` whenever the connection changes, with the new status
+    /// or `None` once disconnected.
+    pub fn connect_status_changed<F: Fn(Option<&Status>) + 'static>(&self, callback: F) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+
+        callback(imp.status.borrow().as_ref());
+        imp.callbacks.borrow_mut().push(Box::new(callback));
+    }
+
+    pub fn is_connected(&self) -> bool {
+        let imp = imp::VikingStatusPage::from_instance(self);
+
+        imp.status.borrow().is_some()
+    }
+
+    fn watch(&self) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        imp.poll_interval.set(status_monitor::poll_interval());
+
+        // Stops once the page is gone.
+        status_monitor::subscribe(
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |event| {
+                page.handle_event(event);
+                glib::Continue(true)
+            }),
+        );
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move |message| {
+                page.handle(message);
+                glib::Continue(true)
+            }),
+        );
+
+        imp.sender.replace(Some(sender));
+        // The first update came before there was a sender to look it up with.
+        self.refresh_insights(Duration::ZERO);
+        imp.connect_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| page.quick_connect()));
+        imp.disconnect_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| page.disconnect()));
+        imp.refresh_public_button
+            .connect_clicked(glib::clone!(@weak self as page => move |_| {
+                page.refresh_insights(Duration::ZERO);
+            }));
+
+        glib::timeout_add_seconds_local(
+            1,
+            glib::clone!(@weak self as page => @default-return glib::Continue(false), move || {
+                page.update_uptime();
+                page.update_idle();
+                glib::Continue(true)
+            }),
+        );
+    }
+
+    /// Connects in the background, showing any error on the page.
+    pub fn connect(&self, option: Option<ConnectOption>) {
+        watchdog::connecting(option.as_ref());
+        self.run(&gettext("Connecting…"), move || {
+            nordvpn::connect(option.as_ref())
+                .map(drop)
+                .map_err(|error| error.to_string())
+        });
+    }
+
+    /// Connects to the default target from the preferences, or else the best
+    /// server matching the preferences in the config.
+    pub fn quick_connect(&self) {
+        if let Some(target) = Preferences::new().default_target() {
+            return self.connect(Some(target));
+        }
+
+        let filters = Config::load().unwrap_or_default().quick_connect_filters();
+
+        watchdog::connecting(None);
+        self.run(&gettext("Finding the fastest server…"), move || {
+            fastest::connect_fastest(&filters)
+                .map(drop)
+                .map_err(|error| error.to_string())
+        });
+    }
+
+    pub fn disconnect(&self) {
+        watchdog::disconnecting();
+        self.run(&gettext("Disconnecting…"), || {
+            nordvpn::disconnect()
+                .map(drop)
+                .map_err(|error| error.to_string())
+        });
+    }
+
+    /// Runs a blocking call on another thread, with the controls disabled and
+    /// `progress` shown until it finishes.
+    fn run<F>(&self, progress: &str, call: F)
+    where
+        F: FnOnce() -> Result<(), String> + Send + 'static,
+    {
+        let imp = imp::VikingStatusPage::from_instance(self);
+        let sender = match imp.sender.borrow().clone() {
+            Some(sender) => sender,
+            None => return,
+        };
+
+        imp.connect_button.set_sensitive(false);
+        imp.disconnect_button.set_sensitive(false);
+        imp.error_label.set_visible(false);
+        imp.state_label.set_label(progress);
+
+        thread::spawn(move || {
+            sender.send(Message::Finished(call())).ok();
+        });
+    }
+
+    /// Looks up the public address after `delay`, replacing any lookup that
+    /// has not finished yet.
+    fn refresh_insights(&self, delay: Duration) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+        let sender = match imp.sender.borrow().clone() {
+            Some(sender) => sender,
+            None => return,
+        };
+        let lookup = imp.insights_lookup.get() + 1;
+
+        imp.insights_lookup.set(lookup);
+        imp.refresh_public_button.set_sensitive(false);
+        imp.protected_label.set_label(&gettext("Checking…"));
+
+        thread::spawn(move || {
+            thread::sleep(delay);
+
+            let insights = NordApi::new().insights().map_err(|error| error.to_string());
+            sender.send(Message::Insights(lookup, insights)).ok();
+        });
+    }
+
+    fn update_insights(&self, insights: Result<Insights, String>) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+
+        imp.refresh_public_button.set_sensitive(true);
+        imp.protected_label.remove_css_class("success");
+        imp.protected_label.remove_css_class("warning");
+
+        let insights = match insights {
+            Ok(insights) => insights,
+            Err(error) => {
+                imp.protected_label.set_label(&gettext("Unknown"));
+                imp.public_ip_label.set_label("…");
+                imp.isp_label.set_label(&error);
+                imp.public_location_label.set_label("…");
+                return;
+            }
+        };
+        let location = [insights.city.as_deref(), insights.country.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        imp.protected_label.set_label(&match insights.protected {
+            true => gettext("Protected"),
+            false => gettext("Unprotected"),
+        });
+        imp.protected_label.add_css_class(match insights.protected {
+            true => "success",
+            false => "warning",
+        });
+        imp.public_ip_label.set_label(&insights.ip.to_string());
+        imp.isp_label
+            .set_label(&insights.isp.unwrap_or_else(|| gettext("Unknown")));
+        imp.public_location_label
+            .set_label(&match location.is_empty() {
+                true => gettext("Unknown"),
+                false => location,
+            });
+    }
+
+    fn handle_event(&self, event: &CliResult<StatusEvent>) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+
+        match event {
+            Ok(StatusEvent::Connected(status) | StatusEvent::ServerChanged(status)) => {
+                self.update(Some(status.clone()))
+            }
+            Ok(StatusEvent::Disconnected) => self.update(None),
+            Ok(StatusEvent::TransferUpdated(transfer)) => {
+                if let Some(status) = imp.status.borrow_mut().as_mut() {
+                    status.transfer = *transfer;
+                }
+                if let Some(rate) = imp.tracker.borrow_mut().update(*transfer) {
+                    imp.speed_graph.push(rate);
+                }
+                self.update_transfer();
+            }
+            Err(error) => self.show_error(&error.to_string()),
+        }
+    }
+
+    fn handle(&self, message: Message) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+
+        match message {
+            Message::Finished(Err(error)) => self.show_error(&error),
+            Message::Finished(Ok(())) => {
+                imp.connect_button.set_sensitive(true);
+                imp.disconnect_button.set_sensitive(true);
+            }
+            // Older lookups may have seen the address before a change.
+            Message::Insights(lookup, insights) if lookup == imp.insights_lookup.get() => {
+                self.update_insights(insights)
+            }
+            Message::Insights(..) => (),
+        }
+    }
+
+    fn show_error(&self, error: &str) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+
+        imp.error_label.set_label(error);
+        imp.error_label.set_visible(true);
+        imp.connect_button.set_sensitive(true);
+        imp.disconnect_button.set_sensitive(true);
+        // Undo the progress shown while the call was running.
+        imp.state_label
+            .set_label(&match imp.status.borrow().is_some() {
+                true => gettext("Connected"),
+                false => gettext("Disconnected"),
+            });
+    }
+
+    fn update(&self, status: Option<Status>) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+        let connected = status.is_some();
+
+        imp.state_label.set_label(&if connected {
+            gettext("Connected")
+        } else {
+            gettext("Disconnected")
+        });
+        imp.details_grid.set_visible(connected);
+        imp.connect_button.set_visible(!connected);
+        imp.disconnect_button.set_visible(connected);
+
+        if let Some(status) = &status {
+            imp.hostname_label.set_label(&status.hostname);
+            imp.location_label
+                .set_label(&format!("{}, {}", status.city, status.country));
+            imp.ip_label.set_label(&status.ip.to_string());
+            imp.technology_label.set_label(&gettext_f(
+                "{} over {}",
+                &[&status.technology, &status.protocol],
+            ));
+        }
+
+        // The totals of a new connection are unrelated to the previous one.
+        imp.tracker.borrow_mut().reset();
+        if let Some(status) = &status {
+            imp.tracker.borrow_mut().update(status.transfer);
+        } else {
+            imp.speed_graph.clear();
+        }
+
+        imp.status.replace(status);
+        self.refresh_insights(INSIGHTS_DELAY);
+
+        for callback in imp.callbacks.borrow().iter() {
+            callback(imp.status.borrow().as_ref());
+        }
+
+        self.update_uptime();
+        self.update_transfer();
+    }
+
+    fn update_uptime(&self) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+
+        if let Some(status) = imp.status.borrow().as_ref() {
+            let seconds = status.current_uptime().num_seconds().max(0);
+            let (days, hours) = (seconds / 86400, seconds / 3600 % 24);
+            let (minutes, seconds) = (seconds / 60 % 60, seconds % 60);
+
+            imp.uptime_label.set_label(&match days {
+                0 => format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
+                _ => format!("{}d {:02}:{:02}:{:02}", days, hours, minutes, seconds),
+            });
+        }
+    }
+
+    /// The totals only change while something is transferred, so the graph
+    /// drops to zero when they have not for a while.
+    fn update_idle(&self) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+        let idle = imp.poll_interval.get() * 3;
+
+        if imp.status.borrow().is_some()
+            && imp
+                .speed_graph
+                .last_sample_age()
+                .is_none_or(|age| age > idle)
+        {
+            imp.speed_graph.push(TransferRate::default());
+        }
+    }
+
+    fn update_transfer(&self) {
+        let imp = imp::VikingStatusPage::from_instance(self);
+
+        if let Some(status) = imp.status.borrow().as_ref() {
+            imp.transfer_label.set_label(&gettext_f(
+                "{} received, {} sent",
+                &[
+                    &status.transfer.received.get_appropriate_unit(true),
+                    &status.transfer.sent.get_appropriate_unit(true),
+                ],
+            ));
+        }
+    }
+}
+
+impl Default for VikingStatusPage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+mod imp {
+    use crate::speed_graph::VikingSpeedGraph;
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use gtk::{glib, CompositeTemplate};
+    use nordvpn_viking::nordvpn::{Status, TransferTracker};
+    use std::cell::{Cell, RefCell};
+    use std::time::Duration;
+
+    type Callback = Box<dyn Fn(Option<&Status>)>;
+
+    #[derive(Default, CompositeTemplate)]
+    #[template(resource = "/com/github/spikespaz/nordvpn-viking/ui/status_page.ui")]
+    pub struct VikingStatusPage {
+        #[template_child]
+        pub state_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub details_grid: TemplateChild<gtk::Grid>,
+        #[template_child]
+        pub hostname_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub location_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub ip_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub technology_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub uptime_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub transfer_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub speed_graph: TemplateChild<VikingSpeedGraph>,
+        #[template_child]
+        pub protected_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub refresh_public_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub public_ip_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub isp_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub public_location_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub error_label: TemplateChild<gtk::Label>,
+        #[template_child]
+        pub connect_button: TemplateChild<gtk::Button>,
+        #[template_child]
+        pub disconnect_button: TemplateChild<gtk::Button>,
+        pub status: RefCell<Option<Status>>,
+        pub sender: RefCell<Option<glib::Sender<super::Message>>>,
+        pub tracker: RefCell<TransferTracker>,
+        pub callbacks: RefCell<Vec<Callback>>,
+        /// Counts the lookups of the public address, to drop stale results.
+        pub insights_lookup: Cell<u64>,
+        /// How often the [`status_monitor`](crate::status_monitor) polls.
+        pub poll_interval: Cell<Duration>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingStatusPage {
+        const NAME: &'static str = "VikingStatusPage";
+        type Type = super::VikingStatusPage;
+        type ParentType = gtk::Box;
+
+        fn class_init(class: &mut Self::Class) {
+            VikingSpeedGraph::static_type();
+            Self::bind_template(class);
+        }
+
+        fn instance_init(obj: &glib::subclass::InitializingObject<Self>) {
+            obj.init_template();
+        }
+    }
+
+    impl ObjectImpl for VikingStatusPage {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+            obj.watch();
+        }
+    }
+
+    impl WidgetImpl for VikingStatusPage {}
+    impl BoxImpl for VikingStatusPage {}
+}
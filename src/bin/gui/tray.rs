@@ -0,0 +1,205 @@
+//! The StatusNotifierItem shown in the system tray, which keeps the
+//! connection controls reachable while the main window is closed.
+
+use crate::i18n::{gettext, gettext_f};
+use crate::status_monitor;
+use crate::watchdog;
+use gtk::glib;
+use ksni::menu::{MenuItem, StandardItem, SubMenu};
+use ksni::{Handle, ToolTip, Tray, TrayService};
+use nordvpn_viking::config::Config;
+use nordvpn_viking::fastest::{self, FastestError};
+use nordvpn_viking::nordvpn::{self, Status, StatusEvent};
+use std::thread;
+
+/// Requests from the tray which have to be handled on the GTK main thread.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrayAction {
+    ShowWindow,
+    Quit,
+}
+
+pub struct VikingTray {
+    status: Option<Status>,
+    actions: glib::Sender<TrayAction>,
+}
+
+impl VikingTray {
+    /// Registers the tray icon and keeps it in sync with the connection
+    /// status from the [`status_monitor`].
+    pub fn spawn(actions: glib::Sender<TrayAction>) -> Handle<Self> {
+        let service = TrayService::new(Self {
+            status: None,
+            actions,
+        });
+        let handle = service.handle();
+
+        service.spawn();
+
+        let watched = handle.clone();
+        status_monitor::subscribe(move |event| {
+            match event {
+                Ok(StatusEvent::Connected(status) | StatusEvent::ServerChanged(status)) => {
+                    let status = status.clone();
+
+                    watched.update(|tray| tray.status = Some(status));
+                }
+                Ok(StatusEvent::Disconnected) => watched.update(|tray| tray.status = None),
+                _ => (),
+            }
+
+            glib::Continue(true)
+        });
+
+        handle
+    }
+
+    fn send(&self, action: TrayAction) {
+        // The receiver only goes away when the application is quitting.
+        self.actions.send(action).ok();
+    }
+}
+
+impl Tray for VikingTray {
+    fn id(&self) -> String {
+        env!("CARGO_PKG_NAME").to_owned()
+    }
+
+    fn title(&self) -> String {
+        "NordVPN Viking".to_owned()
+    }
+
+    fn icon_name(&self) -> String {
+        match self.status {
+            Some(_) => "network-vpn",
+            None => "network-vpn-disconnected",
+        }
+        .to_owned()
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        ToolTip {
+            title: self.title(),
+            description: match &self.status {
+                Some(status) => gettext_f("Connected to {}", &[&status.hostname]),
+                None => gettext("Disconnected"),
+            },
+            ..Default::default()
+        }
+    }
+
+    fn activate(&mut self, _x: i32, _y: i32) {
+        self.send(TrayAction::ShowWindow);
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        let connected = self.status.is_some();
+        // Read each time, since the main window may have changed them.
+        let Config {
+            favorites,
+            profiles,
+            ..
+        } = Config::load().unwrap_or_default();
+
+        vec![
+            StandardItem {
+                label: match &self.status {
+                    Some(status) => {
+                        format!("{} ({}, {})", status.hostname, status.city, status.country)
+                    }
+                    None => gettext("Not connected"),
+                },
+                enabled: false,
+                ..Default::default()
+            }
+            .into(),
+            MenuItem::Separator,
+            StandardItem {
+                label: gettext("_Quick Connect"),
+                visible: !connected,
+                activate: Box::new(|_| {
+                    watchdog::connecting(None);
+                    thread::spawn(quick_connect);
+                }),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: gettext("_Disconnect"),
+                visible: connected,
+                activate: Box::new(|_| {
+                    watchdog::disconnecting();
+                    thread::spawn(nordvpn::disconnect);
+                }),
+                ..Default::default()
+            }
+            .into(),
+            SubMenu {
+                label: gettext("_Favorites"),
+                enabled: !favorites.is_empty(),
+                submenu: favorites
+                    .into_iter()
+                    .map(|favorite| {
+                        StandardItem {
+                            label: favorite.name.replace('_', "__"),
+                            activate: Box::new(move |_| {
+                                let option = favorite.option.clone();
+                                watchdog::connecting(Some(&option));
+                                thread::spawn(move || nordvpn::connect(Some(&option)));
+                            }),
+                            ..Default::default()
+                        }
+                        .into()
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+            .into(),
+            SubMenu {
+                label: gettext("_Profiles"),
+                enabled: !profiles.is_empty(),
+                submenu: profiles
+                    .into_iter()
+                    .map(|profile| {
+                        StandardItem {
+                            label: profile.name.replace('_', "__"),
+                            activate: Box::new(move |_| {
+                                let profile = profile.clone();
+                                watchdog::connecting(profile.target.as_ref());
+                                thread::spawn(move || profile.apply());
+                            }),
+                            ..Default::default()
+                        }
+                        .into()
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+            .into(),
+            MenuItem::Separator,
+            StandardItem {
+                label: gettext("_Show Window"),
+                activate: Box::new(|tray: &mut Self| tray.send(TrayAction::ShowWindow)),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: gettext("_Quit"),
+                activate: Box::new(|tray: &mut Self| tray.send(TrayAction::Quit)),
+                ..Default::default()
+            }
+            .into(),
+        ]
+    }
+}
+
+/// Connects to the fastest server matching the preferences. The tray cannot
+/// show errors, so if no server could be picked this lets the CLI choose.
+pub fn quick_connect() {
+    let filters = Config::load().unwrap_or_default().quick_connect_filters();
+
+    if let Err(FastestError::Api(_) | FastestError::NoServers) = fastest::connect_fastest(&filters)
+    {
+        nordvpn::connect(None).ok();
+    }
+}
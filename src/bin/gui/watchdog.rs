@@ -0,0 +1,212 @@
+//! Reconnects dropped connections for the whole application, whether or not
+//! a window is open, and tells the user with desktop notifications. While the
+//! daemon runs, it reconnects instead and is told what the user does here.
+
+use crate::dbus_service;
+use crate::i18n::{gettext, gettext_f};
+use crate::preferences::Preferences;
+use gtk::prelude::*;
+use gtk::{gio, glib};
+use nordvpn_viking::behavior::{Behavior, WatchdogBehavior};
+use nordvpn_viking::nordvpn::{
+    ConnectOption, RetryPolicy, Watchdog, WatchdogEvent, WatchdogHandle,
+};
+use nordvpn_viking::systemd::BUS_NAME;
+use once_cell::sync::OnceCell;
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+/// How often the watchdog polls the connection status, unless the
+/// [`Behavior`] file says otherwise.
+const INTERVAL: Duration = Duration::from_secs(3);
+
+/// The notification is replaced by each event rather than stacking up.
+const NOTIFICATION_ID: &str = "watchdog";
+
+static HANDLE: OnceCell<WatchdogHandle> = OnceCell::new();
+
+/// The session bus, when the daemon was found on it at startup.
+static DAEMON: OnceCell<gio::DBusConnection> = OnceCell::new();
+
+/// Starts the watchdog, enabled while the preferences say to.
+pub fn spawn(app: &gio::Application) {
+    let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+    let behavior = Behavior::load().unwrap_or_default().watchdog;
+    let watchdog = Watchdog::new(behavior.interval_or(INTERVAL), behavior.policy(policy()));
+    let handle = watchdog.handle();
+
+    let preferences = Preferences::new();
+
+    handle.set_enabled(preferences.auto_reconnect());
+    HANDLE.set(handle).ok();
+    // The handler holds on to the settings for as long as the application.
+    preferences.connect_changed(
+        "auto-reconnect",
+        glib::clone!(@strong preferences => move |_| {
+            set_enabled(preferences.auto_reconnect());
+        }),
+    );
+
+    // The watchdog stops when this thread drops it with the application.
+    thread::spawn(move || {
+        for event in watchdog.events() {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver.attach(
+        None,
+        glib::clone!(@weak app => @default-return glib::Continue(false), move |event| {
+            notify(&app, &event);
+            glib::Continue(true)
+        }),
+    );
+}
+
+/// Leaves reconnecting to the daemon, if it owns its name on `connection`,
+/// rather than starting a second watchdog. Returns whether it does.
+pub fn defer_to_daemon(connection: &gio::DBusConnection) -> bool {
+    let running = connection
+        .call_sync(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameHasOwner",
+            Some(&(BUS_NAME,).to_variant()),
+            Some(glib::VariantTy::new("(b)").unwrap()),
+            gio::DBusCallFlags::NONE,
+            -1,
+            None::<&gio::Cancellable>,
+        )
+        .ok()
+        .and_then(|reply| reply.get::<(bool,)>())
+        .is_some_and(|(running,)| running);
+
+    if running {
+        DAEMON.set(connection.clone()).ok();
+    }
+
+    running
+}
+
+/// The handle of the running watchdog, for others that connect or disconnect.
+pub fn handle() -> Option<WatchdogHandle> {
+    HANDLE.get().cloned()
+}
+
+pub fn set_enabled(enabled: bool) {
+    if let Some(handle) = HANDLE.get() {
+        handle.set_enabled(enabled);
+    }
+}
+
+/// Applies the intervals of the [`Behavior`] file, such as when it changes.
+pub fn configure(behavior: &WatchdogBehavior) {
+    if let Some(handle) = HANDLE.get() {
+        handle.set_interval(behavior.interval_or(INTERVAL));
+        handle.set_policy(behavior.policy(policy()));
+    }
+}
+
+/// Whether the system has a network connection, so that the watchdog waits
+/// for one before reconnecting.
+pub fn set_online(online: bool) {
+    if let Some(handle) = HANDLE.get() {
+        handle.set_online(online);
+    }
+}
+
+/// Checks the connection now, such as after resuming from suspend.
+pub fn check_now() {
+    if let Some(handle) = HANDLE.get() {
+        handle.check_now();
+    }
+}
+
+/// Remembers what the user is connecting to, or `None` for the CLI's choice,
+/// so that a reconnect picks the same.
+pub fn connecting(option: Option<&ConnectOption>) {
+    if let Some(handle) = HANDLE.get() {
+        handle.set_target(option.cloned());
+    } else {
+        let target = option.map(ToString::to_string).unwrap_or_default();
+
+        call_daemon("SetTarget", Some(&(target,).to_variant()));
+    }
+}
+
+/// Keeps the watchdog from reconnecting after the user disconnects.
+pub fn disconnecting() {
+    if let Some(handle) = HANDLE.get() {
+        handle.expect_disconnect();
+    } else {
+        call_daemon("ExpectDisconnect", None);
+    }
+}
+
+/// Calls a method of the daemon's service without waiting for it, since the
+/// bus delivers it before anything the caller does next could be noticed.
+fn call_daemon(method: &str, parameters: Option<&glib::Variant>) {
+    if let Some(connection) = DAEMON.get() {
+        connection.call(
+            Some(BUS_NAME),
+            dbus_service::OBJECT_PATH,
+            dbus_service::INTERFACE_NAME,
+            method,
+            parameters,
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+            None::<&gio::Cancellable>,
+            |result| {
+                if let Err(error) = result {
+                    warn!("Failed to tell the daemon: {}", error);
+                }
+            },
+        );
+    }
+}
+
+/// How reconnecting is retried, unless the [`Behavior`] file says otherwise.
+fn policy() -> RetryPolicy {
+    RetryPolicy::default()
+        .initial_delay(Duration::from_secs(2))
+        .max_delay(Duration::from_secs(30))
+}
+
+fn notify(app: &gio::Application, event: &WatchdogEvent) {
+    if !Preferences::new().notifications() {
+        return;
+    }
+
+    let (title, body) = match event {
+        WatchdogEvent::Reconnecting { attempt: 1, .. } => (
+            gettext("Connection Lost"),
+            gettext("NordVPN disconnected unexpectedly. Reconnecting…"),
+        ),
+        WatchdogEvent::Reconnecting { attempt, .. } => (
+            gettext("Connection Lost"),
+            gettext_f("Reconnecting, attempt {}…", &[attempt]),
+        ),
+        WatchdogEvent::Reconnected(connected) => (
+            gettext("Reconnected"),
+            match (&connected.country, &connected.city) {
+                (Some(country), Some(city)) => {
+                    gettext_f("Connected again in {}, {}.", &[city, country])
+                }
+                _ => gettext("The VPN connection is back."),
+            },
+        ),
+        WatchdogEvent::GaveUp(error) => (
+            gettext("Could Not Reconnect"),
+            gettext_f("The VPN is disconnected: {}", &[error]),
+        ),
+    };
+    let notification = gio::Notification::new(&title);
+
+    notification.set_body(Some(&body));
+    app.send_notification(Some(NOTIFICATION_ID), &notification);
+}
@@ -0,0 +1,443 @@
+use crate::i18n::{gettext, ngettext_f};
+use crate::palette::{self, Shade};
+use gtk::prelude::*;
+use gtk::subclass::prelude::*;
+use gtk::{cairo, glib};
+use nordvpn_viking::nordapi::{ApiResult, Server, ServerFilters};
+use nordvpn_viking::nordvpn::{ConnectOption, ListEntry, Status};
+use std::collections::BTreeMap;
+use std::thread;
+
+const MARKER_COLOR: Shade = palette::BLUE;
+const CONNECTED_COLOR: Shade = palette::GREEN;
+
+/// How far from a marker, in pixels, a click or the pointer still hits it.
+const HIT_RADIUS: f64 = 8.0;
+/// How far the pointer may move, in pixels, for a press to count as a click.
+const CLICK_SLOP: f64 = 4.0;
+const MAX_ZOOM: f64 = 32.0;
+
+glib::wrapper! {
+    pub struct VikingWorldMap(ObjectSubclass<imp::VikingWorldMap>)
+    @extends gtk::Widget, gtk::DrawingArea;
+}
+
+/// A city with servers, which connects to the city when clicked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapMarker {
+    pub country: String,
+    pub city: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// How many servers are in the city.
+    pub servers: usize,
+    pub option: ConnectOption,
+}
+
+impl MapMarker {
+    fn is_at(&self, status: &Status) -> bool {
+        self.country.eq_ignore_ascii_case(&status.country)
+            && self.city.eq_ignore_ascii_case(&status.city)
+    }
+}
+
+impl VikingWorldMap {
+    pub fn new() -> Self {
+        glib::Object::new(&[]).expect("Failed to create `VikingWorldMap`")
+    }
+
+    /// Calls `callback` with the target of every marker the user clicks.
+    pub fn connect_target_activated<F: Fn(&ConnectOption) + 'static>(&self, callback: F) {
+        let imp = imp::VikingWorldMap::from_instance(self);
+
+        imp.callbacks.borrow_mut().push(Box::new(callback));
+    }
+
+    /// Highlights the location of the connection, or nothing if disconnected.
+    pub fn set_connected(&self, status: Option<&Status>) {
+        let imp = imp::VikingWorldMap::from_instance(self);
+
+        imp.connected.replace(status.cloned());
+        self.queue_draw();
+    }
+
+    /// Shows the whole world again.
+    pub fn reset_view(&self) {
+        let imp = imp::VikingWorldMap::from_instance(self);
+
+        imp.zoom.set(1.0);
+        imp.offset.set((0.0, 0.0));
+        self.queue_draw();
+    }
+
+    fn setup(&self) {
+        let drag = gtk::GestureDrag::new();
+        let scroll = gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+        let motion = gtk::EventControllerMotion::new();
+
+        drag.connect_drag_begin(glib::clone!(@weak self as map => move |_, _, _| {
+            let imp = imp::VikingWorldMap::from_instance(&map);
+            imp.drag_origin.set(imp.offset.get());
+        }));
+        drag.connect_drag_update(glib::clone!(@weak self as map => move |_, dx, dy| {
+            let imp = imp::VikingWorldMap::from_instance(&map);
+            let (x, y) = imp.drag_origin.get();
+            map.set_offset((x + dx, y + dy));
+        }));
+        drag.connect_drag_end(glib::clone!(@weak self as map => move |drag, dx, dy| {
+            if dx.hypot(dy) <= CLICK_SLOP {
+                if let Some((x, y)) = drag.start_point() {
+                    map.click(x, y);
+                }
+            }
+        }));
+        scroll.connect_scroll(
+            glib::clone!(@weak self as map => @default-return gtk::Inhibit(false), move |_, _, dy| {
+                let imp = imp::VikingWorldMap::from_instance(&map);
+                map.zoom_at(imp.pointer.get(), 1.25_f64.powf(-dy));
+                gtk::Inhibit(true)
+            }),
+        );
+        motion.connect_motion(glib::clone!(@weak self as map => move |_, x, y| {
+            let imp = imp::VikingWorldMap::from_instance(&map);
+            imp.pointer.set((x, y));
+        }));
+
+        self.add_controller(&drag);
+        self.add_controller(&scroll);
+        self.add_controller(&motion);
+
+        self.set_has_tooltip(true);
+        self.connect_query_tooltip(|map, x, y, _, tooltip| {
+            let imp = imp::VikingWorldMap::from_instance(map);
+            let markers = imp.markers.borrow();
+
+            match map.marker_at(f64::from(x), f64::from(y)) {
+                Some(index) => {
+                    let marker = &markers[index];
+                    tooltip.set_text(Some(&ngettext_f(
+                        "{}, {}\n{} server",
+                        "{}, {}\n{} servers",
+                        marker.servers as u32,
+                        &[&marker.city, &marker.country, &marker.servers],
+                    )));
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    /// Fetches the servers the first time the map is shown, since the list
+    /// is large and most of the time the map is never opened.
+    fn load(&self) {
+        let imp = imp::VikingWorldMap::from_instance(self);
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+
+        if imp.loading.replace(true) {
+            return;
+        }
+
+        imp.message.replace(Some(gettext("Loading servers…")));
+
+        let api = crate::api();
+
+        thread::spawn(move || {
+            sender.send(api.servers(&ServerFilters::default())).ok();
+        });
+
+        receiver.attach(
+            None,
+            glib::clone!(@weak self as map => @default-return glib::Continue(false), move |servers: ApiResult<Vec<Server>>| {
+                let imp = imp::VikingWorldMap::from_instance(&map);
+
+                match servers {
+                    Ok(servers) => {
+                        imp.markers.replace(markers(&servers));
+                        imp.message.replace(None);
+                    }
+                    Err(error) => {
+                        imp.message.replace(Some(error.to_string()));
+                        // Try again the next time the map is shown.
+                        imp.loading.set(false);
+                    }
+                }
+
+                map.queue_draw();
+                glib::Continue(false)
+            }),
+        );
+    }
+
+    /// Pixels per degree at the current zoom, with the whole world fitting
+    /// in the widget at a zoom of one.
+    fn scale(&self) -> f64 {
+        let imp = imp::VikingWorldMap::from_instance(self);
+        let (width, height) = (f64::from(self.width()), f64::from(self.height()));
+
+        (width / 360.0).min(height / 180.0) * imp.zoom.get()
+    }
+
+    /// Where a coordinate is drawn, with an equirectangular projection.
+    fn project(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        let imp = imp::VikingWorldMap::from_instance(self);
+        let scale = self.scale();
+        let (x, y) = imp.offset.get();
+
+        (
+            f64::from(self.width()) / 2.0 + longitude * scale + x,
+            f64::from(self.height()) / 2.0 - latitude * scale + y,
+        )
+    }
+
+    /// Keeps at least half of the world in view.
+    fn set_offset(&self, (x, y): (f64, f64)) {
+        let imp = imp::VikingWorldMap::from_instance(self);
+        let scale = self.scale();
+        let (width, height) = (180.0 * scale, 90.0 * scale);
+
+        imp.offset
+            .set((x.clamp(-width, width), y.clamp(-height, height)));
+        self.queue_draw();
+    }
+
+    /// Zooms by `factor`, keeping the coordinate under `point` in place.
+    fn zoom_at(&self, (x, y): (f64, f64), factor: f64) {
+        let imp = imp::VikingWorldMap::from_instance(self);
+        let old = imp.zoom.get();
+        let new = (old * factor).clamp(1.0, MAX_ZOOM);
+        let (center_x, center_y) = (
+            f64::from(self.width()) / 2.0,
+            f64::from(self.height()) / 2.0,
+        );
+        let (offset_x, offset_y) = imp.offset.get();
+
+        imp.zoom.set(new);
+        self.set_offset((
+            x - center_x - (x - center_x - offset_x) * new / old,
+            y - center_y - (y - center_y - offset_y) * new / old,
+        ));
+    }
+
+    /// The index of the marker nearest to the point, if one is close enough.
+    fn marker_at(&self, x: f64, y: f64) -> Option<usize> {
+        let imp = imp::VikingWorldMap::from_instance(self);
+
+        imp.markers
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(index, marker)| {
+                let (marker_x, marker_y) = self.project(marker.latitude, marker.longitude);
+                (index, (marker_x - x).hypot(marker_y - y))
+            })
+            .filter(|(_, distance)| *distance <= HIT_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+
+    fn click(&self, x: f64, y: f64) {
+        let imp = imp::VikingWorldMap::from_instance(self);
+
+        if let Some(index) = self.marker_at(x, y) {
+            let option = imp.markers.borrow()[index].option.clone();
+
+            for callback in imp.callbacks.borrow().iter() {
+                callback(&option);
+            }
+        }
+    }
+
+    fn draw(&self, cr: &cairo::Context, width: f64, height: f64) -> Result<(), cairo::Error> {
+        let imp = imp::VikingWorldMap::from_instance(self);
+        let foreground = self.style_context().color();
+        let set_foreground = |alpha: f64| {
+            cr.set_source_rgba(
+                foreground.red.into(),
+                foreground.green.into(),
+                foreground.blue.into(),
+                alpha,
+            );
+        };
+
+        // The graticule, every 30 degrees.
+        let (left, top) = self.project(90.0, -180.0);
+        let (right, bottom) = self.project(-90.0, 180.0);
+
+        set_foreground(0.05);
+        cr.rectangle(left, top, right - left, bottom - top);
+        cr.fill()?;
+
+        set_foreground(0.12);
+        cr.set_line_width(1.0);
+
+        for step in -6..=6 {
+            let (x, _) = self.project(0.0, f64::from(step) * 30.0);
+            cr.move_to(x.round() + 0.5, top);
+            cr.line_to(x.round() + 0.5, bottom);
+        }
+        for step in -3..=3 {
+            let (_, y) = self.project(f64::from(step) * 30.0, 0.0);
+            cr.move_to(left, y.round() + 0.5);
+            cr.line_to(right, y.round() + 0.5);
+        }
+        cr.stroke()?;
+
+        let connected = imp.connected.borrow();
+        let markers = imp.markers.borrow();
+        let radius = |marker: &MapMarker| 2.5 + (marker.servers as f64).log2().max(0.0) * 0.5;
+
+        let (red, green, blue) = MARKER_COLOR.get();
+
+        cr.set_source_rgb(red, green, blue);
+
+        for marker in markers.iter() {
+            let (x, y) = self.project(marker.latitude, marker.longitude);
+
+            cr.new_sub_path();
+            cr.arc(x, y, radius(marker), 0.0, std::f64::consts::TAU);
+        }
+        cr.fill()?;
+
+        // The connected location is drawn last so nothing covers it.
+        let current = markers.iter().find(|marker| {
+            connected
+                .as_ref()
+                .is_some_and(|status| marker.is_at(status))
+        });
+
+        if let Some(marker) = current {
+            let (x, y) = self.project(marker.latitude, marker.longitude);
+
+            let (red, green, blue) = CONNECTED_COLOR.get();
+
+            cr.set_source_rgb(red, green, blue);
+            cr.arc(x, y, radius(marker) + 2.0, 0.0, std::f64::consts::TAU);
+            cr.fill()?;
+            cr.set_line_width(2.0);
+            cr.arc(x, y, radius(marker) + 6.0, 0.0, std::f64::consts::TAU);
+            cr.stroke()?;
+        }
+
+        if let Some(message) = imp.message.borrow().as_deref() {
+            cr.set_font_size(14.0);
+            let extents = cr.text_extents(message)?;
+
+            set_foreground(0.7);
+            cr.move_to((width - extents.width) / 2.0, height / 2.0);
+            cr.show_text(message)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for VikingWorldMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A marker for every city among `servers`, placed where its first server is.
+fn markers(servers: &[Server]) -> Vec<MapMarker> {
+    let mut markers = BTreeMap::<(String, String), MapMarker>::new();
+
+    for server in servers {
+        let (country, city) = match (server.country(), server.city()) {
+            (Some(country), Some(city)) => (country, city),
+            _ => continue,
+        };
+        let (latitude, longitude) = server.coordinates().unwrap_or_default();
+
+        markers
+            .entry((country.name.clone(), city.name.clone()))
+            .or_insert_with(|| MapMarker {
+                country: country.name.clone(),
+                city: city.name.clone(),
+                latitude,
+                longitude,
+                servers: 0,
+                option: ConnectOption::CountryCity(
+                    ListEntry::new(&country.name).id,
+                    ListEntry::new(&city.name).id,
+                ),
+            })
+            .servers += 1;
+    }
+
+    markers.into_values().collect()
+}
+
+mod imp {
+    use super::MapMarker;
+    use gtk::glib;
+    use gtk::prelude::*;
+    use gtk::subclass::prelude::*;
+    use nordvpn_viking::nordvpn::{ConnectOption, Status};
+    use std::cell::{Cell, RefCell};
+
+    type Callback = Box<dyn Fn(&ConnectOption)>;
+
+    pub struct VikingWorldMap {
+        pub markers: RefCell<Vec<MapMarker>>,
+        pub connected: RefCell<Option<Status>>,
+        /// Shown in the middle while loading or after failing to.
+        pub message: RefCell<Option<String>>,
+        pub loading: Cell<bool>,
+        pub zoom: Cell<f64>,
+        /// How far the world has been dragged from the center, in pixels.
+        pub offset: Cell<(f64, f64)>,
+        pub drag_origin: Cell<(f64, f64)>,
+        /// The last position of the pointer, which scrolling zooms around.
+        pub pointer: Cell<(f64, f64)>,
+        pub callbacks: RefCell<Vec<Callback>>,
+    }
+
+    impl Default for VikingWorldMap {
+        fn default() -> Self {
+            Self {
+                markers: RefCell::default(),
+                connected: RefCell::default(),
+                message: RefCell::default(),
+                loading: Cell::default(),
+                zoom: Cell::new(1.0),
+                offset: Cell::default(),
+                drag_origin: Cell::default(),
+                pointer: Cell::default(),
+                callbacks: RefCell::default(),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for VikingWorldMap {
+        const NAME: &'static str = "VikingWorldMap";
+        type Type = super::VikingWorldMap;
+        type ParentType = gtk::DrawingArea;
+    }
+
+    impl ObjectImpl for VikingWorldMap {
+        fn constructed(&self, obj: &Self::Type) {
+            self.parent_constructed(obj);
+
+            obj.set_hexpand(true);
+            obj.set_vexpand(true);
+            obj.set_draw_func(|area, cr, width, height| {
+                let map = area.downcast_ref::<super::VikingWorldMap>().unwrap();
+                // A failed draw only leaves this frame incomplete.
+                map.draw(cr, f64::from(width), f64::from(height)).ok();
+            });
+            crate::palette::redraw_on_change(obj);
+            obj.setup();
+        }
+    }
+
+    impl WidgetImpl for VikingWorldMap {
+        fn map(&self, widget: &Self::Type) {
+            self.parent_map(widget);
+            widget.load();
+        }
+    }
+
+    impl DrawingAreaImpl for VikingWorldMap {}
+}
@@ -0,0 +1,592 @@
+//! A command line companion to the application for scripts, which uses the
+//! same backend and server selection, and exits with a code for each kind of
+//! error rather than leaving the output of `nordvpn` to be parsed.
+
+use byte_unit::Byte;
+use clap::error::ErrorKind;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::CompleteEnv;
+use nordvpn_viking::fastest::{self, FastestError, FastestOptions};
+use nordvpn_viking::history::History;
+use nordvpn_viking::json;
+use nordvpn_viking::logging;
+use nordvpn_viking::nordapi::{ApiCache, NordApi, Server, ServerFilters};
+use nordvpn_viking::nordvpn::{
+    CliError, ConnectOption, Connected, NordVPN, NordVpnBackend, Settings,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::io;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  Any other error
+  2  The arguments are invalid
+  3  Not logged in
+  4  The daemon cannot be reached
+  5  No server could be connected to";
+
+/// The groups which the API prefixes with `legacy_`, by the names the CLI
+/// knows them by.
+const LEGACY_GROUPS: &[&str] = &[
+    "dedicated_ip",
+    "double_vpn",
+    "obfuscated_servers",
+    "onion_over_vpn",
+    "p2p",
+    "standard",
+];
+
+/// Why a command failed, which decides the exit code.
+#[derive(Debug)]
+enum Error {
+    Usage(clap::Error),
+    Cli(CliError),
+    Fastest(FastestError),
+    Io(io::Error),
+    Other(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<CliError> for Error {
+    fn from(error: CliError) -> Self {
+        Self::Cli(error)
+    }
+}
+
+impl From<FastestError> for Error {
+    fn from(error: FastestError) -> Self {
+        match error {
+            FastestError::Cli(error) => Self::Cli(error),
+            error => Self::Fastest(error),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Usage(error) => {
+                // Only the message, without the usage and tip clap adds.
+                let rendered = error.to_string();
+                let message = rendered.lines().next().unwrap_or_default();
+
+                f.write_str(message.strip_prefix("error: ").unwrap_or(message))
+            }
+            Self::Other(message) => f.write_str(message),
+            Self::Cli(error) => error.fmt(f),
+            Self::Fastest(error) => error.fmt(f),
+            Self::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error {
+    /// Which error it is, for the JSON output.
+    fn kind(&self) -> &'static str {
+        match self.exit_code() {
+            2 => "usage",
+            3 => "not-logged-in",
+            4 => "daemon",
+            5 => "no-server",
+            _ => "other",
+        }
+    }
+
+    fn exit_code(&self) -> u8 {
+        match self {
+            Self::Usage(_) => 2,
+            Self::Cli(CliError::NotLoggedIn) => 3,
+            Self::Cli(
+                CliError::DaemonNotRunning
+                | CliError::DaemonBusy
+                | CliError::PermissionDenied
+                | CliError::SnapConfinement,
+            ) => 4,
+            Self::Cli(CliError::InvalidServer | CliError::ServerUnavailable)
+            | Self::Fastest(FastestError::NoServers) => 5,
+            _ => 1,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    // Answers the shell and exits if it is asking for completions.
+    CompleteEnv::with_factory(command).complete();
+    logging::init();
+
+    // Known before the arguments are parsed, so that errors with them are
+    // printed as JSON too.
+    let json = env::args_os().any(|arg| arg == "--json");
+    let backend = NordVPN;
+
+    match run(&backend, env::args_os()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            if json {
+                let document = json!({
+                    "error": {
+                        "kind": error.kind(),
+                        "code": error.exit_code(),
+                        "message": error.to_string(),
+                    }
+                });
+
+                json::write(io::stderr(), &document).ok();
+            } else if let Error::Usage(error) = &error {
+                error.print().ok();
+            } else {
+                eprintln!("viking: {}", error);
+            }
+
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+fn run<B, I, T>(backend: &B, args: I) -> Result<(), Error>
+where
+    B: NordVpnBackend,
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = match command().try_get_matches_from(args) {
+        Ok(matches) => matches,
+        // Help is not an error, and is printed to stdout.
+        Err(error) if !error.use_stderr() => return Ok(error.print()?),
+        Err(error) => return Err(Error::Usage(error)),
+    };
+    let json = matches.get_flag("json");
+
+    match matches.subcommand() {
+        Some(("status", _)) => {
+            let status = backend.status()?;
+
+            match status {
+                _ if json => print_json(&json::Status::from(status.as_ref()))?,
+                Some(status) => println!("{}", capitalize(&status.to_string())),
+                None => println!("Disconnected"),
+            }
+        }
+        Some(("connect", matches)) => {
+            let option = connect_option(
+                matches.get_one::<String>("target").map(String::as_str),
+                matches.get_one::<String>("city").map(String::as_str),
+            )?;
+
+            print_connected(&backend.connect(option.as_ref())?, json)?;
+        }
+        Some(("disconnect", _)) => {
+            let disconnected = backend.disconnect()?;
+
+            match disconnected {
+                _ if json => print_json(&json!({ "disconnected": disconnected }))?,
+                true => println!("Disconnected"),
+                false => println!("Not connected"),
+            }
+        }
+        Some(("settings", _)) => {
+            let settings = backend.settings()?;
+
+            if json {
+                print_json(&json::Settings::from(&settings))?;
+            } else {
+                print_settings(&settings);
+            }
+        }
+        Some(("fastest", matches)) => fastest(backend, matches, json)?,
+        Some(("history", matches)) => history(matches, json)?,
+        _ => unreachable!("a subcommand is required"),
+    }
+
+    Ok(())
+}
+
+/// The arguments, which are also completed in the shell from these.
+fn command() -> Command {
+    Command::new("viking")
+        .about("A command line companion to NordVPN Viking")
+        .after_help(EXIT_CODES)
+        .subcommand_required(true)
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Print one JSON document with a stable schema, and errors as JSON on stderr"),
+        )
+        .subcommand(Command::new("status").about("Show the connection"))
+        .subcommand(
+            Command::new("connect")
+                .about("Connect to a country, city, server or group")
+                .arg(
+                    Arg::new("target")
+                        .help(
+                            "A country, or a server, group or city such as `de507` or `group:P2P`",
+                        )
+                        .add(ArgValueCandidates::new(countries)),
+                )
+                .arg(
+                    Arg::new("city")
+                        .help("A city of the country")
+                        .add(ArgValueCandidates::new(cities)),
+                ),
+        )
+        .subcommand(Command::new("disconnect").about("Disconnect"))
+        .subcommand(Command::new("settings").about("Show the settings of the CLI"))
+        .subcommand(
+            Command::new("fastest")
+                .about("Connect to the server with the best load and latency")
+                .arg(
+                    Arg::new("group")
+                        .long("group")
+                        .value_name("GROUP")
+                        .help("Only servers of the group, such as `p2p`"),
+                )
+                .arg(
+                    Arg::new("technology")
+                        .long("technology")
+                        .value_name("TECH")
+                        .help("Only servers with the technology, such as `wireguard_udp`"),
+                )
+                .arg(
+                    Arg::new("list")
+                        .long("list")
+                        .action(ArgAction::SetTrue)
+                        .help("List the servers from best to worst instead"),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("List past connections, newest first")
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_name("COUNT")
+                        .value_parser(value_parser!(usize)),
+                ),
+        )
+}
+
+/// The servers from the cache of the application, since completions cannot
+/// wait on the API, or none if it has not listed them yet.
+fn cached_servers() -> Vec<Server> {
+    NordApi::new()
+        .with_cache(ApiCache::new(Duration::MAX))
+        .offline()
+        .servers(&ServerFilters::default())
+        .unwrap_or_default()
+}
+
+/// The names of the countries, as the CLI takes them, with their codes.
+fn countries() -> Vec<CompletionCandidate> {
+    let countries = cached_servers()
+        .iter()
+        .filter_map(Server::country)
+        .map(|country| (country.name.replace(' ', "_"), country.code.clone()))
+        .collect::<BTreeMap<_, _>>();
+
+    countries
+        .into_iter()
+        .map(|(name, code)| CompletionCandidate::new(name).help(Some(code.into())))
+        .collect()
+}
+
+/// The names of the cities, as the CLI takes them, with their countries.
+fn cities() -> Vec<CompletionCandidate> {
+    let cities = cached_servers()
+        .iter()
+        .filter_map(|server| Some((server.city()?, server.country()?)))
+        .map(|(city, country)| (city.name.replace(' ', "_"), country.name.clone()))
+        .collect::<BTreeMap<_, _>>();
+
+    cities
+        .into_iter()
+        .map(|(name, country)| CompletionCandidate::new(name).help(Some(country.into())))
+        .collect()
+}
+
+/// The option for the arguments of `connect`, which are a country and a
+/// city, or one target which the CLI resolves itself unless it is written
+/// like `country:Germany`.
+fn connect_option(
+    target: Option<&str>,
+    city: Option<&str>,
+) -> Result<Option<ConnectOption>, Error> {
+    match (target, city) {
+        (None, _) => Ok(None),
+        (Some(target), None)
+            if target.contains(':') || target.eq_ignore_ascii_case("dedicated-ip") =>
+        {
+            target
+                .parse::<ConnectOption>()
+                .map(Some)
+                .map_err(|error| Error::Usage(command().error(ErrorKind::InvalidValue, error)))
+        }
+        (Some(target), None) if target.len() == 2 => {
+            Ok(Some(ConnectOption::CountryCode(target.to_owned())))
+        }
+        (Some(target), None) => Ok(Some(ConnectOption::Country(target.to_owned()))),
+        (Some(country), Some(city)) => Ok(Some(ConnectOption::CountryCity(
+            country.to_owned(),
+            city.to_owned(),
+        ))),
+    }
+}
+
+fn fastest<B: NordVpnBackend>(backend: &B, matches: &ArgMatches, json: bool) -> Result<(), Error> {
+    let mut filters = ServerFilters::new();
+    let list = matches.get_flag("list");
+
+    if let Some(group) = matches.get_one::<String>("group") {
+        filters = filters.group(group_identifier(group));
+    }
+    if let Some(technology) = matches.get_one::<String>("technology") {
+        filters = filters.technology(technology);
+    }
+
+    let api = NordApi::new();
+    let options = FastestOptions::default();
+
+    if !list {
+        let connected = fastest::connect_fastest_with(backend, &api, &filters, &options)?;
+
+        return print_connected(&connected, json);
+    }
+
+    let ranked = fastest::fastest(&api, &filters, &options)?;
+
+    if json {
+        print_json(&ranked.iter().map(json::Server::from).collect::<Vec<_>>())?;
+    } else {
+        for ranked in ranked {
+            let latency = match ranked.latency {
+                Some(latency) => format!("{} ms", latency.as_millis()),
+                None => "unreachable".to_owned(),
+            };
+
+            println!(
+                "{}\t{}%\t{}",
+                ranked.server.hostname, ranked.server.load, latency
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn history(matches: &ArgMatches, json: bool) -> Result<(), Error> {
+    let limit = matches
+        .get_one::<usize>("limit")
+        .copied()
+        .unwrap_or(usize::MAX);
+    let entries = History::new()
+        .load()
+        .map_err(|error| Error::Other(format!("failed to read the history: {}", error)))?;
+
+    let entries = entries.iter().rev().take(limit);
+
+    if json {
+        return print_json(&entries.map(json::HistoryEntry::from).collect::<Vec<_>>());
+    }
+
+    for entry in entries {
+        let disconnected = match entry.disconnected {
+            Some(disconnected) => disconnected.format("%Y-%m-%d %H:%M").to_string(),
+            None => "now".to_owned(),
+        };
+
+        println!(
+            "{} to {}\t{}\t{}, {}\t{} received, {} sent",
+            entry.connected.format("%Y-%m-%d %H:%M"),
+            disconnected,
+            entry.hostname,
+            entry.city,
+            entry.country,
+            Byte::from_bytes(entry.received as u128).get_appropriate_unit(true),
+            Byte::from_bytes(entry.sent as u128).get_appropriate_unit(true),
+        );
+    }
+
+    Ok(())
+}
+
+/// The identifier the API filters by, such as `legacy_p2p` for `P2P`.
+fn group_identifier(group: &str) -> String {
+    let group = group.to_lowercase().replace(['-', ' '], "_");
+
+    if LEGACY_GROUPS.contains(&group.as_str()) {
+        format!("legacy_{}", group)
+    } else {
+        group
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(document: &T) -> Result<(), Error> {
+    Ok(json::write(io::stdout().lock(), document)?)
+}
+
+fn print_connected(connected: &Connected, json: bool) -> Result<(), Error> {
+    if json {
+        return print_json(&json::Connected::from(connected));
+    }
+
+    let mut line = format!("Connected to {}", connected.hostname);
+
+    match (&connected.city, &connected.country) {
+        (Some(city), Some(country)) => line += &format!(" in {}, {}", city, country),
+        (None, Some(country)) => line += &format!(" in {}", country),
+        _ => (),
+    }
+    if let Some(load) = connected.load {
+        line += &format!(" ({}% load)", load);
+    }
+
+    println!("{}", line);
+
+    Ok(())
+}
+
+fn print_settings(settings: &Settings) {
+    let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+    let optional = |enabled: Option<bool>| enabled.map(on_off).unwrap_or("unsupported");
+
+    println!("Technology: {}", settings.technology);
+    if let Some(protocol) = settings.protocol {
+        println!("Protocol: {}", protocol);
+    }
+    println!("Firewall: {}", on_off(settings.firewall));
+    println!("Kill switch: {}", on_off(settings.killswitch));
+    println!("Threat protection: {}", on_off(settings.cybersec));
+    println!("Obfuscate: {}", optional(settings.obfuscate));
+    println!("Notify: {}", on_off(settings.notify));
+    println!("Auto-connect: {}", on_off(settings.autoconnect));
+    println!("IPv6: {}", on_off(settings.ipv6));
+    println!("Post-quantum: {}", optional(settings.post_quantum));
+    println!("LAN discovery: {}", optional(settings.lan_discovery));
+    match &settings.dns {
+        Some(dns) => {
+            let mut dns = dns.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+            dns.sort();
+            println!("DNS: {}", dns.join(", "));
+        }
+        None => println!("DNS: NordVPN"),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command, connect_option, group_identifier, run, Error};
+    use nordvpn_viking::nordvpn::{ConnectOption, MockBackend, MockResponse};
+
+    fn args<'a>(args: &[&'a str]) -> Vec<&'a str> {
+        ["viking"].iter().chain(args).copied().collect()
+    }
+
+    #[test]
+    fn test_connect_option() {
+        assert_eq!(connect_option(None, None).unwrap(), None);
+        assert_eq!(
+            connect_option(Some("de"), Some("berlin")).unwrap(),
+            Some(ConnectOption::CountryCity(
+                "de".to_owned(),
+                "berlin".to_owned()
+            ))
+        );
+        assert_eq!(
+            connect_option(Some("de"), None).unwrap(),
+            Some(ConnectOption::CountryCode("de".to_owned()))
+        );
+        assert_eq!(
+            connect_option(Some("group:P2P"), None).unwrap(),
+            Some(ConnectOption::Group("P2P".to_owned()))
+        );
+        assert_eq!(
+            connect_option(Some("dedicated-ip"), None).unwrap(),
+            Some(ConnectOption::DedicatedIp(None))
+        );
+        assert!(matches!(
+            connect_option(Some("planet:Mars"), None),
+            Err(Error::Usage(_))
+        ));
+    }
+
+    #[test]
+    fn test_command() {
+        command().debug_assert();
+
+        for target in [
+            &["de507"][..],
+            &["group:P2P"],
+            &["dedicated-ip"],
+            &["de", "berlin"],
+        ] {
+            let matches =
+                command().try_get_matches_from(args(&[&["connect"][..], target].concat()));
+
+            assert!(matches.is_ok(), "{:?}", target);
+        }
+    }
+
+    #[test]
+    fn test_group_identifier() {
+        assert_eq!(group_identifier("p2p"), "legacy_p2p");
+        assert_eq!(group_identifier("Double-VPN"), "legacy_double_vpn");
+        assert_eq!(group_identifier("europe"), "europe");
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let backend = MockBackend::new();
+
+        backend.push(
+            ["status"],
+            MockResponse::failure(1, "You are not logged in."),
+        );
+
+        assert_eq!(run(&backend, args(&["status"])).unwrap_err().exit_code(), 3);
+        assert_eq!(
+            run(&backend, args(&["status", "now"]))
+                .unwrap_err()
+                .exit_code(),
+            2
+        );
+        assert_eq!(
+            run(&backend, args(&["connect", "de", "berlin", "now"]))
+                .unwrap_err()
+                .exit_code(),
+            2
+        );
+        assert_eq!(
+            run(&backend, args(&["history", "--limit", "ten"]))
+                .unwrap_err()
+                .exit_code(),
+            2
+        );
+        assert_eq!(run(&backend, args(&["fly"])).unwrap_err().exit_code(), 2);
+        assert_eq!(run(&backend, args(&[])).unwrap_err().exit_code(), 2);
+    }
+}
@@ -0,0 +1,220 @@
+//! Data of the application which the NordVPN CLI does not store, kept as JSON
+//! in the XDG config directory. The scalar preferences are kept with GSettings
+//! by the interface instead.
+
+use crate::nordapi::ServerFilters;
+use crate::nordvpn::ConnectOption;
+use crate::profile::Profile;
+use crate::schedule::Rule;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub favorites: Vec<Favorite>,
+    /// The country Quick Connect picks servers from, by its API id.
+    pub preferred_country: Option<u32>,
+    /// The group Quick Connect picks servers from, such as `legacy_p2p`.
+    pub preferred_group: Option<String>,
+    /// Presets to connect with, in the order they are listed.
+    pub profiles: Vec<Profile>,
+    /// Rules to connect and disconnect at times of day.
+    pub schedule: Vec<Rule>,
+}
+
+/// A country, city or server the user starred.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Favorite {
+    /// The name shown for the favorite, such as `Berlin, Germany`.
+    pub name: String,
+    #[serde(with = "connect_option")]
+    pub option: ConnectOption,
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/nordvpn-viking/config.json`, or under `~/.config` if
+    /// the variable is not set.
+    pub fn path() -> PathBuf {
+        xdg_dir("XDG_CONFIG_HOME", ".config").join("config.json")
+    }
+
+    /// Reads the config from the default path, which is the default config
+    /// if the file does not exist yet.
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(Self::path())
+    }
+
+    pub fn load_from<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(serde_json::from_str(&text)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        self.save_to(Self::path())
+    }
+
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// The filters Quick Connect passes to
+    /// [`connect_fastest`](crate::fastest::connect_fastest).
+    pub fn quick_connect_filters(&self) -> ServerFilters {
+        let mut filters = ServerFilters::new();
+
+        filters.country_id = self.preferred_country;
+        filters.group = self.preferred_group.clone();
+        filters
+    }
+
+    pub fn is_favorite(&self, option: &ConnectOption) -> bool {
+        self.favorites
+            .iter()
+            .any(|favorite| &favorite.option == option)
+    }
+
+    /// Stars the option unless it already is, returning whether it was added.
+    pub fn add_favorite<S: Into<String>>(&mut self, name: S, option: ConnectOption) -> bool {
+        if self.is_favorite(&option) {
+            return false;
+        }
+
+        self.favorites.push(Favorite {
+            name: name.into(),
+            option,
+        });
+        true
+    }
+
+    /// Returns whether the option was starred.
+    pub fn remove_favorite(&mut self, option: &ConnectOption) -> bool {
+        let len = self.favorites.len();
+
+        self.favorites.retain(|favorite| &favorite.option != option);
+        self.favorites.len() != len
+    }
+
+    /// Stars or unstars the option, returning whether it is now starred.
+    pub fn toggle_favorite<S: Into<String>>(&mut self, name: S, option: ConnectOption) -> bool {
+        !self.remove_favorite(&option) && self.add_favorite(name, option)
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Adds the profile, or replaces the one with the same name in place.
+    pub fn set_profile(&mut self, profile: Profile) {
+        match self
+            .profiles
+            .iter_mut()
+            .find(|existing| existing.name == profile.name)
+        {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// Returns whether there was a profile by that name.
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        let len = self.profiles.len();
+
+        self.profiles.retain(|profile| profile.name != name);
+        self.profiles.len() != len
+    }
+}
+
+/// The directory of this application under an XDG base directory.
+pub(crate) fn xdg_dir(variable: &str, fallback: &str) -> PathBuf {
+    xdg_base_dir(variable, fallback).join(env!("CARGO_PKG_NAME"))
+}
+
+/// An XDG base directory, which is `fallback` in the home directory if the
+/// variable is not set.
+pub(crate) fn xdg_base_dir(variable: &str, fallback: &str) -> PathBuf {
+    env::var_os(variable)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(fallback)))
+        .unwrap_or_else(env::temp_dir)
+}
+
+/// Stores a [`ConnectOption`] in its `<kind>:<value>` string form.
+mod connect_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        option: &ConnectOption,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(option)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<ConnectOption, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::nordapi::ServerFilters;
+    use crate::nordvpn::ConnectOption;
+    use crate::profile::Profile;
+
+    #[test]
+    fn test_favorites() {
+        let path = std::env::temp_dir()
+            .join(format!("nordvpn-viking-test-{}", std::process::id()))
+            .join("config.json");
+        let berlin = ConnectOption::CountryCity("Germany".to_owned(), "Berlin".to_owned());
+        let mut config = Config::load_from(&path).unwrap();
+
+        assert_eq!(config, Config::default());
+        assert_eq!(config.quick_connect_filters(), ServerFilters::new());
+        assert!(config.add_favorite("Berlin, Germany", berlin.clone()));
+        assert!(!config.add_favorite("Berlin, Germany", berlin.clone()));
+        assert!(config.toggle_favorite("de507", ConnectOption::Server("de507".to_owned())));
+
+        config.save_to(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("\"country-city:Germany/Berlin\""));
+
+        let mut loaded = Config::load_from(&path).unwrap();
+        assert_eq!(loaded, config);
+
+        loaded.preferred_group = Some("legacy_p2p".to_owned());
+        assert_eq!(
+            loaded.quick_connect_filters(),
+            ServerFilters::new().group("legacy_p2p")
+        );
+        assert!(!loaded.toggle_favorite("Berlin, Germany", berlin.clone()));
+        assert!(!loaded.is_favorite(&berlin));
+
+        loaded.set_profile(Profile::new("Berlin", Some(berlin.clone())));
+        loaded.set_profile(Profile::new("Berlin", None));
+        assert_eq!(loaded.profiles.len(), 1);
+        assert_eq!(loaded.profile("Berlin").unwrap().target, None);
+        assert!(loaded.remove_profile("Berlin"));
+        assert!(!loaded.remove_profile("Berlin"));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
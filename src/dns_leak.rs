@@ -0,0 +1,325 @@
+//! Checks whether DNS queries leave through resolvers other than NordVPN's,
+//! using the leak test service at [`LEAK_TEST_URL`]. Unique hostnames are
+//! looked up through the system resolver and every nameserver it is
+//! configured with, and the service reports which resolvers asked for them.
+
+use crate::nordvpn;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+pub const LEAK_TEST_URL: &str = "https://bash.ws";
+
+/// The resolvers NordVPN sets while connected.
+pub const NORDVPN_DNS: [IpAddr; 2] = [
+    IpAddr::V4(Ipv4Addr::new(103, 86, 96, 100)),
+    IpAddr::V4(Ipv4Addr::new(103, 86, 99, 100)),
+];
+
+/// Parts of the names of the networks NordVPN runs its resolvers in.
+const NORDVPN_NETWORKS: [&str; 3] = ["nordvpn", "packethub", "tefincom"];
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+pub type DnsLeakResult<T> = Result<T, DnsLeakError>;
+
+#[derive(Debug, Error)]
+pub enum DnsLeakError {
+    #[error("the leak test service could not be reached: {0}")]
+    Request(#[from] Box<ureq::Error>),
+    #[error("the leak test service sent an unexpected response: {0}")]
+    Decode(#[from] io::Error),
+    #[error("no resolver looked up the test hostnames")]
+    NoResolvers,
+}
+
+/// A resolver which looked up the test hostnames, or the public address the
+/// test was made from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolver {
+    pub ip: IpAddr,
+    /// The name of the country, if the service knows it.
+    pub country: Option<String>,
+    /// The autonomous system the address belongs to, such as
+    /// `AS13335 Cloudflare, Inc.`.
+    pub network: Option<String>,
+    /// Whether the resolver is NordVPN's or one the user chose.
+    pub trusted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsLeakReport {
+    /// The address the service saw the test come from.
+    pub public_ip: Option<Resolver>,
+    pub resolvers: Vec<Resolver>,
+}
+
+impl DnsLeakReport {
+    /// Whether every resolver is trusted.
+    pub fn passed(&self) -> bool {
+        self.resolvers.iter().all(|resolver| resolver.trusted)
+    }
+
+    pub fn leaks(&self) -> impl Iterator<Item = &Resolver> {
+        self.resolvers.iter().filter(|resolver| !resolver.trusted)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DnsLeakTest {
+    agent: ureq::Agent,
+    base_url: String,
+    /// The number of hostnames looked up through each resolver.
+    queries: u32,
+    timeout: Duration,
+    /// Custom DNS servers set in NordVPN, which are not leaks.
+    expected: Vec<IpAddr>,
+}
+
+impl Default for DnsLeakTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DnsLeakTest {
+    pub fn new() -> Self {
+        Self::with_base_url(LEAK_TEST_URL)
+    }
+
+    pub fn with_base_url<S: Into<String>>(base_url: S) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .build(),
+            base_url: base_url.into(),
+            queries: 6,
+            timeout: Duration::from_secs(2),
+            expected: Vec::new(),
+        }
+    }
+
+    /// Treats these resolvers as trusted, for the custom DNS servers of the
+    /// NordVPN settings.
+    pub fn expect<I: IntoIterator<Item = IpAddr>>(mut self, resolvers: I) -> Self {
+        self.expected.extend(resolvers);
+        self
+    }
+
+    pub fn queries(mut self, queries: u32) -> Self {
+        self.queries = queries;
+        self
+    }
+
+    /// How long to wait for each nameserver to answer.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs the test, which takes a few seconds.
+    pub fn run(&self) -> DnsLeakResult<DnsLeakReport> {
+        let id = self
+            .agent
+            .get(&format!("{}/id", self.base_url))
+            .call()
+            .map_err(Box::new)?
+            .into_string()?
+            .trim()
+            .to_owned();
+        let domain = self
+            .base_url
+            .split("://")
+            .last()
+            .unwrap_or_default()
+            .trim_end_matches('/');
+        let nameservers = fs::read_to_string(RESOLV_CONF)
+            .map(|text| parse_nameservers(&text))
+            .unwrap_or_default();
+
+        for query in 0..self.queries {
+            let hostname = format!("{}.{}.{}", query, id, domain);
+
+            // Every lookup fails, since only the question matters.
+            (hostname.as_str(), 0).to_socket_addrs().ok();
+
+            for nameserver in &nameservers {
+                query_nameserver(*nameserver, &hostname, self.timeout).ok();
+            }
+        }
+
+        let entries = self
+            .agent
+            .get(&format!("{}/dnsleak/test/{}", self.base_url, id))
+            .query("json", "")
+            .call()
+            .map_err(Box::new)?
+            .into_json::<Vec<Entry>>()?;
+        let report = self.report(entries);
+
+        match report.resolvers.is_empty() {
+            true => Err(DnsLeakError::NoResolvers),
+            false => Ok(report),
+        }
+    }
+
+    fn report(&self, entries: Vec<Entry>) -> DnsLeakReport {
+        let mut report = DnsLeakReport {
+            public_ip: None,
+            resolvers: Vec::new(),
+        };
+
+        for entry in entries {
+            let ip = match entry.ip.parse() {
+                Ok(ip) => ip,
+                Err(_) => continue,
+            };
+            let network = Some(entry.asn).filter(|asn| !asn.is_empty());
+            let trusted = NORDVPN_DNS.contains(&ip)
+                || self.expected.contains(&ip)
+                || network.as_ref().is_some_and(|network| {
+                    let network = network.to_lowercase();
+                    NORDVPN_NETWORKS.iter().any(|name| network.contains(name))
+                });
+            let resolver = Resolver {
+                ip,
+                country: Some(entry.country_name).filter(|country| !country.is_empty()),
+                network,
+                trusted,
+            };
+
+            match entry.kind.as_str() {
+                "ip" => report.public_ip = Some(resolver),
+                "dns" => report.resolvers.push(resolver),
+                _ => (),
+            }
+        }
+
+        report
+    }
+}
+
+/// Runs the test with the defaults, trusting the custom DNS servers of the
+/// NordVPN settings if they can be read.
+pub fn check() -> DnsLeakResult<DnsLeakReport> {
+    let custom = nordvpn::settings()
+        .ok()
+        .and_then(|settings| settings.dns)
+        .unwrap_or_default();
+
+    DnsLeakTest::new().expect(custom).run()
+}
+
+/// One line of the report of the service.
+#[derive(Debug, Deserialize)]
+struct Entry {
+    #[serde(default)]
+    ip: String,
+    #[serde(default)]
+    country_name: String,
+    #[serde(default)]
+    asn: String,
+    /// `ip` for the public address, `dns` for a resolver, or `conclusion`.
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// The nameservers of a `resolv.conf`.
+fn parse_nameservers(text: &str) -> Vec<IpAddr> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("nameserver"))
+        .filter_map(|address| address.trim().parse().ok())
+        .collect()
+}
+
+/// Sends a query for the A record of `hostname` straight to the nameserver,
+/// bypassing the system resolver, and waits for any answer.
+fn query_nameserver(nameserver: IpAddr, hostname: &str, timeout: Duration) -> io::Result<()> {
+    let local = match nameserver {
+        IpAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+        IpAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+    };
+    let socket = UdpSocket::bind(local)?;
+    let id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos() as u16);
+
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect((nameserver, 53))?;
+    socket.send(&query_packet(id, hostname))?;
+    socket.recv(&mut [0; 512])?;
+
+    Ok(())
+}
+
+/// A DNS query with one question, asking for recursion.
+fn query_packet(id: u16, hostname: &str) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(hostname.len() + 18);
+
+    packet.extend(id.to_be_bytes());
+    // Recursion desired, and one question.
+    packet.extend([0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    for label in hostname.split('.').filter(|label| !label.is_empty()) {
+        packet.push(label.len() as u8);
+        packet.extend(label.as_bytes());
+    }
+
+    // The root label, then type A and class IN.
+    packet.extend([0x00, 0x00, 0x01, 0x00, 0x01]);
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_nameservers, query_packet, DnsLeakTest, Entry};
+    use std::net::IpAddr;
+
+    const REPORT: &str = r#"[
+        {"ip":"185.130.184.75","country":"DE","country_name":"Germany","asn":"AS136787 PacketHub S.A.","type":"ip"},
+        {"ip":"103.86.96.100","country":"DE","country_name":"Germany","asn":"AS136787 PacketHub S.A.","type":"dns"},
+        {"ip":"203.0.113.53","country":"US","country_name":"United States","asn":"AS64500 Example Telecom","type":"dns"},
+        {"ip":"0","country":"","country_name":"","asn":"","type":"conclusion"}
+    ]"#;
+
+    #[test]
+    fn test_dns_leak_report() {
+        let entries = serde_json::from_str::<Vec<Entry>>(REPORT).unwrap();
+        let report = DnsLeakTest::new().report(entries);
+
+        assert_eq!(report.public_ip.as_ref().unwrap().ip.to_string(), "185.130.184.75");
+        assert_eq!(report.resolvers.len(), 2);
+        assert!(!report.passed());
+        assert_eq!(
+            report.leaks().next().unwrap().ip.to_string(),
+            "203.0.113.53"
+        );
+
+        let entries = serde_json::from_str::<Vec<Entry>>(REPORT).unwrap();
+        let report = DnsLeakTest::new()
+            .expect(["203.0.113.53".parse().unwrap()])
+            .report(entries);
+        assert!(report.passed());
+
+        assert_eq!(
+            parse_nameservers(
+                "# generated\nnameserver 103.86.96.100\nnameserver ::1\nsearch lan\n"
+            ),
+            [
+                "103.86.96.100".parse::<IpAddr>().unwrap(),
+                "::1".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            query_packet(0x1234, "1.abc.bash.ws")[12..],
+            *b"\x011\x03abc\x04bash\x02ws\x00\x00\x01\x00\x01"
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Writes the connection history and the transfer totals as CSV or JSON, for
+//! spreadsheets and other tools.
+
+use crate::history::{History, HistoryEntry};
+use crate::stats::{DailyTotal, Stats, StatsError};
+use chrono::{DateTime, Duration, Local, NaiveDate, SecondsFormat};
+use std::io::{self, Write};
+use thiserror::Error;
+
+pub type ExportResult<T> = Result<T, ExportError>;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("the export could not be written: {0}")]
+    Io(#[from] io::Error),
+    #[error("the export could not be written: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Stats(#[from] StatsError),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+#[strum(serialize_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// What to export.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ExportData {
+    /// Every connection, from the [`History`].
+    History,
+    /// The bytes carried each day in each country, from the [`Stats`].
+    Usage,
+}
+
+/// The days to export, inclusive, where a missing end is unbounded.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl ExportFormat {
+    /// The file name extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+impl DateRange {
+    pub fn new(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Self {
+        Self { from, to }
+    }
+
+    /// Every day.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// The `days` days up to and including `today`.
+    pub fn last_days(days: u32, today: NaiveDate) -> Self {
+        Self::new(
+            Some(today - Duration::days(days.saturating_sub(1) as i64)),
+            Some(today),
+        )
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+
+    /// The range with both ends set, for queries which need them.
+    fn bounds(&self) -> (NaiveDate, NaiveDate) {
+        (
+            self.from
+                .unwrap_or(NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
+            self.to
+                .unwrap_or(NaiveDate::from_ymd_opt(9999, 12, 31).unwrap()),
+        )
+    }
+}
+
+/// Exports from the default [`History`] and [`Stats`] database.
+pub fn export<W: Write>(
+    writer: W,
+    data: ExportData,
+    format: ExportFormat,
+    range: DateRange,
+) -> ExportResult<()> {
+    match data {
+        ExportData::History => write_history(writer, &History::new().load()?, format, range),
+        ExportData::Usage => {
+            let (from, to) = range.bounds();
+
+            write_usage(writer, &Stats::open()?.daily(from, to)?, format)
+        }
+    }
+}
+
+/// Writes the entries of connections which started within the range.
+pub fn write_history<W: Write>(
+    mut writer: W,
+    entries: &[HistoryEntry],
+    format: ExportFormat,
+    range: DateRange,
+) -> ExportResult<()> {
+    let entries = entries
+        .iter()
+        .filter(|entry| range.contains(entry.connected.date_naive()))
+        .collect::<Vec<_>>();
+
+    match format {
+        ExportFormat::Json => serde_json::to_writer_pretty(&mut writer, &entries)?,
+        ExportFormat::Csv => {
+            writeln!(
+                writer,
+                "hostname,country,city,connected,disconnected,received,sent"
+            )?;
+
+            let time = |time: &DateTime<Local>| time.to_rfc3339_opts(SecondsFormat::Secs, false);
+
+            for entry in entries {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{},{},{}",
+                    csv_field(&entry.hostname),
+                    csv_field(&entry.country),
+                    csv_field(&entry.city),
+                    time(&entry.connected),
+                    entry.disconnected.as_ref().map(time).unwrap_or_default(),
+                    entry.received,
+                    entry.sent
+                )?;
+            }
+        }
+    }
+
+    Ok(writer.flush()?)
+}
+
+pub fn write_usage<W: Write>(
+    mut writer: W,
+    totals: &[DailyTotal],
+    format: ExportFormat,
+) -> ExportResult<()> {
+    match format {
+        ExportFormat::Json => serde_json::to_writer_pretty(&mut writer, totals)?,
+        ExportFormat::Csv => {
+            writeln!(writer, "day,country,received,sent")?;
+
+            for total in totals {
+                writeln!(
+                    writer,
+                    "{},{},{},{}",
+                    total.day,
+                    csv_field(&total.country),
+                    total.received,
+                    total.sent
+                )?;
+            }
+        }
+    }
+
+    Ok(writer.flush()?)
+}
+
+/// Quotes the field if it has a separator, quote or line break in it.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_history, write_usage, DateRange, ExportFormat};
+    use crate::history::HistoryEntry;
+    use crate::stats::DailyTotal;
+    use chrono::{Local, NaiveDate, TimeZone};
+
+    #[test]
+    fn test_export() {
+        let connected = Local.with_ymd_and_hms(2026, 10, 14, 9, 30, 0).unwrap();
+        let entry = HistoryEntry {
+            hostname: "us1.nordvpn.com".to_owned(),
+            country: "United States".to_owned(),
+            city: "Washington, D.C.".to_owned(),
+            connected,
+            disconnected: None,
+            received: 2048,
+            sent: 1024,
+        };
+        let earlier = HistoryEntry {
+            connected: connected - chrono::Duration::days(10),
+            ..entry.clone()
+        };
+        let today = connected.date_naive();
+        let mut csv = Vec::new();
+
+        write_history(
+            &mut csv,
+            &[earlier, entry],
+            ExportFormat::Csv,
+            DateRange::last_days(7, today),
+        )
+        .unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("us1.nordvpn.com,United States,\"Washington, D.C.\","));
+        assert!(lines[1].ends_with(",,2048,1024"));
+
+        let totals = [DailyTotal {
+            day: NaiveDate::from_ymd_opt(2026, 10, 14).unwrap(),
+            country: "Germany".to_owned(),
+            received: 10,
+            sent: 5,
+        }];
+        let mut json = Vec::new();
+
+        write_usage(&mut json, &totals, ExportFormat::Json).unwrap();
+
+        let json = serde_json::from_slice::<serde_json::Value>(&json).unwrap();
+        assert_eq!(json[0]["day"], "2026-10-14");
+        assert_eq!(json[0]["received"], 10);
+
+        assert_eq!("JSON".parse::<ExportFormat>().unwrap(), ExportFormat::Json);
+        assert!(DateRange::all().contains(today));
+        assert!(!DateRange::new(None, Some(today)).contains(today.succ_opt().unwrap()));
+    }
+}
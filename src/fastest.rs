@@ -0,0 +1,215 @@
+//! Picks the best server by combining the load reported by the API with the
+//! round trip time measured from this machine, since the least loaded server
+//! may be on the other side of the world and the closest may be saturated.
+
+use crate::latency::{self, Latency, LatencyOptions};
+use crate::nordapi::{ApiError, NordApi, Server, ServerFilters};
+use crate::nordvpn::{CliError, ConnectOption, Connected, NordVPN, NordVpnBackend};
+use std::time::Duration;
+use thiserror::Error;
+
+pub type FastestResult<T> = Result<T, FastestError>;
+
+#[derive(Debug, Error)]
+pub enum FastestError {
+    #[error(transparent)]
+    Api(#[from] ApiError),
+    #[error(transparent)]
+    Cli(#[from] CliError),
+    #[error("no server matching the filters could be reached")]
+    NoServers,
+}
+
+/// How much each factor counts towards the score of a server. The weights
+/// are relative to each other, so they need not add up to one.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Weighting {
+    pub load: f64,
+    pub latency: f64,
+}
+
+impl Default for Weighting {
+    fn default() -> Self {
+        Self {
+            load: 1.0,
+            latency: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FastestOptions {
+    pub weighting: Weighting,
+    pub latency: LatencyOptions,
+    /// The number of least loaded servers which are measured, since probing
+    /// every server of a country would take too long.
+    pub candidates: usize,
+}
+
+impl Default for FastestOptions {
+    fn default() -> Self {
+        Self {
+            weighting: Weighting::default(),
+            latency: LatencyOptions::default(),
+            candidates: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ranked {
+    pub server: Server,
+    /// The measured round trip time, or `None` if the server did not answer.
+    pub latency: Option<Duration>,
+    /// Lower is better, between zero and one for servers which answered.
+    pub score: f64,
+}
+
+/// Scores servers against their latencies, which are matched by hostname,
+/// and sorts them from best to worst. Servers which did not answer are last.
+pub fn rank(servers: Vec<Server>, latencies: &[Latency], weighting: &Weighting) -> Vec<Ranked> {
+    let slowest = latencies
+        .iter()
+        .filter_map(Latency::duration)
+        .max()
+        .unwrap_or_default()
+        .as_secs_f64();
+    let total = (weighting.load + weighting.latency).max(f64::EPSILON);
+
+    let mut ranked = servers
+        .into_iter()
+        .map(|server| {
+            let latency = latencies
+                .iter()
+                .find(|latency| latency.hostname == server.hostname)
+                .and_then(Latency::duration);
+            let score = match latency {
+                Some(latency) => {
+                    let load = f64::from(server.load) / 100.0;
+                    let latency = if slowest > 0.0 {
+                        latency.as_secs_f64() / slowest
+                    } else {
+                        0.0
+                    };
+
+                    (weighting.load * load + weighting.latency * latency) / total
+                }
+                None => f64::INFINITY,
+            };
+
+            Ranked {
+                server,
+                latency,
+                score,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    ranked.sort_by(|a, b| a.score.total_cmp(&b.score));
+    ranked
+}
+
+/// Fetches the servers matching the filters, measures the least loaded of
+/// them, and returns them ranked from best to worst.
+pub fn fastest(
+    api: &NordApi,
+    filters: &ServerFilters,
+    options: &FastestOptions,
+) -> FastestResult<Vec<Ranked>> {
+    let mut servers = api.recommendations(filters)?;
+
+    servers.sort_by_key(|server| server.load);
+    servers.truncate(options.candidates.max(1));
+
+    let latencies = latency::measure(
+        servers.iter().map(|server| &server.hostname),
+        &options.latency,
+    );
+
+    Ok(rank(servers, &latencies, &options.weighting))
+}
+
+/// Connects to the best server matching the filters with default options.
+pub fn connect_fastest(filters: &ServerFilters) -> FastestResult<Connected> {
+    connect_fastest_with(
+        &NordVPN,
+        &NordApi::new(),
+        filters,
+        &FastestOptions::default(),
+    )
+}
+
+/// Connects to the best server matching the filters through `backend`.
+pub fn connect_fastest_with<B: NordVpnBackend>(
+    backend: &B,
+    api: &NordApi,
+    filters: &ServerFilters,
+    options: &FastestOptions,
+) -> FastestResult<Connected> {
+    let best = fastest(api, filters, options)?
+        .into_iter()
+        .find(|ranked| ranked.latency.is_some())
+        .ok_or(FastestError::NoServers)?;
+    let option = ConnectOption::server_from_hostname(&best.server.hostname);
+    let mut connected = backend.connect(Some(&option))?;
+
+    connected.load = Some(best.server.load);
+    Ok(connected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Weighting;
+    use crate::latency::Latency;
+    use crate::nordapi::Server;
+    use std::io;
+    use std::time::Duration;
+
+    fn server(hostname: &str, load: u8) -> Server {
+        Server {
+            id: 0,
+            name: hostname.to_owned(),
+            hostname: hostname.to_owned(),
+            station: [127, 0, 0, 1].into(),
+            load,
+            status: "online".to_owned(),
+            locations: Vec::new(),
+            groups: Vec::new(),
+            technologies: Vec::new(),
+        }
+    }
+
+    fn latency(hostname: &str, millis: Option<u64>) -> Latency {
+        Latency {
+            hostname: hostname.to_owned(),
+            result: millis
+                .map(Duration::from_millis)
+                .ok_or_else(|| io::ErrorKind::TimedOut.into()),
+        }
+    }
+
+    #[test]
+    fn test_rank() {
+        let servers = vec![server("a", 10), server("b", 60), server("c", 5)];
+        let latencies = [
+            latency("a", Some(200)),
+            latency("b", Some(20)),
+            latency("c", None),
+        ];
+
+        let ranked = super::rank(servers.clone(), &latencies, &Weighting::default());
+        let order = ranked
+            .iter()
+            .map(|ranked| ranked.server.hostname.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(order, ["b", "a", "c"]);
+
+        let weighting = Weighting {
+            load: 1.0,
+            latency: 0.0,
+        };
+        let ranked = super::rank(servers, &latencies, &weighting);
+        assert_eq!(ranked[0].server.hostname, "a");
+        assert_eq!(ranked[2].latency, None);
+    }
+}
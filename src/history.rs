@@ -0,0 +1,221 @@
+//! A log of past connections, recorded from status events and kept as JSON
+//! lines in the XDG data directory.
+
+use crate::config::xdg_dir;
+use crate::nordvpn::{ConnectOption, Status, StatusEvent};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub hostname: String,
+    pub country: String,
+    pub city: String,
+    pub connected: DateTime<Local>,
+    /// When the connection ended, or `None` while it is still up.
+    pub disconnected: Option<DateTime<Local>>,
+    /// The bytes received over the connection.
+    pub received: u64,
+    /// The bytes sent over the connection.
+    pub sent: u64,
+}
+
+/// The file connections are appended to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+    path: PathBuf,
+}
+
+/// Builds history entries from the events of a
+/// [`StatusWatcher`](crate::nordvpn::StatusWatcher).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryRecorder {
+    current: Option<HistoryEntry>,
+}
+
+impl HistoryEntry {
+    pub fn new(status: &Status) -> Self {
+        Self {
+            hostname: status.hostname.clone(),
+            country: status.country.clone(),
+            city: status.city.clone(),
+            connected: status.connected_since,
+            disconnected: None,
+            received: status.transfer.received.get_bytes() as u64,
+            sent: status.transfer.sent.get_bytes() as u64,
+        }
+    }
+
+    /// The option to connect to the same server again.
+    pub fn option(&self) -> ConnectOption {
+        ConnectOption::server_from_hostname(&self.hostname)
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    /// `$XDG_DATA_HOME/nordvpn-viking/history.jsonl`, or under
+    /// `~/.local/share` if the variable is not set.
+    pub fn new() -> Self {
+        Self::with_path(xdg_dir("XDG_DATA_HOME", ".local/share").join("history.jsonl"))
+    }
+
+    pub fn with_path<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every recorded connection, oldest first. Lines which cannot be read,
+    /// such as one cut short by a crash, are skipped.
+    pub fn load(&self) -> io::Result<Vec<HistoryEntry>> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => Ok(text
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl HistoryRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The connection currently being recorded.
+    pub fn current(&self) -> Option<&HistoryEntry> {
+        self.current.as_ref()
+    }
+
+    /// Updates the current connection, returning the previous one if the
+    /// event ended it.
+    pub fn record(&mut self, event: &StatusEvent) -> Option<HistoryEntry> {
+        self.record_at(event, Local::now())
+    }
+
+    pub fn record_at(&mut self, event: &StatusEvent, now: DateTime<Local>) -> Option<HistoryEntry> {
+        match event {
+            StatusEvent::Connected(status) | StatusEvent::ServerChanged(status) => {
+                let finished = self.finish(now);
+                self.current = Some(HistoryEntry::new(status));
+                finished
+            }
+            StatusEvent::Disconnected => self.finish(now),
+            StatusEvent::TransferUpdated(transfer) => {
+                if let Some(current) = &mut self.current {
+                    current.received = transfer.received.get_bytes() as u64;
+                    current.sent = transfer.sent.get_bytes() as u64;
+                }
+                None
+            }
+        }
+    }
+
+    fn finish(&mut self, now: DateTime<Local>) -> Option<HistoryEntry> {
+        let mut finished = self.current.take()?;
+
+        finished.disconnected = Some(now);
+        Some(finished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, HistoryRecorder};
+    use crate::nordvpn::{MockBackend, NordVpnBackend, Status, StatusEvent, Transfer};
+    use byte_unit::Byte;
+    use chrono::Local;
+
+    #[test]
+    fn test_history() {
+        let backend = MockBackend::new();
+        backend.respond(
+            ["status"],
+            "Status: Connected\n\
+            Current server: de507.nordvpn.com\n\
+            Country: Germany\n\
+            City: Berlin\n\
+            Server IP: 185.0.0.1\n\
+            Current technology: NORDLYNX\n\
+            Current protocol: UDP\n\
+            Transfer: 1.2 MiB received, 300 KiB sent\n\
+            Uptime: 1 hour 2 minutes 3 seconds\n",
+        );
+
+        let status = backend.status().unwrap().unwrap();
+        let mut recorder = HistoryRecorder::new();
+        let now = Local::now();
+
+        assert_eq!(
+            recorder.record_at(&StatusEvent::Connected(status.clone()), now),
+            None
+        );
+        recorder.record_at(
+            &StatusEvent::TransferUpdated(Transfer {
+                received: Byte::from_bytes(2048),
+                sent: Byte::from_bytes(1024),
+            }),
+            now,
+        );
+
+        let moved = Status {
+            hostname: "de508.nordvpn.com".to_owned(),
+            ..status
+        };
+        let first = recorder
+            .record_at(&StatusEvent::ServerChanged(moved), now)
+            .unwrap();
+        let second = recorder.record_at(&StatusEvent::Disconnected, now).unwrap();
+
+        assert_eq!(first.received, 2048);
+        assert_eq!(first.disconnected, Some(now));
+        assert_eq!(second.hostname, "de508.nordvpn.com");
+        assert_eq!(second.option().to_string(), "server:de508");
+        assert_eq!(recorder.current(), None);
+
+        let dir =
+            std::env::temp_dir().join(format!("nordvpn-viking-history-{}", std::process::id()));
+        let history = History::with_path(dir.join("history.jsonl"));
+
+        history.append(&first).unwrap();
+        history.append(&second).unwrap();
+        assert_eq!(history.load().unwrap(), [first, second]);
+
+        history.clear().unwrap();
+        assert!(history.load().unwrap().is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+//! Documents of the state for scripts and status bars, which are written as
+//! JSON with a stable schema. Fields are only ever added, never renamed or
+//! removed, and a field which is not known is `null` rather than missing.
+//! Times are RFC 3339 strings, durations are whole seconds, and amounts of
+//! data are bytes.
+
+use crate::fastest::Ranked;
+use crate::history;
+use crate::nordapi;
+use crate::nordvpn;
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::net::IpAddr;
+
+/// The connection, from [`nordvpn::status`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    pub connected: bool,
+    pub hostname: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub ip: Option<IpAddr>,
+    /// As the CLI prints it, such as `NORDLYNX`.
+    pub technology: Option<String>,
+    /// As the CLI prints it, such as `UDP`.
+    pub protocol: Option<String>,
+    pub received: Option<u64>,
+    pub sent: Option<u64>,
+    pub uptime: Option<i64>,
+    pub connected_since: Option<String>,
+}
+
+/// A connection which was just made, from [`nordvpn::connect`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Connected {
+    pub hostname: String,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub technology: Option<String>,
+    pub protocol: Option<String>,
+    /// As a percentage.
+    pub load: Option<u8>,
+}
+
+/// The settings of the CLI, from [`nordvpn::settings`], where those the
+/// installed CLI does not have are `null`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Settings {
+    pub technology: String,
+    pub protocol: Option<String>,
+    pub firewall: bool,
+    pub killswitch: bool,
+    pub cybersec: bool,
+    pub obfuscate: Option<bool>,
+    pub notify: bool,
+    pub autoconnect: bool,
+    pub ipv6: bool,
+    pub post_quantum: Option<bool>,
+    pub lan_discovery: Option<bool>,
+    /// The custom DNS servers in ascending order, or `null` for NordVPN's.
+    pub dns: Option<Vec<IpAddr>>,
+}
+
+/// A server from the API, with its ranking if it was measured by
+/// [`fastest`](crate::fastest::fastest).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Server {
+    pub id: u64,
+    pub name: String,
+    pub hostname: String,
+    /// As a percentage.
+    pub load: u8,
+    pub country: Option<String>,
+    /// The ISO 3166-1 alpha-2 code, such as `DE`.
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    /// The identifiers, such as `legacy_p2p`.
+    pub groups: Vec<String>,
+    /// The identifiers, such as `wireguard_udp`.
+    pub technologies: Vec<String>,
+    /// The round trip time in milliseconds, or `null` if it was not measured
+    /// or the server did not answer.
+    pub latency: Option<f64>,
+    /// Lower is better, or `null` if it was not ranked.
+    pub score: Option<f64>,
+}
+
+/// A past connection, from the [`History`](crate::history::History).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryEntry {
+    pub hostname: String,
+    pub country: String,
+    pub city: String,
+    pub connected: String,
+    /// `null` while the connection is still up.
+    pub disconnected: Option<String>,
+    pub received: u64,
+    pub sent: u64,
+}
+
+impl From<Option<&nordvpn::Status>> for Status {
+    fn from(status: Option<&nordvpn::Status>) -> Self {
+        Self {
+            connected: status.is_some(),
+            hostname: status.map(|status| status.hostname.clone()),
+            country: status.map(|status| status.country.clone()),
+            city: status.map(|status| status.city.clone()),
+            ip: status.map(|status| status.ip),
+            technology: status.map(|status| status.technology.to_string()),
+            protocol: status.map(|status| status.protocol.to_string()),
+            received: status.map(|status| status.transfer.received.get_bytes() as u64),
+            sent: status.map(|status| status.transfer.sent.get_bytes() as u64),
+            uptime: status.map(|status| status.current_uptime().num_seconds()),
+            connected_since: status.map(|status| time(&status.connected_since)),
+        }
+    }
+}
+
+impl From<&nordvpn::Connected> for Connected {
+    fn from(connected: &nordvpn::Connected) -> Self {
+        Self {
+            hostname: connected.hostname.clone(),
+            country: connected.country.clone(),
+            city: connected.city.clone(),
+            ip: connected.ip,
+            technology: connected
+                .technology
+                .map(|technology| technology.to_string()),
+            protocol: connected.protocol.map(|protocol| protocol.to_string()),
+            load: connected.load,
+        }
+    }
+}
+
+impl From<&nordvpn::Settings> for Settings {
+    fn from(settings: &nordvpn::Settings) -> Self {
+        Self {
+            technology: settings.technology.to_string(),
+            protocol: settings.protocol.map(|protocol| protocol.to_string()),
+            firewall: settings.firewall,
+            killswitch: settings.killswitch,
+            cybersec: settings.cybersec,
+            obfuscate: settings.obfuscate,
+            notify: settings.notify,
+            autoconnect: settings.autoconnect,
+            ipv6: settings.ipv6,
+            post_quantum: settings.post_quantum,
+            lan_discovery: settings.lan_discovery,
+            dns: settings.dns.as_ref().map(|dns| {
+                let mut dns = dns.iter().copied().collect::<Vec<_>>();
+
+                dns.sort();
+                dns
+            }),
+        }
+    }
+}
+
+impl From<&nordapi::Server> for Server {
+    fn from(server: &nordapi::Server) -> Self {
+        Self {
+            id: server.id,
+            name: server.name.clone(),
+            hostname: server.hostname.clone(),
+            load: server.load,
+            country: server.country().map(|country| country.name.clone()),
+            country_code: server.country().map(|country| country.code.clone()),
+            city: server.city().map(|city| city.name.clone()),
+            groups: server
+                .groups
+                .iter()
+                .map(|group| group.identifier.clone())
+                .collect(),
+            technologies: server
+                .technologies
+                .iter()
+                .map(|technology| technology.identifier.clone())
+                .collect(),
+            latency: None,
+            score: None,
+        }
+    }
+}
+
+impl From<&Ranked> for Server {
+    fn from(ranked: &Ranked) -> Self {
+        Self {
+            latency: ranked.latency.map(|latency| latency.as_secs_f64() * 1000.0),
+            score: Some(ranked.score),
+            ..Self::from(&ranked.server)
+        }
+    }
+}
+
+impl From<&history::HistoryEntry> for HistoryEntry {
+    fn from(entry: &history::HistoryEntry) -> Self {
+        Self {
+            hostname: entry.hostname.clone(),
+            country: entry.country.clone(),
+            city: entry.city.clone(),
+            connected: time(&entry.connected),
+            disconnected: entry.disconnected.as_ref().map(time),
+            received: entry.received,
+            sent: entry.sent,
+        }
+    }
+}
+
+/// Writes the document as JSON on one line, followed by a line break, so
+/// that each document can be read as it is written.
+pub fn write<W: Write, T: Serialize + ?Sized>(mut writer: W, document: &T) -> io::Result<()> {
+    serde_json::to_writer(&mut writer, document)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+fn time(time: &DateTime<Local>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write, Settings, Status};
+    use crate::nordvpn::{self, Technology};
+    use serde_json::json;
+
+    #[test]
+    fn test_json() {
+        let settings = nordvpn::Settings {
+            technology: Technology::NordLynx,
+            protocol: None,
+            firewall: true,
+            killswitch: false,
+            cybersec: false,
+            obfuscate: None,
+            notify: true,
+            autoconnect: false,
+            ipv6: false,
+            post_quantum: Some(false),
+            lan_discovery: None,
+            dns: Some(
+                ["103.86.99.100", "103.86.96.100"]
+                    .iter()
+                    .map(|ip| ip.parse().unwrap())
+                    .collect(),
+            ),
+        };
+        let mut text = Vec::new();
+
+        write(&mut text, &Settings::from(&settings)).unwrap();
+
+        let text = String::from_utf8(text).unwrap();
+        let value = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+
+        assert!(text.ends_with("}\n"));
+        assert_eq!(value["technology"], "NORDLYNX");
+        assert_eq!(value["protocol"], json!(null));
+        assert_eq!(value["dns"], json!(["103.86.96.100", "103.86.99.100"]));
+
+        assert_eq!(
+            serde_json::to_value(Status::from(None)).unwrap(),
+            json!({
+                "connected": false,
+                "hostname": null,
+                "country": null,
+                "city": null,
+                "ip": null,
+                "technology": null,
+                "protocol": null,
+                "received": null,
+                "sent": null,
+                "uptime": null,
+                "connected_since": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Round trip time measurements to servers, for ranking them by distance.
+
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How to measure the round trip time to a server.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Probe {
+    /// Time how long a TCP handshake takes on the port.
+    Tcp(u16),
+    /// Send an ICMP echo request, which needs the user's group to be allowed
+    /// by the `net.ipv4.ping_group_range` sysctl. If the kernel refuses,
+    /// falls back to a TCP handshake on the port.
+    Icmp(u16),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LatencyOptions {
+    pub probe: Probe,
+    pub timeout: Duration,
+    /// The number of times each server is probed, keeping the fastest.
+    pub attempts: u32,
+    /// The largest number of servers probed at the same time.
+    pub parallelism: usize,
+}
+
+impl Default for LatencyOptions {
+    fn default() -> Self {
+        Self {
+            // Every NordVPN server accepts OpenVPN over TCP on this port.
+            probe: Probe::Icmp(443),
+            timeout: Duration::from_secs(2),
+            attempts: 3,
+            parallelism: 16,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Latency {
+    pub hostname: String,
+    pub result: io::Result<Duration>,
+}
+
+impl Latency {
+    pub fn duration(&self) -> Option<Duration> {
+        self.result.as_ref().ok().copied()
+    }
+}
+
+/// Measures every server concurrently, returning the results in the same
+/// order as `hostnames`.
+pub fn measure<S, I>(hostnames: I, options: &LatencyOptions) -> Vec<Latency>
+where
+    S: AsRef<str>,
+    I: IntoIterator<Item = S>,
+{
+    let hostnames = hostnames
+        .into_iter()
+        .map(|hostname| hostname.as_ref().to_owned())
+        .collect::<Vec<_>>();
+    let next = Mutex::new(hostnames.iter().enumerate());
+    let results = Mutex::new(Vec::with_capacity(hostnames.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..options.parallelism.clamp(1, hostnames.len().max(1)) {
+            scope.spawn(|| loop {
+                let (index, hostname) = match next.lock().unwrap().next() {
+                    Some(next) => next,
+                    None => break,
+                };
+                let result = measure_one(hostname, options);
+
+                results.lock().unwrap().push((
+                    index,
+                    Latency {
+                        hostname: hostname.clone(),
+                        result,
+                    },
+                ));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, latency)| latency).collect()
+}
+
+/// Measures one server, returning the fastest of the attempts.
+pub fn measure_one(hostname: &str, options: &LatencyOptions) -> io::Result<Duration> {
+    let port = match options.probe {
+        Probe::Tcp(port) | Probe::Icmp(port) => port,
+    };
+    let address = (hostname, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "hostname has no addresses"))?;
+    let mut fastest = None;
+    let mut last_error = None;
+
+    for sequence in 0..options.attempts.max(1) {
+        let result = match options.probe {
+            Probe::Tcp(_) => tcp(address, options.timeout),
+            Probe::Icmp(_) => match icmp(address.ip(), sequence as u16, options.timeout) {
+                Err(error) if error.kind() == io::ErrorKind::PermissionDenied => {
+                    tcp(address, options.timeout)
+                }
+                result => result,
+            },
+        };
+
+        match result {
+            Ok(duration) => {
+                fastest = Some(fastest.map_or(duration, |fastest: Duration| fastest.min(duration)))
+            }
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    fastest.ok_or_else(|| last_error.unwrap())
+}
+
+/// Sorts results from fastest to slowest, with failures last.
+pub fn rank(latencies: &mut [Latency]) {
+    latencies.sort_by_key(|latency| latency.duration().unwrap_or(Duration::MAX));
+}
+
+fn tcp(address: SocketAddr, timeout: Duration) -> io::Result<Duration> {
+    let start = Instant::now();
+    TcpStream::connect_timeout(&address, timeout)?;
+    Ok(start.elapsed())
+}
+
+fn icmp(address: IpAddr, sequence: u16, timeout: Duration) -> io::Result<Duration> {
+    let (domain, protocol, echo_request) = match address {
+        IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4, 8),
+        IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6, 128),
+    };
+    // Unprivileged ping sockets fill in the identifier and checksum.
+    let socket = UdpSocket::from(Socket::new(domain, Type::DGRAM, Some(protocol))?);
+    let sequence = sequence.to_be_bytes();
+    let request = [echo_request, 0, 0, 0, 0, 0, sequence[0], sequence[1]];
+    let mut reply = [0; 64];
+
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect((address, 0))?;
+
+    let start = Instant::now();
+    socket.send(&request)?;
+
+    loop {
+        let length = socket.recv(&mut reply)?;
+
+        // Replies to earlier attempts which timed out may still arrive.
+        if length >= 8 && reply[6..8] == sequence {
+            return Ok(start.elapsed());
+        } else if start.elapsed() >= timeout {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LatencyOptions, Probe};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_measure() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let options = LatencyOptions {
+            probe: Probe::Tcp(listener.local_addr().unwrap().port()),
+            ..LatencyOptions::default()
+        };
+
+        let mut latencies = super::measure(["127.0.0.1", "localhost", "invalid."], &options);
+
+        assert_eq!(latencies[0].hostname, "127.0.0.1");
+        assert!(latencies[0].result.is_ok());
+        assert!(latencies[2].result.is_err());
+
+        super::rank(&mut latencies);
+        assert_eq!(latencies[2].hostname, "invalid.");
+    }
+}
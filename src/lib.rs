@@ -1 +1,17 @@
+pub mod autostart;
+pub mod behavior;
+pub mod config;
+pub mod dns_leak;
+pub mod export;
+pub mod fastest;
+pub mod history;
+pub mod json;
+pub mod latency;
+pub mod logging;
+pub mod login;
+pub mod nordapi;
 pub mod nordvpn;
+pub mod profile;
+pub mod schedule;
+pub mod stats;
+pub mod systemd;
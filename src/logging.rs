@@ -0,0 +1,37 @@
+//! Diagnostics of the commands run and the output parsed, written with
+//! `tracing` to a subscriber which is filtered by an environment variable.
+
+use tracing_subscriber::EnvFilter;
+
+/// The variable which filters what is logged, with the syntax of
+/// [`EnvFilter`], such as `nordvpn_viking=debug`. `RUST_LOG` is read if it is
+/// not set.
+pub const FILTER_VAR: &str = "NORDVPN_VIKING_LOG";
+
+/// The filter given by [`FILTER_VAR`] or `RUST_LOG`, falling back to
+/// `default` if neither is set or valid.
+pub fn filter(default: &str) -> EnvFilter {
+    EnvFilter::try_from_env(FILTER_VAR)
+        .or_else(|_| EnvFilter::try_from_default_env())
+        .unwrap_or_else(|_| EnvFilter::new(default))
+}
+
+/// Writes the events of this process to stderr, unless a subscriber has
+/// already been set. Only warnings and errors are written by default.
+pub fn init() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter("warn"))
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+/// Writes the events of a test to its captured output, so they are shown
+/// only when it fails, including the parsed results by default. Every test
+/// which logs calls this, since only the first call has any effect.
+#[cfg(test)]
+pub(crate) fn init_test() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter("debug"))
+        .with_test_writer()
+        .try_init();
+}
@@ -0,0 +1,254 @@
+//! Finishing the login in the browser without the user having to come back,
+//! by sending the login page back to a short-lived listener on localhost,
+//! which hands the callback to the CLI as `nordvpn login --callback` would
+//! be given it by hand.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The parameter of the login URL which the page returns to once the login
+/// is finished, in place of the `nordvpn://` link. If the page ignores it,
+/// the link is followed as usual and nothing arrives at the listener.
+pub const CALLBACK_PARAM: &str = "redirect_upon_complete";
+
+/// The path on the listener which the page is sent back to.
+const CALLBACK_PATH: &str = "/callback";
+
+/// How often the listener checks for a connection while waiting.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// How long a connection may take to send its request before it is dropped.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const RESPONSE: &str = "\
+HTTP/1.1 200 OK\r
+Content-Type: text/html; charset=utf-8\r
+Connection: close\r
+\r
+<!DOCTYPE html>
+<title>NordVPN Viking</title>
+<p>You are logged in to NordVPN, and can close this page.</p>
+";
+
+const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r\nConnection: close\r\n\r\n";
+
+#[derive(Debug)]
+pub struct LoginListener {
+    listener: TcpListener,
+}
+
+impl LoginListener {
+    /// Listens on a free port of the loopback address, so that only the
+    /// browser on this machine can reach it.
+    pub fn bind() -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+
+        listener.set_nonblocking(true)?;
+
+        Ok(Self { listener })
+    }
+
+    pub fn callback_url(&self) -> io::Result<String> {
+        Ok(format!(
+            "http://{}{}",
+            self.listener.local_addr()?,
+            CALLBACK_PATH
+        ))
+    }
+
+    /// The login URL printed by the CLI, returning to this listener instead.
+    pub fn rewrite(&self, login_url: &str) -> io::Result<String> {
+        Ok(rewrite_url(login_url, &self.callback_url()?))
+    }
+
+    /// Waits up to `timeout` for the browser to come back, answering it with
+    /// a page saying that it can be closed. Returns the `nordvpn://` link it
+    /// carried, for [`login_callback`](crate::nordvpn::login_callback), or
+    /// `None` if it did not come back in time or `cancelled` was set.
+    ///
+    /// Connections which fail or send nothing, such as those browsers open
+    /// ahead of time, are dropped and the listener keeps waiting. Only
+    /// failing to accept connections is an error.
+    pub fn wait(&self, timeout: Duration, cancelled: &AtomicBool) -> io::Result<Option<String>> {
+        let started = Instant::now();
+
+        while started.elapsed() < timeout && !cancelled.load(Ordering::SeqCst) {
+            let stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL);
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            let line = match read_request(&stream) {
+                Ok(line) => line,
+                Err(_) => continue,
+            };
+
+            // Such as `GET /callback?action=login&exchange_token=... HTTP/1.1`.
+            let query = line
+                .split(' ')
+                .nth(1)
+                .and_then(|target| target.strip_prefix(CALLBACK_PATH)?.strip_prefix('?'));
+
+            // The browser may have gone already, which only loses the page.
+            match query {
+                Some(query) => {
+                    (&stream).write_all(RESPONSE.as_bytes()).ok();
+                    return Ok(Some(format!("nordvpn://login?{}", query)));
+                }
+                // Browsers also ask for things like the icon of the page.
+                None => {
+                    (&stream).write_all(NOT_FOUND.as_bytes()).ok();
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// The request line sent on `stream`, after reading the headers too, since
+/// closing the connection with them unread would reset it before the browser
+/// gets the page.
+fn read_request(stream: &TcpStream) -> io::Result<String> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let mut header = String::new();
+
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    Ok(line)
+}
+
+/// Replaces the [`CALLBACK_PARAM`] of `url` with `callback`.
+fn rewrite_url(url: &str, callback: &str) -> String {
+    let (base, query) = url.split_once('?').unwrap_or((url, ""));
+    let mut params = query
+        .split('&')
+        .filter(|param| !param.is_empty())
+        .filter(|param| param.split('=').next() != Some(CALLBACK_PARAM))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+
+    params.push(format!("{}={}", CALLBACK_PARAM, encode(callback)));
+
+    format!("{}?{}", base, params.join("&"))
+}
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986.
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rewrite_url, LoginListener};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_login_listener() {
+        assert_eq!(
+            rewrite_url(
+                "https://api.nordvpn.com/v1/users/oauth/login-redirect?attempt=1&redirect_upon_complete=x",
+                "http://127.0.0.1:8000/callback"
+            ),
+            "https://api.nordvpn.com/v1/users/oauth/login-redirect?attempt=1\
+             &redirect_upon_complete=http%3A%2F%2F127.0.0.1%3A8000%2Fcallback"
+        );
+
+        let listener = LoginListener::bind().unwrap();
+        let address = listener
+            .callback_url()
+            .unwrap()
+            .trim_start_matches("http://")
+            .trim_end_matches("/callback")
+            .to_owned();
+
+        let browser = thread::spawn(move || {
+            let mut pages = Vec::new();
+
+            for target in ["/favicon.ico", "/callback?action=login&status=done"] {
+                let mut stream = TcpStream::connect(&address).unwrap();
+                let mut page = String::new();
+
+                write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).unwrap();
+                stream.read_to_string(&mut page).unwrap();
+                pages.push(page);
+            }
+
+            pages
+        });
+
+        let callback = listener
+            .wait(Duration::from_secs(10), &AtomicBool::new(false))
+            .unwrap();
+        let pages = browser.join().unwrap();
+
+        assert_eq!(
+            callback.as_deref(),
+            Some("nordvpn://login?action=login&status=done")
+        );
+        assert!(pages[0].starts_with("HTTP/1.1 404"));
+        assert!(pages[1].starts_with("HTTP/1.1 200"));
+    }
+
+    #[test]
+    fn test_login_listener_idle_connection() {
+        let listener = LoginListener::bind().unwrap();
+        let address = listener
+            .callback_url()
+            .unwrap()
+            .trim_start_matches("http://")
+            .trim_end_matches("/callback")
+            .to_owned();
+
+        let browser = thread::spawn(move || {
+            // Opened ahead of time and never used, like browsers do.
+            let idle = TcpStream::connect(&address).unwrap();
+            let mut stream = TcpStream::connect(&address).unwrap();
+            let mut page = String::new();
+
+            write!(
+                stream,
+                "GET /callback?action=login HTTP/1.1\r\nHost: localhost\r\n\r\n"
+            )
+            .unwrap();
+            stream.read_to_string(&mut page).unwrap();
+            drop(idle);
+
+            page
+        });
+
+        let callback = listener
+            .wait(Duration::from_secs(20), &AtomicBool::new(false))
+            .unwrap();
+
+        assert_eq!(callback.as_deref(), Some("nordvpn://login?action=login"));
+        assert!(browser.join().unwrap().starts_with("HTTP/1.1 200"));
+    }
+}
@@ -0,0 +1,154 @@
+use crate::config::xdg_dir;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Numbers the temporary files of this process, which may store the same
+/// key from several threads at once.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Responses from the API kept on disk, so that they can be reused while
+/// fresh and as a fallback when the API cannot be reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub etag: Option<String>,
+    /// Seconds since the Unix epoch when the body was last known current.
+    pub fetched: u64,
+    pub body: String,
+}
+
+impl ApiCache {
+    /// A cache in `$XDG_CACHE_HOME/nordvpn-viking/api`, or `~/.cache` if the
+    /// variable is not set.
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_dir(xdg_dir("XDG_CACHE_HOME", ".cache").join("api"), ttl)
+    }
+
+    pub fn with_dir<P: Into<PathBuf>>(dir: P, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn load(&self, key: &str) -> Option<CacheEntry> {
+        let text = fs::read_to_string(self.path(key)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    /// Writes the entry to a temporary file first and then moves it into
+    /// place, so that other processes sharing the cache never read half of
+    /// it.
+    pub fn store(&self, key: &str, entry: &CacheEntry) -> io::Result<()> {
+        let path = self.path(key);
+        let temp = path.with_extension(format!(
+            "{}-{}.tmp",
+            process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&temp, serde_json::to_string(entry)?)?;
+        fs::rename(&temp, &path).inspect_err(|_| {
+            fs::remove_file(&temp).ok();
+        })
+    }
+
+    pub fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        now().saturating_sub(entry.fetched) < self.ttl.as_secs()
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    /// The file of the key, named by its hash so that any URL fits in a
+    /// file name and no two are mapped to the same one.
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+    }
+}
+
+impl CacheEntry {
+    pub fn new(etag: Option<String>, body: String) -> Self {
+        Self {
+            etag,
+            fetched: now(),
+            body,
+        }
+    }
+}
+
+/// The 64-bit FNV-1a hash, which unlike the hasher of the standard library is
+/// the same across Rust versions, so the cache outlives an upgrade.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiCache, CacheEntry};
+    use std::time::Duration;
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("nordvpn-viking-test-{}", std::process::id()));
+        let cache = ApiCache::with_dir(&dir, Duration::from_secs(60));
+        let key = "/v1/servers?limit=5";
+
+        assert_eq!(cache.load(key), None);
+
+        let mut entry = CacheEntry::new(Some("\"abc\"".to_owned()), "[]".to_owned());
+        cache.store(key, &entry).unwrap();
+        assert_eq!(cache.load(key).as_ref(), Some(&entry));
+        assert!(cache.is_fresh(&entry));
+
+        entry.fetched -= 120;
+        assert!(!cache.is_fresh(&entry));
+
+        // Keys which differ only in punctuation, or are too long to be file
+        // names themselves, are kept apart.
+        let other = "/v1/servers?limit_5";
+        let long = format!("/v1/servers?filters[hostname]={}", "a".repeat(1000));
+
+        cache
+            .store(other, &CacheEntry::new(None, "{}".to_owned()))
+            .unwrap();
+        cache
+            .store(&long, &CacheEntry::new(None, "0".to_owned()))
+            .unwrap();
+        assert_eq!(cache.load(key).unwrap().body, "[]");
+        assert_eq!(cache.load(other).unwrap().body, "{}");
+        assert_eq!(cache.load(&long).unwrap().body, "0");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+        cache.clear().unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,64 @@
+use super::{ApiResult, NordApi};
+use serde::Deserialize;
+use std::net::IpAddr;
+
+/// What NordVPN can tell about the public address a request came from.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Insights {
+    pub ip: IpAddr,
+    pub isp: Option<String>,
+    pub isp_asn: Option<u32>,
+    pub country: Option<String>,
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    /// Whether the address belongs to a NordVPN server, in other words that
+    /// traffic is going through the VPN.
+    pub protected: bool,
+}
+
+impl NordApi {
+    pub fn insights(&self) -> ApiResult<Insights> {
+        self.get("/v1/helpers/ips/insights", &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Insights;
+    use crate::nordapi::NordApi;
+    use tracing::info;
+
+    #[test]
+    fn test_deserialize_insights() {
+        let insights = serde_json::from_str::<Insights>(
+            r#"{
+                "ip": "203.0.113.7",
+                "country": "Germany",
+                "country_code": "DE",
+                "region": "Berlin",
+                "zip_code": "10178",
+                "city": "Berlin",
+                "state_code": "BE",
+                "longitude": 13.4,
+                "latitude": 52.5167,
+                "isp": "Example Telecom",
+                "isp_asn": 64500,
+                "gdpr": true,
+                "protected": false
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(insights.ip.to_string(), "203.0.113.7");
+        assert_eq!(insights.isp.as_deref(), Some("Example Telecom"));
+        assert!(!insights.protected);
+    }
+
+    #[test]
+    fn test_insights() {
+        crate::logging::init_test();
+
+        let insights = NordApi::new().insights().unwrap();
+        info!("Insights: {:#?}", insights);
+    }
+}
@@ -0,0 +1,113 @@
+use super::{ApiResult, NordApi, Server};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Deserialize)]
+struct ServerStats {
+    percent: u8,
+}
+
+impl NordApi {
+    /// The current load of every server as a percentage, keyed by hostname.
+    pub fn loads(&self) -> ApiResult<HashMap<String, u8>> {
+        let stats = self.get::<HashMap<String, ServerStats>>("/server/stats", &[])?;
+
+        Ok(stats
+            .into_iter()
+            .map(|(hostname, stats)| (hostname, stats.percent))
+            .collect())
+    }
+
+    /// The current load of one server, or `None` if there is no such server.
+    pub fn load(&self, hostname: &str) -> ApiResult<Option<u8>> {
+        let servers = self.get::<Vec<Server>>(
+            "/v1/servers",
+            &[
+                ("filters[hostname]".to_owned(), hostname.to_owned()),
+                ("limit".to_owned(), "1".to_owned()),
+            ],
+        )?;
+
+        Ok(servers.first().map(|server| server.load))
+    }
+}
+
+/// A cached copy of [`NordApi::loads`], which is refetched once it is older
+/// than the interval, or on demand with [`ServerLoads::refresh`].
+#[derive(Debug, Clone)]
+pub struct ServerLoads {
+    api: NordApi,
+    interval: Duration,
+    loads: HashMap<String, u8>,
+    fetched: Option<Instant>,
+}
+
+impl ServerLoads {
+    pub fn new(api: NordApi, interval: Duration) -> Self {
+        Self {
+            api,
+            interval,
+            loads: HashMap::new(),
+            fetched: None,
+        }
+    }
+
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.fetched
+            .is_none_or(|fetched| fetched.elapsed() >= self.interval)
+    }
+
+    pub fn refresh(&mut self) -> ApiResult<()> {
+        self.loads = self.api.loads()?;
+        self.fetched = Some(Instant::now());
+        Ok(())
+    }
+
+    /// The loads keyed by hostname, refreshing them first if they are stale.
+    pub fn get(&mut self) -> ApiResult<&HashMap<String, u8>> {
+        if self.is_stale() {
+            self.refresh()?;
+        }
+
+        Ok(&self.loads)
+    }
+
+    /// Updates the load of each server from the cache as it is, without
+    /// refreshing it.
+    pub fn apply(&self, servers: &mut [Server]) {
+        for server in servers.iter_mut() {
+            if let Some(load) = self.loads.get(&server.hostname) {
+                server.load = *load;
+            }
+        }
+    }
+
+    /// Updates the load of each server from the cache and sorts them from
+    /// least to most loaded.
+    pub fn sort(&mut self, servers: &mut [Server]) -> ApiResult<()> {
+        self.get()?;
+        self.apply(servers);
+        servers.sort_by_key(|server| server.load);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nordapi::NordApi;
+    use tracing::info;
+
+    #[test]
+    fn test_loads() {
+        crate::logging::init_test();
+
+        let loads = NordApi::new().loads().unwrap();
+        info!("Loads: {:?}", loads);
+    }
+}
@@ -0,0 +1,198 @@
+//! A client for the public NordVPN web API, which knows more about servers
+//! than the CLI prints, such as their load and supported technologies.
+
+mod cache;
+mod insights;
+mod loads;
+mod servers;
+
+pub use cache::*;
+pub use insights::*;
+pub use loads::*;
+pub use servers::*;
+
+use serde::de::DeserializeOwned;
+use std::io;
+use thiserror::Error;
+use tracing::warn;
+
+pub const BASE_URL: &str = "https://api.nordvpn.com";
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Debug, Error)]
+pub enum ApiError {
+    #[error("the request failed: {0}")]
+    Request(#[from] Box<ureq::Error>),
+    #[error("the response could not be decoded: {0}")]
+    Decode(#[from] io::Error),
+    #[error("the response is not cached")]
+    NotCached,
+    /// A [`ServerId::Number`] matched the servers of several countries, by
+    /// hostname.
+    #[error("several servers have that number: {}", .0.join(", "))]
+    AmbiguousServer(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct NordApi {
+    agent: ureq::Agent,
+    base_url: String,
+    cache: Option<ApiCache>,
+    offline: bool,
+}
+
+impl Default for NordApi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NordApi {
+    pub fn new() -> Self {
+        Self::with_base_url(BASE_URL)
+    }
+
+    pub fn with_base_url<S: Into<String>>(base_url: S) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .build(),
+            base_url: base_url.into(),
+            cache: None,
+            offline: false,
+        }
+    }
+
+    /// Reuses responses from the cache while they are fresh, revalidates
+    /// them with their ETag once stale, and falls back to them regardless of
+    /// age when the API cannot be reached or answers with a server error.
+    pub fn with_cache(mut self, cache: ApiCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Only answers from the cache, whatever the age of the responses, and
+    /// fails with [`ApiError::NotCached`] rather than calling the API, for
+    /// when the answer is needed before the network could give it.
+    pub fn offline(mut self) -> Self {
+        self.offline = true;
+        self
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str, query: &[(String, String)]) -> ApiResult<T> {
+        let mut request = self.agent.get(&format!("{}{}", self.base_url, path));
+
+        for (key, value) in query {
+            request = request.query(key, value);
+        }
+
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None if self.offline => return Err(ApiError::NotCached),
+            None => return Ok(request.call().map_err(Box::new)?.into_json()?),
+        };
+        let key = request.url().to_owned();
+        let cached = cache.load(&key);
+
+        let body = match cached {
+            Some(entry) if self.offline || cache.is_fresh(&entry) => entry.body,
+            None if self.offline => return Err(ApiError::NotCached),
+            cached => {
+                if let Some(etag) = cached.as_ref().and_then(|entry| entry.etag.as_ref()) {
+                    request = request.set("If-None-Match", etag);
+                }
+
+                match (request.call(), cached) {
+                    (Ok(response), Some(entry)) if response.status() == 304 => {
+                        store(
+                            cache,
+                            &key,
+                            &CacheEntry::new(entry.etag, entry.body.clone()),
+                        );
+                        entry.body
+                    }
+                    (Ok(response), _) => {
+                        let etag = response.header("ETag").map(str::to_owned);
+                        let body = response.into_string()?;
+                        store(cache, &key, &CacheEntry::new(etag, body.clone()));
+                        body
+                    }
+                    // The API is unreachable, or down or limiting the rate.
+                    (
+                        Err(ureq::Error::Transport(_) | ureq::Error::Status(429 | 500..=599, _)),
+                        Some(entry),
+                    ) => entry.body,
+                    (Err(error), _) => return Err(Box::new(error).into()),
+                }
+            }
+        };
+
+        Ok(serde_json::from_str(&body).map_err(io::Error::from)?)
+    }
+}
+
+/// Caches a response, which is only a warning if it fails, such as when the
+/// cache directory is read-only or the disk is full, since the response was
+/// received regardless.
+fn store(cache: &ApiCache, key: &str, entry: &CacheEntry) {
+    if let Err(error) = cache.store(key, entry) {
+        warn!("Failed to cache the response of {}: {}", key, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiCache, CacheEntry, NordApi};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_stale_on_server_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let dir =
+            std::env::temp_dir().join(format!("nordvpn-viking-test-stale-{}", std::process::id()));
+        let cache = ApiCache::with_dir(&dir, Duration::ZERO);
+        let body = r#"{"de507.nordvpn.com": {"percent": 12}}"#;
+
+        cache
+            .store(
+                &format!("{}/server/stats", base_url),
+                &CacheEntry::new(None, body.to_owned()),
+            )
+            .unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+
+                // Reads up to the empty line which ends the headers.
+                while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                    line.clear();
+                }
+                stream
+                    .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n")
+                    .ok();
+            }
+        });
+
+        let api = NordApi::with_base_url(base_url);
+
+        assert!(api.loads().is_err());
+        assert_eq!(
+            api.with_cache(cache.clone()).loads().unwrap()["de507.nordvpn.com"],
+            12
+        );
+
+        cache.clear().unwrap();
+    }
+}
@@ -0,0 +1,391 @@
+use super::{ApiError, ApiResult, NordApi};
+use crate::nordvpn;
+use serde::Deserialize;
+use std::net::IpAddr;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Server {
+    pub id: u64,
+    /// The display name, such as `Germany #507`.
+    pub name: String,
+    pub hostname: String,
+    /// The address that clients connect to.
+    pub station: IpAddr,
+    /// The load as a percentage.
+    pub load: u8,
+    pub status: String,
+    #[serde(default)]
+    pub locations: Vec<Location>,
+    #[serde(default)]
+    pub groups: Vec<Group>,
+    #[serde(default)]
+    pub technologies: Vec<ServerTechnology>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Location {
+    /// Degrees north of the equator.
+    #[serde(default)]
+    pub latitude: f64,
+    /// Degrees east of the prime meridian.
+    #[serde(default)]
+    pub longitude: f64,
+    pub country: Country,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Country {
+    pub id: u32,
+    pub name: String,
+    /// The ISO 3166-1 alpha-2 code, such as `DE`.
+    pub code: String,
+    pub city: Option<City>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct City {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Group {
+    pub id: u32,
+    pub title: String,
+    /// The identifier used in filters, such as `legacy_p2p`.
+    pub identifier: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ServerTechnology {
+    pub id: u32,
+    pub name: String,
+    /// The identifier used in filters, such as `wireguard_udp`.
+    pub identifier: String,
+}
+
+impl Server {
+    pub fn country(&self) -> Option<&Country> {
+        self.locations.first().map(|location| &location.country)
+    }
+
+    pub fn city(&self) -> Option<&City> {
+        self.country().and_then(|country| country.city.as_ref())
+    }
+
+    /// The number of the server, such as `507` for `de507.nordvpn.com`.
+    pub fn number(&self) -> Option<u32> {
+        nordvpn::server_number(&self.hostname)
+    }
+
+    /// The latitude and longitude of the server, in degrees.
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        self.locations
+            .first()
+            .map(|location| (location.latitude, location.longitude))
+    }
+
+    pub fn has_group(&self, identifier: &str) -> bool {
+        self.groups
+            .iter()
+            .any(|group| group.identifier == identifier)
+    }
+
+    pub fn has_technology(&self, identifier: &str) -> bool {
+        self.technologies
+            .iter()
+            .any(|technology| technology.identifier == identifier)
+    }
+}
+
+/// Identifies a single server for [`NordApi::find_server`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerId {
+    /// The hostname, with or without the domain, such as `de507`.
+    Hostname(String),
+    /// The number of the server, such as `507` for `de507.nordvpn.com`.
+    /// Servers of different countries can share a number, in which case
+    /// finding it fails with [`ApiError::AmbiguousServer`].
+    Number(u32),
+    /// The internal identifier of the API, which is not shown anywhere else.
+    ApiId(u64),
+}
+
+impl ServerId {
+    pub fn matches(&self, server: &Server) -> bool {
+        match self {
+            Self::Hostname(hostname) => {
+                let hostname = hostname.to_ascii_lowercase();
+
+                server.hostname == hostname
+                    || server.hostname.split('.').next() == Some(hostname.as_str())
+            }
+            Self::Number(number) => server.number() == Some(*number),
+            Self::ApiId(id) => server.id == *id,
+        }
+    }
+}
+
+impl From<&str> for ServerId {
+    fn from(hostname: &str) -> Self {
+        Self::Hostname(hostname.trim().to_owned())
+    }
+}
+
+impl From<String> for ServerId {
+    fn from(hostname: String) -> Self {
+        Self::from(hostname.as_str())
+    }
+}
+
+impl From<u32> for ServerId {
+    fn from(number: u32) -> Self {
+        Self::Number(number)
+    }
+}
+
+/// Filters for [`NordApi::servers`] and [`NordApi::recommendations`], all of
+/// which are optional.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerFilters {
+    pub limit: Option<u32>,
+    pub country_id: Option<u32>,
+    pub group: Option<String>,
+    pub technology: Option<String>,
+}
+
+impl ServerFilters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn country_id(mut self, country_id: u32) -> Self {
+        self.country_id = Some(country_id);
+        self
+    }
+
+    pub fn group<S: Into<String>>(mut self, identifier: S) -> Self {
+        self.group = Some(identifier.into());
+        self
+    }
+
+    pub fn technology<S: Into<String>>(mut self, identifier: S) -> Self {
+        self.technology = Some(identifier.into());
+        self
+    }
+
+    fn query(&self) -> Vec<(String, String)> {
+        let mut query = Vec::new();
+
+        if let Some(limit) = self.limit {
+            query.push(("limit".to_owned(), limit.to_string()));
+        }
+        query.extend(self.filters());
+
+        query
+    }
+
+    fn filters(&self) -> Vec<(String, String)> {
+        let mut query = Vec::new();
+
+        if let Some(country_id) = self.country_id {
+            query.push(("filters[country_id]".to_owned(), country_id.to_string()));
+        }
+        if let Some(group) = &self.group {
+            query.push((
+                "filters[servers_groups][identifier]".to_owned(),
+                group.clone(),
+            ));
+        }
+        if let Some(technology) = &self.technology {
+            query.push((
+                "filters[servers_technologies][identifier]".to_owned(),
+                technology.clone(),
+            ));
+        }
+
+        query
+    }
+}
+
+/// The number of servers requested at once when listing every server.
+const PAGE_SIZE: u32 = 1000;
+
+impl NordApi {
+    /// Every server matching the filters, up to the limit if one is set. The
+    /// listing is fetched a page at a time.
+    pub fn servers(&self, filters: &ServerFilters) -> ApiResult<Vec<Server>> {
+        let mut servers = Vec::new();
+
+        loop {
+            let remaining = filters.limit.map_or(PAGE_SIZE, |limit| {
+                limit.saturating_sub(servers.len() as u32)
+            });
+            let page_size = remaining.min(PAGE_SIZE);
+
+            if page_size == 0 {
+                break;
+            }
+
+            let mut query = filters.filters();
+            query.push(("limit".to_owned(), page_size.to_string()));
+            query.push(("offset".to_owned(), servers.len().to_string()));
+
+            let page = self.get::<Vec<Server>>("/v1/servers", &query)?;
+            let done = (page.len() as u32) < page_size;
+
+            servers.extend(page);
+
+            // The API may return more than was asked for.
+            if let Some(limit) = filters.limit {
+                servers.truncate(limit as usize);
+            }
+
+            if done {
+                break;
+            }
+        }
+
+        Ok(servers)
+    }
+
+    /// The full details of one server, or `None` if there is no such server.
+    /// Every server is listed to find it, so this should be used with a
+    /// cache when called repeatedly.
+    pub fn find_server<I: Into<ServerId>>(&self, id: I) -> ApiResult<Option<Server>> {
+        find(self.servers(&ServerFilters::default())?, &id.into())
+    }
+
+    /// The servers NordVPN recommends for this client, best first.
+    pub fn recommendations(&self, filters: &ServerFilters) -> ApiResult<Vec<Server>> {
+        self.get("/v1/servers/recommendations", &filters.query())
+    }
+}
+
+/// The one server among `servers` matching `id`.
+fn find(servers: Vec<Server>, id: &ServerId) -> ApiResult<Option<Server>> {
+    let mut matches = servers
+        .into_iter()
+        .filter(|server| id.matches(server))
+        .collect::<Vec<_>>();
+
+    match matches.len() {
+        0 | 1 => Ok(matches.pop()),
+        _ => Err(ApiError::AmbiguousServer(
+            matches.into_iter().map(|server| server.hostname).collect(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find, Server, ServerFilters, ServerId};
+    use crate::nordapi::{ApiError, NordApi};
+    use tracing::info;
+
+    const RECOMMENDATIONS: &str = r#"[{
+        "id": 929912,
+        "created_at": "2018-10-31 12:31:13",
+        "name": "Germany #507",
+        "station": "185.130.184.75",
+        "ipv6_station": "",
+        "hostname": "de507.nordvpn.com",
+        "load": 12,
+        "status": "online",
+        "locations": [{
+            "id": 117,
+            "latitude": 52.516667,
+            "longitude": 13.4,
+            "country": {
+                "id": 81,
+                "name": "Germany",
+                "code": "DE",
+                "city": { "id": 2181458, "name": "Berlin", "latitude": 52.516667, "longitude": 13.4, "dns_name": "berlin", "hub_score": 0 }
+            }
+        }],
+        "groups": [
+            { "id": 11, "title": "Standard VPN servers", "identifier": "legacy_standard", "type": { "id": 3, "identifier": "legacy_group_category" } },
+            { "id": 15, "title": "P2P", "identifier": "legacy_p2p", "type": { "id": 3, "identifier": "legacy_group_category" } }
+        ],
+        "technologies": [
+            { "id": 35, "name": "Wireguard", "identifier": "wireguard_udp", "pivot": { "technology_id": 35, "server_id": 929912, "status": "online" } }
+        ]
+    }]"#;
+
+    #[test]
+    fn test_deserialize_recommendations() {
+        let servers = serde_json::from_str::<Vec<Server>>(RECOMMENDATIONS).unwrap();
+        let server = &servers[0];
+
+        assert_eq!(server.hostname, "de507.nordvpn.com");
+        assert_eq!(server.load, 12);
+        assert_eq!(server.city().unwrap().name, "Berlin");
+        assert_eq!(server.coordinates(), Some((52.516667, 13.4)));
+        assert!(server.has_group("legacy_p2p"));
+        assert!(server.has_technology("wireguard_udp"));
+    }
+
+    #[test]
+    fn test_server_id() {
+        let server = &serde_json::from_str::<Vec<Server>>(RECOMMENDATIONS).unwrap()[0];
+
+        assert!(ServerId::from("de507").matches(server));
+        assert!(ServerId::from("DE507.nordvpn.com").matches(server));
+        assert!(ServerId::ApiId(929912).matches(server));
+        assert!(!ServerId::from("de50").matches(server));
+        assert!(ServerId::from(507).matches(server));
+        assert!(!ServerId::from(929912).matches(server));
+
+        let mut other = server.clone();
+
+        other.hostname = "us507.nordvpn.com".to_owned();
+
+        let servers = vec![server.clone(), other];
+
+        assert_eq!(
+            find(servers.clone(), &ServerId::from("us507")).unwrap(),
+            Some(servers[1].clone())
+        );
+        assert!(find(servers.clone(), &ServerId::from("de508"))
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            find(servers, &ServerId::from(507)),
+            Err(ApiError::AmbiguousServer(hostnames))
+                if hostnames == ["de507.nordvpn.com", "us507.nordvpn.com"]
+        ));
+    }
+
+    #[test]
+    fn test_find_server() {
+        crate::logging::init_test();
+
+        let server = NordApi::new().find_server("de507").unwrap();
+        info!("Server: {:#?}", server);
+    }
+
+    #[test]
+    fn test_servers() {
+        crate::logging::init_test();
+
+        let servers = NordApi::new()
+            .servers(&ServerFilters::new().group("legacy_p2p").limit(20))
+            .unwrap();
+        info!("P2P servers: {:#?}", servers);
+    }
+
+    #[test]
+    fn test_recommendations() {
+        crate::logging::init_test();
+
+        let servers = NordApi::new()
+            .recommendations(&ServerFilters::new().limit(5))
+            .unwrap();
+        info!("Recommendations: {:#?}", servers);
+    }
+}
@@ -0,0 +1,419 @@
+//! Ports and subnets whose traffic bypasses the VPN, managed with
+//! `nordvpn allowlist`, which was called `nordvpn whitelist` before 3.16.
+
+use super::cli::{command, CliError, CliResult, CommandOutput, Protocol};
+use super::compat::Compat;
+use super::re::{self, RegexError};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Allowlist {
+    pub ports: Vec<PortRange>,
+    pub subnets: Vec<Subnet>,
+}
+
+/// A single port when `start` and `end` are equal.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+    /// The protocol the ports are allowed for, or `None` for both.
+    pub protocol: Option<Protocol>,
+}
+
+/// An IP network in CIDR notation, such as `192.168.1.0/24`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Subnet {
+    pub address: IpAddr,
+    pub prefix: u8,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AllowlistEntry {
+    Ports(PortRange),
+    Subnet(Subnet),
+}
+
+/// An entry which the CLI would refuse, described for showing to the user.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{0}")]
+pub struct InvalidAllowlistEntry(pub String);
+
+/// The subnets which LAN discovery already lets bypass the VPN.
+const PRIVATE_SUBNETS: [&str; 6] = [
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "169.254.0.0/16",
+    "fc00::/7",
+    "fe80::/10",
+];
+
+impl Allowlist {
+    /// The subnets which overlap the private networks that LAN discovery
+    /// allows on its own. The CLI refuses to add these while LAN discovery
+    /// is enabled.
+    pub fn lan_conflicts(&self) -> Vec<&Subnet> {
+        self.subnets
+            .iter()
+            .filter(|subnet| subnet.is_private())
+            .collect()
+    }
+}
+
+impl PortRange {
+    pub fn new(
+        start: u16,
+        end: u16,
+        protocol: Option<Protocol>,
+    ) -> Result<Self, InvalidAllowlistEntry> {
+        if start == 0 || end == 0 {
+            Err(InvalidAllowlistEntry(
+                "ports range from 1 to 65535".to_owned(),
+            ))
+        } else if start > end {
+            Err(InvalidAllowlistEntry(format!(
+                "the range {} - {} ends before it starts",
+                start, end
+            )))
+        } else {
+            Ok(Self {
+                start,
+                end,
+                protocol,
+            })
+        }
+    }
+
+    pub fn single(port: u16, protocol: Option<Protocol>) -> Result<Self, InvalidAllowlistEntry> {
+        Self::new(port, port, protocol)
+    }
+
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+}
+
+impl Subnet {
+    pub fn new(address: IpAddr, prefix: u8) -> Result<Self, InvalidAllowlistEntry> {
+        let bits = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+
+        if prefix > bits {
+            return Err(InvalidAllowlistEntry(format!(
+                "the prefix of {} cannot be longer than {} bits",
+                address, bits
+            )));
+        }
+
+        let subnet = Self { address, prefix };
+
+        // The CLI rejects addresses with bits set past the prefix.
+        if subnet.bits() & !subnet.mask() != 0 {
+            return Err(InvalidAllowlistEntry(format!(
+                "{} has host bits set, the network is {}",
+                subnet,
+                subnet.network()
+            )));
+        }
+
+        Ok(subnet)
+    }
+
+    /// The same network with any bits past the prefix cleared.
+    pub fn network(&self) -> Self {
+        let bits = self.bits() & self.mask();
+        let address = match self.address {
+            IpAddr::V4(_) => IpAddr::from((bits as u32).to_be_bytes()),
+            IpAddr::V6(_) => IpAddr::from(bits.to_be_bytes()),
+        };
+
+        Self {
+            address,
+            prefix: self.prefix,
+        }
+    }
+
+    pub fn contains(&self, address: IpAddr) -> bool {
+        self.overlaps(&Self {
+            address,
+            prefix: self.width(),
+        })
+    }
+
+    /// Whether any address is in both networks.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        if self.address.is_ipv4() != other.address.is_ipv4() {
+            return false;
+        }
+
+        let shorter = if self.prefix <= other.prefix {
+            self
+        } else {
+            other
+        };
+        (self.bits() & shorter.mask()) == (other.bits() & shorter.mask())
+    }
+
+    /// Whether the network overlaps a private or link-local range.
+    pub fn is_private(&self) -> bool {
+        PRIVATE_SUBNETS
+            .iter()
+            .any(|private| self.overlaps(&private.parse().unwrap()))
+    }
+
+    fn width(&self) -> u8 {
+        match self.address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    fn bits(&self) -> u128 {
+        match self.address {
+            IpAddr::V4(address) => u32::from(address).into(),
+            IpAddr::V6(address) => u128::from(address),
+        }
+    }
+
+    fn mask(&self) -> u128 {
+        let full = match self.address {
+            IpAddr::V4(_) => u128::from(u32::MAX),
+            IpAddr::V6(_) => u128::MAX,
+        };
+
+        full & !full.checked_shr(self.prefix.into()).unwrap_or(0)
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = InvalidAllowlistEntry;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || InvalidAllowlistEntry(format!("`{}` is not a subnet like 192.168.1.0/24", text));
+        let (address, prefix) = text.trim().split_once('/').ok_or_else(invalid)?;
+
+        Self::new(
+            address.parse().map_err(|_| invalid())?,
+            prefix.parse().map_err(|_| invalid())?,
+        )
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)?;
+        } else {
+            write!(f, "{} - {}", self.start, self.end)?;
+        }
+
+        match self.protocol {
+            Some(protocol) => write!(f, " ({})", protocol),
+            None => write!(f, " (UDP|TCP)"),
+        }
+    }
+}
+
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+impl fmt::Display for AllowlistEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ports(ports) => ports.fmt(f),
+            Self::Subnet(subnet) => subnet.fmt(f),
+        }
+    }
+}
+
+/// The allowlist as listed by `nordvpn settings`.
+pub fn list() -> CliResult<Allowlist> {
+    parse_list(command(["nordvpn", "settings"])?)
+}
+
+pub(super) fn parse_list((output, stdout): (CommandOutput, String)) -> CliResult<Allowlist> {
+    if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    let allowlist = (|| {
+        let mut allowlist = Allowlist::default();
+
+        for (section, text) in re::parse_sections(&stdout) {
+            let lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+            match section.as_str() {
+                "allowlisted ports" | "whitelisted ports" => {
+                    for line in lines {
+                        allowlist.ports.push(parse_port(line)?);
+                    }
+                }
+                "allowlisted subnets" | "whitelisted subnets" => {
+                    for line in lines {
+                        allowlist
+                            .subnets
+                            .push(line.parse().map_err(|_| RegexError::AllowlistSubnet)?);
+                    }
+                }
+                _ => continue,
+            }
+        }
+
+        Ok(allowlist)
+    })()
+    .map_err(|error| CliError::regex(error, output))?;
+
+    Ok(allowlist)
+}
+
+fn parse_port(line: &str) -> Result<PortRange, RegexError> {
+    let captures = re::ALLOWLIST_PORT
+        .captures(line)
+        .ok_or(RegexError::AllowlistPort)?;
+    let port = |name| {
+        captures.name(name).map(|port| {
+            port.as_str()
+                .parse::<u16>()
+                .map_err(|_| RegexError::AllowlistPort)
+        })
+    };
+    let start = port("start").unwrap()?;
+    let end = port("end").unwrap_or(Ok(start))?;
+    let protocols = captures.name("protocols").unwrap().as_str();
+    // Both protocols are listed as `UDP|TCP`.
+    let protocol = match protocols.contains('|') {
+        true => None,
+        false => Some(protocols.parse().map_err(|_| RegexError::AllowlistPort)?),
+    };
+
+    Ok(PortRange {
+        start,
+        end,
+        protocol,
+    })
+}
+
+pub fn add(entry: &AllowlistEntry) -> CliResult<()> {
+    parse_edit(command(edit_args(Compat::get()?, "add", entry))?)
+}
+
+pub fn remove(entry: &AllowlistEntry) -> CliResult<()> {
+    parse_edit(command(edit_args(Compat::get()?, "remove", entry))?)
+}
+
+/// Removes every port and subnet.
+pub fn clear() -> CliResult<()> {
+    parse_edit(command([
+        "nordvpn",
+        Compat::get()?.allowlist_command(),
+        "remove",
+        "all",
+    ])?)
+}
+
+pub(super) fn edit_args(compat: &Compat, action: &str, entry: &AllowlistEntry) -> Vec<String> {
+    let mut args = vec![
+        "nordvpn".to_owned(),
+        compat.allowlist_command().to_owned(),
+        action.to_owned(),
+    ];
+
+    match entry {
+        AllowlistEntry::Ports(ports) => {
+            if ports.start == ports.end {
+                args.extend(["port".to_owned(), ports.start.to_string()]);
+            } else {
+                args.extend([
+                    "ports".to_owned(),
+                    ports.start.to_string(),
+                    ports.end.to_string(),
+                ]);
+            }
+
+            if let Some(protocol) = ports.protocol {
+                args.extend(["protocol".to_owned(), protocol.to_string()]);
+            }
+        }
+        AllowlistEntry::Subnet(subnet) => {
+            args.extend(["subnet".to_owned(), subnet.to_string()]);
+        }
+    }
+
+    args
+}
+
+pub(super) fn parse_edit((output, _stdout): (CommandOutput, String)) -> CliResult<()> {
+    if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AllowlistEntry, PortRange, Subnet};
+    use crate::nordvpn::{MockBackend, NordVpnBackend, Protocol};
+
+    const SETTINGS: &str = "Technology: NORDLYNX\n\
+        Firewall: enabled\n\
+        Kill Switch: disabled\n\
+        LAN Discovery: disabled\n\
+        Allowlisted ports:\n\
+        \t22 (UDP|TCP)\n\
+        \t8000 - 8080 (TCP)\n\
+        Allowlisted subnets:\n\
+        \t192.168.1.0/24\n\
+        \t203.0.113.0/24\n";
+
+    #[test]
+    fn test_parse_allowlist() {
+        let backend = MockBackend::new();
+        backend.respond(["settings"], SETTINGS);
+
+        let allowlist = backend.allowlist().unwrap();
+
+        assert_eq!(
+            allowlist.ports,
+            [
+                PortRange::single(22, None).unwrap(),
+                PortRange::new(8000, 8080, Some(Protocol::Tcp)).unwrap(),
+            ]
+        );
+        assert_eq!(allowlist.subnets.len(), 2);
+        assert_eq!(allowlist.ports[1].to_string(), "8000 - 8080 (TCP)");
+        assert_eq!(allowlist.lan_conflicts(), [&allowlist.subnets[0]]);
+    }
+
+    #[test]
+    fn test_validate_allowlist() {
+        assert!(PortRange::new(0, 10, None).is_err());
+        assert!(PortRange::new(10, 5, None).is_err());
+        assert!("192.168.1.1/24".parse::<Subnet>().is_err());
+        assert!("10.0.0.0/33".parse::<Subnet>().is_err());
+        assert!("10.0.0.0".parse::<Subnet>().is_err());
+
+        let subnet = "fd00::/8".parse::<Subnet>().unwrap();
+        assert!(subnet.is_private());
+        assert!(subnet.contains("fd12::1".parse().unwrap()));
+        assert!(!"0.0.0.0/0"
+            .parse::<Subnet>()
+            .unwrap()
+            .contains("::1".parse().unwrap()));
+        assert!("0.0.0.0/0".parse::<Subnet>().unwrap().is_private());
+        assert!(!"8.8.8.8/32".parse::<Subnet>().unwrap().is_private());
+
+        let entry = AllowlistEntry::Ports(PortRange::single(22, Some(Protocol::Udp)).unwrap());
+        assert_eq!(entry.to_string(), "22 (UDP)");
+    }
+}
@@ -0,0 +1,242 @@
+//! Non-blocking variants of the wrapper functions, which spawn the CLI with
+//! `tokio::process` so that an async runtime is not stalled while a command
+//! such as `nordvpn connect` takes several seconds to finish.
+
+use super::cli::{self, command_output, CliError, CliResult, CommandOutput};
+use super::cli::{Account, ConnectOption, Connected, ListEntry, Settings, Status};
+use semver::Version;
+use std::ffi::OsStr;
+use std::time::Instant;
+use tokio::process::Command;
+
+pub async fn account() -> CliResult<Option<Account>> {
+    cli::parse_account(command(["nordvpn", "account"]).await?)
+}
+
+pub async fn cities(country: &str) -> CliResult<Vec<ListEntry>> {
+    cli::parse_cities(command(["nordvpn", "cities", country]).await?)
+}
+
+pub async fn connect(option: Option<&ConnectOption>) -> CliResult<Connected> {
+    let mut connected = cli::parse_connect(command(cli::connect_args(option)).await?)?;
+
+    if let Ok(Some(status)) = status().await {
+        connected.enrich(&status);
+    }
+
+    Ok(connected)
+}
+
+pub async fn countries() -> CliResult<Vec<ListEntry>> {
+    cli::parse_countries(command(["nordvpn", "countries"]).await?)
+}
+
+pub async fn disconnect() -> CliResult<bool> {
+    cli::parse_disconnect(command(["nordvpn", "disconnect"]).await?)
+}
+
+pub async fn groups() -> CliResult<Vec<ListEntry>> {
+    cli::parse_groups(command(["nordvpn", "groups"]).await?)
+}
+
+pub async fn login() -> CliResult<Option<String>> {
+    cli::parse_login(command(["nordvpn", "login"]).await?)
+}
+
+pub async fn login_callback(url: &str) -> CliResult<bool> {
+    cli::parse_login_callback(command(["nordvpn", "login", "--callback", url]).await?)
+}
+
+pub async fn logout() -> CliResult<bool> {
+    cli::parse_logout(command(["nordvpn", "logout"]).await?)
+}
+
+/// Sets a configuration option. Unlike the blocking variant this is not
+/// deprecated, because `Settings` has no asynchronous setters.
+pub async fn set<S, T, V>(setting: S, values: V) -> CliResult<()>
+where
+    S: AsRef<str>,
+    T: AsRef<str>,
+    V: IntoIterator<Item = T>,
+{
+    let values = values
+        .into_iter()
+        .map(|value| value.as_ref().to_owned())
+        .collect::<Vec<_>>();
+
+    cli::parse_set(
+        command(cli::set_args(setting.as_ref(), &values)).await?,
+        setting.as_ref(),
+        &values,
+    )
+}
+
+pub async fn reset_settings() -> CliResult<bool> {
+    cli::parse_reset_settings(command(["nordvpn", "set", "defaults"]).await?)
+}
+
+pub async fn settings() -> CliResult<Settings> {
+    cli::parse_settings(command(["nordvpn", "settings"]).await?)
+}
+
+pub async fn status() -> CliResult<Option<Status>> {
+    cli::parse_status(command(["nordvpn", "status"]).await?)
+}
+
+pub async fn version() -> CliResult<Version> {
+    cli::parse_version(command(["nordvpn", "version"]).await?)
+}
+
+pub mod allowlist {
+    use super::{command, version};
+    use crate::nordvpn::allowlist::{self, Allowlist, AllowlistEntry};
+    use crate::nordvpn::cli::CliResult;
+    use crate::nordvpn::compat::Compat;
+
+    pub async fn list() -> CliResult<Allowlist> {
+        allowlist::parse_list(command(["nordvpn", "settings"]).await?)
+    }
+
+    pub async fn add(entry: &AllowlistEntry) -> CliResult<()> {
+        let compat = Compat::for_version(version().await?);
+        allowlist::parse_edit(command(allowlist::edit_args(&compat, "add", entry)).await?)
+    }
+
+    pub async fn remove(entry: &AllowlistEntry) -> CliResult<()> {
+        let compat = Compat::for_version(version().await?);
+        allowlist::parse_edit(command(allowlist::edit_args(&compat, "remove", entry)).await?)
+    }
+
+    pub async fn clear() -> CliResult<()> {
+        let compat = Compat::for_version(version().await?);
+        allowlist::parse_edit(
+            command(["nordvpn", compat.allowlist_command(), "remove", "all"]).await?,
+        )
+    }
+}
+
+pub mod meshnet {
+    use super::command;
+    use crate::nordvpn::cli::CliResult;
+    use crate::nordvpn::meshnet::{self, Invites, Peers, Permission};
+
+    pub async fn peers() -> CliResult<Peers> {
+        meshnet::parse_peers(command(["nordvpn", "meshnet", "peer", "list"]).await?)
+    }
+
+    pub async fn remove_peer(peer: &str) -> CliResult<()> {
+        meshnet::parse_remove_peer(
+            command(["nordvpn", "meshnet", "peer", "remove", peer]).await?,
+            peer,
+        )
+    }
+
+    pub async fn set_permission(
+        peer: &str,
+        permission: Permission,
+        allowed: bool,
+    ) -> CliResult<bool> {
+        meshnet::parse_set_permission(
+            command([
+                "nordvpn",
+                "meshnet",
+                "peer",
+                permission.to_string().as_str(),
+                if allowed { "allow" } else { "deny" },
+                peer,
+            ])
+            .await?,
+            peer,
+        )
+    }
+
+    pub async fn invites() -> CliResult<Invites> {
+        meshnet::parse_invites(command(["nordvpn", "meshnet", "invite", "list"]).await?)
+    }
+
+    pub async fn send_invite(email: &str) -> CliResult<()> {
+        invite("send", email, "was sent").await
+    }
+
+    pub async fn accept_invite(email: &str) -> CliResult<()> {
+        invite("accept", email, "was accepted").await
+    }
+
+    pub async fn deny_invite(email: &str) -> CliResult<()> {
+        invite("deny", email, "was denied").await
+    }
+
+    pub async fn revoke_invite(email: &str) -> CliResult<()> {
+        invite("revoke", email, "was revoked").await
+    }
+
+    async fn invite(action: &str, email: &str, success: &str) -> CliResult<()> {
+        meshnet::parse_invite(
+            command(["nordvpn", "meshnet", "invite", action, email]).await?,
+            email,
+            success,
+        )
+    }
+}
+
+pub mod fileshare {
+    use super::command;
+    use crate::nordvpn::cli::CliResult;
+    use crate::nordvpn::fileshare::{self, Transfers};
+    use std::path::Path;
+
+    pub async fn send<P, I>(peer: &str, paths: I) -> CliResult<String>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = P>,
+    {
+        fileshare::parse_send(command(fileshare::send_args(peer, paths)).await?, peer)
+    }
+
+    pub async fn transfers() -> CliResult<Transfers> {
+        fileshare::parse_transfers(command(["nordvpn", "fileshare", "list"]).await?)
+    }
+
+    pub async fn accept(id: &str, destination: Option<&Path>) -> CliResult<()> {
+        fileshare::parse_transfer_command(
+            command(fileshare::accept_args(id, destination)).await?,
+            id,
+        )
+    }
+
+    pub async fn cancel(id: &str) -> CliResult<()> {
+        fileshare::parse_transfer_command(
+            command(["nordvpn", "fileshare", "cancel", id]).await?,
+            id,
+        )
+    }
+}
+
+async fn command<S, I>(run: I) -> CliResult<(CommandOutput, String)>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = S>,
+{
+    let mut run = run.into_iter();
+    let mut command = Command::from(cli::new_command(run.next().unwrap()));
+
+    command.args(run);
+
+    let started = Instant::now();
+    let output = command.output().await.map_err(CliError::spawn)?;
+
+    command_output(command.as_std(), output, started)
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::info;
+
+    #[tokio::test]
+    async fn test_status() {
+        crate::logging::init_test();
+
+        let status = super::status().await.unwrap();
+        info!("Status: {:#?}", status);
+    }
+}
@@ -0,0 +1,500 @@
+use super::allowlist::{self, Allowlist, AllowlistEntry};
+use super::cli::{self, CliError, CliResult, CommandOutput};
+use super::cli::{Account, ConnectOption, Connected, ListEntry, Settings, Status};
+use super::compat::Compat;
+use super::fileshare::{self, Transfers};
+use super::meshnet::{self, Invites, Peers, Permission};
+use semver::Version;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::sync::Mutex;
+
+/// A source of NordVPN state and actions.
+///
+/// Implementors only need to provide [`NordVpnBackend::command`], which runs
+/// one invocation of the `nordvpn` command line interface; every other method
+/// has a default implementation that builds the arguments and parses the
+/// output the same way as the free functions in this module. Backends which
+/// can answer more directly may override any of them.
+pub trait NordVpnBackend {
+    /// Runs the CLI with the given arguments, the first being the program.
+    fn command(&self, run: Vec<OsString>) -> CliResult<(CommandOutput, String)>;
+
+    fn account(&self) -> CliResult<Option<Account>> {
+        cli::parse_account(self.command(args(["nordvpn", "account"]))?)
+    }
+
+    fn cities(&self, country: &str) -> CliResult<Vec<ListEntry>> {
+        cli::parse_cities(self.command(args(["nordvpn", "cities", country]))?)
+    }
+
+    fn connect(&self, option: Option<&ConnectOption>) -> CliResult<Connected> {
+        let mut connected = cli::parse_connect(self.command(args(cli::connect_args(option)))?)?;
+
+        if let Ok(Some(status)) = self.status() {
+            connected.enrich(&status);
+        }
+
+        Ok(connected)
+    }
+
+    fn countries(&self) -> CliResult<Vec<ListEntry>> {
+        cli::parse_countries(self.command(args(["nordvpn", "countries"]))?)
+    }
+
+    fn disconnect(&self) -> CliResult<bool> {
+        cli::parse_disconnect(self.command(args(["nordvpn", "disconnect"]))?)
+    }
+
+    fn groups(&self) -> CliResult<Vec<ListEntry>> {
+        cli::parse_groups(self.command(args(["nordvpn", "groups"]))?)
+    }
+
+    fn login(&self) -> CliResult<Option<String>> {
+        cli::parse_login(self.command(args(["nordvpn", "login"]))?)
+    }
+
+    fn login_callback(&self, url: &str) -> CliResult<bool> {
+        cli::parse_login_callback(self.command(args(["nordvpn", "login", "--callback", url]))?)
+    }
+
+    fn logout(&self) -> CliResult<bool> {
+        cli::parse_logout(self.command(args(["nordvpn", "logout"]))?)
+    }
+
+    fn set(&self, setting: &str, values: &[String]) -> CliResult<()> {
+        cli::parse_set(
+            self.command(args(cli::set_args(setting, values)))?,
+            setting,
+            values,
+        )
+    }
+
+    fn reset_settings(&self) -> CliResult<bool> {
+        cli::parse_reset_settings(self.command(args(["nordvpn", "set", "defaults"]))?)
+    }
+
+    fn settings(&self) -> CliResult<Settings> {
+        cli::parse_settings(self.command(args(["nordvpn", "settings"]))?)
+    }
+
+    fn status(&self) -> CliResult<Option<Status>> {
+        cli::parse_status(self.command(args(["nordvpn", "status"]))?)
+    }
+
+    fn version(&self) -> CliResult<Version> {
+        cli::parse_version(self.command(args(["nordvpn", "version"]))?)
+    }
+
+    fn allowlist(&self) -> CliResult<Allowlist> {
+        allowlist::parse_list(self.command(args(["nordvpn", "settings"]))?)
+    }
+
+    fn allowlist_add(&self, entry: &AllowlistEntry) -> CliResult<()> {
+        let compat = Compat::for_version(self.version()?);
+        allowlist::parse_edit(self.command(args(allowlist::edit_args(&compat, "add", entry)))?)
+    }
+
+    fn allowlist_remove(&self, entry: &AllowlistEntry) -> CliResult<()> {
+        let compat = Compat::for_version(self.version()?);
+        allowlist::parse_edit(self.command(args(allowlist::edit_args(&compat, "remove", entry)))?)
+    }
+
+    fn allowlist_clear(&self) -> CliResult<()> {
+        let compat = Compat::for_version(self.version()?);
+        allowlist::parse_edit(self.command(args([
+            "nordvpn",
+            compat.allowlist_command(),
+            "remove",
+            "all",
+        ]))?)
+    }
+
+    fn meshnet_peers(&self) -> CliResult<Peers> {
+        meshnet::parse_peers(self.command(args(["nordvpn", "meshnet", "peer", "list"]))?)
+    }
+
+    fn meshnet_remove_peer(&self, peer: &str) -> CliResult<()> {
+        meshnet::parse_remove_peer(
+            self.command(args(["nordvpn", "meshnet", "peer", "remove", peer]))?,
+            peer,
+        )
+    }
+
+    fn meshnet_set_permission(
+        &self,
+        peer: &str,
+        permission: Permission,
+        allowed: bool,
+    ) -> CliResult<bool> {
+        meshnet::parse_set_permission(
+            self.command(args([
+                "nordvpn",
+                "meshnet",
+                "peer",
+                permission.to_string().as_str(),
+                if allowed { "allow" } else { "deny" },
+                peer,
+            ]))?,
+            peer,
+        )
+    }
+
+    fn meshnet_invites(&self) -> CliResult<Invites> {
+        meshnet::parse_invites(self.command(args(["nordvpn", "meshnet", "invite", "list"]))?)
+    }
+
+    fn meshnet_send_invite(&self, email: &str) -> CliResult<()> {
+        meshnet::parse_invite(
+            self.command(args(["nordvpn", "meshnet", "invite", "send", email]))?,
+            email,
+            "was sent",
+        )
+    }
+
+    fn meshnet_accept_invite(&self, email: &str) -> CliResult<()> {
+        meshnet::parse_invite(
+            self.command(args(["nordvpn", "meshnet", "invite", "accept", email]))?,
+            email,
+            "was accepted",
+        )
+    }
+
+    fn meshnet_deny_invite(&self, email: &str) -> CliResult<()> {
+        meshnet::parse_invite(
+            self.command(args(["nordvpn", "meshnet", "invite", "deny", email]))?,
+            email,
+            "was denied",
+        )
+    }
+
+    fn meshnet_revoke_invite(&self, email: &str) -> CliResult<()> {
+        meshnet::parse_invite(
+            self.command(args(["nordvpn", "meshnet", "invite", "revoke", email]))?,
+            email,
+            "was revoked",
+        )
+    }
+
+    fn fileshare_send(&self, peer: &str, paths: &[&Path]) -> CliResult<String> {
+        fileshare::parse_send(self.command(fileshare::send_args(peer, paths))?, peer)
+    }
+
+    fn fileshare_transfers(&self) -> CliResult<Transfers> {
+        fileshare::parse_transfers(self.command(args(["nordvpn", "fileshare", "list"]))?)
+    }
+
+    fn fileshare_accept(&self, id: &str, destination: Option<&Path>) -> CliResult<()> {
+        fileshare::parse_transfer_command(
+            self.command(fileshare::accept_args(id, destination))?,
+            id,
+        )
+    }
+
+    fn fileshare_cancel(&self, id: &str) -> CliResult<()> {
+        fileshare::parse_transfer_command(
+            self.command(args(["nordvpn", "fileshare", "cancel", id]))?,
+            id,
+        )
+    }
+}
+
+/// The backend which spawns the `nordvpn` command line interface.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct NordVPN;
+
+impl NordVpnBackend for NordVPN {
+    fn command(&self, run: Vec<OsString>) -> CliResult<(CommandOutput, String)> {
+        cli::command(run)
+    }
+}
+
+/// A scripted backend for testing code which depends on [`NordVpnBackend`]
+/// without a NordVPN installation.
+///
+/// Responses are the raw text the CLI would print, queued per argument list
+/// (excluding the program name), so they go through the real parsers. The
+/// last response queued for a command is repeated for every later call.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    responses: Mutex<HashMap<Vec<String>, VecDeque<MockResponse>>>,
+    calls: Mutex<Vec<Vec<String>>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl MockResponse {
+    pub fn success<S: Into<String>>(stdout: S) -> Self {
+        Self {
+            code: 0,
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+
+    pub fn failure<S: Into<String>>(code: i32, stdout: S) -> Self {
+        Self {
+            code,
+            stdout: stdout.into(),
+            stderr: String::new(),
+        }
+    }
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a successful response for the command with the given arguments.
+    pub fn respond<S, I, O>(&self, run: I, stdout: O) -> &Self
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = S>,
+        O: Into<String>,
+    {
+        self.push(run, MockResponse::success(stdout))
+    }
+
+    /// Queues an arbitrary response for the command with the given arguments.
+    pub fn push<S, I>(&self, run: I, response: MockResponse) -> &Self
+    where
+        S: AsRef<str>,
+        I: IntoIterator<Item = S>,
+    {
+        let run = run.into_iter().map(|arg| arg.as_ref().to_owned()).collect();
+
+        self.responses
+            .lock()
+            .unwrap()
+            .entry(run)
+            .or_default()
+            .push_back(response);
+
+        self
+    }
+
+    /// The argument lists of every command run so far, in order.
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl NordVpnBackend for MockBackend {
+    fn command(&self, run: Vec<OsString>) -> CliResult<(CommandOutput, String)> {
+        let run = run
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+
+        self.calls.lock().unwrap().push(run[1..].to_vec());
+
+        let response = {
+            let mut responses = self.responses.lock().unwrap();
+            let queue = responses
+                .get_mut(&run[1..])
+                .filter(|queue| !queue.is_empty());
+
+            match queue {
+                Some(queue) if queue.len() > 1 => queue.pop_front(),
+                Some(queue) => queue.front().cloned(),
+                None => None,
+            }
+        };
+
+        let response = response.ok_or_else(|| {
+            CliError::IoError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "no scripted response for `{}`",
+                    cli::redact_args(run.clone()).join(" ")
+                ),
+            ))
+        })?;
+
+        Ok((
+            CommandOutput {
+                args: cli::redact_args(run),
+                status: ExitStatus::from_raw(response.code << 8),
+                stdout: response.stdout.clone(),
+                stderr: response.stderr,
+            },
+            response.stdout,
+        ))
+    }
+}
+
+fn args<S, I>(run: I) -> Vec<OsString>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = S>,
+{
+    run.into_iter().map(|arg| arg.as_ref().to_owned()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cli, MockBackend, MockResponse, NordVpnBackend};
+    use crate::nordvpn::{CliError, Technology};
+
+    const STATUS: &str = "Status: Connected\n\
+        Current server: de507.nordvpn.com\n\
+        Country: Germany\n\
+        City: Berlin\n\
+        Server IP: 185.0.0.1\n\
+        Current technology: NORDLYNX\n\
+        Current protocol: UDP\n\
+        Transfer: 1.2 MiB received, 300 KiB sent\n\
+        Uptime: 1 hour 2 minutes 3 seconds\n";
+
+    #[test]
+    fn test_mock_status() {
+        let backend = MockBackend::new();
+
+        backend.respond(["status"], STATUS);
+
+        let status = backend.status().unwrap().unwrap();
+
+        assert_eq!(status.hostname, "de507.nordvpn.com");
+        assert_eq!(status.server(), Some(507));
+        assert_eq!(status.technology, Technology::NordLynx);
+        assert_eq!(status.raw_uptime.hours, 1);
+        assert_eq!(status.uptime.num_seconds(), 3723);
+        assert_eq!(backend.calls(), vec![vec!["status".to_owned()]]);
+    }
+
+    #[test]
+    fn test_mock_connect() {
+        let backend = MockBackend::new();
+
+        backend
+            .respond(
+                ["connect"],
+                "Connecting to Germany #507 (de507.nordvpn.com)\n\
+                 You are connected to Germany #507 (de507.nordvpn.com)!\n",
+            )
+            .respond(["status"], STATUS);
+
+        let connected = backend.connect(None).unwrap();
+
+        assert_eq!(connected.server, Some(507));
+        assert_eq!(connected.city.as_deref(), Some("Berlin"));
+        assert_eq!(connected.technology, Some(Technology::NordLynx));
+        assert_eq!(
+            backend.calls(),
+            vec![vec!["connect".to_owned()], vec!["status".to_owned()]]
+        );
+    }
+
+    #[test]
+    fn test_mock_account() {
+        let backend = MockBackend::new();
+
+        backend.respond(
+            ["account"],
+            "Account Information:\n\
+             Email Address: user@example.com\n\
+             VPN Service: Active (Expires on Feb 3rd, 2027)\n\
+             Dedicated IP: Active (Expires on Mar 4th, 2027)\n\
+             Dedicated IP assigned servers: de507.nordvpn.com\n",
+        );
+
+        let account = backend.account().unwrap().unwrap();
+
+        assert!(account.active);
+        assert_eq!(account.expires.to_string(), "2027-02-03");
+        assert_eq!(
+            account.dedicated_ip,
+            Some(vec!["de507.nordvpn.com".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_mock_failure() {
+        let backend = MockBackend::new();
+
+        backend.push(
+            ["disconnect"],
+            MockResponse::failure(1, "Whoops! Cannot reach System Daemon.\n"),
+        );
+
+        assert!(matches!(
+            backend.disconnect(),
+            Err(CliError::DaemonNotRunning)
+        ));
+        assert!(backend.countries().is_err());
+    }
+
+    #[test]
+    fn test_mock_busy() {
+        let backend = MockBackend::new();
+
+        backend
+            .push(
+                ["disconnect"],
+                MockResponse::failure(1, "The daemon is busy. Please try again later.\n"),
+            )
+            .push(
+                ["disconnect"],
+                MockResponse::failure(1, "Something went wrong. Please try again later.\n"),
+            );
+
+        assert!(matches!(backend.disconnect(), Err(CliError::DaemonBusy)));
+        assert!(matches!(
+            backend.disconnect(),
+            Err(CliError::FailedCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_login_callback_redacted() {
+        const CALLBACK: &str = "nordvpn://login?action=login&exchange_token=secret";
+
+        let backend = MockBackend::new();
+
+        backend.push(
+            ["login", "--callback", CALLBACK],
+            MockResponse::failure(1, "Something went wrong.\n"),
+        );
+
+        let error = backend.login_callback(CALLBACK).unwrap_err();
+
+        assert!(!error.to_string().contains("secret"));
+        assert!(!format!("{:?}", error).contains("secret"));
+
+        let error = cli::parse_login_callback(
+            cli::command(["false", "login", "--callback", CALLBACK]).unwrap(),
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("--callback <redacted>"));
+        assert!(!error.to_string().contains("secret"));
+    }
+
+    #[test]
+    fn test_mock_reset_settings() {
+        let backend = MockBackend::new();
+
+        backend
+            .respond(
+                ["set", "defaults"],
+                "Settings were successfully restored to defaults.\n",
+            )
+            .respond(
+                ["set", "defaults"],
+                "Settings were successfully restored to defaults.\nYou have been logged out.\n",
+            );
+
+        assert!(!backend.reset_settings().unwrap());
+        assert!(backend.reset_settings().unwrap());
+    }
+}
@@ -1,32 +1,223 @@
+use super::compat::{Capability, Compat};
+use super::install::Installation;
 use super::re::{self, RegexError};
 use byte_unit::Byte;
-use chrono::{Duration, NaiveDate};
+use chrono::{DateTime, Duration, Local, Months, NaiveDate, TimeZone};
+use once_cell::sync::Lazy;
 use semver::Version;
 use std::collections::HashSet;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::io::{self, BufReader, Read};
 use std::net::IpAddr;
-use std::process::{Command, Output};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::thread;
+use std::time::Instant;
 use strum;
 use thiserror::Error;
+use tracing::{debug, instrument, trace, warn};
 
 pub type CliResult<T> = Result<T, CliError>;
 
+/// Environment variables to set, or remove if `None`, on spawned commands.
+type EnvOverrides = Vec<(OsString, Option<OsString>)>;
+
+static COMMAND_ENV: Lazy<RwLock<EnvOverrides>> = Lazy::new(Default::default);
+
+/// The options whose values must never be logged, such as the link with the
+/// exchange token of a login, which would let anyone finish it.
+const SECRET_OPTIONS: &[&str] = &["--callback"];
+
+/// What is shown in place of the value of a [`SECRET_OPTIONS`] option.
+const REDACTED: &str = "<redacted>";
+
+/// Whether this runs inside a Flatpak sandbox, where the CLI is only on the
+/// host and has to be run through `flatpak-spawn --host`.
+static IN_FLATPAK: Lazy<bool> = Lazy::new(|| Path::new("/.flatpak-info").exists());
+
 #[derive(Debug, Error)]
 pub enum CliError {
     #[error("unable to create command")]
-    IoError(#[from] std::io::Error),
-    #[error("command terminated unsuccessfully")]
-    FailedCommand(Command),
+    IoError(#[from] io::Error),
+    #[error("command terminated unsuccessfully: {0}")]
+    FailedCommand(CommandOutput),
     #[error("failed to get command output as UTF-8")]
     BadEncoding(#[from] std::string::FromUtf8Error),
-    #[error("command output did not match as expected")]
-    BadOutput(Command),
-    #[error("a regex pattern failed to match")]
-    RegexError(RegexError, Command),
+    #[error("command output did not match as expected: {0}")]
+    BadOutput(CommandOutput),
+    #[error("a regex pattern failed to match: {1}")]
+    RegexError(RegexError, CommandOutput),
+    #[error("you are not logged in")]
+    NotLoggedIn,
+    #[error("the NordVPN daemon is not running or cannot be reached")]
+    DaemonNotRunning,
+    #[error("the NordVPN daemon is busy with another request")]
+    DaemonBusy,
+    #[error("permission denied, the user may not be in the `nordvpn` group")]
+    PermissionDenied,
+    #[error("the NordVPN CLI is not installed, or could not be run from the sandbox")]
+    NotInstalled,
+    #[error(
+        "the confinement of the NordVPN snap denied access to the daemon, \
+         check that its interfaces are connected with `snap connections nordvpn`"
+    )]
+    SnapConfinement,
+    #[error("there is no internet connection")]
+    NoInternet,
+    #[error("the NordVPN subscription has expired")]
+    SubscriptionExpired,
+    #[error("already connected to a server")]
+    AlreadyConnected,
+    #[error("the specified server, country, city or group does not exist")]
+    InvalidServer,
+    #[error("the specified server is not available with the current settings")]
+    ServerUnavailable,
     #[error("setting does not exist")]
     InvalidSettingName(String),
     #[error("the provided value for a setting is malformed or invalid")]
     InvalidSettingValue(String, Vec<String>),
+    #[error("meshnet is not enabled")]
+    MeshnetDisabled,
+    #[error("the email address is invalid")]
+    InvalidEmail(String),
+    #[error("no meshnet invitation exists for the email address")]
+    InviteNotFound(String),
+    #[error("a meshnet invitation already exists for the email address")]
+    InviteExists(String),
+    #[error("meshnet peer does not exist")]
+    UnknownPeer(String),
+    #[error("the fileshare daemon is not running")]
+    FileshareNotRunning,
+    #[error("file transfer does not exist")]
+    UnknownTransfer(String),
+    #[error("{0} is not supported by NordVPN {1}, it requires {since}", since = .0.since())]
+    UnsupportedByCliVersion(Capability, Version),
+    #[error("failed to communicate with the daemon: {0}")]
+    DaemonTransport(String),
+}
+
+/// The arguments, exit status and captured output streams of a finished
+/// command, retained so that failures can be diagnosed without rerunning it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandOutput {
+    pub args: Vec<String>,
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl fmt::Display for CommandOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` exited with {}", self.args.join(" "), self.status)?;
+
+        if !self.stderr.trim().is_empty() {
+            write!(f, ", stderr: {}", self.stderr.trim())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CliError {
+    /// Creates an error for a command that terminated unsuccessfully,
+    /// preferring a specific variant when the CLI printed a known message.
+    pub(super) fn failed(output: CommandOutput) -> Self {
+        Self::detect(&output).unwrap_or(Self::FailedCommand(output))
+    }
+
+    /// Creates an error for a command whose output was not recognized,
+    /// preferring a specific variant when the CLI printed a known message.
+    pub(super) fn bad_output(output: CommandOutput) -> Self {
+        Self::detect(&output).unwrap_or_else(|| {
+            warn!(
+                command = %output.args.join(" "),
+                stdout = %output.stdout,
+                "the command output was not recognized"
+            );
+            Self::BadOutput(output)
+        })
+    }
+
+    /// Creates an error for a command which could not be started, which is
+    /// [`CliError::NotInstalled`] if the program was not found.
+    pub(super) fn spawn(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => Self::NotInstalled,
+            _ => Self::IoError(error),
+        }
+    }
+
+    /// Creates an error for output which a pattern failed to match, logging
+    /// the output so the pattern can be fixed for it.
+    pub(super) fn regex(error: RegexError, output: CommandOutput) -> Self {
+        warn!(
+            command = %output.args.join(" "),
+            stdout = %output.stdout,
+            "a regex pattern failed to match: {:?}",
+            error
+        );
+        Self::RegexError(error, output)
+    }
+
+    /// The captured output of the command that caused the error, if any.
+    pub fn output(&self) -> Option<&CommandOutput> {
+        match self {
+            Self::FailedCommand(output) | Self::BadOutput(output) | Self::RegexError(_, output) => {
+                Some(output)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the error is likely to go away by itself, such as the daemon
+    /// still starting after boot, so the command is worth running again.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::DaemonNotRunning | Self::DaemonBusy => true,
+            Self::IoError(error) => matches!(
+                error.kind(),
+                io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::Interrupted
+                    | io::ErrorKind::WouldBlock
+            ),
+            _ => false,
+        }
+    }
+
+    fn detect(output: &CommandOutput) -> Option<Self> {
+        let text = format!("{}\n{}", output.stdout, output.stderr).to_lowercase();
+
+        if text.contains("cannot reach system daemon") || text.contains("connection refused") {
+            Some(Self::DaemonNotRunning)
+        } else if text.contains("daemon is busy") {
+            Some(Self::DaemonBusy)
+        } else if text.contains("permission denied") && Installation::get() == Installation::Snap {
+            Some(Self::SnapConfinement)
+        } else if text.contains("permission denied") || text.contains("add your user to the") {
+            Some(Self::PermissionDenied)
+        } else if text.contains("failed to start command") || text.contains("portal call failed") {
+            // Printed by `flatpak-spawn` when the host has no such command,
+            // or the sandbox may not talk to the Flatpak portal.
+            Some(Self::NotInstalled)
+        } else if text.contains("you are not logged in") {
+            Some(Self::NotLoggedIn)
+        } else if text.contains("check your internet connection") {
+            Some(Self::NoInternet)
+        } else if text.contains("has expired") || text.contains("renew your subscription") {
+            Some(Self::SubscriptionExpired)
+        } else if text.contains("you are already connected") {
+            Some(Self::AlreadyConnected)
+        } else if text.contains("does not exist") || text.contains("doesn't exist") {
+            Some(Self::InvalidServer)
+        } else if text.contains("is not available at the moment") {
+            Some(Self::ServerUnavailable)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -34,13 +225,36 @@ pub struct Account {
     pub email: String,
     pub active: bool,
     pub expires: NaiveDate,
+    /// The hostnames of the dedicated IP servers assigned to the account, or
+    /// `None` if it has no active dedicated IP subscription.
+    pub dedicated_ip: Option<Vec<String>>,
+}
+
+/// A country, city or group as listed by the CLI.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListEntry {
+    /// The human readable name, such as `Bosnia And Herzegovina`.
+    pub name: String,
+    /// The identifier to pass to `nordvpn connect`, such as
+    /// `Bosnia_And_Herzegovina`.
+    pub id: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Connected {
-    pub country: String,
-    pub server: u32,
+    pub country: Option<String>,
+    pub server: Option<u32>,
     pub hostname: String,
+    /// The fields below are not printed by `nordvpn connect`, and are filled
+    /// in from the status after connecting if it could be read.
+    pub city: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub technology: Option<Technology>,
+    pub protocol: Option<Protocol>,
+    /// The load of the server as a percentage, which the CLI never reports,
+    /// so it is only known when the server was chosen by its load, as by
+    /// [`connect_fastest`](crate::fastest::connect_fastest).
+    pub load: Option<u8>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -51,6 +265,9 @@ pub enum ConnectOption {
     City(String),
     Group(String),
     CountryCity(String, String),
+    MeshnetPeer(String),
+    /// A dedicated IP server of the account, or any of them if `None`.
+    DedicatedIp(Option<String>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -62,7 +279,25 @@ pub struct Status {
     pub technology: Technology,
     pub protocol: Protocol,
     pub transfer: Transfer,
+    /// The uptime as of parsing, rounded to the units printed by the CLI.
     pub uptime: Duration,
+    /// The uptime exactly as printed by the CLI.
+    pub raw_uptime: Uptime,
+    /// When the connection was established, which unlike `uptime` can be
+    /// used to derive the current uptime without polling again.
+    pub connected_since: DateTime<Local>,
+}
+
+/// A duration in the calendar units printed by the CLI, where months and
+/// years have no fixed length.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Uptime {
+    pub years: u32,
+    pub months: u32,
+    pub days: u32,
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, strum::Display, strum::EnumString)]
@@ -87,63 +322,419 @@ pub struct Transfer {
     pub sent: Byte,
 }
 
+/// An invalid string passed to [`ConnectOption::from_str`].
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("invalid connect option `{0}`, expected `<kind>:<value>`")]
+pub struct ParseConnectOptionError(pub String);
+
+impl ListEntry {
+    /// Creates an entry from either a name or an identifier as printed by the
+    /// CLI, which uses underscores in place of spaces.
+    pub fn new(entry: &str) -> Self {
+        let words = entry.split(|c: char| c == '_' || c.is_whitespace());
+        let words = words.filter(|word| !word.is_empty()).collect::<Vec<_>>();
+
+        Self {
+            name: words.join(" "),
+            id: words.join("_"),
+        }
+    }
+}
+
+impl fmt::Display for ListEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, expires {})",
+            self.email,
+            if self.active { "active" } else { "inactive" },
+            self.expires
+        )?;
+
+        match &self.dedicated_ip {
+            Some(servers) if !servers.is_empty() => {
+                write!(f, ", dedicated IP {}", servers.join(", "))
+            }
+            Some(_) => write!(f, ", dedicated IP"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ConnectOption {
+    /// The option to connect to the server with the hostname, such as
+    /// `de507.nordvpn.com`, which the CLI only takes by its first label.
+    pub fn server_from_hostname(hostname: &str) -> Self {
+        Self::Server(hostname.split('.').next().unwrap_or_default().to_owned())
+    }
+}
+
+/// Formats as `<kind>:<value>`, such as `country:Germany`, `group:P2P` or
+/// `country-city:Germany/Berlin`, which [`ConnectOption::from_str`] parses.
+/// A dedicated IP without a specific server is just `dedicated-ip`.
+impl fmt::Display for ConnectOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Country(country) => write!(f, "country:{}", country),
+            Self::Server(server) => write!(f, "server:{}", server),
+            Self::CountryCode(country_code) => write!(f, "country-code:{}", country_code),
+            Self::City(city) => write!(f, "city:{}", city),
+            Self::Group(group) => write!(f, "group:{}", group),
+            Self::CountryCity(country, city) => write!(f, "country-city:{}/{}", country, city),
+            Self::MeshnetPeer(peer) => write!(f, "meshnet-peer:{}", peer),
+            Self::DedicatedIp(Some(server)) => write!(f, "dedicated-ip:{}", server),
+            Self::DedicatedIp(None) => write!(f, "dedicated-ip"),
+        }
+    }
+}
+
+impl FromStr for ConnectOption {
+    type Err = ParseConnectOptionError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = || ParseConnectOptionError(text.to_owned());
+
+        if text.trim().eq_ignore_ascii_case("dedicated-ip") {
+            return Ok(Self::DedicatedIp(None));
+        }
+
+        let (kind, value) = text.split_once(':').ok_or_else(error)?;
+        let value = value.trim();
+
+        if value.is_empty() {
+            return Err(error());
+        }
+
+        Ok(match kind.trim().to_lowercase().as_str() {
+            "country" => Self::Country(value.to_owned()),
+            "server" => Self::Server(value.to_owned()),
+            "country-code" => Self::CountryCode(value.to_owned()),
+            "city" => Self::City(value.to_owned()),
+            "group" => Self::Group(value.to_owned()),
+            "country-city" => match value.split_once('/') {
+                Some((country, city)) => Self::CountryCity(country.to_owned(), city.to_owned()),
+                None => return Err(error()),
+            },
+            "meshnet-peer" => Self::MeshnetPeer(value.to_owned()),
+            "dedicated-ip" => Self::DedicatedIp(Some(value.to_owned())),
+            _ => return Err(error()),
+        })
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.uptime.num_seconds();
+
+        write!(
+            f,
+            "connected to {} ({}, {}, {}) with {} over {}, {} received, {} sent, up {}h {}m {}s",
+            self.hostname,
+            self.country,
+            self.city,
+            self.ip,
+            self.technology,
+            self.protocol,
+            self.transfer.received.get_appropriate_unit(true),
+            self.transfer.sent.get_appropriate_unit(true),
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    }
+}
+
+impl Uptime {
+    /// Splits a duration into days and smaller units.
+    pub fn from_duration(duration: Duration) -> Self {
+        let seconds = duration.num_seconds().max(0);
+
+        Self {
+            years: 0,
+            months: 0,
+            days: (seconds / 86400) as u32,
+            hours: (seconds % 86400 / 3600) as u32,
+            minutes: (seconds % 3600 / 60) as u32,
+            seconds: (seconds % 60) as u32,
+        }
+    }
+
+    /// The moment this long before `now`, going back by calendar months so
+    /// that their varying lengths are accounted for.
+    pub fn since<Tz: TimeZone>(&self, now: DateTime<Tz>) -> DateTime<Tz> {
+        let months = Months::new(self.years * 12 + self.months);
+        let rest = Duration::days(self.days.into())
+            + Duration::hours(self.hours.into())
+            + Duration::minutes(self.minutes.into())
+            + Duration::seconds(self.seconds.into());
+
+        now.clone().checked_sub_months(months).unwrap_or(now) - rest
+    }
+}
+
+impl Connected {
+    /// Fills in the details that only the status shows, if it describes the
+    /// same connection.
+    pub fn enrich(&mut self, status: &Status) {
+        if status.hostname != self.hostname {
+            return;
+        }
+
+        self.country.get_or_insert_with(|| status.country.clone());
+        self.city = Some(status.city.clone());
+        self.ip = Some(status.ip);
+        self.technology = Some(status.technology);
+        self.protocol = Some(status.protocol);
+    }
+
+    /// Whether the connection is routed through a meshnet peer rather than
+    /// a NordVPN server.
+    pub fn is_meshnet_peer(&self) -> bool {
+        is_meshnet_hostname(&self.hostname)
+    }
+}
+
+impl Status {
+    /// The uptime as of now, derived from `connected_since`.
+    pub fn current_uptime(&self) -> Duration {
+        Local::now() - self.connected_since
+    }
+
+    /// The number of the NordVPN server, or `None` if the connection is routed
+    /// through a meshnet peer.
+    pub fn server(&self) -> Option<u32> {
+        server_number(&self.hostname)
+    }
+
+    /// Whether the connection is routed through a meshnet peer rather than
+    /// a NordVPN server.
+    pub fn is_meshnet_peer(&self) -> bool {
+        is_meshnet_hostname(&self.hostname)
+    }
+
+    /// Whether the connection is to one of the dedicated IP servers of the
+    /// account, which the status output does not show by itself.
+    pub fn is_dedicated_ip(&self, account: &Account) -> bool {
+        account
+            .dedicated_ip
+            .as_ref()
+            .is_some_and(|servers| servers.contains(&self.hostname))
+    }
+}
+
+/// The number of a NordVPN server from its hostname, such as `507` for
+/// `de507.nordvpn.com`, or `None` if it is not the hostname of one.
+pub fn server_number(hostname: &str) -> Option<u32> {
+    re::SERVER_HOSTNAME
+        .captures(hostname)
+        .map(|captures| captures.name("server").unwrap().as_str().parse().unwrap())
+}
+
+/// Whether `hostname` is that of a meshnet device, such as
+/// `friend-alps.nord`.
+pub fn is_meshnet_hostname(hostname: &str) -> bool {
+    hostname.ends_with(".nord")
+}
+
+#[instrument(level = "debug", ret)]
 pub fn account() -> CliResult<Option<Account>> {
-    let (command, output, stdout) = command(["nordvpn", "account"])?;
+    parse_account(command(["nordvpn", "account"])?)
+}
 
+pub(super) fn parse_account(
+    (output, stdout): (CommandOutput, String),
+) -> CliResult<Option<Account>> {
     if stdout.contains("You are not logged in.") {
         return Ok(None);
     } else if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     let captures = match re::ACCOUNT.captures(&stdout) {
         Some(captures) => captures,
-        None => return Err(CliError::RegexError(RegexError::Account, command)),
+        None => return Err(CliError::regex(RegexError::Account, output)),
     };
 
     let account = Account {
         email: match captures.name("email") {
             Some(email) => email.as_str().to_owned(),
-            None => return Err(CliError::RegexError(RegexError::AccountEmail, command)),
+            None => return Err(CliError::regex(RegexError::AccountEmail, output)),
         },
         active: match captures.name("active") {
             Some(active) => active.as_str().to_lowercase() == "active",
-            None => return Err(CliError::RegexError(RegexError::AccountActive, command)),
+            None => return Err(CliError::regex(RegexError::AccountActive, output)),
         },
         expires: NaiveDate::parse_from_str(
             &(|| {
                 Some(format!(
                     "{}-{:02}-{}",
                     captures.name("expires_month")?.as_str(),
-                    captures.name("expires_day")?.as_str(),
+                    captures.name("expires_day")?.as_str().parse::<u32>().ok()?,
                     captures.name("expires_year")?.as_str(),
                 ))
             })()
-            .ok_or(CliError::RegexError(RegexError::AccountExpires, command))?,
+            .ok_or(CliError::regex(RegexError::AccountExpires, output))?,
             "%b-%d-%Y",
         )
         .unwrap(),
+        dedicated_ip: match captures.name("dedicated_ip") {
+            Some(active) if active.as_str().to_lowercase() == "active" => Some(
+                captures
+                    .name("dedicated_ip_servers")
+                    .map(|servers| {
+                        servers
+                            .as_str()
+                            .split(|c: char| c == ',' || c.is_whitespace())
+                            .filter(|server| !server.is_empty())
+                            .map(str::to_owned)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            ),
+            _ => None,
+        },
     };
 
     Ok(Some(account))
 }
 
-pub fn cities(country: &str) -> CliResult<Vec<String>> {
-    let (command, output, stdout) = command(["nordvpn", "cities", country])?;
+#[instrument(level = "debug", ret)]
+pub fn cities(country: &str) -> CliResult<Vec<ListEntry>> {
+    parse_cities(command(["nordvpn", "cities", country])?)
+}
 
+pub(super) fn parse_cities((output, stdout): (CommandOutput, String)) -> CliResult<Vec<ListEntry>> {
     if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     let cities = match re::parse_list(&stdout) {
-        Some(cities) => cities,
-        None => return Err(CliError::RegexError(RegexError::Cities, command)),
+        Some(cities) => cities.iter().map(|entry| ListEntry::new(entry)).collect(),
+        None => return Err(CliError::regex(RegexError::Cities, output)),
     };
 
     Ok(cities)
 }
 
+/// Connects to a server, then reads the status to fill in the details which
+/// are not printed when connecting.
+#[instrument(level = "debug", ret)]
 pub fn connect(option: Option<&ConnectOption>) -> CliResult<Connected> {
+    let mut connected = parse_connect(command(connect_args(option))?)?;
+
+    // The connection is up regardless, so a failure here is not an error.
+    if let Ok(Some(status)) = status() {
+        connected.enrich(&status);
+    }
+
+    Ok(connected)
+}
+
+/// An intermediate state reported while `nordvpn connect` is running.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectEvent {
+    /// The command has started and is choosing a server.
+    Resolving,
+    /// A server has been chosen, if the CLI named it, and the tunnel is being
+    /// established.
+    Connecting(Option<String>),
+    Connected(Connected),
+    /// The connection attempt failed, with the reason printed by the CLI.
+    Failed(String),
+}
+
+/// Like [`connect`], but reads the output of the CLI as it is printed and
+/// reports each state change to `progress` before returning the final result.
+pub fn connect_with_progress<F>(
+    option: Option<&ConnectOption>,
+    mut progress: F,
+) -> CliResult<Connected>
+where
+    F: FnMut(ConnectEvent),
+{
+    let mut run = connect_args(option).into_iter();
+    let mut command = new_command(run.next().unwrap());
+
+    command
+        .args(run)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let started = Instant::now();
+    let mut child = command.spawn().map_err(CliError::spawn)?;
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr = thread::spawn(move || {
+        let mut buffer = Vec::new();
+        stderr.read_to_end(&mut buffer).map(|_| buffer)
+    });
+
+    progress(ConnectEvent::Resolving);
+
+    let mut stdout = Vec::new();
+    let mut line = Vec::new();
+
+    // The CLI redraws a spinner with carriage returns, so both line endings
+    // delimit a message.
+    for byte in BufReader::new(child.stdout.take().unwrap()).bytes() {
+        let byte = byte?;
+        stdout.push(byte);
+
+        if byte == b'\n' || byte == b'\r' {
+            if let Some(event) = connect_event(&String::from_utf8_lossy(&line)) {
+                progress(event);
+            }
+            line.clear();
+        } else {
+            line.push(byte);
+        }
+    }
+
+    let output = Output {
+        status: child.wait()?,
+        stdout,
+        stderr: stderr.join().unwrap()?,
+    };
+    let mut result = parse_connect(command_output(&command, output, started)?);
+
+    if let Ok(connected) = &mut result {
+        if let Ok(Some(status)) = status() {
+            connected.enrich(&status);
+        }
+    }
+
+    match &result {
+        Ok(connected) => progress(ConnectEvent::Connected(connected.clone())),
+        Err(error) => progress(ConnectEvent::Failed(match error.output() {
+            Some(output) => output
+                .stdout
+                .lines()
+                .map(str::trim)
+                .rfind(|line| !line.is_empty())
+                .map_or_else(|| error.to_string(), str::to_owned),
+            None => error.to_string(),
+        })),
+    }
+
+    result
+}
+
+pub(super) fn connect_event(line: &str) -> Option<ConnectEvent> {
+    let captures = re::CONNECTING.captures(line.trim())?;
+
+    Some(ConnectEvent::Connecting(
+        captures
+            .name("hostname")
+            .map(|hostname| hostname.as_str().to_owned()),
+    ))
+}
+
+pub(super) fn connect_args(option: Option<&ConnectOption>) -> Vec<&str> {
     let mut run = vec!["nordvpn", "connect"];
 
     if let Some(option) = option {
@@ -157,52 +748,85 @@ pub fn connect(option: Option<&ConnectOption>) -> CliResult<Connected> {
                 run.push(country);
                 run.push(city);
             }
+            ConnectOption::MeshnetPeer(peer) => run.push(peer),
+            ConnectOption::DedicatedIp(server) => {
+                run.push("--group");
+                run.push("dedicated_ip");
+                if let Some(server) = server {
+                    run.push(server);
+                }
+            }
         };
     }
 
-    let (command, output, stdout) = command(run)?;
+    run
+}
 
+pub(super) fn parse_connect((output, stdout): (CommandOutput, String)) -> CliResult<Connected> {
     if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
-    let captures = match re::CONNECT.captures(&stdout) {
+    let captures = match re::CONNECT
+        .captures(&stdout)
+        .or_else(|| re::CONNECT_PEER.captures(&stdout))
+    {
         Some(captures) => captures,
-        None => return Err(CliError::RegexError(RegexError::Connect, command)),
+        None => return Err(CliError::regex(RegexError::Connect, output)),
     };
 
     let connected = (|| {
         Some(Connected {
-            country: captures.name("country")?.as_str().to_owned(),
-            server: captures.name("server")?.as_str().parse::<u32>().unwrap(),
+            country: captures
+                .name("country")
+                .map(|country| country.as_str().to_owned()),
+            server: captures
+                .name("server")
+                .map(|server| server.as_str().parse::<u32>().unwrap()),
             hostname: captures.name("hostname")?.as_str().to_owned(),
+            city: None,
+            ip: None,
+            technology: None,
+            protocol: None,
+            load: None,
         })
     })()
-    .ok_or(CliError::RegexError(RegexError::Connect, command))?;
+    .ok_or(CliError::regex(RegexError::Connect, output))?;
 
     Ok(connected)
 }
 
-pub fn countries() -> CliResult<Vec<String>> {
-    let (command, output, stdout) = command(["nordvpn", "countries"])?;
+#[instrument(level = "debug", ret)]
+pub fn countries() -> CliResult<Vec<ListEntry>> {
+    parse_countries(command(["nordvpn", "countries"])?)
+}
 
+pub(super) fn parse_countries(
+    (output, stdout): (CommandOutput, String),
+) -> CliResult<Vec<ListEntry>> {
     if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     let countries = match re::parse_list(&stdout) {
-        Some(countries) => countries,
-        None => return Err(CliError::RegexError(RegexError::Countries, command)),
+        Some(countries) => countries
+            .iter()
+            .map(|entry| ListEntry::new(entry))
+            .collect(),
+        None => return Err(CliError::regex(RegexError::Countries, output)),
     };
 
     Ok(countries)
 }
 
+#[instrument(level = "debug", ret)]
 pub fn disconnect() -> CliResult<bool> {
-    let (command, output, stdout) = command(["nordvpn", "disconnect"])?;
+    parse_disconnect(command(["nordvpn", "disconnect"])?)
+}
 
+pub(super) fn parse_disconnect((output, stdout): (CommandOutput, String)) -> CliResult<bool> {
     if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     if stdout.contains("You are not connected to NordVPN.") {
@@ -211,36 +835,42 @@ pub fn disconnect() -> CliResult<bool> {
         return Ok(true);
     }
 
-    Err(CliError::BadOutput(command))
+    Err(CliError::bad_output(output))
 }
 
-pub fn groups() -> CliResult<Vec<String>> {
-    let (command, output, stdout) = command(["nordvpn", "groups"])?;
+#[instrument(level = "debug", ret)]
+pub fn groups() -> CliResult<Vec<ListEntry>> {
+    parse_groups(command(["nordvpn", "groups"])?)
+}
 
+pub(super) fn parse_groups((output, stdout): (CommandOutput, String)) -> CliResult<Vec<ListEntry>> {
     if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     let groups = match re::parse_list(&stdout) {
-        Some(groups) => groups,
-        None => return Err(CliError::RegexError(RegexError::Groups, command)),
+        Some(groups) => groups.iter().map(|entry| ListEntry::new(entry)).collect(),
+        None => return Err(CliError::regex(RegexError::Groups, output)),
     };
 
     Ok(groups)
 }
 
+#[instrument(level = "debug", ret)]
 pub fn login() -> CliResult<Option<String>> {
-    let (command, output, stdout) = command(["nordvpn", "login"])?;
+    parse_login(command(["nordvpn", "login"])?)
+}
 
+pub(super) fn parse_login((output, stdout): (CommandOutput, String)) -> CliResult<Option<String>> {
     if stdout.contains("You are already logged in.") {
         return Ok(None);
     } else if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     let captures = match re::LOGIN.captures(&stdout) {
         Some(captures) => captures,
-        None => return Err(CliError::RegexError(RegexError::Login, command)),
+        None => return Err(CliError::regex(RegexError::Login, output)),
     };
 
     let url = captures.name("url").unwrap().as_str().to_owned();
@@ -248,18 +878,39 @@ pub fn login() -> CliResult<Option<String>> {
     Ok(Some(url))
 }
 
+/// Finishes a login with the `nordvpn://` link which the login page returns
+/// to, such as one received by a [`LoginListener`](crate::login::LoginListener).
+/// Returns `false` if there already was a session.
+#[instrument(level = "debug", skip(url), ret)]
+pub fn login_callback(url: &str) -> CliResult<bool> {
+    parse_login_callback(command(["nordvpn", "login", "--callback", url])?)
+}
+
+pub(super) fn parse_login_callback((output, stdout): (CommandOutput, String)) -> CliResult<bool> {
+    if stdout.contains("You are already logged in.") {
+        return Ok(false);
+    } else if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    Ok(true)
+}
+
+#[instrument(level = "debug", ret)]
 pub fn logout() -> CliResult<bool> {
-    let (command, output, stdout) = command(["nordvpn", "logout"])?;
+    parse_logout(command(["nordvpn", "logout"])?)
+}
 
+pub(super) fn parse_logout((output, stdout): (CommandOutput, String)) -> CliResult<bool> {
     if stdout.contains("You are not logged in.") {
         return Ok(false);
     } else if stdout.contains("You are logged out.") {
         return Ok(true);
     } else if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
-    Err(CliError::BadOutput(command))
+    Err(CliError::bad_output(output))
 }
 
 pub fn rate() -> CliResult<()> {
@@ -277,50 +928,81 @@ where
     T: AsRef<str>,
     V: IntoIterator<Item = T>,
 {
-    let values = values.into_iter().collect::<Vec<_>>();
-    let (command, output, stdout) = command(
-        ["nordvpn", "set", setting.as_ref()]
-            .into_iter()
-            .chain(values.iter().map(AsRef::as_ref)),
-    )?;
+    let values = values
+        .into_iter()
+        .map(|value| value.as_ref().to_owned())
+        .collect::<Vec<_>>();
+
+    parse_set(
+        command(set_args(setting.as_ref(), &values))?,
+        setting.as_ref(),
+        &values,
+    )
+}
 
+pub(super) fn set_args<'a>(setting: &'a str, values: &'a [String]) -> Vec<&'a str> {
+    ["nordvpn", "set", setting]
+        .into_iter()
+        .chain(values.iter().map(String::as_str))
+        .collect()
+}
+
+pub(super) fn parse_set(
+    (output, stdout): (CommandOutput, String),
+    setting: &str,
+    values: &[String],
+) -> CliResult<()> {
     if stdout.contains("The command you entered is not valid.") {
         return Err(CliError::InvalidSettingValue(
-            setting.as_ref().to_owned(),
-            values
-                .iter()
-                .map(|value| value.as_ref().to_owned())
-                .collect(),
+            setting.to_owned(),
+            values.to_vec(),
         ));
     } else if re::INVALID_SETTING.is_match(&stdout) {
-        return Err(CliError::InvalidSettingName(setting.as_ref().to_owned()));
+        return Err(CliError::InvalidSettingName(setting.to_owned()));
     } else if !output.status.success() {
-        return Err(CliError::BadOutput(command));
+        return Err(CliError::bad_output(output));
     }
 
     Ok(())
 }
 
+/// Restores every setting to its default, returning whether the account was
+/// logged out by the reset, in which case [`login`] must be run again.
+#[instrument(level = "debug", ret)]
+pub fn reset_settings() -> CliResult<bool> {
+    parse_reset_settings(command(["nordvpn", "set", "defaults"])?)
+}
+
+pub(super) fn parse_reset_settings((output, stdout): (CommandOutput, String)) -> CliResult<bool> {
+    if !output.status.success() {
+        return Err(CliError::failed(output));
+    } else if !stdout.contains("restored to defaults") {
+        return Err(CliError::bad_output(output));
+    }
+
+    Ok(stdout.contains("logged out"))
+}
+
+#[instrument(level = "debug", ret)]
 pub fn settings() -> CliResult<Settings> {
-    let (command, output, stdout) = command(["nordvpn", "settings"])?;
+    parse_settings(command(["nordvpn", "settings"])?)
+}
 
+pub(super) fn parse_settings((output, stdout): (CommandOutput, String)) -> CliResult<Settings> {
     if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     let captures = match re::SETTINGS.captures(&stdout) {
         Some(captures) => captures,
-        None => return Err(CliError::RegexError(RegexError::Status, command)),
+        None => return Err(CliError::regex(RegexError::Status, output)),
     };
 
     let settings = Settings {
         technology: match captures.name("technology") {
             Some(technology) => technology.as_str().parse::<Technology>().unwrap(),
             None => {
-                return Err(CliError::RegexError(
-                    RegexError::SettingsTechnology,
-                    command,
-                ));
+                return Err(CliError::regex(RegexError::SettingsTechnology, output));
             }
         },
         protocol: captures
@@ -328,45 +1010,45 @@ pub fn settings() -> CliResult<Settings> {
             .map(|protocol| protocol.as_str().parse::<Protocol>().unwrap()),
         firewall: match captures.name("firewall") {
             Some(firewall) => firewall.as_str().to_lowercase() == "enabled",
-            None => return Err(CliError::RegexError(RegexError::SettingsFirewall, command)),
+            None => return Err(CliError::regex(RegexError::SettingsFirewall, output)),
         },
         killswitch: match captures.name("killswitch") {
             Some(killswitch) => killswitch.as_str().to_lowercase() == "enabled",
             None => {
-                return Err(CliError::RegexError(
-                    RegexError::SettingsKillswitch,
-                    command,
-                ));
+                return Err(CliError::regex(RegexError::SettingsKillswitch, output));
             }
         },
         cybersec: match captures.name("cybersec") {
             Some(cybersec) => cybersec.as_str().to_lowercase() == "enabled",
-            None => return Err(CliError::RegexError(RegexError::SettingsCybersec, command)),
+            None => return Err(CliError::regex(RegexError::SettingsCybersec, output)),
         },
         obfuscate: captures
             .name("obfuscate")
             .map(|obfuscate| obfuscate.as_str().to_lowercase() == "enabled"),
         notify: match captures.name("notify") {
             Some(notify) => notify.as_str().to_lowercase() == "enabled",
-            None => return Err(CliError::RegexError(RegexError::SettingsNotify, command)),
+            None => return Err(CliError::regex(RegexError::SettingsNotify, output)),
         },
         autoconnect: match captures.name("autoconnect") {
             Some(autoconnect) => autoconnect.as_str().to_lowercase() == "enabled",
             None => {
-                return Err(CliError::RegexError(
-                    RegexError::SettingsAutoconnect,
-                    command,
-                ));
+                return Err(CliError::regex(RegexError::SettingsAutoconnect, output));
             }
         },
         ipv6: match captures.name("ipv6") {
             Some(ipv6) => ipv6.as_str().to_lowercase() == "enabled",
-            None => return Err(CliError::RegexError(RegexError::SettingsIpv6, command)),
+            None => return Err(CliError::regex(RegexError::SettingsIpv6, output)),
         },
+        post_quantum: captures
+            .name("post_quantum")
+            .map(|post_quantum| post_quantum.as_str().to_lowercase() == "enabled"),
+        lan_discovery: captures
+            .name("lan_discovery")
+            .map(|lan_discovery| lan_discovery.as_str().to_lowercase() == "enabled"),
         dns: match captures.name("dns_disabled") {
             Some(disabled) => {
                 if disabled.as_str().to_lowercase() != "disabled" {
-                    return Err(CliError::RegexError(RegexError::SettingsDns, command));
+                    return Err(CliError::regex(RegexError::SettingsDns, output));
                 }
 
                 None
@@ -386,59 +1068,63 @@ pub fn settings() -> CliResult<Settings> {
 
     if settings.technology == Technology::OpenVpn {
         if settings.protocol.is_none() {
-            return Err(CliError::RegexError(RegexError::SettingsProtocol, command));
+            return Err(CliError::regex(RegexError::SettingsProtocol, output));
         }
 
         if settings.obfuscate.is_none() {
-            return Err(CliError::RegexError(RegexError::SettingsObfuscate, command));
+            return Err(CliError::regex(RegexError::SettingsObfuscate, output));
         }
     }
 
     if settings.dns.is_some() && settings.dns.as_ref().unwrap().is_empty() {
-        return Err(CliError::RegexError(RegexError::SettingsDns, command));
+        return Err(CliError::regex(RegexError::SettingsDns, output));
     }
 
     Ok(settings)
 }
 
+#[instrument(level = "debug", ret)]
 pub fn status() -> CliResult<Option<Status>> {
-    let (command, output, stdout) = command(["nordvpn", "status"])?;
+    parse_status(command(["nordvpn", "status"])?)
+}
 
+pub(super) fn parse_status((output, stdout): (CommandOutput, String)) -> CliResult<Option<Status>> {
     if stdout.contains("Disconnected") {
         return Ok(None);
     } else if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     let captures = match re::STATUS.captures(&stdout) {
         Some(captures) => captures,
-        None => return Err(CliError::RegexError(RegexError::Status, command)),
+        None => return Err(CliError::regex(RegexError::Status, output)),
     };
 
-    let status = Status {
+    let now = Local::now();
+    let mut status = Status {
         hostname: match captures.name("hostname") {
             Some(hostname) => hostname.as_str().to_owned(),
-            None => return Err(CliError::RegexError(RegexError::StatusHostname, command)),
+            None => return Err(CliError::regex(RegexError::StatusHostname, output)),
         },
         country: match captures.name("country") {
             Some(country) => country.as_str().to_owned(),
-            None => return Err(CliError::RegexError(RegexError::StatusCountry, command)),
+            None => return Err(CliError::regex(RegexError::StatusCountry, output)),
         },
         city: match captures.name("city") {
             Some(city) => city.as_str().to_owned(),
-            None => return Err(CliError::RegexError(RegexError::StatusCity, command)),
+            None => return Err(CliError::regex(RegexError::StatusCity, output)),
         },
         ip: match captures.name("ip") {
             Some(ip) => ip.as_str().parse::<IpAddr>().unwrap(),
-            None => return Err(CliError::RegexError(RegexError::StatusIp, command)),
+            None => return Err(CliError::regex(RegexError::StatusIp, output)),
         },
         technology: match captures.name("technology") {
             Some(technology) => technology.as_str().parse::<Technology>().unwrap(),
-            None => return Err(CliError::RegexError(RegexError::StatusTechnology, command)),
+            None => return Err(CliError::regex(RegexError::StatusTechnology, output)),
         },
         protocol: match captures.name("protocol") {
             Some(protocol) => protocol.as_str().parse::<Protocol>().unwrap(),
-            None => return Err(CliError::RegexError(RegexError::StatusProtocol, command)),
+            None => return Err(CliError::regex(RegexError::StatusProtocol, output)),
         },
         transfer: (|| {
             Some(Transfer {
@@ -454,57 +1140,51 @@ pub fn status() -> CliResult<Option<Status>> {
                     .unwrap(),
             })
         })()
-        .ok_or(CliError::RegexError(RegexError::StatusTransfer, command))?,
-        uptime: {
-            let years = captures
-                .name("uptime_years")
-                .map_or(0_f64, |value| value.as_str().parse::<f64>().unwrap());
-            let months = captures
-                .name("uptime_months")
-                .map_or(0_f64, |value| value.as_str().parse::<f64>().unwrap());
-            let days = captures
-                .name("uptime_days")
-                .map_or(0_f64, |value| value.as_str().parse::<f64>().unwrap());
-            let hours = captures
-                .name("uptime_hours")
-                .map_or(0_f64, |value| value.as_str().parse::<f64>().unwrap());
-            let minutes = captures
-                .name("uptime_minutes")
-                .map_or(0_f64, |value| value.as_str().parse::<f64>().unwrap());
-            let seconds = captures
-                .name("uptime_seconds")
-                .map_or(0_f64, |value| value.as_str().parse::<f64>().unwrap());
-
-            Duration::milliseconds(
-                (100_f64
-                    * (seconds
-                        + minutes * 60_f64
-                        + hours * 3600_f64
-                        + days * 86400_f64
-                        + months * (2.628_f64 * 10_f64.powi(6))
-                        + years * (3.154_f64 * 10_f64.powi(7))))
-                .round() as i64,
-            )
+        .ok_or(CliError::regex(RegexError::StatusTransfer, output))?,
+        raw_uptime: {
+            let part = |name| {
+                captures
+                    .name(name)
+                    .map_or(0, |value| value.as_str().parse::<u32>().unwrap())
+            };
+
+            Uptime {
+                years: part("uptime_years"),
+                months: part("uptime_months"),
+                days: part("uptime_days"),
+                hours: part("uptime_hours"),
+                minutes: part("uptime_minutes"),
+                seconds: part("uptime_seconds"),
+            }
         },
+        uptime: Duration::zero(),
+        connected_since: now,
     };
 
+    status.connected_since = status.raw_uptime.since(now);
+    status.uptime = now - status.connected_since;
+
     Ok(Some(status))
 }
 
-pub fn whitelist() -> CliResult<()> {
-    todo!();
+#[deprecated(note = "renamed to `allowlist::list`")]
+pub fn whitelist() -> CliResult<super::allowlist::Allowlist> {
+    super::allowlist::list()
 }
 
+#[instrument(level = "debug", ret)]
 pub fn version() -> CliResult<Version> {
-    let (command, output, stdout) = command(["nordvpn", "version"])?;
+    parse_version(command(["nordvpn", "version"])?)
+}
 
+pub(super) fn parse_version((output, stdout): (CommandOutput, String)) -> CliResult<Version> {
     if !output.status.success() {
-        return Err(CliError::FailedCommand(command));
+        return Err(CliError::failed(output));
     }
 
     let captures = match re::VERSION.captures(&stdout) {
         Some(captures) => captures,
-        None => return Err(CliError::RegexError(RegexError::Version, command)),
+        None => return Err(CliError::regex(RegexError::Version, output)),
     };
 
     let version = captures
@@ -528,6 +1208,11 @@ pub struct Settings {
     pub notify: bool,
     pub autoconnect: bool,
     pub ipv6: bool,
+    /// `None` if the installed CLI does not have the setting.
+    pub post_quantum: Option<bool>,
+    /// Whether private subnets bypass the VPN, or `None` if the installed
+    /// CLI does not have the setting.
+    pub lan_discovery: Option<bool>,
     pub dns: Option<HashSet<IpAddr>>,
 }
 
@@ -547,6 +1232,12 @@ impl Settings {
         self.set_notify(self.notify)?;
         self.set_autoconnect(self.autoconnect)?;
         self.set_ipv6(self.ipv6)?;
+        if let Some(post_quantum) = self.post_quantum {
+            self.set_post_quantum(post_quantum)?;
+        }
+        if let Some(lan_discovery) = self.lan_discovery {
+            self.set_lan_discovery(lan_discovery)?;
+        }
         self.set_dns(self.dns.clone())?;
 
         Ok(())
@@ -577,7 +1268,10 @@ impl Settings {
     }
 
     pub fn set_cybersec(&mut self, enabled: bool) -> CliResult<&mut Self> {
-        set("cybersec", [enabled.to_string().as_str()])?;
+        set(
+            Compat::get()?.cybersec_setting(),
+            [enabled.to_string().as_str()],
+        )?;
         self.dns = None;
         self.cybersec = enabled;
         Ok(self)
@@ -608,6 +1302,20 @@ impl Settings {
         Ok(self)
     }
 
+    pub fn set_post_quantum(&mut self, enabled: bool) -> CliResult<&mut Self> {
+        Compat::get()?.require(Capability::PostQuantum)?;
+        set("post-quantum", [enabled.to_string().as_str()])?;
+        self.post_quantum = Some(enabled);
+        Ok(self)
+    }
+
+    pub fn set_lan_discovery(&mut self, enabled: bool) -> CliResult<&mut Self> {
+        Compat::get()?.require(Capability::LanDiscovery)?;
+        set("lan-discovery", [enabled.to_string().as_str()])?;
+        self.lan_discovery = Some(enabled);
+        Ok(self)
+    }
+
     pub fn set_dns<V>(&mut self, addresses: Option<V>) -> CliResult<&mut Self>
     where
         V: IntoIterator<Item = IpAddr>,
@@ -631,18 +1339,137 @@ impl Settings {
     }
 }
 
-fn command<S, I>(run: I) -> CliResult<(Command, Output, String)>
+/// Overrides environment variables for every command spawned by this module,
+/// replacing any previous overrides, where a value of `None` removes the
+/// variable. These are applied after the locale is forced to `C`, so they take
+/// precedence, but the parsers only understand the English messages.
+pub fn set_command_env<K, V, I>(vars: I)
+where
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+    I: IntoIterator<Item = (K, Option<V>)>,
+{
+    *COMMAND_ENV.write().unwrap() = vars
+        .into_iter()
+        .map(|(key, value)| {
+            (
+                key.as_ref().to_owned(),
+                value.map(|value| value.as_ref().to_owned()),
+            )
+        })
+        .collect();
+}
+
+pub(super) fn new_command<S: AsRef<OsStr>>(program: S) -> Command {
+    let program = match program.as_ref() {
+        program if program == "nordvpn" => OsStr::new(Installation::get().program()),
+        program => program,
+    };
+    // The output is only parsed correctly when the CLI prints in English.
+    let mut env: EnvOverrides = vec![
+        ("LANGUAGE".into(), None),
+        ("LC_ALL".into(), Some("C".into())),
+        ("LANG".into(), Some("C".into())),
+    ];
+
+    env.extend(COMMAND_ENV.read().unwrap().iter().cloned());
+
+    if !*IN_FLATPAK {
+        let mut command = Command::new(program);
+
+        for (key, value) in env {
+            match value {
+                Some(value) => command.env(key, value),
+                None => command.env_remove(key),
+            };
+        }
+
+        return command;
+    }
+
+    // The variables are for the CLI on the host, not for `flatpak-spawn`.
+    let mut command = Command::new("flatpak-spawn");
+
+    command.arg("--host");
+
+    for (key, value) in env {
+        let mut arg = OsString::from(match value {
+            Some(_) => "--env=",
+            None => "--unset-env=",
+        });
+
+        arg.push(key);
+        if let Some(value) = value {
+            arg.push("=");
+            arg.push(value);
+        }
+        command.arg(arg);
+    }
+
+    command.arg(program);
+    command
+}
+
+pub(super) fn command<S, I>(run: I) -> CliResult<(CommandOutput, String)>
 where
     S: AsRef<OsStr>,
     I: IntoIterator<Item = S>,
 {
     let mut run = run.into_iter();
-    let mut command = Command::new(run.next().unwrap());
+    let mut command = new_command(run.next().unwrap());
 
     command.args(run);
 
-    let output = command.output()?;
-    let stdout = String::from_utf8(output.stdout.clone())?;
+    let started = Instant::now();
+    let output = command.output().map_err(CliError::spawn)?;
+
+    command_output(&command, output, started)
+}
+
+/// Replaces the value of each option in [`SECRET_OPTIONS`] with
+/// [`REDACTED`], so that the arguments can be logged and shown in errors.
+pub(super) fn redact_args<I: IntoIterator<Item = String>>(args: I) -> Vec<String> {
+    let mut secret = false;
+
+    args.into_iter()
+        .map(|arg| {
+            let redacted = match secret {
+                true => REDACTED.to_owned(),
+                false => arg.clone(),
+            };
+
+            secret = SECRET_OPTIONS.contains(&arg.as_str());
+            redacted
+        })
+        .collect()
+}
+
+/// Captures the output of `command`, which was started at `started`, and
+/// logs how it finished.
+pub(super) fn command_output(
+    command: &Command,
+    output: Output,
+    started: Instant,
+) -> CliResult<(CommandOutput, String)> {
+    let stdout = String::from_utf8(output.stdout)?;
+    let output = CommandOutput {
+        args: redact_args(
+            std::iter::once(command.get_program())
+                .chain(command.get_args())
+                .map(|arg| arg.to_string_lossy().into_owned()),
+        ),
+        status: output.status,
+        stdout: stdout.clone(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    };
+
+    debug!(
+        command = %output.args.join(" "),
+        status = %output.status,
+        elapsed = ?started.elapsed(),
+        "ran command"
+    );
+    trace!(stdout = %output.stdout, stderr = %output.stderr, "command output");
 
-    Ok((command, output, stdout))
+    Ok((output, stdout))
 }
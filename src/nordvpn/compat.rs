@@ -0,0 +1,135 @@
+//! Differences between releases of the NordVPN CLI.
+//!
+//! Commands and settings have been renamed and whole features added over
+//! time, so the installed version is probed once with `nordvpn version` and
+//! the names used by the wrappers are chosen to match it.
+
+use super::cli::{version, CliError, CliResult};
+use once_cell::sync::OnceCell;
+use semver::Version;
+use strum::{self, IntoEnumIterator};
+
+static COMPAT: OnceCell<Compat> = OnceCell::new();
+
+/// A feature of the CLI which is not available in every supported version.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, strum::Display, strum::EnumIter)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Capability {
+    /// The `meshnet` subcommands.
+    Meshnet,
+    /// `whitelist` was renamed to `allowlist`.
+    Allowlist,
+    /// The `cybersec` setting was renamed to `threatprotectionlite`.
+    ThreatProtectionLite,
+    /// The `fileshare` subcommands.
+    Fileshare,
+    /// The `post-quantum` setting.
+    PostQuantum,
+    /// The `lan-discovery` setting.
+    LanDiscovery,
+}
+
+impl Capability {
+    /// The first version of the CLI with this capability.
+    pub fn since(&self) -> Version {
+        match self {
+            Self::Meshnet => Version::new(3, 14, 0),
+            Self::Allowlist | Self::ThreatProtectionLite | Self::Fileshare | Self::LanDiscovery => {
+                Version::new(3, 16, 0)
+            }
+            Self::PostQuantum => Version::new(3, 19, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compat {
+    version: Version,
+}
+
+impl Compat {
+    /// The compatibility information for the installed CLI, running
+    /// `nordvpn version` the first time this is called.
+    pub fn get() -> CliResult<&'static Self> {
+        COMPAT.get_or_try_init(|| Ok(Self::for_version(version()?)))
+    }
+
+    pub fn for_version(version: Version) -> Self {
+        Self { version }
+    }
+
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.version >= capability.since()
+    }
+
+    /// Fails with [`CliError::UnsupportedByCliVersion`] if the installed
+    /// version lacks the capability.
+    pub fn require(&self, capability: Capability) -> CliResult<()> {
+        if self.supports(capability) {
+            Ok(())
+        } else {
+            Err(CliError::UnsupportedByCliVersion(
+                capability,
+                self.version.clone(),
+            ))
+        }
+    }
+
+    pub fn capabilities(&self) -> Vec<Capability> {
+        Capability::iter()
+            .filter(|capability| self.supports(*capability))
+            .collect()
+    }
+
+    /// The name of the command which manages ports and subnets that bypass
+    /// the VPN.
+    pub fn allowlist_command(&self) -> &'static str {
+        if self.supports(Capability::Allowlist) {
+            "allowlist"
+        } else {
+            "whitelist"
+        }
+    }
+
+    /// The name of the setting for DNS based ad and malware blocking.
+    pub fn cybersec_setting(&self) -> &'static str {
+        if self.supports(Capability::ThreatProtectionLite) {
+            "threatprotectionlite"
+        } else {
+            "cybersec"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capability, Compat};
+    use crate::nordvpn::CliError;
+    use semver::Version;
+
+    #[test]
+    fn test_compat() {
+        let old = Compat::for_version(Version::new(3, 12, 0));
+        assert!(old.capabilities().is_empty());
+        assert_eq!(old.allowlist_command(), "whitelist");
+        assert_eq!(old.cybersec_setting(), "cybersec");
+
+        let new = Compat::for_version(Version::new(3, 16, 2));
+        assert!(new.supports(Capability::Meshnet));
+        assert!(new.supports(Capability::Fileshare));
+        assert!(new.supports(Capability::LanDiscovery));
+        assert_eq!(new.allowlist_command(), "allowlist");
+        assert_eq!(new.cybersec_setting(), "threatprotectionlite");
+        assert!(matches!(
+            new.require(Capability::PostQuantum),
+            Err(CliError::UnsupportedByCliVersion(
+                Capability::PostQuantum,
+                _
+            ))
+        ));
+    }
+}
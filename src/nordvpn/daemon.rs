@@ -0,0 +1,170 @@
+//! A backend which queries `nordvpnd` directly over its gRPC socket instead of
+//! spawning the `nordvpn` binary, so frequent calls such as status polling
+//! neither pay for a process per call nor depend on parsing human output.
+//!
+//! Only the messages this backend needs are declared here, mirroring the
+//! `pb.Daemon` service definitions in the NordVPN Linux client. Everything
+//! the daemon is not asked for directly falls back to the CLI.
+
+use super::backend::NordVpnBackend;
+use super::cli::{self, CliError, CliResult, CommandOutput};
+use super::cli::{Protocol, Status, Technology, Transfer, Uptime};
+use super::install::Installation;
+use byte_unit::Byte;
+use chrono::{Duration, Local};
+use hyper_util::rt::TokioIo;
+use std::ffi::OsString;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tokio::net::UnixStream;
+use tokio::runtime::{Builder, Runtime};
+use tonic::codec::ProstCodec;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tower::service_fn;
+
+pub use super::install::{SNAP_SOCKET_PATH, SOCKET_PATH};
+
+pub struct DaemonBackend {
+    runtime: Runtime,
+    channel: Channel,
+}
+
+impl DaemonBackend {
+    /// Connects to the socket of the daemon of the installed CLI.
+    pub fn new() -> CliResult<Self> {
+        Self::with_socket(Installation::get().socket_path())
+    }
+
+    pub fn with_socket<P: AsRef<Path>>(socket: P) -> CliResult<Self> {
+        let socket = PathBuf::from(socket.as_ref());
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        let channel = runtime
+            .block_on(
+                // The URI is required by the endpoint but never resolved,
+                // since the connector always dials the Unix socket.
+                Endpoint::from_static("http://[::]:50051").connect_with_connector(service_fn(
+                    move |_: Uri| {
+                        let socket = socket.clone();
+                        async move {
+                            Ok::<_, std::io::Error>(TokioIo::new(
+                                UnixStream::connect(socket).await?,
+                            ))
+                        }
+                    },
+                )),
+            )
+            .map_err(|error| CliError::DaemonTransport(error.to_string()))?;
+
+        Ok(Self { runtime, channel })
+    }
+
+    fn unary<Q, R>(&self, method: &'static str, request: Q) -> CliResult<R>
+    where
+        Q: prost::Message + Send + Sync + 'static,
+        R: prost::Message + Default + Send + Sync + 'static,
+    {
+        let mut client = tonic::client::Grpc::new(self.channel.clone());
+
+        self.runtime.block_on(async move {
+            client
+                .ready()
+                .await
+                .map_err(|error| CliError::DaemonTransport(error.to_string()))?;
+
+            client
+                .unary(
+                    tonic::Request::new(request),
+                    method.parse().unwrap(),
+                    ProstCodec::default(),
+                )
+                .await
+                .map(tonic::Response::into_inner)
+                .map_err(|status| CliError::DaemonTransport(status.message().to_owned()))
+        })
+    }
+}
+
+impl NordVpnBackend for DaemonBackend {
+    fn command(&self, run: Vec<OsString>) -> CliResult<(CommandOutput, String)> {
+        cli::command(run)
+    }
+
+    fn status(&self) -> CliResult<Option<Status>> {
+        let response: pb::StatusResponse = self.unary("/pb.Daemon/Status", pb::Empty {})?;
+
+        if !response.state.eq_ignore_ascii_case("connected") {
+            return Ok(None);
+        }
+
+        let uptime = Duration::nanoseconds(response.uptime.max(0));
+
+        Ok(Some(Status {
+            hostname: response.hostname,
+            country: response.country,
+            city: response.city,
+            ip: response
+                .ip
+                .parse::<IpAddr>()
+                .map_err(|error| CliError::DaemonTransport(error.to_string()))?,
+            technology: match response.technology {
+                2 => Technology::NordLynx,
+                _ => Technology::OpenVpn,
+            },
+            protocol: match response.protocol {
+                2 => Protocol::Tcp,
+                _ => Protocol::Udp,
+            },
+            transfer: Transfer {
+                received: Byte::from_bytes(response.download as u128),
+                sent: Byte::from_bytes(response.upload as u128),
+            },
+            uptime,
+            raw_uptime: Uptime::from_duration(uptime),
+            connected_since: Local::now() - uptime,
+        }))
+    }
+}
+
+mod pb {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Empty {}
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct StatusResponse {
+        #[prost(string, tag = "1")]
+        pub state: String,
+        #[prost(int32, tag = "2")]
+        pub technology: i32,
+        #[prost(int32, tag = "3")]
+        pub protocol: i32,
+        #[prost(string, tag = "4")]
+        pub ip: String,
+        #[prost(string, tag = "5")]
+        pub hostname: String,
+        #[prost(string, tag = "6")]
+        pub country: String,
+        #[prost(string, tag = "7")]
+        pub city: String,
+        #[prost(uint64, tag = "8")]
+        pub download: u64,
+        #[prost(uint64, tag = "9")]
+        pub upload: u64,
+        #[prost(int64, tag = "10")]
+        pub uptime: i64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::nordvpn::NordVpnBackend;
+    use tracing::info;
+
+    #[test]
+    fn test_daemon_status() {
+        crate::logging::init_test();
+
+        let backend = super::DaemonBackend::new().unwrap();
+        let status = backend.status().unwrap();
+        info!("Status: {:#?}", status);
+    }
+}
@@ -0,0 +1,217 @@
+use super::cli::{command, CliError, CliResult, CommandOutput};
+use super::meshnet::MESHNET_DISABLED;
+use super::re::{self, RegexError};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use strum;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transfers {
+    pub incoming: Vec<FileTransfer>,
+    pub outgoing: Vec<FileTransfer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileTransfer {
+    pub id: String,
+    pub peer: String,
+    pub files: Option<u32>,
+    pub status: TransferStatus,
+    pub transferred: Option<String>,
+    pub created: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum TransferStatus {
+    #[strum(to_string = "requested", serialize = "waiting", serialize = "pending")]
+    Requested,
+    #[strum(
+        to_string = "in progress",
+        serialize = "ongoing",
+        serialize = "downloading",
+        serialize = "uploading"
+    )]
+    InProgress,
+    #[strum(to_string = "completed", serialize = "finished", serialize = "success")]
+    Completed,
+    #[strum(to_string = "canceled", serialize = "cancelled")]
+    Canceled,
+    #[strum(to_string = "failed", serialize = "error")]
+    Failed,
+    #[strum(default)]
+    Other(String),
+}
+
+/// Sends files or directories to a meshnet peer in the background, returning
+/// the identifier of the new transfer.
+pub fn send<P, I>(peer: &str, paths: I) -> CliResult<String>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = P>,
+{
+    parse_send(command(send_args(peer, paths))?, peer)
+}
+
+pub(super) fn send_args<P, I>(peer: &str, paths: I) -> Vec<OsString>
+where
+    P: AsRef<Path>,
+    I: IntoIterator<Item = P>,
+{
+    ["nordvpn", "fileshare", "send", "--background", peer]
+        .into_iter()
+        .map(OsString::from)
+        .chain(paths.into_iter().map(|path| path.as_ref().into()))
+        .collect()
+}
+
+pub(super) fn parse_send(
+    (output, stdout): (CommandOutput, String),
+    peer: &str,
+) -> CliResult<String> {
+    check_output(&stdout)?;
+
+    if stdout.contains("is unknown") || stdout.contains("not found") {
+        return Err(CliError::UnknownPeer(peer.to_owned()));
+    } else if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    let captures = match re::FILESHARE_TRANSFER_ID.captures(&stdout) {
+        Some(captures) => captures,
+        None => return Err(CliError::regex(RegexError::FileshareSend, output)),
+    };
+
+    Ok(captures.name("id").unwrap().as_str().to_owned())
+}
+
+pub fn transfers() -> CliResult<Transfers> {
+    parse_transfers(command(["nordvpn", "fileshare", "list"])?)
+}
+
+pub(super) fn parse_transfers((output, stdout): (CommandOutput, String)) -> CliResult<Transfers> {
+    check_output(&stdout)?;
+
+    if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    let transfers = (|| {
+        let mut transfers = Transfers::default();
+
+        for (section, text) in re::parse_sections(&stdout) {
+            let list = if section.starts_with("incoming") {
+                &mut transfers.incoming
+            } else if section.starts_with("outgoing") {
+                &mut transfers.outgoing
+            } else {
+                continue;
+            };
+
+            for row in re::parse_table(text) {
+                list.push(parse_transfer(&row)?);
+            }
+        }
+
+        Ok(transfers)
+    })()
+    .map_err(|error| CliError::regex(error, output))?;
+
+    Ok(transfers)
+}
+
+/// Accepts an incoming transfer in the background, optionally downloading
+/// the files into `destination` instead of the default download directory.
+pub fn accept(id: &str, destination: Option<&Path>) -> CliResult<()> {
+    transfer_command(id, accept_args(id, destination))
+}
+
+pub(super) fn accept_args(id: &str, destination: Option<&Path>) -> Vec<OsString> {
+    let mut run = ["nordvpn", "fileshare", "accept", "--background"]
+        .into_iter()
+        .map(OsString::from)
+        .collect::<Vec<_>>();
+
+    if let Some(destination) = destination {
+        run.push("--path".into());
+        run.push(destination.into());
+    }
+
+    run.push(id.into());
+
+    run
+}
+
+pub fn cancel(id: &str) -> CliResult<()> {
+    transfer_command(id, ["nordvpn", "fileshare", "cancel", id])
+}
+
+fn transfer_command<S, I>(id: &str, run: I) -> CliResult<()>
+where
+    S: AsRef<OsStr>,
+    I: IntoIterator<Item = S>,
+{
+    parse_transfer_command(command(run)?, id)
+}
+
+pub(super) fn parse_transfer_command(
+    (output, stdout): (CommandOutput, String),
+    id: &str,
+) -> CliResult<()> {
+    check_output(&stdout)?;
+
+    if stdout.contains("not found") || stdout.contains("doesn't exist") {
+        return Err(CliError::UnknownTransfer(id.to_owned()));
+    } else if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    Ok(())
+}
+
+fn check_output(stdout: &str) -> CliResult<()> {
+    if stdout.contains(MESHNET_DISABLED) {
+        return Err(CliError::MeshnetDisabled);
+    } else if stdout.contains("Fileshare is not running") {
+        return Err(CliError::FileshareNotRunning);
+    }
+
+    Ok(())
+}
+
+fn parse_transfer(row: &HashMap<String, &str>) -> Result<FileTransfer, RegexError> {
+    Ok(FileTransfer {
+        id: match row.get("id") {
+            Some(id) => id.to_string(),
+            None => return Err(RegexError::FileshareTransferId),
+        },
+        peer: match row.get("peer") {
+            Some(peer) => peer.to_string(),
+            None => return Err(RegexError::FileshareTransferPeer),
+        },
+        files: row.get("files").and_then(|files| files.parse::<u32>().ok()),
+        status: match row.get("status") {
+            Some(status) => status.parse::<TransferStatus>().unwrap(),
+            None => return Err(RegexError::FileshareTransferStatus),
+        },
+        transferred: row
+            .get("transferred")
+            .or_else(|| row.get("size"))
+            .map(|transferred| transferred.to_string()),
+        created: row.get("created").map(|created| created.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::info;
+
+    #[test]
+    fn test_fileshare() {
+        crate::logging::init_test();
+
+        let transfers = super::transfers().unwrap();
+        info!("Transfers: {:#?}", transfers);
+    }
+}
@@ -0,0 +1,239 @@
+//! Outputs of the CLI captured from each version which it has been tested
+//! with, so that every parser is checked against all of them and a change to
+//! the format is noticed here rather than by users as a
+//! [`BadOutput`](super::CliError::BadOutput) or a
+//! [`RegexError`](super::CliError::RegexError).
+//!
+//! Each version has a directory under `fixtures`, such as `fixtures/3.16.0`,
+//! with the standard output of each command in a file named after its
+//! arguments joined by underscores, such as `meshnet_peer_list.txt`. Other
+//! outputs of the same command have a label after a dot, such as
+//! `status.disconnected.txt`, which is passed to the check to decide what it
+//! expects. They are all of one account, which is connected to
+//! `de507.nordvpn.com` in Berlin unless labelled otherwise.
+//!
+//! To add a version, capture the outputs of the commands in [`FIXTURES`] from
+//! it, replacing the email address, keys and addresses with those used by the
+//! others.
+
+use super::{CliResult, MockBackend, NordVpnBackend, Status, Technology};
+use semver::Version;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// The directory of the corpus, which does not depend on where the tests are
+/// run from.
+const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/nordvpn/fixtures");
+
+struct Fixture {
+    /// The name of the files, which is the arguments joined by underscores.
+    command: &'static str,
+    args: &'static [&'static str],
+    /// Checks the parsed output of the file with the version and label.
+    check: fn(&MockBackend, &Version, Option<&str>) -> CliResult<()>,
+}
+
+const FIXTURES: &[Fixture] = &[
+    Fixture {
+        command: "account",
+        args: &["account"],
+        check: |backend, _, label| {
+            let account = backend.account()?;
+
+            if label == Some("logged_out") {
+                assert_eq!(account, None);
+            } else {
+                let account = account.expect("the account is logged in");
+
+                assert_eq!(account.email, "user@example.com");
+                assert!(account.active);
+            }
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "cities",
+        args: &["cities", "Germany"],
+        check: |backend, _, _| {
+            assert!(backend
+                .cities("Germany")?
+                .iter()
+                .any(|city| city.id == "Berlin"));
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "connect",
+        args: &["connect"],
+        check: |backend, _, label| {
+            let connected = backend.connect(None)?;
+
+            if label == Some("meshnet") {
+                assert_eq!(connected.hostname, "friend-alps.nord");
+                assert_eq!(connected.country, None);
+                assert!(connected.is_meshnet_peer());
+            } else {
+                assert_eq!(connected.hostname, "de507.nordvpn.com");
+                assert_eq!(connected.country.as_deref(), Some("Germany"));
+                assert!(!connected.is_meshnet_peer());
+            }
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "countries",
+        args: &["countries"],
+        check: |backend, _, _| {
+            let countries = backend.countries()?;
+
+            assert!(countries.iter().any(|country| country.id == "Germany"));
+            assert!(countries.iter().all(|country| !country.id.contains(' ')));
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "disconnect",
+        args: &["disconnect"],
+        check: |backend, _, _| {
+            assert!(backend.disconnect()?);
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "fileshare_list",
+        args: &["fileshare", "list"],
+        check: |backend, _, _| {
+            let transfers = backend.fileshare_transfers()?;
+
+            assert!(transfers
+                .incoming
+                .iter()
+                .chain(&transfers.outgoing)
+                .all(|transfer| transfer.peer.ends_with(".nord")));
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "groups",
+        args: &["groups"],
+        check: |backend, _, _| {
+            assert!(backend.groups()?.iter().any(|group| group.id == "P2P"));
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "meshnet_invite_list",
+        args: &["meshnet", "invite", "list"],
+        check: |backend, _, _| {
+            let invites = backend.meshnet_invites()?;
+
+            assert!(invites
+                .sent
+                .iter()
+                .chain(&invites.received)
+                .all(|email| email == "friend@example.com"));
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "meshnet_peer_list",
+        args: &["meshnet", "peer", "list"],
+        check: |backend, _, _| {
+            let peers = backend.meshnet_peers()?;
+
+            assert_eq!(peers.this_device.hostname, "user-everest.nord");
+            assert!(!peers.external.is_empty());
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "settings",
+        args: &["settings"],
+        check: |backend, _, _| {
+            let settings = backend.settings()?;
+
+            if settings.technology == Technology::NordLynx {
+                assert_eq!(settings.protocol, None);
+            }
+            backend.allowlist()?;
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "status",
+        args: &["status"],
+        check: |backend, _, label| {
+            let status = backend.status()?;
+
+            if label == Some("disconnected") {
+                assert_eq!(status, None);
+            } else {
+                let Status { hostname, city, .. } = status.expect("the status is connected");
+
+                assert_eq!(hostname, "de507.nordvpn.com");
+                assert_eq!(city, "Berlin");
+            }
+            Ok(())
+        },
+    },
+    Fixture {
+        command: "version",
+        args: &["version"],
+        check: |backend, version, _| {
+            assert_eq!(&backend.version()?, version);
+            Ok(())
+        },
+    },
+];
+
+fn entries(dir: &Path) -> Vec<PathBuf> {
+    let mut paths = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_fixtures() {
+    crate::logging::init_test();
+
+    let mut count = 0;
+
+    for dir in entries(Path::new(CORPUS)) {
+        let version = dir
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .parse::<Version>()
+            .unwrap();
+
+        for path in entries(&dir) {
+            let name = path.file_stem().unwrap().to_str().unwrap();
+            let (command, label) = match name.split_once('.') {
+                Some((command, label)) => (command, Some(label)),
+                None => (name, None),
+            };
+            let fixture = FIXTURES
+                .iter()
+                .find(|fixture| fixture.command == command)
+                .unwrap_or_else(|| panic!("no parser for the fixture {}", path.display()));
+            let backend = MockBackend::new();
+
+            info!("Parsing {}", path.display());
+            backend.respond(fixture.args, fs::read_to_string(&path).unwrap());
+
+            if let Err(error) = (fixture.check)(&backend, &version, label) {
+                panic!("failed to parse {}: {:?}", path.display(), error);
+            }
+
+            count += 1;
+        }
+    }
+
+    assert!(count > 0);
+}
@@ -0,0 +1,118 @@
+//! How the NordVPN CLI is installed, which decides the program that is run
+//! and where the daemon listens.
+//!
+//! The snap is run from `/snap/bin`, which is often missing from `PATH` for
+//! services and desktop sessions, and keeps the socket of the daemon in its
+//! own directory. It is found once, the first time it is needed.
+
+use once_cell::sync::Lazy;
+use std::env;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// The program of the snap, which is a link to `snap` itself.
+pub const SNAP_PROGRAM: &str = "/snap/bin/nordvpn";
+
+/// The socket of the daemon of a package.
+pub const SOCKET_PATH: &str = "/run/nordvpn/nordvpnd.sock";
+
+/// The socket of the daemon of the snap, under its `$SNAP_COMMON`.
+pub const SNAP_SOCKET_PATH: &str = "/var/snap/nordvpn/common/run/nordvpn/nordvpnd.sock";
+
+static INSTALLATION: Lazy<Installation> = Lazy::new(|| {
+    Installation::detect(
+        &env::var_os("PATH").unwrap_or_default(),
+        Path::new(SNAP_PROGRAM),
+    )
+});
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Installation {
+    /// A package, or anything else run as `nordvpn` from `PATH`.
+    Package,
+    /// The snap, which is confined, so it can only reach what its
+    /// interfaces allow.
+    Snap,
+}
+
+impl Installation {
+    /// The installation of the CLI on this system, which is a package if
+    /// none is found, so that running it fails as it would have.
+    pub fn get() -> Self {
+        *INSTALLATION
+    }
+
+    /// Finds `nordvpn` in the directories of `path`, or else at
+    /// `snap_program`.
+    pub fn detect(path: &OsStr, snap_program: &Path) -> Self {
+        let found = env::split_paths(path)
+            .map(|dir| dir.join("nordvpn"))
+            .find(|program| program.is_file());
+
+        match found {
+            Some(program) if program.starts_with("/snap") => Self::Snap,
+            Some(_) => Self::Package,
+            None if snap_program.exists() => Self::Snap,
+            None => Self::Package,
+        }
+    }
+
+    /// The program to run for the CLI.
+    pub fn program(&self) -> &'static str {
+        match self {
+            Self::Package => "nordvpn",
+            Self::Snap => SNAP_PROGRAM,
+        }
+    }
+
+    /// Where the daemon listens for the CLI.
+    pub fn socket_path(&self) -> &'static str {
+        match self {
+            Self::Package => SOCKET_PATH,
+            Self::Snap => SNAP_SOCKET_PATH,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Installation;
+    use std::ffi::OsStr;
+    use std::fs;
+
+    #[test]
+    fn test_installation() {
+        let dir = std::env::temp_dir().join(format!(
+            "nordvpn-viking-install-test-{}",
+            std::process::id()
+        ));
+        let bin = dir.join("bin");
+        let snap = dir.join("snap/bin/nordvpn");
+
+        fs::create_dir_all(&bin).unwrap();
+        fs::create_dir_all(snap.parent().unwrap()).unwrap();
+
+        assert_eq!(
+            Installation::detect(bin.as_os_str(), &snap),
+            Installation::Package
+        );
+
+        fs::write(&snap, "").unwrap();
+        assert_eq!(
+            Installation::detect(bin.as_os_str(), &snap),
+            Installation::Snap
+        );
+
+        fs::write(bin.join("nordvpn"), "").unwrap();
+        assert_eq!(
+            Installation::detect(bin.as_os_str(), &snap),
+            Installation::Package
+        );
+        assert_eq!(
+            Installation::detect(OsStr::new(""), &snap).program(),
+            "/snap/bin/nordvpn"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+use super::cli::{self, command, CliError, CliResult, CommandOutput};
+use super::re::{self, RegexError};
+use regex::Captures;
+use std::net::IpAddr;
+use strum;
+
+pub(super) const MESHNET_DISABLED: &str = "Meshnet is not enabled";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peers {
+    pub this_device: Device,
+    pub local: Vec<Peer>,
+    pub external: Vec<Peer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    pub hostname: String,
+    pub ip: IpAddr,
+    pub public_key: String,
+    pub os: String,
+    pub distribution: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peer {
+    pub hostname: String,
+    pub nickname: Option<String>,
+    pub status: PeerStatus,
+    pub ip: IpAddr,
+    pub public_key: String,
+    pub os: String,
+    pub distribution: Option<String>,
+    pub permissions: Permissions,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Permissions {
+    pub incoming: bool,
+    pub routing: bool,
+    pub local: bool,
+    pub fileshare: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+#[strum(serialize_all = "lowercase")]
+pub enum Permission {
+    Incoming,
+    Routing,
+    Local,
+    Fileshare,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, strum::Display, strum::EnumString)]
+#[strum(ascii_case_insensitive)]
+pub enum PeerStatus {
+    #[strum(to_string = "connected", serialize = "online")]
+    Connected,
+    #[strum(to_string = "disconnected", serialize = "offline")]
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Invites {
+    pub sent: Vec<String>,
+    pub received: Vec<String>,
+}
+
+/// Turns meshnet on or off for this device, which links it to the peers of
+/// the account.
+pub fn set_enabled(enabled: bool) -> CliResult<()> {
+    let values = [enabled.to_string()];
+
+    cli::parse_set(
+        command(cli::set_args("meshnet", &values))?,
+        "meshnet",
+        &values,
+    )
+}
+
+pub fn peers() -> CliResult<Peers> {
+    parse_peers(command(["nordvpn", "meshnet", "peer", "list"])?)
+}
+
+pub(super) fn parse_peers((output, stdout): (CommandOutput, String)) -> CliResult<Peers> {
+    if stdout.contains(MESHNET_DISABLED) {
+        return Err(CliError::MeshnetDisabled);
+    } else if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    let peers = (|| {
+        let mut this_device = None;
+        let mut local = Vec::new();
+        let mut external = Vec::new();
+
+        for (section, text) in re::parse_sections(&stdout) {
+            let peers = match section.as_str() {
+                "this device" => {
+                    let captures = re::MESHNET_PEER
+                        .captures(text)
+                        .ok_or(RegexError::MeshnetPeer)?;
+                    this_device = Some(parse_device(&captures)?);
+                    continue;
+                }
+                "local peers" => &mut local,
+                "external peers" => &mut external,
+                _ => continue,
+            };
+
+            for block in re::parse_blocks(text) {
+                if let Some(peer) = parse_peer(block)? {
+                    peers.push(peer);
+                }
+            }
+        }
+
+        Ok(Peers {
+            this_device: this_device.ok_or(RegexError::MeshnetPeer)?,
+            local,
+            external,
+        })
+    })()
+    .map_err(|error| CliError::regex(error, output))?;
+
+    Ok(peers)
+}
+
+pub fn remove_peer(peer: &str) -> CliResult<()> {
+    parse_remove_peer(
+        command(["nordvpn", "meshnet", "peer", "remove", peer])?,
+        peer,
+    )
+}
+
+pub(super) fn parse_remove_peer(
+    (output, stdout): (CommandOutput, String),
+    peer: &str,
+) -> CliResult<()> {
+    if stdout.contains("removed from the meshnet") {
+        return Ok(());
+    } else if stdout.contains(MESHNET_DISABLED) {
+        return Err(CliError::MeshnetDisabled);
+    } else if stdout.contains("is unknown") {
+        return Err(CliError::UnknownPeer(peer.to_owned()));
+    } else if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    Err(CliError::bad_output(output))
+}
+
+/// Allows or denies a permission for a peer, returning `false` if the
+/// permission was already in the requested state.
+pub fn set_permission(peer: &str, permission: Permission, allowed: bool) -> CliResult<bool> {
+    parse_set_permission(
+        command([
+            "nordvpn",
+            "meshnet",
+            "peer",
+            permission.to_string().as_str(),
+            if allowed { "allow" } else { "deny" },
+            peer,
+        ])?,
+        peer,
+    )
+}
+
+pub(super) fn parse_set_permission(
+    (output, stdout): (CommandOutput, String),
+    peer: &str,
+) -> CliResult<bool> {
+    if stdout.contains(" already ") {
+        return Ok(false);
+    } else if stdout.contains(MESHNET_DISABLED) {
+        return Err(CliError::MeshnetDisabled);
+    } else if stdout.contains("is unknown") {
+        return Err(CliError::UnknownPeer(peer.to_owned()));
+    } else if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    Ok(true)
+}
+
+pub fn invites() -> CliResult<Invites> {
+    parse_invites(command(["nordvpn", "meshnet", "invite", "list"])?)
+}
+
+pub(super) fn parse_invites((output, stdout): (CommandOutput, String)) -> CliResult<Invites> {
+    if stdout.contains(MESHNET_DISABLED) {
+        return Err(CliError::MeshnetDisabled);
+    } else if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    let sections = re::parse_sections(&stdout);
+
+    if sections.is_empty() {
+        return Err(CliError::regex(RegexError::MeshnetInvites, output));
+    }
+
+    let mut invites = Invites::default();
+
+    for (section, text) in sections {
+        let emails = re::MESHNET_INVITE
+            .captures_iter(text)
+            .map(|captures| captures.name("email").unwrap().as_str().to_owned());
+
+        if section.starts_with("sent") {
+            invites.sent.extend(emails);
+        } else if section.starts_with("received") {
+            invites.received.extend(emails);
+        }
+    }
+
+    Ok(invites)
+}
+
+pub fn send_invite(email: &str) -> CliResult<()> {
+    invite("send", email, "was sent")
+}
+
+pub fn accept_invite(email: &str) -> CliResult<()> {
+    invite("accept", email, "was accepted")
+}
+
+pub fn deny_invite(email: &str) -> CliResult<()> {
+    invite("deny", email, "was denied")
+}
+
+pub fn revoke_invite(email: &str) -> CliResult<()> {
+    invite("revoke", email, "was revoked")
+}
+
+fn invite(action: &str, email: &str, success: &str) -> CliResult<()> {
+    parse_invite(
+        command(["nordvpn", "meshnet", "invite", action, email])?,
+        email,
+        success,
+    )
+}
+
+pub(super) fn parse_invite(
+    (output, stdout): (CommandOutput, String),
+    email: &str,
+    success: &str,
+) -> CliResult<()> {
+    if stdout.contains(success) {
+        return Ok(());
+    } else if stdout.contains(MESHNET_DISABLED) {
+        return Err(CliError::MeshnetDisabled);
+    } else if stdout.contains("No invitation") {
+        return Err(CliError::InviteNotFound(email.to_owned()));
+    } else if stdout.contains("already exists") {
+        return Err(CliError::InviteExists(email.to_owned()));
+    } else if stdout.contains("Invalid email") {
+        return Err(CliError::InvalidEmail(email.to_owned()));
+    } else if !output.status.success() {
+        return Err(CliError::failed(output));
+    }
+
+    Err(CliError::bad_output(output))
+}
+
+fn parse_device(captures: &Captures) -> Result<Device, RegexError> {
+    Ok(Device {
+        hostname: match captures.name("hostname") {
+            Some(hostname) => hostname.as_str().to_owned(),
+            None => return Err(RegexError::MeshnetPeerHostname),
+        },
+        ip: match captures.name("ip") {
+            Some(ip) => ip.as_str().parse::<IpAddr>().unwrap(),
+            None => return Err(RegexError::MeshnetPeerIp),
+        },
+        public_key: match captures.name("public_key") {
+            Some(public_key) => public_key.as_str().to_owned(),
+            None => return Err(RegexError::MeshnetPeerPublicKey),
+        },
+        os: match captures.name("os") {
+            Some(os) => os.as_str().to_owned(),
+            None => return Err(RegexError::MeshnetPeerOs),
+        },
+        distribution: captures
+            .name("distribution")
+            .map(|distribution| distribution.as_str().to_owned()),
+    })
+}
+
+fn parse_peer(block: &str) -> Result<Option<Peer>, RegexError> {
+    let captures = match re::MESHNET_PEER.captures(block) {
+        Some(captures) if captures.name("hostname").is_some() => captures,
+        // Empty sections print a placeholder such as `[no peers]`.
+        _ => return Ok(None),
+    };
+    let device = parse_device(&captures)?;
+
+    Ok(Some(Peer {
+        hostname: device.hostname,
+        nickname: captures
+            .name("nickname")
+            .map(|nickname| nickname.as_str().to_owned())
+            .filter(|nickname| nickname != "-"),
+        status: match captures.name("status") {
+            Some(status) => status.as_str().parse::<PeerStatus>().unwrap(),
+            None => return Err(RegexError::MeshnetPeerStatus),
+        },
+        ip: device.ip,
+        public_key: device.public_key,
+        os: device.os,
+        distribution: device.distribution,
+        permissions: (|| {
+            Some(Permissions {
+                incoming: captures.name("allow_incoming")?.as_str().to_lowercase() == "enabled",
+                routing: captures.name("allow_routing")?.as_str().to_lowercase() == "enabled",
+                local: captures.name("allow_local")?.as_str().to_lowercase() == "enabled",
+                fileshare: captures.name("allow_fileshare")?.as_str().to_lowercase() == "enabled",
+            })
+        })()
+        .ok_or(RegexError::MeshnetPeerPermissions)?,
+    }))
+}
+
+impl Peer {
+    pub fn set_incoming(&mut self, allowed: bool) -> CliResult<&mut Self> {
+        set_permission(&self.hostname, Permission::Incoming, allowed)?;
+        self.permissions.incoming = allowed;
+        Ok(self)
+    }
+
+    pub fn set_routing(&mut self, allowed: bool) -> CliResult<&mut Self> {
+        set_permission(&self.hostname, Permission::Routing, allowed)?;
+        self.permissions.routing = allowed;
+        Ok(self)
+    }
+
+    pub fn set_local(&mut self, allowed: bool) -> CliResult<&mut Self> {
+        set_permission(&self.hostname, Permission::Local, allowed)?;
+        self.permissions.local = allowed;
+        Ok(self)
+    }
+
+    pub fn set_fileshare(&mut self, allowed: bool) -> CliResult<&mut Self> {
+        set_permission(&self.hostname, Permission::Fileshare, allowed)?;
+        self.permissions.fileshare = allowed;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::info;
+
+    #[test]
+    fn test_meshnet() {
+        crate::logging::init_test();
+
+        let peers = super::peers().unwrap();
+        info!("Peers: {:#?}", peers);
+
+        let invites = super::invites().unwrap();
+        info!("Invites: {:#?}", invites);
+    }
+}
@@ -1,52 +1,167 @@
+pub mod allowlist;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+mod backend;
 mod cli;
+mod compat;
+#[cfg(feature = "daemon")]
+pub mod daemon;
+pub mod fileshare;
+#[cfg(test)]
+mod fixtures;
+mod install;
+pub mod meshnet;
 mod re;
+mod retry;
+mod watchdog;
+mod watcher;
 
+pub use backend::*;
 pub use cli::*;
+pub use compat::*;
+pub use install::Installation;
 pub use re::RegexError;
+pub use retry::*;
+pub use watchdog::*;
+pub use watcher::*;
 
 #[cfg(test)]
 mod tests {
     use semver::Version;
+    use tracing::info;
 
     #[test]
     fn test_nordvpn() {
+        crate::logging::init_test();
+
         let version = super::version().unwrap();
-        println!("Version: {}", version);
+        info!("Version: {}", version);
         assert!(version >= Version::new(3, 12, 0));
 
         let account = super::account().unwrap();
-        println!("Account: {:#?}", account);
+        info!("Account: {:#?}", account);
 
         let countries = super::countries().unwrap();
-        println!("Countries: {:?}", countries);
+        info!("Countries: {:?}", countries);
 
         for country in countries {
-            let cities = super::cities(&country).unwrap();
-            println!("Cities in {}: {:?}", country, cities);
+            let cities = super::cities(&country.id).unwrap();
+            info!("Cities in {}: {:?}", country, cities);
         }
 
         let groups = super::groups().unwrap();
-        println!("Groups: {:?}", groups);
+        info!("Groups: {:?}", groups);
 
         let status = super::status().unwrap();
-        println!("Status: {:#?}", status);
+        info!("Status: {:#?}", status);
 
         let connect = super::connect(None).unwrap();
-        println!("Connect response: {:#?}", connect);
+        info!("Connect response: {:#?}", connect);
 
         let status = super::status().unwrap();
-        println!("Status: {:#?}", status);
+        info!("Status: {:#?}", status);
 
         let disconnect = super::disconnect().unwrap();
-        println!("Disconnected: {}", disconnect);
+        info!("Disconnected: {}", disconnect);
 
         let status = super::status().unwrap();
-        println!("Status: {:#?}", status);
+        info!("Status: {:#?}", status);
+    }
+
+    #[test]
+    fn test_connect_event() {
+        use super::{connect_event, ConnectEvent};
+
+        assert_eq!(
+            connect_event("Connecting to Germany #507 (de507.nordvpn.com)"),
+            Some(ConnectEvent::Connecting(Some(
+                "de507.nordvpn.com".to_owned()
+            )))
+        );
+        assert_eq!(
+            connect_event("Connecting to laptop.nord"),
+            Some(ConnectEvent::Connecting(None))
+        );
+        assert_eq!(connect_event("-"), None);
+    }
+
+    #[test]
+    fn test_connect_option() {
+        use super::ConnectOption;
+
+        for option in [
+            ConnectOption::Country("United States".to_owned()),
+            ConnectOption::Server("de507".to_owned()),
+            ConnectOption::CountryCode("de".to_owned()),
+            ConnectOption::City("Berlin".to_owned()),
+            ConnectOption::Group("P2P".to_owned()),
+            ConnectOption::CountryCity("Germany".to_owned(), "Berlin".to_owned()),
+            ConnectOption::MeshnetPeer("laptop.nord".to_owned()),
+            ConnectOption::DedicatedIp(None),
+            ConnectOption::DedicatedIp(Some("de507.nordvpn.com".to_owned())),
+        ] {
+            assert_eq!(option.to_string().parse::<ConnectOption>(), Ok(option));
+        }
+
+        assert!("Germany".parse::<ConnectOption>().is_err());
+        assert!("country:".parse::<ConnectOption>().is_err());
+        assert!("country-city:Germany".parse::<ConnectOption>().is_err());
+        assert_eq!(
+            ConnectOption::server_from_hostname("de507.nordvpn.com"),
+            ConnectOption::Server("de507".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_command_locale() {
+        use std::ffi::OsStr;
+
+        let command = super::new_command("nordvpn");
+        let envs = command.get_envs().collect::<Vec<_>>();
+
+        assert!(envs.contains(&(OsStr::new("LC_ALL"), Some(OsStr::new("C")))));
+        assert!(envs.contains(&(OsStr::new("LANGUAGE"), None)));
+    }
+
+    #[test]
+    fn test_list_entry() {
+        use super::ListEntry;
+
+        for entry in ["Bosnia_And_Herzegovina", "Bosnia And Herzegovina"] {
+            assert_eq!(
+                ListEntry::new(entry),
+                ListEntry {
+                    name: "Bosnia And Herzegovina".to_owned(),
+                    id: "Bosnia_And_Herzegovina".to_owned(),
+                }
+            );
+        }
+
+        assert_eq!(ListEntry::new("P2P").id, "P2P");
+    }
+
+    #[test]
+    fn test_uptime_since() {
+        use super::Uptime;
+        use chrono::{TimeZone, Utc};
+
+        let uptime = Uptime {
+            months: 1,
+            hours: 2,
+            ..Uptime::default()
+        };
+
+        assert_eq!(
+            uptime.since(Utc.with_ymd_and_hms(2024, 3, 31, 12, 0, 0).unwrap()),
+            Utc.with_ymd_and_hms(2024, 2, 29, 10, 0, 0).unwrap()
+        );
     }
 
     #[test]
     fn test_settings() {
+        crate::logging::init_test();
+
         let settings = super::settings().unwrap();
-        println!("Settings: {:#?}", settings);
+        info!("Settings: {:#?}", settings);
     }
 }
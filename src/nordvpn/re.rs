@@ -1,5 +1,6 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub enum RegexError {
@@ -11,7 +12,21 @@ pub enum RegexError {
     Connect,
     Countries,
     Groups,
+    FileshareSend,
+    FileshareTransferId,
+    FileshareTransferPeer,
+    FileshareTransferStatus,
     Login,
+    AllowlistPort,
+    AllowlistSubnet,
+    MeshnetInvites,
+    MeshnetPeer,
+    MeshnetPeerHostname,
+    MeshnetPeerIp,
+    MeshnetPeerPublicKey,
+    MeshnetPeerOs,
+    MeshnetPeerPermissions,
+    MeshnetPeerStatus,
     Settings,
     SettingsTechnology,
     SettingsProtocol,
@@ -34,12 +49,30 @@ pub enum RegexError {
     Version,
 }
 
-pub static WORD_LIST: Lazy<Regex> = Lazy::new(|| Regex::new(strings::WORD_LIST).unwrap());
+pub static LIST_SEPARATOR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(strings::shared::LIST_SEPARATOR).unwrap());
+pub static LIST_ENTRY: Lazy<Regex> = Lazy::new(|| Regex::new(strings::shared::LIST_ENTRY).unwrap());
+pub static ALLOWLIST_PORT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(strings::allowlist::PORT).unwrap());
+pub static SECTION: Lazy<Regex> = Lazy::new(|| Regex::new(strings::shared::SECTION).unwrap());
+pub static TABLE_COLUMNS: Lazy<Regex> =
+    Lazy::new(|| Regex::new(strings::shared::TABLE_COLUMNS).unwrap());
 
 pub static ACCOUNT: Lazy<Regex> = Lazy::new(|| Regex::new(strings::ACCOUNT).unwrap());
 pub static CONNECT: Lazy<Regex> =
     Lazy::new(|| Regex::new(strings::connect::COUNTRY_SERVER_HOSTNAME).unwrap());
+pub static CONNECT_PEER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(strings::connect::MESHNET_PEER_HOSTNAME).unwrap());
+pub static CONNECTING: Lazy<Regex> =
+    Lazy::new(|| Regex::new(strings::connect::CONNECTING).unwrap());
+pub static SERVER_HOSTNAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(strings::shared::SERVER_HOSTNAME).unwrap());
+pub static FILESHARE_TRANSFER_ID: Lazy<Regex> =
+    Lazy::new(|| Regex::new(strings::fileshare::TRANSFER_ID).unwrap());
 pub static LOGIN: Lazy<Regex> = Lazy::new(|| Regex::new(strings::login::URL).unwrap());
+pub static MESHNET_PEER: Lazy<Regex> = Lazy::new(|| Regex::new(strings::MESHNET_PEER).unwrap());
+pub static MESHNET_INVITE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(strings::meshnet::INVITE_EMAIL).unwrap());
 pub static INVALID_SETTING: Lazy<Regex> =
     Lazy::new(|| Regex::new(strings::settings::INVALID_NAME).unwrap());
 pub static SETTINGS: Lazy<Regex> = Lazy::new(|| Regex::new(strings::SETTINGS).unwrap());
@@ -49,16 +82,31 @@ pub static VERSION: Lazy<Regex> = Lazy::new(|| Regex::new(strings::version::VERS
 pub mod strings {
     use const_format::*;
 
-    pub const WORD_LIST: &str = r#"(\w+)(?:,\s*|\s*$)"#;
-
     pub const ACCOUNT: &str = formatcp!(
-        r#"(?:{}|{}|{})+"#,
+        r#"(?:{}|{}|{}|{}|{})+"#,
         account::EMAIL,
         account::ACTIVE,
-        account::EXPIRES
+        account::EXPIRES,
+        account::DEDICATED_IP,
+        account::DEDICATED_IP_SERVERS
+    );
+    pub const MESHNET_PEER: &str = formatcp!(
+        r#"(?:{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{})+"#,
+        meshnet::HOSTNAME,
+        meshnet::NICKNAME,
+        meshnet::STATUS,
+        meshnet::IP,
+        meshnet::PUBLIC_KEY,
+        meshnet::OS,
+        meshnet::DISTRIBUTION,
+        meshnet::ALLOW_INCOMING,
+        meshnet::ALLOW_ROUTING,
+        meshnet::ALLOW_LOCAL,
+        meshnet::ALLOW_FILESHARE,
+        shared::UNKNOWN_LINE,
     );
     pub const SETTINGS: &str = formatcp!(
-        r#"(?:{}|{}|{}|{}|{}|{}|{}|{}|{}|{})+"#,
+        r#"(?:{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{})+"#,
         settings::TECHNOLOGY,
         settings::PROTOCOL,
         settings::FIREWALL,
@@ -69,9 +117,12 @@ pub mod strings {
         settings::AUTOCONNECT,
         settings::IPV6,
         settings::DNS,
+        settings::POST_QUANTUM,
+        settings::LAN_DISCOVERY,
+        shared::UNKNOWN_LINE,
     );
     pub const STATUS: &str = formatcp!(
-        r#"(?:{}|{}|{}|{}|{}|{}|{}|{})+"#,
+        r#"(?:{}|{}|{}|{}|{}|{}|{}|{}|{})+"#,
         status::HOSTNAME,
         status::COUNTRY,
         status::CITY,
@@ -79,7 +130,8 @@ pub mod strings {
         status::TECHNOLOGY,
         status::PROTOCOL,
         status::TRANSFER,
-        status::UPTIME
+        status::UPTIME,
+        shared::UNKNOWN_LINE,
     );
 
     pub mod shared {
@@ -89,6 +141,14 @@ pub mod strings {
         pub const OPENVPN_OR_NORDLYNX: &str = r#"(?P<GROUP_NAME>(?i)OPENVPN|NORDLYNX)"#;
         pub const TCP_OR_UDP: &str = r#"(?P<GROUP_NAME>(?i)TCP|UDP)"#;
         pub const ENABLED_OR_DISABLED: &str = r#"(?P<GROUP_NAME>(?i)enabled|disabled)"#;
+        pub const LIST_SEPARATOR: &str = r#"\s*,\s*|\t+|\s{2,}|[\r\n]+"#;
+        pub const LIST_ENTRY: &str = r#"^(?i)[a-z0-9][\w'\-\.]*(?: [\w'\-\.]+)*$"#;
+        pub const UNKNOWN_LINE: &str = r#"[^\n]*\n"#;
+        pub const SECTION: &str = r#"(?im)^\s*(?P<section>[a-z][a-z ]*):\s*$"#;
+        pub const TABLE_COLUMNS: &str = r#"\s{2,}|\t+"#;
+        pub const SERVER_HOSTNAME: &str =
+            r#"^(?i)[a-z]{2}(?:-[a-z]{2})?(?P<server>\d+)\.nordvpn\.com$"#;
+        pub const MESHNET_HOSTNAME: &str = r#"[\w\d\-]+(?:\.[\w\d\-]+)*\.nord"#;
     }
 
     pub mod account {
@@ -97,11 +157,32 @@ pub mod strings {
 
         pub const EMAIL: &str = concatcp!(r#"Email Address:\s+(?P<email>.+)"#, LINE_END_OR_NEWLINE);
         pub const ACTIVE: &str = r#"VPN Service:\s+(?P<active>(?i)[a-z]+)\s*"#;
+        pub const DEDICATED_IP: &str =
+            r#"\s*Dedicated IP:\s+(?P<dedicated_ip>(?i)[a-z]+(?: [a-z]+)*)[^\n]*"#;
+        pub const DEDICATED_IP_SERVERS: &str = concatcp!(
+            r#"\s*Dedicated IP assigned servers?:\s+(?P<dedicated_ip_servers>[^\n]+?)"#,
+            LINE_END_OR_NEWLINE
+        );
         pub const EXPIRES: &str = r#"\(Expires on\s+(?P<expires_month>(?i)[a-z]{3})\s+(?P<expires_day>\d+)(?i:st|nd|rd|th),\s+(?P<expires_year>\d{4})\)"#;
     }
 
     pub mod connect {
+        use super::shared::*;
+        use const_format::*;
+
         pub const COUNTRY_SERVER_HOSTNAME: &str = r#"You are connected to\s+(?P<country>(?i)[a-z_ ]+)\s+#(?P<server>\d+)\s+\((?P<hostname>[\w\d\-\.]+)\)!"#;
+        pub const CONNECTING: &str =
+            r#"Connecting to\s+[^\r\n]*?(?:\((?P<hostname>[\w\d\-\.]+)\))?\s*$"#;
+        pub const MESHNET_PEER_HOSTNAME: &str = concatcp!(
+            r#"You are connected to\s+(?:[^\n]*?\s)?'?(?P<hostname>"#,
+            MESHNET_HOSTNAME,
+            r#")'?"#
+        );
+    }
+
+    pub mod fileshare {
+        pub const TRANSFER_ID: &str =
+            r#"(?i)transfer\s+'?(?P<id>[\da-f]{8}(?:-[\da-f]{4}){3}-[\da-f]{12})'?"#;
     }
 
     pub mod login {
@@ -114,6 +195,58 @@ pub mod strings {
         );
     }
 
+    pub mod meshnet {
+        use super::shared::*;
+        use const_format::*;
+
+        pub const HOSTNAME: &str = concatcp!(
+            r#"Hostname:\s+(?P<hostname>[\w\d\-\.]+)"#,
+            LINE_END_OR_NEWLINE
+        );
+        pub const NICKNAME: &str =
+            concatcp!(r#"Nickname:\s+(?P<nickname>.+?)"#, LINE_END_OR_NEWLINE);
+        pub const STATUS: &str = concatcp!(
+            r#"Status:\s+(?P<status>(?i)connected|disconnected|online|offline)"#,
+            LINE_END_OR_NEWLINE
+        );
+        pub const IP: &str = concatcp!(
+            r#"IP:\s+"#,
+            str_replace!(IPV4_OR_IPV6, "GROUP_NAME", "ip"),
+            LINE_END_OR_NEWLINE
+        );
+        pub const PUBLIC_KEY: &str = concatcp!(
+            r#"Public Key:\s+(?P<public_key>[\w\d+/=]+)"#,
+            LINE_END_OR_NEWLINE
+        );
+        pub const OS: &str = concatcp!(r#"OS:\s+(?P<os>.+?)"#, LINE_END_OR_NEWLINE);
+        pub const DISTRIBUTION: &str = concatcp!(
+            r#"Distribution:\s+(?P<distribution>.+?)"#,
+            LINE_END_OR_NEWLINE
+        );
+        pub const ALLOW_INCOMING: &str = concatcp!(
+            r#"Allow Incoming Traffic:\s+"#,
+            str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "allow_incoming"),
+            LINE_END_OR_NEWLINE
+        );
+        pub const ALLOW_ROUTING: &str = concatcp!(
+            r#"Allow Routing:\s+"#,
+            str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "allow_routing"),
+            LINE_END_OR_NEWLINE
+        );
+        pub const ALLOW_LOCAL: &str = concatcp!(
+            r#"Allow Local Network Access:\s+"#,
+            str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "allow_local"),
+            LINE_END_OR_NEWLINE
+        );
+        pub const ALLOW_FILESHARE: &str = concatcp!(
+            r#"Allow Sending Files:\s+"#,
+            str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "allow_fileshare"),
+            LINE_END_OR_NEWLINE
+        );
+
+        pub const INVITE_EMAIL: &str = concatcp!(r#"Email:\s+(?P<email>.+?)"#, LINE_END_OR_NEWLINE);
+    }
+
     pub mod settings {
         use super::shared::*;
         use const_format::*;
@@ -141,7 +274,7 @@ pub mod strings {
             LINE_END_OR_NEWLINE
         );
         pub const CYBERSEC: &str = concatcp!(
-            r#"CyberSec:\s+"#,
+            r#"(?:CyberSec|Threat Protection Lite):\s+"#,
             str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "cybersec"),
             LINE_END_OR_NEWLINE
         );
@@ -165,6 +298,16 @@ pub mod strings {
             str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "ipv6"),
             LINE_END_OR_NEWLINE
         );
+        pub const POST_QUANTUM: &str = concatcp!(
+            r#"Post-quantum VPN:\s+"#,
+            str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "post_quantum"),
+            LINE_END_OR_NEWLINE
+        );
+        pub const LAN_DISCOVERY: &str = concatcp!(
+            r#"LAN Discovery:\s+"#,
+            str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "lan_discovery"),
+            LINE_END_OR_NEWLINE
+        );
         pub const DNS: &str = formatcp!(
             r#"DNS:\s+(?:{}|(?:{}(?:,\s+)?)?(?:{}(?:,\s+)?)?{}?){}"#,
             str_replace!(ENABLED_OR_DISABLED, "GROUP_NAME", "dns_disabled"),
@@ -175,12 +318,18 @@ pub mod strings {
         );
     }
 
+    pub mod allowlist {
+        /// One line under `Allowlisted ports:`, such as `8000 - 8080 (TCP)`.
+        pub const PORT: &str =
+            r#"^\s*(?P<start>\d+)(?:\s*-\s*(?P<end>\d+))?\s*\((?P<protocols>(?i)[a-z|]+)\)\s*$"#;
+    }
+
     pub mod status {
         use super::shared::*;
         use const_format::*;
 
         pub const HOSTNAME: &str = concatcp!(
-            r#"Current server:\s+(?P<hostname>[\w\d\-\.]+)"#,
+            r#"(?:Current server|Hostname):\s+(?P<hostname>[\w\d\-\.]+)"#,
             LINE_END_OR_NEWLINE
         );
         pub const COUNTRY: &str = concatcp!(
@@ -192,7 +341,7 @@ pub mod strings {
             LINE_END_OR_NEWLINE
         );
         pub const IP: &str = concatcp!(
-            r#"Server IP:\s+"#,
+            r#"(?:Server IP|IP):\s+"#,
             str_replace!(IPV4_OR_IPV6, "GROUP_NAME", "ip"),
             LINE_END_OR_NEWLINE
         );
@@ -224,30 +373,256 @@ pub mod strings {
     }
 }
 
+/// Splits a list printed by commands such as `nordvpn countries`, which may
+/// be separated by commas or laid out in columns, into its complete entries.
+/// Anything that is not a plausible name, such as the spinner the CLI draws
+/// while waiting for the daemon, is skipped.
 pub fn parse_list(text: &str) -> Option<Vec<String>> {
-    let mut captures = WORD_LIST.captures_iter(text).peekable();
+    let items = LIST_SEPARATOR
+        .split(text)
+        .map(str::trim)
+        .filter(|item| LIST_ENTRY.is_match(item))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
 
-    captures.peek()?;
+    if items.is_empty() {
+        None
+    } else {
+        Some(items)
+    }
+}
+
+/// Splits the output of a command into its titled sections, such as the
+/// `This device:` and `External Peers:` headings printed by meshnet commands.
+/// The section titles are returned in lowercase.
+pub fn parse_sections(text: &str) -> Vec<(String, &str)> {
+    let headings = SECTION.captures_iter(text).collect::<Vec<_>>();
+
+    headings
+        .iter()
+        .enumerate()
+        .map(|(index, captures)| {
+            let start = captures.get(0).unwrap().end();
+            let end = headings
+                .get(index + 1)
+                .map_or(text.len(), |next| next.get(0).unwrap().start());
 
-    let items = captures.map(|capture| capture.get(1).unwrap().as_str().to_owned());
+            (
+                captures.name("section").unwrap().as_str().to_lowercase(),
+                &text[start..end],
+            )
+        })
+        .collect()
+}
+
+/// Parses a table with a header row into one map per row, keyed by the
+/// lowercase column names. Columns are separated by runs of whitespace.
+pub fn parse_table(text: &str) -> Vec<HashMap<String, &str>> {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
 
-    Some(items.collect())
+    let header = match lines.next() {
+        Some(header) => TABLE_COLUMNS
+            .split(header)
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>(),
+        None => return Vec::new(),
+    };
+
+    lines
+        .map(|line| {
+            header
+                .iter()
+                .cloned()
+                .zip(TABLE_COLUMNS.split(line))
+                .collect()
+        })
+        .collect()
+}
+
+/// Splits a section of text into blocks separated by blank lines.
+pub fn parse_blocks(text: &str) -> impl Iterator<Item = &str> {
+    text.split("\n\n")
+        .map(str::trim)
+        .filter(|block| !block.is_empty())
 }
 
 #[cfg(test)]
 mod tests {
+    use tracing::info;
+
+    const COUNTRIES: &[&str] = &[
+        "Albania",
+        "Algeria",
+        "Andorra",
+        "Argentina",
+        "Armenia",
+        "Australia",
+        "Austria",
+        "Azerbaijan",
+        "Bahamas",
+        "Bangladesh",
+        "Belgium",
+        "Belize",
+        "Bermuda",
+        "Bhutan",
+        "Bolivia",
+        "Bosnia_And_Herzegovina",
+        "Brazil",
+        "Brunei_Darussalam",
+        "Bulgaria",
+        "Cambodia",
+        "Canada",
+        "Cayman_Islands",
+        "Chile",
+        "Colombia",
+        "Costa_Rica",
+        "Croatia",
+        "Cyprus",
+        "Czech_Republic",
+        "Denmark",
+        "Dominican_Republic",
+        "Ecuador",
+        "Egypt",
+        "El_Salvador",
+        "Estonia",
+        "Finland",
+        "France",
+        "Georgia",
+        "Germany",
+        "Ghana",
+        "Greece",
+        "Greenland",
+        "Guam",
+        "Guatemala",
+        "Honduras",
+        "Hong_Kong",
+        "Hungary",
+        "Iceland",
+        "India",
+        "Indonesia",
+        "Ireland",
+        "Isle_Of_Man",
+        "Israel",
+        "Italy",
+        "Jamaica",
+        "Japan",
+        "Jersey",
+        "Kazakhstan",
+        "Kenya",
+        "Lao_Peoples_Democratic_Republic",
+        "Latvia",
+        "Lebanon",
+        "Liechtenstein",
+        "Lithuania",
+        "Luxembourg",
+        "Malaysia",
+        "Malta",
+        "Mexico",
+        "Moldova",
+        "Monaco",
+        "Mongolia",
+        "Montenegro",
+        "Morocco",
+        "Myanmar",
+        "Nepal",
+        "Netherlands",
+        "New_Zealand",
+        "Nigeria",
+        "North_Macedonia",
+        "Norway",
+        "Pakistan",
+        "Panama",
+        "Papua_New_Guinea",
+        "Paraguay",
+        "Peru",
+        "Philippines",
+        "Poland",
+        "Portugal",
+        "Puerto_Rico",
+        "Romania",
+        "Serbia",
+        "Singapore",
+        "Slovakia",
+        "Slovenia",
+        "South_Africa",
+        "South_Korea",
+        "Spain",
+        "Sri_Lanka",
+        "Sweden",
+        "Switzerland",
+        "Taiwan",
+        "Thailand",
+        "Trinidad_And_Tobago",
+        "Turkey",
+        "Ukraine",
+        "United_Arab_Emirates",
+        "United_Kingdom",
+        "United_States",
+        "Uruguay",
+        "Uzbekistan",
+        "Venezuela",
+        "Vietnam",
+    ];
+    const GROUPS: &[&str] = &[
+        "Africa_The_Middle_East_And_India",
+        "Asia_Pacific",
+        "Dedicated_IP",
+        "Double_VPN",
+        "Europe",
+        "Obfuscated_Servers",
+        "Onion_Over_VPN",
+        "P2P",
+        "Standard_VPN_Servers",
+        "The_Americas",
+    ];
+
+    #[test]
+    fn test_parse_list() {
+        for names in [COUNTRIES, GROUPS] {
+            let commas = format!("\r-\r  \r\r{}\n", names.join(", "));
+            let columns = names
+                .chunks(4)
+                .map(|row| row.join("\t\t"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            for text in [commas, columns] {
+                assert_eq!(super::parse_list(&text).unwrap(), names);
+            }
+        }
+
+        assert_eq!(
+            super::parse_list("North Macedonia, Bosnia-Herzegovina, St. Louis\n").unwrap(),
+            ["North Macedonia", "Bosnia-Herzegovina", "St. Louis"]
+        );
+        assert_eq!(super::parse_list("\r-\r  \r"), None);
+    }
+
     #[test]
     fn print_account_pattern() {
-        println!("Account Pattern: {}", super::strings::ACCOUNT);
+        crate::logging::init_test();
+
+        info!("Account Pattern: {}", super::strings::ACCOUNT);
+    }
+
+    #[test]
+    fn print_meshnet_peer_pattern() {
+        crate::logging::init_test();
+
+        info!("Meshnet Peer Pattern: {}", super::strings::MESHNET_PEER);
     }
 
     #[test]
     fn print_settings_pattern() {
-        println!("Settings Pattern: {}", super::strings::SETTINGS);
+        crate::logging::init_test();
+
+        info!("Settings Pattern: {}", super::strings::SETTINGS);
     }
 
     #[test]
     fn print_status_pattern() {
-        println!("Status Pattern: {}", super::strings::STATUS);
+        crate::logging::init_test();
+
+        info!("Status Pattern: {}", super::strings::STATUS);
     }
 }
@@ -0,0 +1,136 @@
+use super::cli::{CliError, CliResult};
+use std::thread;
+use std::time::Duration;
+
+/// How many times, and how patiently, to repeat a command which failed with
+/// a transient error (see [`CliError::is_transient`]). The policy is applied
+/// per call, as in `RetryPolicy::default().retry(nordvpn::status)`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// The total number of attempts, including the first.
+    pub max_attempts: u32,
+    /// The delay before the second attempt.
+    pub initial_delay: Duration,
+    /// The factor the delay is multiplied by after each attempt.
+    pub multiplier: u32,
+    /// The upper bound for any one delay.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(250),
+            multiplier: 2,
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy which runs the command once and never retries.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    pub fn multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// The delay to wait after the given (zero-based) failed attempt.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.multiplier
+            .checked_pow(attempt)
+            .and_then(|factor| self.initial_delay.checked_mul(factor))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    /// Runs `call` until it succeeds, fails with an error that is not
+    /// transient, or the attempts are exhausted, returning the last result.
+    pub fn retry<T, F>(&self, mut call: F) -> CliResult<T>
+    where
+        F: FnMut() -> CliResult<T>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            match call() {
+                Err(error) if self.should_retry(&error, attempt) => {
+                    thread::sleep(self.delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn should_retry(&self, error: &CliError, attempt: u32) -> bool {
+        error.is_transient() && attempt + 1 < self.max_attempts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryPolicy;
+    use crate::nordvpn::CliError;
+    use std::time::Duration;
+
+    #[test]
+    fn test_retry() {
+        let policy = RetryPolicy::default()
+            .max_attempts(3)
+            .initial_delay(Duration::from_millis(1));
+
+        let mut calls = 0;
+        let result = policy.retry(|| {
+            calls += 1;
+            match calls {
+                1 => Err(CliError::DaemonBusy),
+                _ => Ok(calls),
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+
+        let mut calls = 0;
+        let result = policy.retry(|| -> Result<(), _> {
+            calls += 1;
+            Err(CliError::DaemonNotRunning)
+        });
+        assert!(matches!(result, Err(CliError::DaemonNotRunning)));
+        assert_eq!(calls, 3);
+
+        let mut calls = 0;
+        let result = policy.retry(|| -> Result<(), _> {
+            calls += 1;
+            Err(CliError::NotLoggedIn)
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+
+        assert_eq!(policy.delay(2), Duration::from_millis(4));
+        assert_eq!(
+            policy.max_delay(Duration::from_millis(3)).delay(10),
+            Duration::from_millis(3)
+        );
+    }
+}
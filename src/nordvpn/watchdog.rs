@@ -0,0 +1,362 @@
+use super::backend::{NordVPN, NordVpnBackend};
+use super::cli::{CliError, ConnectOption, Connected, Status};
+use super::retry::RetryPolicy;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// What the watchdog did about a connection that dropped.
+#[derive(Debug)]
+pub enum WatchdogEvent {
+    /// The connection dropped without being asked to, and this attempt (from
+    /// one) to reconnect is starting.
+    Reconnecting {
+        option: ConnectOption,
+        attempt: u32,
+    },
+    Reconnected(Connected),
+    /// Every attempt failed, with the last error. The watchdog tries again
+    /// when the network comes back (see [`WatchdogHandle::set_online`]), or
+    /// waits for the next connection.
+    GaveUp(CliError),
+}
+
+/// The state shared between a [`Watchdog`] and its handles.
+#[derive(Debug, Default)]
+struct Shared {
+    enabled: AtomicBool,
+    /// Set when the next disconnect was asked for.
+    expected: AtomicBool,
+    /// Cleared while the system has no network, when reconnecting would fail.
+    online: AtomicBool,
+    target: Mutex<Option<ConnectOption>>,
+    interval: Mutex<Duration>,
+    policy: Mutex<RetryPolicy>,
+    wake: Mutex<Wake>,
+    woken: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Wake {
+    /// Poll now instead of at the end of the interval.
+    poll: bool,
+    /// Try reconnecting again even after giving up.
+    retry: bool,
+    stop: bool,
+}
+
+/// Tells the watchdog what the user did, from any thread.
+#[derive(Debug, Clone)]
+pub struct WatchdogHandle {
+    shared: Arc<Shared>,
+}
+
+impl WatchdogHandle {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.shared.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.shared.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Remembers what the user last connected to, so that a reconnect picks
+    /// the same kind of server rather than exactly the one that dropped.
+    pub fn set_target(&self, option: Option<ConnectOption>) {
+        *self.shared.target.lock().unwrap() = option;
+    }
+
+    /// Changes how often the status is polled, from the end of the current
+    /// interval.
+    pub fn set_interval(&self, interval: Duration) {
+        *self.shared.interval.lock().unwrap() = interval;
+    }
+
+    /// Changes how reconnecting is retried, from the next connection that
+    /// drops.
+    pub fn set_policy(&self, policy: RetryPolicy) {
+        *self.shared.policy.lock().unwrap() = policy;
+    }
+
+    /// Marks the next disconnect as intended, which should be called just
+    /// before disconnecting.
+    pub fn expect_disconnect(&self) {
+        self.shared.expected.store(true, Ordering::SeqCst);
+    }
+
+    /// Tells the watchdog whether the system has a network connection. While
+    /// offline a dropped connection is only noted, and once back online it
+    /// is reconnected straight away.
+    pub fn set_online(&self, online: bool) {
+        let was_online = self.shared.online.swap(online, Ordering::SeqCst);
+
+        if online && !was_online {
+            self.wake(true);
+        }
+    }
+
+    /// Polls the status immediately, such as after the system resumes from
+    /// suspend, rather than at the end of the interval.
+    pub fn check_now(&self) {
+        self.wake(false);
+    }
+
+    fn wake(&self, retry: bool) {
+        let mut wake = self.shared.wake.lock().unwrap();
+
+        wake.poll = true;
+        wake.retry |= retry;
+        self.shared.woken.notify_all();
+    }
+}
+
+/// Polls the connection status on a background thread and reconnects when
+/// the connection drops unexpectedly. Disconnects made outside of the
+/// application cannot be told apart from dropped connections, so they are
+/// reconnected too. Polling stops when the watchdog is dropped.
+pub struct Watchdog {
+    events: Receiver<WatchdogEvent>,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    pub fn new(interval: Duration, policy: RetryPolicy) -> Self {
+        Self::with_backend(NordVPN, interval, policy)
+    }
+
+    /// Every attempt to reconnect is made after the delay of `policy`, up to
+    /// its maximum attempts. Unlike [`RetryPolicy::retry`], any error is
+    /// retried.
+    pub fn with_backend<B>(backend: B, interval: Duration, policy: RetryPolicy) -> Self
+    where
+        B: NordVpnBackend + Send + 'static,
+    {
+        let (event_sender, events) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            enabled: AtomicBool::new(true),
+            online: AtomicBool::new(true),
+            interval: Mutex::new(interval),
+            policy: Mutex::new(policy),
+            ..Shared::default()
+        });
+
+        let state = shared.clone();
+        let thread = thread::spawn(move || {
+            let mut previous: Option<Status> = None;
+            // What to reconnect to, while disconnected unexpectedly.
+            let mut pending = None;
+            let mut gave_up = false;
+            // Waits for the duration or until woken, returning the reason it
+            // was woken, if it was.
+            let wait = |duration| {
+                let wake = state.wake.lock().unwrap();
+                let (mut wake, _) = state
+                    .woken
+                    .wait_timeout_while(wake, duration, |wake| !wake.poll && !wake.stop)
+                    .unwrap();
+
+                std::mem::take(&mut *wake)
+            };
+
+            loop {
+                // Errors are usually the daemon being briefly unavailable,
+                // which is not a reason to reconnect.
+                if let Ok(status) = backend.status() {
+                    let dropped = previous.is_some() && status.is_none();
+
+                    if status.is_some() {
+                        // Whoever connected did so on purpose, and a
+                        // disconnect flagged while disconnected does not
+                        // apply to the new connection.
+                        pending = None;
+                        gave_up = false;
+
+                        if previous.is_none() {
+                            state.expected.store(false, Ordering::SeqCst);
+                        }
+                    } else if dropped
+                        && !state.expected.swap(false, Ordering::SeqCst)
+                        && state.enabled.load(Ordering::SeqCst)
+                    {
+                        pending = Some(state.target.lock().unwrap().clone().unwrap_or_else(|| {
+                            ConnectOption::server_from_hostname(
+                                &previous.as_ref().unwrap().hostname,
+                            )
+                        }));
+                        gave_up = false;
+                    }
+
+                    previous = status;
+                }
+
+                let reconnect = match &pending {
+                    Some(option)
+                        if !gave_up
+                            && state.online.load(Ordering::SeqCst)
+                            && state.enabled.load(Ordering::SeqCst) =>
+                    {
+                        option.clone()
+                    }
+                    _ => {
+                        let interval = *state.interval.lock().unwrap();
+                        let wake = wait(interval);
+
+                        if wake.stop {
+                            return;
+                        }
+                        gave_up &= !wake.retry;
+                        continue;
+                    }
+                };
+
+                let policy = *state.policy.lock().unwrap();
+
+                for attempt in 0..policy.max_attempts.max(1) {
+                    if attempt > 0 && wait(policy.delay(attempt - 1)).stop {
+                        return;
+                    }
+
+                    let reconnecting = WatchdogEvent::Reconnecting {
+                        option: reconnect.clone(),
+                        attempt: attempt + 1,
+                    };
+
+                    if event_sender.send(reconnecting).is_err() {
+                        return;
+                    }
+
+                    let event = match backend.connect(Some(&reconnect)) {
+                        Ok(connected) => {
+                            pending = None;
+                            WatchdogEvent::Reconnected(connected)
+                        }
+                        Err(error) if attempt + 1 >= policy.max_attempts => {
+                            gave_up = true;
+                            WatchdogEvent::GaveUp(error)
+                        }
+                        Err(_) => continue,
+                    };
+
+                    if event_sender.send(event).is_err() {
+                        return;
+                    }
+
+                    break;
+                }
+            }
+        });
+
+        Self {
+            events,
+            shared,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn handle(&self) -> WatchdogHandle {
+        WatchdogHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    pub fn events(&self) -> &Receiver<WatchdogEvent> {
+        &self.events
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.shared.wake.lock().unwrap().stop = true;
+        self.shared.woken.notify_all();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Watchdog, WatchdogEvent};
+    use crate::nordvpn::{ConnectOption, MockBackend, RetryPolicy};
+    use std::time::Duration;
+
+    const STATUS: &str = "Status: Connected\n\
+        Current server: de507.nordvpn.com\n\
+        Country: Germany\n\
+        City: Berlin\n\
+        Server IP: 185.0.0.1\n\
+        Current technology: NORDLYNX\n\
+        Current protocol: UDP\n\
+        Transfer: 1.2 MiB received, 300 KiB sent\n\
+        Uptime: 1 hour 2 minutes 3 seconds\n";
+
+    #[test]
+    fn test_watchdog() {
+        let backend = MockBackend::new();
+
+        backend
+            .respond(["status"], STATUS)
+            .respond(["status"], "Status: Disconnected\n")
+            .respond(["status"], STATUS)
+            .respond(
+                ["connect", "de507"],
+                "Connecting to Germany #507 (de507.nordvpn.com)\n\
+                 You are connected to Germany #507 (de507.nordvpn.com)!\n",
+            );
+
+        let watchdog = Watchdog::with_backend(
+            backend,
+            Duration::from_millis(5),
+            RetryPolicy::default().initial_delay(Duration::from_millis(1)),
+        );
+        let event = || watchdog.events().recv_timeout(Duration::from_secs(5));
+
+        match event().unwrap() {
+            WatchdogEvent::Reconnecting { option, attempt } => {
+                assert_eq!(option, ConnectOption::Server("de507".to_owned()));
+                assert_eq!(attempt, 1);
+            }
+            event => panic!("unexpected {:?}", event),
+        }
+        match event().unwrap() {
+            WatchdogEvent::Reconnected(connected) => assert_eq!(connected.server, Some(507)),
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_watchdog_offline() {
+        let backend = MockBackend::new();
+
+        backend
+            .respond(["status"], STATUS)
+            .respond(["status"], "Status: Disconnected\n")
+            .respond(
+                ["connect", "de507"],
+                "Connecting to Germany #507 (de507.nordvpn.com)\n\
+                 You are connected to Germany #507 (de507.nordvpn.com)!\n",
+            );
+
+        // Polls rarely, so that only being woken can explain a quick result.
+        let watchdog =
+            Watchdog::with_backend(backend, Duration::from_secs(60), RetryPolicy::default());
+        let handle = watchdog.handle();
+
+        handle.set_online(false);
+        handle.check_now();
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(watchdog.events().try_recv().is_err());
+
+        handle.set_online(true);
+
+        let event = watchdog.events().recv_timeout(Duration::from_secs(5));
+        assert!(matches!(
+            event,
+            Ok(WatchdogEvent::Reconnecting { attempt: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+use super::backend::{NordVPN, NordVpnBackend};
+use super::cli::{CliResult, Status, Transfer};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A change between two consecutive polls of the connection status.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusEvent {
+    Connected(Status),
+    Disconnected,
+    /// The connection moved to a different server without disconnecting.
+    ServerChanged(Status),
+    TransferUpdated(Transfer),
+}
+
+impl StatusEvent {
+    /// The events describing the change from `previous` to `next`, in the
+    /// order they should be handled.
+    pub fn diff(previous: Option<&Status>, next: Option<&Status>) -> Vec<Self> {
+        match (previous, next) {
+            (None, None) => Vec::new(),
+            (None, Some(next)) => vec![Self::Connected(next.clone())],
+            (Some(_), None) => vec![Self::Disconnected],
+            (Some(previous), Some(next)) if previous.hostname != next.hostname => {
+                vec![Self::ServerChanged(next.clone())]
+            }
+            (Some(previous), Some(next)) if previous.transfer != next.transfer => {
+                vec![Self::TransferUpdated(next.transfer)]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Polls the connection status on a background thread and sends an event
+/// whenever it changes. Polling stops when the watcher is dropped.
+pub struct StatusWatcher {
+    events: Receiver<CliResult<StatusEvent>>,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl StatusWatcher {
+    pub fn new(interval: Duration) -> Self {
+        Self::with_backend(NordVPN, interval)
+    }
+
+    pub fn with_backend<B>(backend: B, interval: Duration) -> Self
+    where
+        B: NordVpnBackend + Send + 'static,
+    {
+        let (event_sender, events) = mpsc::channel();
+        let (stop, stop_receiver) = mpsc::channel::<()>();
+
+        let thread = thread::spawn(move || {
+            let mut previous = None;
+
+            loop {
+                match backend.status() {
+                    Ok(status) => {
+                        for event in StatusEvent::diff(previous.as_ref(), status.as_ref()) {
+                            if event_sender.send(Ok(event)).is_err() {
+                                return;
+                            }
+                        }
+                        previous = status;
+                    }
+                    Err(error) => {
+                        if event_sender.send(Err(error)).is_err() {
+                            return;
+                        }
+                    }
+                }
+
+                match stop_receiver.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return,
+                }
+            }
+        });
+
+        Self {
+            events,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// The receiving end of the event channel. Errors from polling are sent
+    /// as they happen and do not stop the watcher.
+    pub fn events(&self) -> &Receiver<CliResult<StatusEvent>> {
+        &self.events
+    }
+}
+
+impl Drop for StatusWatcher {
+    fn drop(&mut self) {
+        // Disconnecting the stop channel wakes the thread immediately.
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Download and upload speeds in bytes per second.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct TransferRate {
+    pub received: f64,
+    pub sent: f64,
+}
+
+/// Turns successive [`Transfer`] totals, such as those from
+/// [`StatusEvent::TransferUpdated`], into transfer rates.
+#[derive(Debug, Clone, Default)]
+pub struct TransferTracker {
+    last: Option<(Instant, Transfer)>,
+}
+
+impl TransferTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a snapshot taken now, returning the rate since the previous
+    /// one, or `None` for the first snapshot.
+    pub fn update(&mut self, transfer: Transfer) -> Option<TransferRate> {
+        self.update_at(transfer, Instant::now())
+    }
+
+    /// Records a snapshot taken at `at`.
+    ///
+    /// The totals restart from zero when the connection is re-established, so
+    /// a total lower than the previous one is counted as transferred entirely
+    /// since the previous snapshot instead of producing a negative rate.
+    pub fn update_at(&mut self, transfer: Transfer, at: Instant) -> Option<TransferRate> {
+        let last = self.last.replace((at, transfer));
+        let (last_at, last_transfer) = last?;
+        let seconds = at.checked_duration_since(last_at)?.as_secs_f64();
+
+        if seconds == 0.0 {
+            return None;
+        }
+
+        let rate = |total: u128, last: u128| {
+            let delta = if total >= last { total - last } else { total };
+            delta as f64 / seconds
+        };
+
+        Some(TransferRate {
+            received: rate(
+                transfer.received.get_bytes(),
+                last_transfer.received.get_bytes(),
+            ),
+            sent: rate(transfer.sent.get_bytes(), last_transfer.sent.get_bytes()),
+        })
+    }
+
+    /// Forgets the previous snapshot, such as after disconnecting.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StatusEvent, StatusWatcher, TransferRate, TransferTracker};
+    use crate::nordvpn::MockBackend;
+    use crate::nordvpn::Transfer;
+    use byte_unit::Byte;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    const CONNECTED: &str = "Status: Connected\n\
+        Current server: de507.nordvpn.com\n\
+        Country: Germany\n\
+        City: Berlin\n\
+        Server IP: 185.0.0.1\n\
+        Current technology: NORDLYNX\n\
+        Current protocol: UDP\n\
+        Transfer: 1.2 MiB received, 300 KiB sent\n\
+        Uptime: 2 seconds\n";
+
+    #[test]
+    fn test_transfer_tracker() {
+        let transfer = |received, sent| Transfer {
+            received: Byte::from_bytes(received),
+            sent: Byte::from_bytes(sent),
+        };
+        let start = Instant::now();
+        let mut tracker = TransferTracker::new();
+
+        assert_eq!(tracker.update_at(transfer(1000, 100), start), None);
+        assert_eq!(
+            tracker.update_at(transfer(3000, 300), start + Duration::from_secs(2)),
+            Some(TransferRate {
+                received: 1000.0,
+                sent: 100.0
+            })
+        );
+        // Reconnected, so the totals started over.
+        assert_eq!(
+            tracker.update_at(transfer(500, 50), start + Duration::from_secs(3)),
+            Some(TransferRate {
+                received: 500.0,
+                sent: 50.0
+            })
+        );
+    }
+
+    #[test]
+    fn test_status_watcher() {
+        let backend = MockBackend::new();
+
+        backend
+            .respond(["status"], "Status: Disconnected\n")
+            .respond(["status"], CONNECTED)
+            .respond(["status"], CONNECTED.replace("1.2 MiB", "2.4 MiB"))
+            .respond(["status"], CONNECTED.replace("de507", "de508"))
+            .respond(["status"], "Status: Disconnected\n");
+
+        let watcher = StatusWatcher::with_backend(backend, Duration::from_millis(1));
+        let events = watcher
+            .events()
+            .iter()
+            .take(4)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        assert!(matches!(events[0], StatusEvent::Connected(_)));
+        assert!(matches!(events[1], StatusEvent::TransferUpdated(_)));
+        assert!(
+            matches!(&events[2], StatusEvent::ServerChanged(status) if status.hostname == "de508.nordvpn.com")
+        );
+        assert_eq!(events[3], StatusEvent::Disconnected);
+    }
+}
@@ -0,0 +1,175 @@
+//! Named presets of what to connect to and the daemon settings to connect
+//! with, which are applied together.
+
+use crate::nordvpn::{self, CliResult, ConnectOption, Connected, Protocol, Settings, Technology};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+/// Settings left as `None` are not changed when the profile is applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    /// What to connect to, or `None` to let the CLI choose.
+    #[serde(default, with = "optional")]
+    pub target: Option<ConnectOption>,
+    #[serde(default, with = "optional")]
+    pub technology: Option<Technology>,
+    /// Only applies to OpenVPN, and is skipped for NordLynx.
+    #[serde(default, with = "optional")]
+    pub protocol: Option<Protocol>,
+    /// Custom DNS servers, or an empty list for NordVPN's.
+    #[serde(default)]
+    pub dns: Option<Vec<IpAddr>>,
+    #[serde(default)]
+    pub killswitch: Option<bool>,
+}
+
+impl Profile {
+    /// A profile which only connects to `target`.
+    pub fn new<S: Into<String>>(name: S, target: Option<ConnectOption>) -> Self {
+        Self {
+            name: name.into(),
+            target,
+            technology: None,
+            protocol: None,
+            dns: None,
+            killswitch: None,
+        }
+    }
+
+    /// A profile which connects to `target` with every setting it covers
+    /// as they are in `settings`.
+    pub fn from_settings<S: Into<String>>(
+        name: S,
+        target: Option<ConnectOption>,
+        settings: &Settings,
+    ) -> Self {
+        Self {
+            technology: Some(settings.technology),
+            protocol: settings.protocol,
+            dns: Some(settings.dns.iter().flatten().copied().collect()),
+            killswitch: Some(settings.killswitch),
+            ..Self::new(name, target)
+        }
+    }
+
+    /// Changes the settings of the profile, then connects. If any setting
+    /// cannot be changed, those changed already are put back and nothing is
+    /// connected, so that the profile is applied completely or not at all.
+    pub fn apply(&self) -> CliResult<Connected> {
+        let mut settings = nordvpn::settings()?;
+        let previous = settings.clone();
+
+        if let Err(error) = self.apply_settings(&mut settings) {
+            Self::from_settings("", None, &previous)
+                .apply_settings(&mut settings)
+                .ok();
+            // Custom DNS turns off Threat Protection Lite.
+            if previous.cybersec && !settings.cybersec {
+                settings.set_cybersec(true).ok();
+            }
+
+            return Err(error);
+        }
+
+        nordvpn::connect(self.target.as_ref())
+    }
+
+    /// Changes the settings which differ from the profile.
+    fn apply_settings(&self, settings: &mut Settings) -> CliResult<()> {
+        if let Some(technology) = self.technology {
+            if settings.technology != technology {
+                settings.set_technology(technology)?;
+            }
+        }
+        // NordLynx only runs over UDP, and the CLI refuses to set a protocol.
+        if let Some(protocol) = self.protocol {
+            if settings.technology == Technology::OpenVpn && settings.protocol != Some(protocol) {
+                settings.set_protocol(protocol)?;
+            }
+        }
+        if let Some(dns) = &self.dns {
+            let dns = match dns.is_empty() {
+                true => None,
+                false => Some(dns.iter().copied().collect::<HashSet<_>>()),
+            };
+
+            if settings.dns != dns {
+                settings.set_dns(dns)?;
+            }
+        }
+        if let Some(killswitch) = self.killswitch {
+            if settings.killswitch != killswitch {
+                settings.set_killswitch(killswitch)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Stores an optional value in its string form, for the types of the CLI.
+pub(crate) mod optional {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|text| text.parse().map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Profile;
+    use crate::nordvpn::{ConnectOption, Protocol, Settings, Technology};
+
+    #[test]
+    fn test_profile() {
+        let settings = Settings {
+            technology: Technology::OpenVpn,
+            protocol: Some(Protocol::Tcp),
+            firewall: true,
+            killswitch: true,
+            cybersec: false,
+            obfuscate: Some(false),
+            notify: false,
+            autoconnect: false,
+            ipv6: false,
+            post_quantum: None,
+            lan_discovery: None,
+            dns: Some(["1.1.1.1".parse().unwrap()].into_iter().collect()),
+        };
+        let target = ConnectOption::Group("P2P".to_owned());
+        let profile = Profile::from_settings("Torrents", Some(target.clone()), &settings);
+
+        assert_eq!(profile.technology, Some(Technology::OpenVpn));
+        assert_eq!(profile.dns, Some(vec!["1.1.1.1".parse().unwrap()]));
+
+        let text = serde_json::to_string(&profile).unwrap();
+        assert!(text.contains("\"group:P2P\""));
+        assert!(text.contains("\"TCP\""));
+        assert_eq!(serde_json::from_str::<Profile>(&text).unwrap(), profile);
+
+        let minimal = serde_json::from_str::<Profile>(r#"{ "name": "Any" }"#).unwrap();
+        assert_eq!(minimal, Profile::new("Any", None));
+    }
+}
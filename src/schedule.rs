@@ -0,0 +1,246 @@
+//! Rules which connect or disconnect at a time of day, and the background
+//! thread which carries them out.
+
+use crate::config::Config;
+use crate::nordvpn::{self, CliError, ConnectOption, WatchdogHandle};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, TimeZone, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the rules are read again, so that changes are picked up.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How late a rule may still run, such as after resuming from suspend.
+/// Runs missed by more than this are skipped.
+const MISSED_GRACE: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub enabled: bool,
+    /// The local time of day to run at.
+    pub time: NaiveTime,
+    /// The days to run on, or every day if empty.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub action: ScheduledAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScheduledAction {
+    /// Connects to the option, or `None` to let the CLI choose.
+    Connect(#[serde(with = "crate::profile::optional")] Option<ConnectOption>),
+    /// Applies the profile by its name.
+    Profile(String),
+    Disconnect,
+}
+
+/// A rule that ran, and whether it succeeded.
+#[derive(Debug)]
+pub struct ScheduleEvent {
+    pub rule: Rule,
+    pub result: Result<(), ScheduleError>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error(transparent)]
+    Cli(#[from] CliError),
+    #[error("there is no profile named {0:?}")]
+    NoProfile(String),
+}
+
+impl Rule {
+    /// An enabled rule which runs every day.
+    pub fn new(time: NaiveTime, action: ScheduledAction) -> Self {
+        Self {
+            enabled: true,
+            time,
+            days: Vec::new(),
+            action,
+        }
+    }
+
+    pub fn runs_on(&self, weekday: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&weekday)
+    }
+
+    /// When the rule runs next, strictly after `after`, whether or not it is
+    /// enabled. This is `None` only if the time never exists on the days of
+    /// the rule, such as during a daylight saving gap every week.
+    pub fn next_run<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let timezone = after.timezone();
+
+        (0..=7)
+            .map(|days| after.date_naive() + ChronoDuration::days(days))
+            .filter(|date| self.runs_on(date.weekday()))
+            .filter_map(|date| {
+                timezone
+                    .from_local_datetime(&date.and_time(self.time))
+                    .earliest()
+            })
+            .find(|run| run > after)
+    }
+
+    /// Carries out the action of the rule, telling the watchdog about it so
+    /// that it does not undo it.
+    pub fn run(&self, watchdog: Option<&WatchdogHandle>) -> Result<(), ScheduleError> {
+        match &self.action {
+            ScheduledAction::Connect(option) => {
+                if let Some(watchdog) = watchdog {
+                    watchdog.set_target(option.clone());
+                }
+                nordvpn::connect(option.as_ref())?;
+            }
+            ScheduledAction::Profile(name) => {
+                let config = Config::load().unwrap_or_default();
+                let profile = config
+                    .profile(name)
+                    .ok_or_else(|| ScheduleError::NoProfile(name.clone()))?;
+
+                if let Some(watchdog) = watchdog {
+                    watchdog.set_target(profile.target.clone());
+                }
+                profile.apply()?;
+            }
+            ScheduledAction::Disconnect => {
+                if let Some(watchdog) = watchdog {
+                    watchdog.expect_disconnect();
+                }
+                nordvpn::disconnect()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for ScheduledAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Connect(Some(option)) => write!(f, "Connect to {}", option),
+            Self::Connect(None) => write!(f, "Connect"),
+            Self::Profile(name) => write!(f, "Connect with {}", name),
+            Self::Disconnect => write!(f, "Disconnect"),
+        }
+    }
+}
+
+/// Runs the enabled rules of the config on a background thread, sending an
+/// event for every rule that ran. The rules are read again regularly, so the
+/// config can be changed while it runs. Running stops when the scheduler is
+/// dropped.
+pub struct Scheduler {
+    events: Receiver<ScheduleEvent>,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Scheduler {
+    pub fn new(watchdog: Option<WatchdogHandle>) -> Self {
+        let (event_sender, events) = mpsc::channel();
+        let (stop, stop_receiver) = mpsc::channel::<()>();
+
+        let thread = thread::spawn(move || {
+            let mut checked = Local::now();
+
+            loop {
+                let rules = Config::load().unwrap_or_default().schedule;
+                let now = Local::now();
+
+                for rule in rules.iter().filter(|rule| rule.enabled) {
+                    let due = match rule.next_run(&checked) {
+                        Some(due) if due <= now => due,
+                        _ => continue,
+                    };
+
+                    if (now - due).to_std().unwrap_or_default() > MISSED_GRACE {
+                        continue;
+                    }
+
+                    let event = ScheduleEvent {
+                        rule: rule.clone(),
+                        result: rule.run(watchdog.as_ref()),
+                    };
+
+                    if event_sender.send(event).is_err() {
+                        return;
+                    }
+                }
+
+                checked = now;
+
+                // Wakes for the next run, or to read the rules again.
+                let wait = rules
+                    .iter()
+                    .filter(|rule| rule.enabled)
+                    .filter_map(|rule| rule.next_run(&now))
+                    .filter_map(|run| (run - Local::now()).to_std().ok())
+                    .fold(RELOAD_INTERVAL, Duration::min);
+
+                match stop_receiver.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return,
+                }
+            }
+        });
+
+        Self {
+            events,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn events(&self) -> &Receiver<ScheduleEvent> {
+        &self.events
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread, which then returns.
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rule, ScheduledAction};
+    use crate::nordvpn::ConnectOption;
+    use chrono::{NaiveTime, TimeZone, Utc, Weekday};
+
+    #[test]
+    fn test_next_run() {
+        let time = NaiveTime::from_hms_opt(22, 0, 0).unwrap();
+        let p2p = ScheduledAction::Connect(Some(ConnectOption::Group("P2P".to_owned())));
+        let mut rule = Rule::new(time, p2p);
+        // A Wednesday.
+        let morning = Utc.with_ymd_and_hms(2026, 10, 14, 7, 0, 0).unwrap();
+        let night = Utc.with_ymd_and_hms(2026, 10, 14, 22, 0, 0).unwrap();
+
+        assert_eq!(rule.next_run(&morning), Some(night));
+        assert_eq!(
+            rule.next_run(&night),
+            Some(Utc.with_ymd_and_hms(2026, 10, 15, 22, 0, 0).unwrap())
+        );
+
+        rule.days = vec![Weekday::Mon];
+        assert_eq!(
+            rule.next_run(&morning),
+            Some(Utc.with_ymd_and_hms(2026, 10, 19, 22, 0, 0).unwrap())
+        );
+
+        let text = serde_json::to_string(&rule).unwrap();
+        assert!(text.contains("\"connect\":\"group:P2P\""));
+        assert_eq!(serde_json::from_str::<Rule>(&text).unwrap(), rule);
+        assert_eq!(rule.action.to_string(), "Connect to group:P2P");
+    }
+}
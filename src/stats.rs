@@ -0,0 +1,501 @@
+//! Transfer totals per connection and per day, recorded from status events
+//! and kept in a SQLite database in the XDG data directory.
+
+use crate::config::xdg_dir;
+use crate::nordvpn::{Status, StatusEvent, Transfer};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// How far apart the start of a connection may be read, since it is derived
+/// from the rounded uptime, and still be the same connection.
+const SAME_SESSION_SECONDS: i64 = 5;
+
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        id INTEGER PRIMARY KEY,
+        hostname TEXT NOT NULL,
+        country TEXT NOT NULL,
+        city TEXT NOT NULL,
+        started INTEGER NOT NULL,
+        ended INTEGER,
+        received INTEGER NOT NULL,
+        sent INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS daily (
+        day TEXT NOT NULL,
+        country TEXT NOT NULL,
+        received INTEGER NOT NULL,
+        sent INTEGER NOT NULL,
+        PRIMARY KEY (day, country)
+    );
+";
+
+pub type StatsResult<T> = Result<T, StatsError>;
+
+#[derive(Debug, Error)]
+pub enum StatsError {
+    #[error("the statistics database could not be created: {0}")]
+    Io(#[from] io::Error),
+    #[error("the statistics database failed: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+/// The length of time totals are grouped by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    /// Weeks starting on Monday.
+    Week,
+    Month,
+}
+
+/// The bytes carried within one period, starting on the date.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeriodTotal {
+    pub start: NaiveDate,
+    pub received: u64,
+    pub sent: u64,
+}
+
+/// The bytes carried through servers in one country on one day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyTotal {
+    pub day: NaiveDate,
+    pub country: String,
+    pub received: u64,
+    pub sent: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountryTotal {
+    pub country: String,
+    pub received: u64,
+    pub sent: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub hostname: String,
+    pub country: String,
+    pub city: String,
+    pub started: DateTime<Local>,
+    /// When the connection ended, or `None` while it is still up or if the
+    /// application was not running to see it end.
+    pub ended: Option<DateTime<Local>>,
+    pub received: u64,
+    pub sent: u64,
+}
+
+/// The session being recorded, with the totals last written for it.
+#[derive(Debug, Clone)]
+struct Current {
+    id: i64,
+    country: String,
+    received: u64,
+    sent: u64,
+}
+
+pub struct Stats {
+    connection: Connection,
+    current: Option<Current>,
+}
+
+impl Period {
+    /// The first day of the period which `date` is in.
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Self::Month => date.with_day(1).unwrap(),
+        }
+    }
+}
+
+impl Stats {
+    /// `$XDG_DATA_HOME/nordvpn-viking/stats.sqlite3`, or under
+    /// `~/.local/share` if the variable is not set.
+    pub fn path() -> PathBuf {
+        xdg_dir("XDG_DATA_HOME", ".local/share").join("stats.sqlite3")
+    }
+
+    /// Opens the database at the default path, creating it if needed.
+    pub fn open() -> StatsResult<Self> {
+        Self::open_at(Self::path())
+    }
+
+    pub fn open_at<P: AsRef<Path>>(path: P) -> StatsResult<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A database which is gone once dropped.
+    pub fn in_memory() -> StatsResult<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> StatsResult<Self> {
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            connection,
+            current: None,
+        })
+    }
+
+    /// Adds what changed with the event to the totals.
+    pub fn record(&mut self, event: &StatusEvent) -> StatsResult<()> {
+        self.record_at(event, Local::now())
+    }
+
+    pub fn record_at(&mut self, event: &StatusEvent, now: DateTime<Local>) -> StatsResult<()> {
+        match event {
+            StatusEvent::Connected(status) | StatusEvent::ServerChanged(status) => {
+                self.finish(now)?;
+                self.start(status, now)
+            }
+            StatusEvent::Disconnected => self.finish(now),
+            StatusEvent::TransferUpdated(transfer) => self.transfer(*transfer, now),
+        }
+    }
+
+    /// Starts recording the connection, or carries on with it if it was
+    /// recorded before, such as before the application restarted.
+    fn start(&mut self, status: &Status, now: DateTime<Local>) -> StatsResult<()> {
+        let started = status.connected_since.timestamp();
+        let existing = self
+            .connection
+            .query_row(
+                "SELECT id, received, sent FROM sessions
+                 WHERE hostname = ?1 AND ended IS NULL AND ABS(started - ?2) <= ?3",
+                params![status.hostname, started, SAME_SESSION_SECONDS],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+
+        self.current = Some(match existing {
+            Some((id, received, sent)) => Current {
+                id,
+                country: status.country.clone(),
+                received,
+                sent,
+            },
+            None => {
+                self.connection.execute(
+                    "INSERT INTO sessions (hostname, country, city, started, received, sent)
+                     VALUES (?1, ?2, ?3, ?4, 0, 0)",
+                    params![status.hostname, status.country, status.city, started],
+                )?;
+
+                Current {
+                    id: self.connection.last_insert_rowid(),
+                    country: status.country.clone(),
+                    received: 0,
+                    sent: 0,
+                }
+            }
+        });
+
+        self.transfer(status.transfer, now)
+    }
+
+    fn finish(&mut self, now: DateTime<Local>) -> StatsResult<()> {
+        if let Some(current) = self.current.take() {
+            self.connection.execute(
+                "UPDATE sessions SET ended = ?1 WHERE id = ?2",
+                params![now.timestamp(), current.id],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the bytes carried since the last update to the session and to
+    /// the day of `now`.
+    fn transfer(&mut self, transfer: Transfer, now: DateTime<Local>) -> StatsResult<()> {
+        let current = match &mut self.current {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        let received = transfer.received.get_bytes() as u64;
+        let sent = transfer.sent.get_bytes() as u64;
+        // The totals only go down if the daemon restarted the connection.
+        let delta = |total: u64, last: u64| total.checked_sub(last).unwrap_or(total);
+        let (received_delta, sent_delta) =
+            (delta(received, current.received), delta(sent, current.sent));
+
+        current.received = received;
+        current.sent = sent;
+
+        let transaction = self.connection.transaction()?;
+
+        transaction.execute(
+            "UPDATE sessions SET received = ?1, sent = ?2 WHERE id = ?3",
+            params![received, sent, current.id],
+        )?;
+        if received_delta > 0 || sent_delta > 0 {
+            transaction.execute(
+                "INSERT INTO daily (day, country, received, sent) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (day, country) DO UPDATE SET
+                     received = received + excluded.received,
+                     sent = sent + excluded.sent",
+                params![
+                    now.format(DAY_FORMAT).to_string(),
+                    current.country,
+                    received_delta,
+                    sent_delta
+                ],
+            )?;
+        }
+
+        Ok(transaction.commit()?)
+    }
+
+    /// The totals of every period from the one containing `from` through the
+    /// one containing `to`, oldest first, leaving out periods with nothing.
+    pub fn totals(
+        &self,
+        period: Period,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> StatsResult<Vec<PeriodTotal>> {
+        let from = period.start_of(from);
+        let mut statement = self.connection.prepare(
+            "SELECT day, SUM(received), SUM(sent) FROM daily
+             WHERE day >= ?1 AND day <= ?2 GROUP BY day",
+        )?;
+        let rows = statement.query_map(
+            params![
+                from.format(DAY_FORMAT).to_string(),
+                to.format(DAY_FORMAT).to_string()
+            ],
+            |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let mut totals = BTreeMap::<NaiveDate, (u64, u64)>::new();
+
+        for row in rows {
+            let (day, received, sent): (String, u64, u64) = row?;
+            let day = match NaiveDate::parse_from_str(&day, DAY_FORMAT) {
+                Ok(day) => day,
+                Err(_) => continue,
+            };
+            let total = totals.entry(period.start_of(day)).or_default();
+
+            total.0 += received;
+            total.1 += sent;
+        }
+
+        Ok(totals
+            .into_iter()
+            .map(|(start, (received, sent))| PeriodTotal {
+                start,
+                received,
+                sent,
+            })
+            .collect())
+    }
+
+    /// The totals of every day and country between the dates, inclusive,
+    /// oldest first.
+    pub fn daily(&self, from: NaiveDate, to: NaiveDate) -> StatsResult<Vec<DailyTotal>> {
+        let mut statement = self.connection.prepare(
+            "SELECT day, country, received, sent FROM daily
+             WHERE day >= ?1 AND day <= ?2 ORDER BY day, country",
+        )?;
+        let rows = statement.query_map(
+            params![
+                from.format(DAY_FORMAT).to_string(),
+                to.format(DAY_FORMAT).to_string()
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                ))
+            },
+        )?;
+        let mut totals = Vec::new();
+
+        for row in rows {
+            let (day, country, received, sent) = row?;
+
+            if let Ok(day) = NaiveDate::parse_from_str(&day, DAY_FORMAT) {
+                totals.push(DailyTotal {
+                    day,
+                    country,
+                    received,
+                    sent,
+                });
+            }
+        }
+
+        Ok(totals)
+    }
+
+    /// The totals of each country between the dates, inclusive, with the
+    /// most carried first.
+    pub fn by_country(&self, from: NaiveDate, to: NaiveDate) -> StatsResult<Vec<CountryTotal>> {
+        let mut statement = self.connection.prepare(
+            "SELECT country, SUM(received), SUM(sent) FROM daily
+             WHERE day >= ?1 AND day <= ?2
+             GROUP BY country ORDER BY SUM(received) + SUM(sent) DESC",
+        )?;
+        let totals = statement
+            .query_map(
+                params![
+                    from.format(DAY_FORMAT).to_string(),
+                    to.format(DAY_FORMAT).to_string()
+                ],
+                |row| {
+                    Ok(CountryTotal {
+                        country: row.get(0)?,
+                        received: row.get(1)?,
+                        sent: row.get(2)?,
+                    })
+                },
+            )?
+            .collect::<Result<_, _>>()?;
+
+        Ok(totals)
+    }
+
+    /// The sessions which started between the times, newest first.
+    pub fn sessions(
+        &self,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> StatsResult<Vec<Session>> {
+        let mut statement = self.connection.prepare(
+            "SELECT hostname, country, city, started, ended, received, sent FROM sessions
+             WHERE started >= ?1 AND started <= ?2 ORDER BY started DESC",
+        )?;
+        let time = |seconds: i64| Local.timestamp_opt(seconds, 0).unwrap();
+        let sessions = statement
+            .query_map(params![from.timestamp(), to.timestamp()], |row| {
+                Ok(Session {
+                    hostname: row.get(0)?,
+                    country: row.get(1)?,
+                    city: row.get(2)?,
+                    started: time(row.get(3)?),
+                    ended: row.get::<_, Option<i64>>(4)?.map(time),
+                    received: row.get(5)?,
+                    sent: row.get(6)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+
+        Ok(sessions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Period, Stats};
+    use crate::nordvpn::{MockBackend, NordVpnBackend, Status, StatusEvent, Transfer};
+    use byte_unit::Byte;
+    use chrono::{Duration, Local, NaiveDate, TimeZone};
+
+    #[test]
+    fn test_stats() {
+        let backend = MockBackend::new();
+        backend.respond(
+            ["status"],
+            "Status: Connected\n\
+            Current server: de507.nordvpn.com\n\
+            Country: Germany\n\
+            City: Berlin\n\
+            Server IP: 185.0.0.1\n\
+            Current technology: NORDLYNX\n\
+            Current protocol: UDP\n\
+            Transfer: 1 KiB received, 1 KiB sent\n\
+            Uptime: 1 minute\n",
+        );
+
+        let status = backend.status().unwrap().unwrap();
+        let transfer = |received, sent| {
+            StatusEvent::TransferUpdated(Transfer {
+                received: Byte::from_bytes(received),
+                sent: Byte::from_bytes(sent),
+            })
+        };
+        // A Sunday, so that the next day starts a new week.
+        let sunday = Local.with_ymd_and_hms(2026, 10, 18, 23, 0, 0).unwrap();
+        let monday = sunday + Duration::hours(2);
+        let mut stats = Stats::in_memory().unwrap();
+
+        stats
+            .record_at(&StatusEvent::Connected(status.clone()), sunday)
+            .unwrap();
+        stats.record_at(&transfer(3072, 2048), sunday).unwrap();
+        stats.record_at(&transfer(5120, 2048), monday).unwrap();
+
+        // The application restarting carries on with the same session.
+        let mut restarted = Stats {
+            connection: stats.connection,
+            current: None,
+        };
+        let resumed = Status {
+            transfer: Transfer {
+                received: Byte::from_bytes(6144),
+                sent: Byte::from_bytes(2048),
+            },
+            ..status.clone()
+        };
+        restarted
+            .record_at(&StatusEvent::Connected(resumed), monday)
+            .unwrap();
+
+        let moved = Status {
+            hostname: "nl1.nordvpn.com".to_owned(),
+            country: "Netherlands".to_owned(),
+            ..status
+        };
+        restarted
+            .record_at(&StatusEvent::ServerChanged(moved), monday)
+            .unwrap();
+        restarted
+            .record_at(&StatusEvent::Disconnected, monday)
+            .unwrap();
+
+        let (sunday, monday) = (sunday.date_naive(), monday.date_naive());
+        let days = restarted.totals(Period::Day, sunday, monday).unwrap();
+
+        assert_eq!(days.len(), 2);
+        assert_eq!((days[0].received, days[0].sent), (3072, 2048));
+        assert_eq!((days[1].received, days[1].sent), (4096, 1024));
+
+        let weeks = restarted.totals(Period::Week, sunday, monday).unwrap();
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[1].start, monday);
+
+        let months = restarted.totals(Period::Month, sunday, monday).unwrap();
+        assert_eq!(months.len(), 1);
+        assert_eq!(
+            months[0].start,
+            NaiveDate::from_ymd_opt(2026, 10, 1).unwrap()
+        );
+        assert_eq!(months[0].received, 7168);
+
+        let countries = restarted.by_country(sunday, monday).unwrap();
+        assert_eq!(countries[0].country, "Germany");
+        assert_eq!(countries[1].country, "Netherlands");
+
+        let sessions = restarted
+            .sessions(Local::now() - Duration::days(1), Local::now())
+            .unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.iter().all(|session| session.ended.is_some()));
+    }
+}
@@ -0,0 +1,187 @@
+//! Running the application headless as a systemd user service, which starts
+//! with the user's session and is restarted if it fails, so that dropped
+//! connections are reconnected even while the window and tray are closed.
+
+use crate::config::xdg_base_dir;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The argument which runs the watchdog, the scheduler and the D-Bus service
+/// without any interface.
+pub const DAEMON_ARG: &str = "--daemon";
+
+/// The name the daemon owns on the session bus, which the service waits for
+/// before it counts as started.
+pub const BUS_NAME: &str = "com.github.spikespaz.nordvpn-viking.Daemon";
+
+const UNIT_NAME: &str = "nordvpn-viking.service";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserService {
+    path: PathBuf,
+}
+
+impl UserService {
+    /// The unit at `$XDG_CONFIG_HOME/systemd/user`, or under `~/.config` if
+    /// the variable is not set.
+    pub fn new() -> Self {
+        Self::with_path(
+            xdg_base_dir("XDG_CONFIG_HOME", ".config")
+                .join("systemd/user")
+                .join(UNIT_NAME),
+        )
+    }
+
+    pub fn with_path<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_installed(&self) -> bool {
+        self.path.is_file()
+    }
+
+    /// Writes a unit which runs the current executable with [`DAEMON_ARG`],
+    /// then enables and starts it with `systemctl`.
+    pub fn install(&self) -> io::Result<()> {
+        self.write(&env::current_exe()?, &[DAEMON_ARG])?;
+        systemctl(&["daemon-reload"])?;
+        systemctl(&["enable", "--now", UNIT_NAME])
+    }
+
+    /// Stops and disables the service, then removes its unit, which is not
+    /// an error if it was never installed.
+    pub fn uninstall(&self) -> io::Result<()> {
+        if !self.is_installed() {
+            return Ok(());
+        }
+
+        systemctl(&["disable", "--now", UNIT_NAME])?;
+        self.remove()?;
+        systemctl(&["daemon-reload"])
+    }
+
+    /// Writes the unit without telling systemd, which only reads it again
+    /// after `systemctl --user daemon-reload`.
+    pub fn write(&self, program: &Path, args: &[&str]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let exec = [program.to_string_lossy().as_ref()]
+            .into_iter()
+            .chain(args.iter().copied())
+            .map(quote_exec)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        fs::write(
+            &self.path,
+            format!(
+                "[Unit]\n\
+                 Description=NordVPN Viking\n\
+                 Documentation={}\n\
+                 \n\
+                 [Service]\n\
+                 Type=dbus\n\
+                 BusName={}\n\
+                 ExecStart={}\n\
+                 Restart=on-failure\n\
+                 \n\
+                 [Install]\n\
+                 WantedBy=default.target\n",
+                env!("CARGO_PKG_REPOSITORY"),
+                BUS_NAME,
+                exec
+            ),
+        )
+    }
+
+    /// Removes the unit, which is not an error if there is none.
+    pub fn remove(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for UserService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs `systemctl --user`, failing with what it printed if it does.
+fn systemctl(args: &[&str]) -> io::Result<()> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()?;
+
+    if output.status.success() {
+        return Ok(());
+    }
+
+    Err(io::Error::other(format!(
+        "systemctl --user {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    )))
+}
+
+/// Quotes an argument of `ExecStart` if it has characters which systemd
+/// would split or expand.
+fn quote_exec(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%").replace('$', "$$");
+
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '"', '\'', '\\', ';']) {
+        return escaped;
+    }
+
+    format!(
+        "\"{}\"",
+        escaped
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserService;
+    use std::path::Path;
+
+    #[test]
+    fn test_user_service() {
+        let dir = std::env::temp_dir().join(format!(
+            "nordvpn-viking-systemd-test-{}",
+            std::process::id()
+        ));
+        let service = UserService::with_path(dir.join("viking.service"));
+
+        assert!(!service.is_installed());
+        service.uninstall().unwrap();
+
+        service
+            .write(Path::new("/opt/Nord VPN/viking"), &["--daemon"])
+            .unwrap();
+        assert!(service.is_installed());
+
+        let text = std::fs::read_to_string(service.path()).unwrap();
+        assert!(text.contains("ExecStart=\"/opt/Nord VPN/viking\" --daemon\n"));
+        assert!(text.contains("BusName=com.github.spikespaz.nordvpn-viking.Daemon\n"));
+
+        service.remove().unwrap();
+        assert!(!service.is_installed());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
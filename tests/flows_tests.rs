@@ -0,0 +1,176 @@
+use nordvpn_viking::nordvpn::{self, CliError, ConnectOption};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Mutex;
+
+static SCENARIO_DIR: &str = "tests/files/scenarios";
+static TEMP_DIR: &str = "target/tmp/fake-nordvpn";
+
+/// The directory with the fake as `nordvpn`, which is put first in `PATH` for
+/// this process and every command it runs.
+static BIN_DIR: Lazy<PathBuf> = Lazy::new(|| {
+    let bin_dir = Path::new(TEMP_DIR).join("bin");
+    let program = bin_dir.join("nordvpn");
+
+    fs::create_dir_all(&bin_dir).unwrap();
+    fs::remove_file(&program).ok();
+    symlink(env!("CARGO_BIN_EXE_nordvpn"), &program).unwrap();
+
+    let bin_dir = bin_dir.canonicalize().unwrap();
+    let path = env::var_os("PATH").unwrap_or_default();
+    let path = env::join_paths([bin_dir.clone()].into_iter().chain(env::split_paths(&path)));
+
+    env::set_var("PATH", path.unwrap());
+    bin_dir
+});
+
+/// The scenario of the library, which only one test can set at a time.
+static LIBRARY: Mutex<()> = Mutex::new(());
+
+struct Scenario {
+    path: PathBuf,
+}
+
+impl Scenario {
+    /// Copies the scenario to a directory of its own for `test`, where the
+    /// fake keeps its state, starting over from any previous run.
+    fn new(name: &str, test: &str) -> Self {
+        Lazy::force(&BIN_DIR);
+
+        let file = format!("{}.toml", name);
+        let dir = Path::new(TEMP_DIR).join(test);
+        let path = dir.join(&file);
+
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy(Path::new(SCENARIO_DIR).join(&file), &path).unwrap();
+
+        Self {
+            path: path.canonicalize().unwrap(),
+        }
+    }
+
+    /// Runs `viking` with `args` against the scenario.
+    fn viking(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_viking"))
+            .args(args)
+            .env("FAKE_NORDVPN_SCENARIO", &self.path)
+            .output()
+            .unwrap()
+    }
+
+    /// Runs `flow` with the library answered by the scenario.
+    fn with_library<F: FnOnce()>(&self, flow: F) {
+        let _lock = LIBRARY.lock().unwrap_or_else(|error| error.into_inner());
+
+        nordvpn::set_command_env([("FAKE_NORDVPN_SCENARIO", Some(&self.path))]);
+        flow();
+    }
+
+    /// The arguments of every run of the fake so far.
+    fn calls(&self) -> Vec<String> {
+        fs::read_to_string(self.path.with_extension("calls"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_owned)
+            .collect()
+    }
+}
+
+fn stdout_json(output: &Output) -> Value {
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_connect_flow() {
+    let scenario = Scenario::new("connect", "connect-flow");
+
+    scenario.with_library(|| {
+        assert!(nordvpn::status().unwrap().is_none());
+
+        let connected = nordvpn::connect(Some(&ConnectOption::Country("Germany".to_owned())));
+        let connected = connected.unwrap();
+
+        assert_eq!(connected.hostname, "de507.nordvpn.com");
+        assert_eq!(connected.city.as_deref(), Some("Berlin"));
+        assert_eq!(nordvpn::status().unwrap().unwrap().country, "Germany");
+        assert!(nordvpn::disconnect().unwrap());
+        assert!(!nordvpn::disconnect().unwrap());
+    });
+
+    assert_eq!(
+        scenario.calls(),
+        [
+            "status",
+            "connect Germany",
+            "status",
+            "status",
+            "disconnect",
+            "disconnect"
+        ]
+    );
+}
+
+#[test]
+fn test_login_flow() {
+    let scenario = Scenario::new("login", "login-flow");
+
+    scenario.with_library(|| {
+        assert_eq!(nordvpn::account().unwrap(), None);
+
+        let url = nordvpn::login().unwrap().unwrap();
+
+        assert!(url.starts_with("https://api.nordvpn.com/"));
+        assert!(nordvpn::login_callback("nordvpn://login?action=login&exchange_token=x").unwrap());
+        assert_eq!(
+            nordvpn::account().unwrap().unwrap().email,
+            "user@example.com"
+        );
+        assert_eq!(nordvpn::login().unwrap(), None);
+        assert!(nordvpn::logout().unwrap());
+        assert_eq!(nordvpn::account().unwrap(), None);
+    });
+}
+
+#[test]
+fn test_viking_flow() {
+    let scenario = Scenario::new("connect", "viking-flow");
+
+    assert_eq!(
+        stdout_json(&scenario.viking(&["--json", "status"]))["connected"],
+        false
+    );
+    assert_eq!(
+        stdout_json(&scenario.viking(&["--json", "connect", "Germany"]))["city"],
+        "Berlin"
+    );
+    assert_eq!(
+        stdout_json(&scenario.viking(&["--json", "status"]))["hostname"],
+        "de507.nordvpn.com"
+    );
+
+    let output = scenario.viking(&["disconnect"]);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Disconnected\n");
+}
+
+#[test]
+fn test_daemon_down() {
+    let scenario = Scenario::new("daemon_down", "daemon-down");
+
+    scenario.with_library(|| {
+        assert!(matches!(nordvpn::status(), Err(CliError::DaemonNotRunning)));
+    });
+
+    let output = scenario.viking(&["status"]);
+
+    assert_eq!(output.status.code(), Some(4));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("viking: "));
+}
@@ -0,0 +1,125 @@
+//! A stand-in for the `nordvpn` CLI, which the integration tests put first in
+//! `PATH` so that whole flows can be tested where NordVPN is not installed.
+//! It is only built with the `fake-nordvpn` feature.
+//!
+//! It answers from the scenario at `FAKE_NORDVPN_SCENARIO`, a TOML file of
+//! responses, of which the first with the same arguments is printed. An
+//! argument of `*` matches any. A response with `when` is only given in that
+//! state, and one with `then` changes to that state after it, starting from
+//! the `state` of the scenario:
+//!
+//! ```toml
+//! state = "disconnected"
+//!
+//! [[responses]]
+//! args = ["status"]
+//! when = "disconnected"
+//! stdout = "Status: Disconnected\n"
+//!
+//! [[responses]]
+//! args = ["connect"]
+//! then = "connected"
+//! stdout = "You are connected to Germany #507 (de507.nordvpn.com)!\n"
+//! ```
+//!
+//! The state is kept beside the scenario with the extension `state`, and the
+//! arguments of every run are added to the file with the extension `calls`,
+//! one run per line.
+
+use serde::Deserialize;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const SCENARIO_VAR: &str = "FAKE_NORDVPN_SCENARIO";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    responses: Vec<Response>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Response {
+    args: Vec<String>,
+    when: Option<String>,
+    then: Option<String>,
+    #[serde(default)]
+    stdout: String,
+    #[serde(default)]
+    stderr: String,
+    #[serde(default)]
+    code: i32,
+}
+
+impl Response {
+    fn matches(&self, args: &[String], state: &str) -> bool {
+        self.args.len() == args.len()
+            && self
+                .args
+                .iter()
+                .zip(args)
+                .all(|(expected, arg)| expected == "*" || expected == arg)
+            && self.when.as_ref().is_none_or(|when| when == state)
+    }
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let path = match env::var_os(SCENARIO_VAR) {
+        Some(path) => PathBuf::from(path),
+        None => fail(format!("{} is not set", SCENARIO_VAR)),
+    };
+    let scenario = read_scenario(&path).unwrap_or_else(|error| fail(error));
+    let state_path = path.with_extension("state");
+    let state = fs::read_to_string(&state_path).unwrap_or(scenario.state);
+
+    record(&path.with_extension("calls"), &args).unwrap_or_else(|error| fail(error));
+
+    let response = match scenario
+        .responses
+        .into_iter()
+        .find(|response| response.matches(&args, &state))
+    {
+        Some(response) => response,
+        None => fail(format!(
+            "no response to `{}` in the state `{}` of {}",
+            args.join(" "),
+            state,
+            path.display()
+        )),
+    };
+
+    if let Some(then) = &response.then {
+        fs::write(&state_path, then).unwrap_or_else(|error| fail(error));
+    }
+
+    print!("{}", response.stdout);
+    eprint!("{}", response.stderr);
+    process::exit(response.code);
+}
+
+fn read_scenario(path: &Path) -> Result<Scenario, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+
+    toml::from_str(&text).map_err(|error| error.to_string())
+}
+
+fn record(path: &Path, args: &[String]) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    writeln!(file, "{}", args.join(" "))
+}
+
+/// Exits with a code which no scenario should use, so that a mistake in one
+/// is not taken for an answer of the CLI.
+fn fail<E: ToString>(error: E) -> ! {
+    eprintln!("fake nordvpn: {}", error.to_string());
+    process::exit(127);
+}